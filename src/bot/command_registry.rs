@@ -5,20 +5,39 @@ use serenity::all::CreateCommand;
 pub fn get_all_commands() -> Vec<CreateCommand> {
     vec![
         // Slash commands
+        commands::admin_stats::register(),
         commands::allow::register(),
+        commands::anonymous::register(),
+        commands::announcements::register(),
         commands::bg::register(),
         commands::convert::register(),
+        commands::delete_me::register(),
+        commands::export::register(),
         commands::get_nightscout_url::register(),
         commands::graph::register(),
+        commands::graph_settings::register(),
         commands::help::register(),
         commands::info::register(),
+        commands::leaderboard::register(),
+        commands::nightscout::register(),
+        commands::nightscout_status::register(),
+        commands::remind::register(),
+        commands::rotate_key::register(),
         commands::set_nightscout_url::register(),
+        commands::set_range::register(),
+        commands::set_stale_time::register(),
         commands::set_threshold::register(),
+        commands::set_timezone::register(),
         commands::set_token::register(),
         commands::set_visibility::register(),
+        commands::server_config::register(),
         commands::setup::register(),
+        commands::silence::register(),
+        commands::stats::register(),
         commands::stickers::register(),
+        commands::tir::register(),
         commands::token::register(),
+        commands::weekly_digest::register(),
         // Context menu commands
         commands::add_sticker::register(),
         commands::analyze_units::register(),
@@ -17,6 +17,11 @@ pub async fn route_component_interaction(
             commands::setup::handle_button(handler, context, component).await
         }
 
+        // Account deletion confirmation buttons
+        "delete_me_confirm" | "delete_me_cancel" => {
+            commands::delete_me::handle_button(handler, context, component).await
+        }
+
         // Help pagination buttons
         id if id.starts_with("help_page_") => {
             commands::help::handle_button(handler, context, component).await
@@ -27,6 +32,11 @@ pub async fn route_component_interaction(
             commands::add_sticker::handle_button(handler, context, component).await
         }
 
+        // Sticker size buttons shown after adding a sticker
+        id if id.starts_with("sticker_size:") => {
+            commands::add_sticker::handle_size_button(handler, context, component).await
+        }
+
         // Stickers management buttons
         id if id.starts_with("remove_sticker_")
             || id == "clear_all_stickers"
@@ -2,11 +2,118 @@ use crate::bot::{
     Handler, command_registry, component_router, helpers::command_handler, version_checker,
 };
 use crate::commands;
+use crate::utils::metrics::METRICS;
 use serenity::all::{
-    Command, CreateInteractionResponse, CreateInteractionResponseMessage, Interaction, Ready,
+    Command, CommandInteraction, ConnectionStage, CreateInteractionResponse,
+    CreateInteractionResponseMessage, Interaction, Ready, ResolvedValue, ShardStageUpdateEvent,
 };
 use serenity::prelude::*;
 
+/// Apply the per-user rate limit for the command. Tells the user to slow down and returns
+/// `false` if they've used it too often.
+async fn check_rate_limit(
+    handler: &Handler,
+    context: &Context,
+    command: &CommandInteraction,
+) -> anyhow::Result<bool> {
+    let Err(wait) = handler
+        .take_rate_limit(command.user.id.get(), &command.data.name)
+        .await
+    else {
+        return Ok(true);
+    };
+
+    tracing::info!(
+        "[RATE LIMIT] User {} is using /{} too often",
+        command.user.id.get(),
+        command.data.name
+    );
+    commands::error::run(
+        context,
+        command,
+        &format!(
+            "Slow down! You can use `/{}` again in {} seconds.",
+            command.data.name,
+            wait.as_secs_f64().ceil().max(1.0)
+        ),
+    )
+    .await?;
+    Ok(false)
+}
+
+/// Commands that show someone's glucose data, and so follow the guild's `/server-config`
+/// channel and privacy rules
+const DATA_COMMANDS: &[&str] = &[
+    "bg",
+    "graph",
+    "tir",
+    "stats",
+    "announcements",
+    "nightscout-status",
+    "leaderboard",
+];
+
+fn shows_user_data(command_name: &str) -> bool {
+    DATA_COMMANDS.contains(&command_name)
+}
+
+/// Apply the guild's `/server-config` rules to [`DATA_COMMANDS`]. Sends an error to the
+/// user and returns `false` if the command isn't allowed here.
+async fn check_guild_settings(
+    handler: &Handler,
+    context: &Context,
+    command: &CommandInteraction,
+) -> anyhow::Result<bool> {
+    let Some(guild_id) = command.guild_id else {
+        return Ok(true);
+    };
+    if !shows_user_data(&command.data.name) {
+        return Ok(true);
+    }
+
+    let settings = handler.database.get_guild_settings(guild_id.get()).await?;
+
+    if !settings.allows_channel(command.channel_id.get()) {
+        commands::error::run(
+            context,
+            command,
+            "This server's admins have limited that command to specific channels.",
+        )
+        .await?;
+        return Ok(false);
+    }
+
+    if settings.force_private {
+        let command_user_id = command.user.id.get();
+        let target_user_id = command
+            .data
+            .options()
+            .iter()
+            .find_map(|option| match option.value {
+                ResolvedValue::User(user, _) => Some(user.id.get()),
+                _ => None,
+            });
+
+        if let Some(target_user_id) = target_user_id
+            && target_user_id != command_user_id
+            && handler.database.user_exists(target_user_id).await?
+        {
+            let target = handler.database.get_user_info(target_user_id).await?;
+            if !target.nightscout.allowed_people.contains(&command_user_id) {
+                commands::error::run(
+                    context,
+                    command,
+                    "In this server, members' data is private unless they've allowed you with `/allow`.",
+                )
+                .await?;
+                return Ok(false);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
 #[serenity::async_trait]
 impl EventHandler for Handler {
     async fn interaction_create(&self, context: Context, interaction: Interaction) {
@@ -17,8 +124,19 @@ impl EventHandler for Handler {
                     if command.data.kind == serenity::model::application::CommandType::Message {
                         command_handler::handle_context_command(self, &context, command).await
                     } else {
-                        command_handler::handle_slash_command(self, &context, command).await
+                        let allowed = match check_rate_limit(self, &context, command).await {
+                            Ok(true) => check_guild_settings(self, &context, command).await,
+                            other => other,
+                        };
+                        match allowed {
+                            Ok(true) => {
+                                command_handler::handle_slash_command(self, &context, command).await
+                            }
+                            Ok(false) => Ok(()),
+                            Err(e) => Err(e),
+                        }
                     };
+                METRICS.record_command(&command.data.name, command_result.is_ok());
 
                 // Check for version updates after successful command execution
                 if command_result.is_ok()
@@ -37,6 +155,10 @@ impl EventHandler for Handler {
                 component_router::route_component_interaction(self, &context, component).await
             }
 
+            Interaction::Autocomplete(ref command) => {
+                command_handler::handle_autocomplete(&context, command).await
+            }
+
             _ => Ok(()),
         };
 
@@ -75,6 +197,8 @@ impl EventHandler for Handler {
                         );
                     }
                 }
+                // There's no way to show an error from an autocomplete request
+                Interaction::Autocomplete(_) => {}
                 _ => {
                     eprintln!("Unhandled interaction type in error handler");
                 }
@@ -84,6 +208,8 @@ impl EventHandler for Handler {
 
     async fn ready(&self, context: Context, ready: Ready) {
         tracing::info!("[BOT] {} is ready and connected!", ready.user.name);
+        self.started_at.get_or_init(std::time::Instant::now);
+        METRICS.set_discord_connected(true);
 
         let commands_vec = command_registry::get_all_commands();
         let command_count = commands_vec.len();
@@ -95,4 +221,30 @@ impl EventHandler for Handler {
             command_count
         );
     }
+
+    async fn shard_stage_update(&self, _context: Context, event: ShardStageUpdateEvent) {
+        METRICS.set_discord_connected(event.new == ConnectionStage::Connected);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guild_rules_cover_every_data_command() {
+        for name in [
+            "bg",
+            "graph",
+            "tir",
+            "stats",
+            "announcements",
+            "nightscout-status",
+            "leaderboard",
+        ] {
+            assert!(shows_user_data(name), "{name} skips the guild rules");
+        }
+        assert!(!shows_user_data("help"));
+        assert!(!shows_user_data("convert"));
+    }
 }
@@ -1,14 +1,36 @@
 use ab_glyph::FontArc;
 use anyhow::anyhow;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Instant;
+use tokio::sync::{Mutex, RwLock};
 
+use crate::bot::helpers::rate_limit::{self, TokenBucket};
 use crate::utils::database::Database;
+use crate::utils::graph::{GlyphCache, GraphOutput};
 use crate::utils::nightscout::Nightscout;
 
+/// How long a rendered graph is reused for identical `/graph` requests
+const GRAPH_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(60);
+
+struct CachedGraph {
+    graph: GraphOutput,
+    rendered_at: Instant,
+}
+
 #[allow(dead_code)]
 pub struct Handler {
     pub nightscout_client: Nightscout,
     pub database: Database,
     pub font: FontArc,
+    /// Glyphs rasterized from `font`, reused across graph renders
+    pub glyph_cache: GlyphCache,
+    /// When the bot first became ready, for `/info` uptime
+    pub started_at: OnceLock<Instant>,
+    /// Recently rendered graphs keyed by user, hours and a hash of their graph settings
+    graph_cache: RwLock<HashMap<u64, CachedGraph>>,
+    /// Remaining command uses keyed by user and command name
+    rate_limits: Mutex<HashMap<(u64, String), TokenBucket>>,
 }
 
 impl Handler {
@@ -23,6 +45,120 @@ impl Handler {
             font: FontArc::try_from_vec(font_bytes)
                 .map_err(|_| anyhow!("Failed to parse font"))
                 .unwrap(),
+            glyph_cache: GlyphCache::default(),
+            started_at: OnceLock::new(),
+            graph_cache: RwLock::new(HashMap::new()),
+            rate_limits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A graph rendered for the same key within the last [`GRAPH_CACHE_TTL`]
+    pub async fn cached_graph(&self, key: u64) -> Option<GraphOutput> {
+        self.graph_cache
+            .read()
+            .await
+            .get(&key)
+            .filter(|cached| cached.rendered_at.elapsed() < GRAPH_CACHE_TTL)
+            .map(|cached| cached.graph.clone())
+    }
+
+    /// Remember a rendered graph, dropping any that have expired
+    pub async fn cache_graph(&self, key: u64, graph: GraphOutput) {
+        let mut cache = self.graph_cache.write().await;
+        cache.retain(|_, cached| cached.rendered_at.elapsed() < GRAPH_CACHE_TTL);
+        cache.insert(
+            key,
+            CachedGraph {
+                graph,
+                rendered_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Use one of the user's tokens for a command, or return how long they have to wait
+    /// before using it again. Buckets that have filled back up are dropped.
+    pub async fn take_rate_limit(
+        &self,
+        user_id: u64,
+        command: &str,
+    ) -> Result<(), std::time::Duration> {
+        let now = Instant::now();
+        let limit = rate_limit::limit_for(command);
+        let mut buckets = self.rate_limits.lock().await;
+        buckets.retain(|(_, name), bucket| !bucket.is_refilled(rate_limit::limit_for(name), now));
+        buckets
+            .entry((user_id, command.to_string()))
+            .or_insert_with(|| TokenBucket::full(limit, now))
+            .try_take(limit, now)
+    }
+
+    /// The user's target range in mg/dL, resolved the same way as `/bg`: a `/set-range`
+    /// override, then Nightscout's status thresholds, then the profile, then 70-180.
+    pub async fn user_target_range_mg(&self, user_id: u64) -> (f32, f32) {
+        let Ok(user_info) = self.database.get_user_info(user_id).await else {
+            return (70.0, 180.0);
+        };
+        let user_range_mg = user_info.nightscout.target_range_mg;
+        let Some(base_url) = user_info.nightscout.nightscout_url.as_deref() else {
+            return user_range_mg.unwrap_or((70.0, 180.0));
+        };
+        let token = user_info.nightscout.nightscout_token.as_deref();
+
+        let (profile, status) = tokio::join!(
+            self.nightscout_client.get_profile(base_url, token),
+            self.nightscout_client.get_status(base_url, token),
+        );
+
+        let thresholds = status
+            .as_ref()
+            .ok()
+            .and_then(|s| s.settings.as_ref())
+            .and_then(|settings| settings.thresholds.as_ref());
+
+        match profile
+            .as_ref()
+            .ok()
+            .and_then(|profile| profile.default_store())
+        {
+            Some(store) => (
+                store.get_target_low_mg(user_range_mg, thresholds),
+                store.get_target_high_mg(user_range_mg, thresholds),
+            ),
+            None => user_range_mg.unwrap_or((70.0, 180.0)),
+        }
+    }
+
+    /// The user's `/set-timezone` override or the timezone from their Nightscout profile,
+    /// falling back to UTC
+    pub async fn user_timezone(&self, user_id: u64) -> String {
+        let Ok(user_info) = self.database.get_user_info(user_id).await else {
+            return "UTC".to_string();
+        };
+        if let Some(timezone) = user_info.nightscout.timezone_override {
+            return timezone;
+        }
+        let Some(base_url) = user_info.nightscout.nightscout_url.as_deref() else {
+            return "UTC".to_string();
+        };
+
+        match self
+            .nightscout_client
+            .get_profile(base_url, user_info.nightscout.nightscout_token.as_deref())
+            .await
+        {
+            Ok(profile) => profile
+                .default_store()
+                .map(|store| store.timezone.clone())
+                .filter(|tz| tz.parse::<chrono_tz::Tz>().is_ok())
+                .unwrap_or_else(|| "UTC".to_string()),
+            Err(e) => {
+                tracing::warn!(
+                    "[PROFILE] Couldn't fetch profile for user {}, using UTC: {}",
+                    user_id,
+                    e
+                );
+                "UTC".to_string()
+            }
         }
     }
 }
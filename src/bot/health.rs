@@ -0,0 +1,110 @@
+use crate::bot::Handler;
+use crate::utils::metrics::METRICS;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Requests are tiny `GET`s, so anything longer than this is cut off
+const MAX_REQUEST_BYTES: usize = 4096;
+
+/// Serve `/health` and `/metrics` over plain HTTP when `HEALTH_PORT` is set, for self-hosters
+/// who want to monitor the bot
+pub fn spawn_health_server(handler: Arc<Handler>) {
+    let Some(port) = dotenvy::var("HEALTH_PORT")
+        .ok()
+        .and_then(|port| port.trim().parse::<u16>().ok())
+    else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let listener = match TcpListener::bind(("0.0.0.0", port)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                tracing::error!("[HEALTH] Couldn't listen on port {}: {}", port, e);
+                return;
+            }
+        };
+        tracing::info!("[HEALTH] Serving /health and /metrics on port {}", port);
+
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let handler = handler.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = serve(stream, &handler).await {
+                            tracing::debug!("[HEALTH] Failed to answer request: {}", e);
+                        }
+                    });
+                }
+                Err(e) => tracing::warn!("[HEALTH] Failed to accept connection: {}", e),
+            }
+        }
+    });
+}
+
+async fn serve(mut stream: TcpStream, handler: &Handler) -> std::io::Result<()> {
+    let mut buffer = vec![0; MAX_REQUEST_BYTES];
+    let read = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..read]);
+
+    let (status, content_type, body) = match request_path(&request) {
+        Some("/health") => {
+            let discord_up = METRICS.discord_connected();
+            let database_up = handler.database.ping().await.is_ok();
+            let status = if discord_up && database_up {
+                "200 OK"
+            } else {
+                "503 Service Unavailable"
+            };
+            (
+                status,
+                "application/json",
+                format!(
+                    "{{\"discord\":{},\"database\":{}}}",
+                    discord_up, database_up
+                ),
+            )
+        }
+        Some("/metrics") => ("200 OK", "text/plain; version=0.0.4", METRICS.render()),
+        _ => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Path of a `GET` request, without any query string
+fn request_path(request: &str) -> Option<&str> {
+    let mut parts = request.lines().next()?.split_whitespace();
+    if parts.next()? != "GET" {
+        return None;
+    }
+    parts.next()?.split('?').next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_path() {
+        assert_eq!(
+            request_path("GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            Some("/metrics")
+        );
+        assert_eq!(
+            request_path("GET /health?probe=1 HTTP/1.1\r\n"),
+            Some("/health")
+        );
+        assert_eq!(request_path("POST /health HTTP/1.1\r\n"), None);
+        assert_eq!(request_path(""), None);
+    }
+}
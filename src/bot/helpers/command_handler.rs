@@ -4,7 +4,16 @@ use anyhow::Result;
 use serenity::all::{CommandInteraction, Context};
 
 /// List of commands that don't require user setup
-const UNRESTRICTED_COMMANDS: &[&str] = &["setup", "convert", "help"];
+const UNRESTRICTED_COMMANDS: &[&str] = &[
+    "setup",
+    "convert",
+    "help",
+    "delete-me",
+    "server-config",
+    "admin-stats",
+    "rotate-key",
+    "leaderboard",
+];
 
 /// Route a slash command to its handler
 pub async fn handle_slash_command(
@@ -26,20 +35,39 @@ pub async fn handle_slash_command(
 
     // Route to appropriate command handler
     match command.data.name.as_str() {
+        "admin-stats" => commands::admin_stats::run(handler, context, command).await,
         "allow" => commands::allow::run(handler, context, command).await,
+        "anonymous" => commands::anonymous::run(handler, context, command).await,
+        "announcements" => commands::announcements::run(handler, context, command).await,
         "bg" => commands::bg::run(handler, context, command).await,
         "convert" => commands::convert::run(handler, context, command).await,
+        "delete-me" => commands::delete_me::run(handler, context, command).await,
+        "export" => commands::export::run(handler, context, command).await,
         "get-nightscout-url" => commands::get_nightscout_url::run(handler, context, command).await,
         "graph" => commands::graph::run(handler, context, command).await,
+        "graph-settings" => commands::graph_settings::run(handler, context, command).await,
         "help" => commands::help::run(handler, context, command).await,
         "info" => commands::info::run(handler, context, command).await,
+        "leaderboard" => commands::leaderboard::run(handler, context, command).await,
+        "nightscout" => commands::nightscout::run(handler, context, command).await,
+        "nightscout-status" => commands::nightscout_status::run(handler, context, command).await,
+        "remind" => commands::remind::run(handler, context, command).await,
+        "rotate-key" => commands::rotate_key::run(handler, context, command).await,
         "set-nightscout-url" => commands::set_nightscout_url::run(handler, context, command).await,
+        "set-range" => commands::set_range::run(handler, context, command).await,
+        "set-stale-time" => commands::set_stale_time::run(handler, context, command).await,
         "set-threshold" => commands::set_threshold::run(handler, context, command).await,
+        "set-timezone" => commands::set_timezone::run(handler, context, command).await,
         "set-token" => commands::set_token::run(handler, context, command).await,
         "set-visibility" => commands::set_visibility::run(handler, context, command).await,
+        "server-config" => commands::server_config::run(handler, context, command).await,
         "setup" => commands::setup::run(handler, context, command).await,
+        "silence" => commands::silence::run(handler, context, command).await,
+        "stats" => commands::stats::run(handler, context, command).await,
         "stickers" => commands::stickers::run(handler, context, command).await,
+        "tir" => commands::tir::run(handler, context, command).await,
         "token" => commands::token::run(handler, context, command).await,
+        "weekly-digest" => commands::weekly_digest::run(handler, context, command).await,
         unknown_command => {
             eprintln!("Unknown slash command received: '{}'", unknown_command);
             commands::error::run(
@@ -81,3 +109,18 @@ pub async fn handle_context_command(
         }
     }
 }
+
+/// Answer an autocomplete request for a slash command option
+pub async fn handle_autocomplete(context: &Context, command: &CommandInteraction) -> Result<()> {
+    match command.data.name.as_str() {
+        "help" => commands::help::autocomplete(context, command).await,
+        "set-timezone" => commands::set_timezone::autocomplete(context, command).await,
+        unknown_command => {
+            tracing::debug!(
+                "Autocomplete requested for unknown command '{}'",
+                unknown_command
+            );
+            Ok(())
+        }
+    }
+}
@@ -1,3 +1,6 @@
 pub mod command_handler;
 pub mod components;
+pub mod owner;
 pub mod pagination;
+pub mod rate_limit;
+pub mod schedule;
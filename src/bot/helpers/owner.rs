@@ -0,0 +1,9 @@
+/// Discord id of the bot operator from `BOT_OWNER_ID`, if configured
+fn owner_id() -> Option<u64> {
+    dotenvy::var("BOT_OWNER_ID").ok()?.trim().parse().ok()
+}
+
+/// Whether the user is the bot operator allowed to run owner-only commands
+pub fn is_owner(user_id: u64) -> bool {
+    owner_id() == Some(user_id)
+}
@@ -0,0 +1,111 @@
+use std::time::{Duration, Instant};
+
+/// How many uses of a command a user can burst, and how quickly those refill
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CommandLimit {
+    pub burst: f64,
+    /// Seconds for one use to come back
+    pub refill_secs: f64,
+}
+
+impl CommandLimit {
+    const fn new(burst: f64, refill_secs: f64) -> Self {
+        Self { burst, refill_secs }
+    }
+
+    /// How long an emptied bucket takes to fill back up
+    pub fn full_refill(&self) -> Duration {
+        Duration::from_secs_f64(self.burst * self.refill_secs)
+    }
+}
+
+/// Limits for commands that render images or hit Nightscout hard. Anything not listed
+/// gets [`DEFAULT_LIMIT`].
+const COMMAND_LIMITS: &[(&str, CommandLimit)] = &[
+    ("graph", CommandLimit::new(3.0, 20.0)),
+    ("stats", CommandLimit::new(3.0, 20.0)),
+    ("leaderboard", CommandLimit::new(2.0, 30.0)),
+    ("export", CommandLimit::new(2.0, 60.0)),
+    ("bg", CommandLimit::new(6.0, 5.0)),
+    ("tir", CommandLimit::new(6.0, 5.0)),
+];
+
+const DEFAULT_LIMIT: CommandLimit = CommandLimit::new(10.0, 2.0);
+
+pub fn limit_for(command: &str) -> CommandLimit {
+    COMMAND_LIMITS
+        .iter()
+        .find(|(name, _)| *name == command)
+        .map(|(_, limit)| *limit)
+        .unwrap_or(DEFAULT_LIMIT)
+}
+
+/// A user's remaining uses of one command
+#[derive(Clone, Copy, Debug)]
+pub struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    pub fn full(limit: CommandLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst,
+            updated_at: now,
+        }
+    }
+
+    fn refill(&mut self, limit: CommandLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed / limit.refill_secs).min(limit.burst);
+        self.updated_at = now;
+    }
+
+    /// Use one token, or return how long until one is available
+    pub fn try_take(&mut self, limit: CommandLimit, now: Instant) -> Result<(), Duration> {
+        self.refill(limit, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) * limit.refill_secs,
+            ))
+        }
+    }
+
+    /// Whether the bucket would be full by `now`, so it can be forgotten
+    pub fn is_refilled(&self, limit: CommandLimit, now: Instant) -> bool {
+        now.saturating_duration_since(self.updated_at) >= limit.full_refill()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_bursts_then_refills() {
+        let limit = limit_for("graph");
+        assert!(limit.burst < limit_for("bg").burst);
+        assert_eq!(limit_for("info"), DEFAULT_LIMIT);
+
+        let start = Instant::now();
+        let mut bucket = TokenBucket::full(limit, start);
+        for _ in 0..3 {
+            assert!(bucket.try_take(limit, start).is_ok());
+        }
+
+        let wait = bucket.try_take(limit, start).unwrap_err();
+        assert_eq!(wait.as_secs(), 20);
+
+        let later = start + Duration::from_secs(10);
+        let wait = bucket.try_take(limit, later).unwrap_err();
+        assert_eq!(wait.as_secs(), 10);
+
+        let later = start + Duration::from_secs(20);
+        assert!(bucket.try_take(limit, later).is_ok());
+        assert!(!bucket.is_refilled(limit, later));
+        assert!(bucket.is_refilled(limit, later + limit.full_refill()));
+    }
+}
@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+/// Poll intervals shorter than this are ignored, so a typo can't hammer Nightscout hosts
+const MIN_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Poll interval read from an env var holding seconds, falling back to `default` when it's
+/// unset, invalid or below a minute
+pub fn poll_interval_from_env(var: &str, default: Duration) -> Duration {
+    dotenvy::var(var)
+        .ok()
+        .and_then(|secs| secs.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .filter(|interval| *interval >= MIN_POLL_INTERVAL)
+        .unwrap_or(default)
+}
+
+/// Fixed offset into each poll interval at which a user is handled. Spreading users across
+/// the interval keeps a background task from hitting every Nightscout site at once, and
+/// keeping it stable gives each user an even gap between polls.
+pub fn stagger_offset(discord_id: u64, interval: Duration) -> Duration {
+    let interval_ms = interval.as_millis() as u64;
+    if interval_ms == 0 {
+        return Duration::ZERO;
+    }
+
+    // Snowflakes created close together share their high bits, so mix them first
+    let mixed = (discord_id ^ (discord_id >> 22)).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    Duration::from_millis((mixed >> 16) % interval_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stagger_offset_spreads_users_within_interval() {
+        let interval = Duration::from_secs(300);
+        let first_id = 1_100_000_000_000_000_000u64;
+        let offsets: Vec<Duration> = (0..50)
+            .map(|i| stagger_offset(first_id + i * 4_194_304, interval))
+            .collect();
+
+        assert!(offsets.iter().all(|offset| *offset < interval));
+        assert_eq!(offsets[0], stagger_offset(first_id, interval));
+
+        let early = offsets
+            .iter()
+            .filter(|offset| **offset < interval / 2)
+            .count();
+        assert!((10..40).contains(&early), "{} of 50 in first half", early);
+    }
+}
@@ -1,22 +1,268 @@
 use crate::bot::Handler;
+use crate::bot::health::spawn_health_server;
+use crate::bot::helpers::schedule::{poll_interval_from_env, stagger_offset};
+use crate::commands::stats::{fetch_stats_window, summary_embed};
+use crate::utils::database::Database;
+use crate::utils::reminders::next_occurrence;
+use crate::utils::stats::{GlucoseSummary, week_start};
 use anyhow::Result;
+use chrono::{Datelike, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use serenity::all::{Colour, CreateEmbed, CreateMessage, Http, UserId};
 use serenity::prelude::*;
+use std::sync::Arc;
+
+/// How often the reminder scheduler checks for due reminders
+const REMINDER_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+/// How often the weekly digest scheduler checks whose digest is due, unless overridden with
+/// `DIGEST_POLL_INTERVAL_SECS`
+const DEFAULT_DIGEST_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+/// Local hour on Sunday from which the weekly digest is sent
+const DIGEST_HOUR: u32 = 19;
 
 /// Initialize and start the Discord bot
 pub async fn start_bot() -> Result<()> {
     tracing::info!("[INIT] Starting Beetroot Discord Bot");
 
     let token = dotenvy::var("DISCORD_TOKEN").expect("Expected a token in the environment");
-    let handler = Handler::new().await;
+    let handler = Arc::new(Handler::new().await);
 
     let mut client = Client::builder(token, GatewayIntents::empty())
-        .event_handler(handler)
+        .event_handler_arc(handler.clone())
         .await
         .expect("Error creating client");
 
+    spawn_reminder_scheduler(client.http.clone(), handler.database.clone());
+    spawn_digest_scheduler(client.http.clone(), handler.clone());
+    spawn_health_server(handler);
+
     if let Err(why) = client.start().await {
         tracing::error!("[ERROR] Discord client error: {why:?}");
     }
 
     Ok(())
 }
+
+/// Spawn a background task that periodically DMs users their due reminders
+fn spawn_reminder_scheduler(http: Arc<Http>, database: Database) {
+    tokio::spawn(async move {
+        tracing::info!(
+            "[REMIND] Reminder scheduler started (every {}s)",
+            REMINDER_POLL_INTERVAL.as_secs()
+        );
+
+        let mut interval = tokio::time::interval(REMINDER_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = fire_due_reminders(&http, &database).await {
+                tracing::error!("[REMIND] Failed to process due reminders: {}", e);
+            }
+        }
+    });
+}
+
+async fn fire_due_reminders(http: &Http, database: &Database) -> Result<()> {
+    let now = Utc::now();
+    let due = database.get_due_reminders(now.timestamp()).await?;
+
+    for reminder in due {
+        let embed = CreateEmbed::new()
+            .title("⏰ Reminder")
+            .description(&reminder.message)
+            .color(Colour::from_rgb(252, 186, 0));
+
+        match UserId::new(reminder.discord_id)
+            .direct_message(http, CreateMessage::new().embed(embed))
+            .await
+        {
+            Ok(_) => tracing::info!(
+                "[REMIND] Sent reminder #{} to user {}",
+                reminder.id,
+                reminder.discord_id
+            ),
+            Err(e) => tracing::warn!(
+                "[REMIND] Couldn't DM reminder #{} to user {}: {}",
+                reminder.id,
+                reminder.discord_id,
+                e
+            ),
+        }
+
+        let tz = reminder.timezone.parse().unwrap_or(chrono_tz::UTC);
+        let next = chrono::DateTime::from_timestamp(reminder.next_fire, 0)
+            .and_then(|fired_at| next_occurrence(fired_at, reminder.repeat, tz, now));
+
+        match next {
+            Some(next) => {
+                database
+                    .reschedule_reminder(reminder.id, next.timestamp())
+                    .await?
+            }
+            None => database.complete_reminder(reminder.id).await?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Spawn a background task that DMs opted-in users their weekly digest on Sunday evening
+fn spawn_digest_scheduler(http: Arc<Http>, handler: Arc<Handler>) {
+    let poll_interval =
+        poll_interval_from_env("DIGEST_POLL_INTERVAL_SECS", DEFAULT_DIGEST_POLL_INTERVAL);
+
+    tokio::spawn(async move {
+        tracing::info!(
+            "[DIGEST] Weekly digest scheduler started (every {}s)",
+            poll_interval.as_secs()
+        );
+
+        let mut interval = tokio::time::interval(poll_interval);
+        // Staggered sends can take most of the interval; never fire the missed ticks in a burst
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(e) = send_weekly_digests(&http, &handler, poll_interval).await {
+                tracing::error!("[DIGEST] Failed to process weekly digests: {}", e);
+            }
+        }
+    });
+}
+
+async fn send_weekly_digests(
+    http: &Http,
+    handler: &Handler,
+    poll_interval: std::time::Duration,
+) -> Result<()> {
+    let cycle_start = tokio::time::Instant::now();
+    if !digest_may_be_due(Utc::now()) {
+        return Ok(());
+    }
+
+    let mut subscribers = handler.database.get_digest_subscribers().await?;
+    subscribers.sort_by_key(|(discord_id, _)| stagger_offset(*discord_id, poll_interval));
+
+    for (discord_id, sent_week) in subscribers {
+        // Spread the Nightscout requests, including the profile fetch behind the user's
+        // timezone, over the interval instead of sending them all at once
+        tokio::time::sleep_until(cycle_start + stagger_offset(discord_id, poll_interval)).await;
+
+        let now = Utc::now();
+        let tz: Tz = handler
+            .user_timezone(discord_id)
+            .await
+            .parse()
+            .unwrap_or(chrono_tz::UTC);
+        let local_now = now.with_timezone(&tz);
+        if local_now.weekday() != Weekday::Sun || local_now.hour() < DIGEST_HOUR {
+            continue;
+        }
+
+        let week = local_now.format("%G-W%V").to_string();
+        if sent_week.as_deref() == Some(week.as_str()) {
+            continue;
+        }
+
+        match send_digest(http, handler, discord_id, local_now).await {
+            // Try again on a later poll, once the user is no longer silenced
+            Ok(DigestOutcome::Deferred) => continue,
+            Ok(DigestOutcome::Sent | DigestOutcome::NoReadings) => {}
+            Err(e) => tracing::warn!(
+                "[DIGEST] Couldn't send weekly digest to user {}: {}",
+                discord_id,
+                e
+            ),
+        }
+
+        // Mark the week as handled even on failure so a broken site doesn't retry every poll
+        handler.database.mark_digest_sent(discord_id, &week).await?;
+    }
+
+    Ok(())
+}
+
+/// Whether it's Sunday evening somewhere, from 19:00 at UTC+14 (Sunday 05:00 UTC) to the end
+/// of Sunday at UTC-12 (Monday 12:00 UTC). Outside that window no digest can be due, so the
+/// poll skips resolving every subscriber's timezone.
+fn digest_may_be_due(now: chrono::DateTime<Utc>) -> bool {
+    match now.weekday() {
+        Weekday::Sun => now.hour() >= DIGEST_HOUR - 14,
+        Weekday::Mon => now.hour() < 12,
+        _ => false,
+    }
+}
+
+/// What happened to a user's weekly digest
+#[derive(Debug, PartialEq, Eq)]
+enum DigestOutcome {
+    Sent,
+    /// There were no readings this week, so there was nothing to send
+    NoReadings,
+    /// The user has silenced notifications; the digest is kept for a later poll
+    Deferred,
+}
+
+async fn send_digest(
+    http: &Http,
+    handler: &Handler,
+    discord_id: u64,
+    local_now: chrono::DateTime<Tz>,
+) -> Result<DigestOutcome> {
+    let nightscout = handler.database.get_user_info(discord_id).await?.nightscout;
+    if nightscout.is_snoozed(local_now.timestamp()) {
+        tracing::info!("[DIGEST] Deferring digest for silenced user {}", discord_id);
+        return Ok(DigestOutcome::Deferred);
+    }
+
+    let start = week_start(local_now).ok_or_else(|| anyhow::anyhow!("Invalid week start"))?;
+    let hours = (local_now.with_timezone(&Utc) - start).num_minutes() / 60 + 2;
+    let window = fetch_stats_window(handler, &nightscout, hours).await?;
+    let entries: Vec<_> = window
+        .entries
+        .into_iter()
+        .filter(|entry| entry.millis_to_user_timezone(&window.timezone) >= start)
+        .collect();
+
+    let Some(summary) =
+        GlucoseSummary::from_entries(&entries, window.target_low_mg, window.target_high_mg)
+    else {
+        tracing::info!("[DIGEST] No readings this week for user {}", discord_id);
+        return Ok(DigestOutcome::NoReadings);
+    };
+
+    let embed = summary_embed(&summary, window.target_low_mg, window.target_high_mg)
+        .title("📅 Your Week in Review")
+        .description(format!(
+            "{} – {}",
+            start
+                .with_timezone(&local_now.timezone())
+                .format("%a %b %-d"),
+            local_now.format("%a %b %-d")
+        ));
+
+    UserId::new(discord_id)
+        .direct_message(http, CreateMessage::new().embed(embed))
+        .await?;
+    tracing::info!("[DIGEST] Sent weekly digest to user {}", discord_id);
+
+    Ok(DigestOutcome::Sent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_digest_may_be_due_only_around_sunday_evening() {
+        let at = |day: u32, hour: u32| Utc.with_ymd_and_hms(2025, 9, day, hour, 0, 0).unwrap();
+
+        // 21 September 2025 is a Sunday
+        assert!(!digest_may_be_due(at(20, 23)));
+        assert!(!digest_may_be_due(at(21, 4)));
+        assert!(digest_may_be_due(at(21, 5)));
+        assert!(digest_may_be_due(at(21, 23)));
+        assert!(digest_may_be_due(at(22, 11)));
+        assert!(!digest_may_be_due(at(22, 12)));
+        assert!(!digest_may_be_due(at(24, 19)));
+    }
+}
@@ -1,11 +1,12 @@
-mod command_registry;
+pub mod command_registry;
 mod component_router;
 mod event_handler;
 mod handler;
-mod version_checker;
+mod health;
 
 pub mod helpers;
 pub mod init;
+pub mod version_checker;
 
 // Re-export Handler for convenience
 pub use handler::Handler;
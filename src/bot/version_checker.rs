@@ -3,13 +3,18 @@ use crate::commands;
 use anyhow::Result;
 use serenity::all::{CommandInteraction, Context, CreateInteractionResponseFollowup};
 
+/// The running bot version, from `BOT_VERSION` or the crate version
+pub fn current_version() -> String {
+    dotenvy::var("BOT_VERSION").unwrap_or_else(|_| env!("CARGO_PKG_VERSION").to_string())
+}
+
 /// Check if user needs to be notified of version update and send notification
 pub async fn check_and_notify_version_update(
     handler: &Handler,
     context: &Context,
     command: &CommandInteraction,
 ) -> Result<()> {
-    let current_version = dotenvy::var("BOT_VERSION").unwrap_or_else(|_| "0.1.1".to_string());
+    let current_version = current_version();
     let user_id = command.user.id.get();
 
     tracing::info!(
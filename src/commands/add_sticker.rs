@@ -1,5 +1,6 @@
 use crate::bot::Handler;
-use crate::utils::database::StickerCategory;
+use crate::utils::database::{StickerCategory, StickerSize};
+use crate::utils::graph::random_sticker_rotation;
 use serenity::all::{
     ButtonStyle, Colour, CommandInteraction, ComponentInteraction, Context, CreateActionRow,
     CreateButton, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
@@ -56,6 +57,7 @@ pub async fn run(
     };
 
     let (sticker_name, sticker_url) = sticker_info;
+    let (target_low_mg, target_high_mg) = handler.user_target_range_mg(user_id).await;
 
     let buttons = vec![
         CreateButton::new(format!("add_sticker_low:{}:{}", sticker_name, sticker_url))
@@ -81,11 +83,14 @@ pub async fn run(
         .title("Select Sticker Category")
         .description(format!(
             "Choose a category for **{}**:\n\n\
-            • **Low**: Shows when blood glucose is low (<70 mg/dL)\n\
-            • **In Range**: Shows when blood glucose is in range (70-180 mg/dL)\n\
-            • **High**: Shows when blood glucose is high (>180 mg/dL)\n\
+            • **Low**: Shows when blood glucose is {}\n\
+            • **In Range**: Shows when blood glucose is {}\n\
+            • **High**: Shows when blood glucose is {}\n\
             • **Any**: Shows randomly regardless of blood glucose",
-            sticker_name
+            sticker_name,
+            category_condition(StickerCategory::Low, target_low_mg, target_high_mg),
+            category_condition(StickerCategory::InRange, target_low_mg, target_high_mg),
+            category_condition(StickerCategory::High, target_low_mg, target_high_mg),
         ))
         .color(Colour::BLUE);
 
@@ -155,30 +160,35 @@ pub async fn handle_button(
             return Ok(());
         }
 
+        let (target_low_mg, target_high_mg) = handler.user_target_range_mg(user_id).await;
+
         match handler
             .database
-            .insert_sticker(user_id, sticker_url, sticker_name, category)
+            .insert_sticker(
+                user_id,
+                sticker_url,
+                sticker_name,
+                category,
+                random_sticker_rotation(),
+            )
             .await
         {
-            Ok(_) => {
+            Ok(sticker_id) => {
                 let embed = CreateEmbed::new()
                     .title("Sticker Added")
                     .description(format!(
                         "Successfully added **{}** to your **{}** stickers!\n\n\
-                        It will appear on your next `/graph` command when your blood glucose is {}.",
+                        It will appear on your next `/graph` command when your blood glucose is {}.\n\n\
+                        It's drawn at **Medium** size. Pick another size below if you like.",
                         sticker_name,
                         category.display_name(),
-                        match category {
-                            StickerCategory::Low => "low (<70 mg/dL)",
-                            StickerCategory::InRange => "in range (70-180 mg/dL)",
-                            StickerCategory::High => "high (>180 mg/dL)",
-                            StickerCategory::Any => "in any state",
-                        }
+                        category_condition(category, target_low_mg, target_high_mg)
                     ))
                     .color(Colour::DARK_GREEN);
 
                 let response = CreateInteractionResponseMessage::new()
                     .embed(embed)
+                    .components(vec![size_buttons(sticker_id, StickerSize::Medium)])
                     .ephemeral(true);
 
                 interaction
@@ -207,6 +217,111 @@ pub async fn handle_button(
     Ok(())
 }
 
+/// Buttons to resize a freshly added sticker, with the current size highlighted
+fn size_buttons(sticker_id: i64, current: StickerSize) -> CreateActionRow {
+    CreateActionRow::Buttons(
+        StickerSize::ALL
+            .iter()
+            .map(|size| {
+                CreateButton::new(format!("sticker_size:{}:{}", sticker_id, size.to_str()))
+                    .label(size.display_name())
+                    .style(if *size == current {
+                        ButtonStyle::Primary
+                    } else {
+                        ButtonStyle::Secondary
+                    })
+            })
+            .collect(),
+    )
+}
+
+/// Resize a sticker from the buttons under the "Sticker Added" message
+pub async fn handle_size_button(
+    handler: &Handler,
+    context: &Context,
+    interaction: &ComponentInteraction,
+) -> anyhow::Result<()> {
+    let Some((sticker_id, size)) = interaction
+        .data
+        .custom_id
+        .strip_prefix("sticker_size:")
+        .and_then(|data| data.split_once(':'))
+        .and_then(|(id, size)| Some((id.parse::<i64>().ok()?, StickerSize::from_str(size)?)))
+    else {
+        return Ok(());
+    };
+
+    let user_id = interaction.user.id.get();
+    let updated = handler
+        .database
+        .update_sticker_scale(user_id, sticker_id, size.scale())
+        .await?;
+
+    let message = if updated {
+        tracing::info!(
+            "[STICKER] User {} resized sticker {} to {}",
+            user_id,
+            sticker_id,
+            size.to_str()
+        );
+        let embed = CreateEmbed::new()
+            .title("Sticker Size Updated")
+            .description(format!(
+                "This sticker will now be drawn at **{}** size on your graphs.",
+                size.display_name()
+            ))
+            .color(Colour::DARK_GREEN);
+        CreateInteractionResponseMessage::new()
+            .embed(embed)
+            .components(vec![size_buttons(sticker_id, size)])
+    } else {
+        let embed = CreateEmbed::new()
+            .title("Sticker Not Found")
+            .description("This sticker was removed, so it can't be resized anymore.")
+            .color(Colour::RED);
+        CreateInteractionResponseMessage::new()
+            .embed(embed)
+            .components(vec![])
+    };
+
+    interaction
+        .create_response(
+            &context.http,
+            CreateInteractionResponse::UpdateMessage(message),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Describe when a sticker category shows, using the user's own target range
+fn category_condition(
+    category: StickerCategory,
+    target_low_mg: f32,
+    target_high_mg: f32,
+) -> String {
+    match category {
+        StickerCategory::Low => format!(
+            "low (<{:.0} mg/dL, {:.1} mmol/L)",
+            target_low_mg,
+            target_low_mg / 18.0
+        ),
+        StickerCategory::InRange => format!(
+            "in range ({:.0}-{:.0} mg/dL, {:.1}-{:.1} mmol/L)",
+            target_low_mg,
+            target_high_mg,
+            target_low_mg / 18.0,
+            target_high_mg / 18.0
+        ),
+        StickerCategory::High => format!(
+            "high (>{:.0} mg/dL, {:.1} mmol/L)",
+            target_high_mg,
+            target_high_mg / 18.0
+        ),
+        StickerCategory::Any => "in any state".to_string(),
+    }
+}
+
 fn extract_sticker_name(content: &str) -> Option<String> {
     let words: Vec<&str> = content.split_whitespace().collect();
 
@@ -0,0 +1,67 @@
+use crate::bot::Handler;
+use crate::bot::helpers::owner::is_owner;
+use serenity::all::{
+    Colour, CommandInteraction, Context, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage, InteractionContext, Permissions,
+};
+use serenity::builder::CreateCommand;
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    if !is_owner(interaction.user.id.get()) {
+        tracing::warn!(
+            "[ADMIN] User {} tried to use /admin-stats",
+            interaction.user.id.get()
+        );
+        crate::commands::error::run(
+            context,
+            interaction,
+            "This command is only available to the bot owner.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let (users, stickers, with_token) = tokio::try_join!(
+        handler.database.user_count(),
+        handler.database.count_stickers_total(),
+        handler.database.count_users_with_token(),
+    )?;
+    let average = if users > 0 {
+        stickers as f64 / users as f64
+    } else {
+        0.0
+    };
+
+    let embed = CreateEmbed::new()
+        .title("Admin Stats")
+        .field("Users", users.to_string(), true)
+        .field("Stickers", stickers.to_string(), true)
+        .field("Stickers per user", format!("{:.2}", average), true)
+        .field("Users with a token", with_token.to_string(), true)
+        .color(Colour::from_rgb(59, 130, 246));
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("admin-stats")
+        .description("Bot owner only: usage statistics")
+        // Hidden from members by default; the owner check in `run` is what actually gates it
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
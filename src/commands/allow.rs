@@ -2,48 +2,29 @@ use crate::bot::Handler;
 use serenity::all::{
     Colour, CommandInteraction, CommandOptionType, Context, CreateEmbed, CreateInteractionResponse,
     CreateInteractionResponseMessage, InteractionContext, ResolvedOption, ResolvedValue, User,
+    UserId,
 };
 use serenity::builder::{CreateCommand, CreateCommandOption};
 
+/// Allowed users shown by `/allow list` before the rest are summarised
+const MAX_LISTED_USERS: usize = 50;
+
 pub async fn run(
     handler: &Handler,
     context: &Context,
     interaction: &CommandInteraction,
 ) -> anyhow::Result<()> {
-    let mut target_user: Option<&User> = None;
-    let mut action = "add";
-
-    for option in &interaction.data.options() {
-        match option {
-            ResolvedOption {
-                name: "user",
-                value: ResolvedValue::User(user, _),
-                ..
-            } => {
-                target_user = Some(user);
-            }
-            ResolvedOption {
-                name: "action",
-                value: ResolvedValue::String(act),
-                ..
-            } => {
-                action = act;
-            }
-            _ => {}
-        }
-    }
-
-    let target_user = target_user.ok_or_else(|| anyhow::anyhow!("User parameter is required"))?;
-
-    if target_user.id.get() == interaction.user.id.get() {
-        crate::commands::error::run(
-            context,
-            interaction,
-            "You cannot add or remove yourself from your own allowed users list.",
-        )
-        .await?;
+    let options = interaction.data.options();
+    let Some(ResolvedOption {
+        name: subcommand,
+        value: ResolvedValue::SubCommand(sub_options),
+        ..
+    }) = options.first()
+    else {
+        crate::commands::error::run(context, interaction, "Please choose an /allow subcommand.")
+            .await?;
         return Ok(());
-    }
+    };
 
     if !handler
         .database
@@ -59,50 +40,77 @@ pub async fn run(
         return Ok(());
     }
 
-    let result = match action {
-        "add" => {
-            handler
-                .database
-                .add_allowed_user(interaction.user.id.get(), target_user.id.get())
-                .await
-        }
-        "remove" => {
-            handler
-                .database
-                .remove_allowed_user(interaction.user.id.get(), target_user.id.get())
-                .await
-        }
-        _ => {
-            crate::commands::error::run(
-                context,
-                interaction,
-                "Invalid action. Use 'add' or 'remove'.",
-            )
-            .await?;
-            return Ok(());
+    match *subcommand {
+        "add" | "remove" => {
+            update_allowed_user(handler, context, interaction, subcommand, sub_options).await
         }
+        "list" => list_allowed_users(handler, context, interaction).await,
+        "clear" => clear_allowed_users(handler, context, interaction).await,
+        _ => crate::commands::error::run(context, interaction, "Unknown /allow subcommand.").await,
+    }
+}
+
+async fn update_allowed_user(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+    action: &str,
+    options: &[ResolvedOption<'_>],
+) -> anyhow::Result<()> {
+    let target_user: &User = options
+        .iter()
+        .find_map(|option| match option {
+            ResolvedOption {
+                name: "user",
+                value: ResolvedValue::User(user, _),
+                ..
+            } => Some(*user),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow::anyhow!("User parameter is required"))?;
+
+    if target_user.id.get() == interaction.user.id.get() {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "You cannot add or remove yourself from your own allowed users list.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let result = if action == "add" {
+        handler
+            .database
+            .add_allowed_user(interaction.user.id.get(), target_user.id.get())
+            .await
+    } else {
+        handler
+            .database
+            .remove_allowed_user(interaction.user.id.get(), target_user.id.get())
+            .await
     };
 
     match result {
         Ok(true) => {
-            let (title, description, color) = match action {
-                "add" => (
+            let (title, description, color) = if action == "add" {
+                (
                     "User Added",
                     format!(
                         "{} has been added to your allowed users list. They can now view your blood glucose data.",
                         target_user.display_name()
                     ),
                     Colour::from_rgb(34, 197, 94),
-                ),
-                "remove" => (
+                )
+            } else {
+                (
                     "User Removed",
                     format!(
                         "{} has been removed from your allowed users list. They can no longer view your blood glucose data.",
                         target_user.display_name()
                     ),
                     Colour::from_rgb(249, 115, 22),
-                ),
-                _ => unreachable!(),
+                )
             };
 
             let embed = CreateEmbed::new()
@@ -119,16 +127,16 @@ pub async fn run(
                 .await?;
         }
         Ok(false) => {
-            let message = match action {
-                "add" => format!(
+            let message = if action == "add" {
+                format!(
                     "{} is already in your allowed users list.",
                     target_user.display_name()
-                ),
-                "remove" => format!(
+                )
+            } else {
+                format!(
                     "{} is not in your allowed users list.",
                     target_user.display_name()
-                ),
-                _ => unreachable!(),
+                )
             };
 
             crate::commands::error::run(context, interaction, &message).await?;
@@ -147,29 +155,161 @@ pub async fn run(
     Ok(())
 }
 
+async fn list_allowed_users(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let allowed_people = handler
+        .database
+        .get_allowed_people(interaction.user.id.get())
+        .await?;
+
+    let resolved = futures::future::join_all(allowed_people.iter().take(MAX_LISTED_USERS).map(
+        |id| async move {
+            let name = context
+                .http
+                .get_user(UserId::new(*id))
+                .await
+                .ok()
+                .map(|user| user.display_name().to_string());
+            (*id, name)
+        },
+    ))
+    .await;
+
+    let embed = CreateEmbed::new()
+        .title("Allowed Users")
+        .description(format_allowed_list(&resolved, allowed_people.len()))
+        .color(Colour::from_rgb(59, 130, 246));
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+async fn clear_allowed_users(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let removed = handler
+        .database
+        .clear_allowed(interaction.user.id.get())
+        .await?;
+
+    if removed == 0 {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "Your allowed users list is already empty.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    tracing::info!(
+        "[ALLOW] User {} cleared {} allowed users",
+        interaction.user.id.get(),
+        removed
+    );
+
+    let embed = CreateEmbed::new()
+        .title("Allowed Users Cleared")
+        .description(format!(
+            "Removed {} {} from your allowed users list. While your profile is private, only you can view your blood glucose data.",
+            removed,
+            if removed == 1 { "user" } else { "users" }
+        ))
+        .color(Colour::from_rgb(249, 115, 22));
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+/// One line per allowed user, with their display name when Discord could resolve it
+fn format_allowed_list(resolved: &[(u64, Option<String>)], total: usize) -> String {
+    if resolved.is_empty() {
+        return "Nobody is on your allowed users list. Add someone with `/allow add`.".to_string();
+    }
+
+    let mut lines: Vec<String> = resolved
+        .iter()
+        .map(|(id, name)| match name {
+            Some(name) => format!("• **{}** (<@{}>)", name, id),
+            None => format!("• <@{}>", id),
+        })
+        .collect();
+    if total > resolved.len() {
+        lines.push(format!("…and {} more", total - resolved.len()));
+    }
+    lines.join("\n")
+}
+
 pub fn register() -> CreateCommand {
+    let user_option = |description: &str| {
+        CreateCommandOption::new(CommandOptionType::User, "user", description).required(true)
+    };
+
     CreateCommand::new("allow")
         .description("Manage who can view your blood glucose data")
         .add_option(
             CreateCommandOption::new(
-                CommandOptionType::User,
-                "user",
-                "User to add or remove from your allowed list",
+                CommandOptionType::SubCommand,
+                "add",
+                "Let a user view your data while your profile is private",
             )
-            .required(true),
+            .add_sub_option(user_option("User to add to your allowed list")),
         )
         .add_option(
             CreateCommandOption::new(
-                CommandOptionType::String,
-                "action",
-                "Whether to add or remove the user",
+                CommandOptionType::SubCommand,
+                "remove",
+                "Stop a user from viewing your data",
             )
-            .add_string_choice("Add user", "add")
-            .add_string_choice("Remove user", "remove")
-            .required(false),
+            .add_sub_option(user_option("User to remove from your allowed list")),
         )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "Show everyone on your allowed list",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "clear",
+            "Remove everyone from your allowed list",
+        ))
         .contexts(vec![
             InteractionContext::Guild,
             InteractionContext::PrivateChannel,
         ])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_allowed_list() {
+        let resolved = vec![(1, Some("Ana".to_string())), (2, None)];
+        assert_eq!(
+            format_allowed_list(&resolved, 2),
+            "• **Ana** (<@1>)\n• <@2>"
+        );
+        assert!(format_allowed_list(&resolved, 5).ends_with("…and 3 more"));
+        assert!(format_allowed_list(&[], 0).contains("/allow add"));
+    }
+}
@@ -0,0 +1,191 @@
+use crate::bot::Handler;
+use anyhow::Context as AnyhowContext;
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateEmbed, CreateEmbedFooter,
+    CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext,
+    ResolvedOption, ResolvedValue,
+};
+use serenity::builder::{CreateCommand, CreateCommandOption};
+
+/// Most announcements listed in a single response
+const MAX_ANNOUNCEMENTS: usize = 10;
+const DEFAULT_HOURS: i64 = 24;
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let command_user_id = interaction.user.id.get();
+    let mut target_user_id = command_user_id;
+    let mut hours = DEFAULT_HOURS;
+
+    for option in &interaction.data.options() {
+        match option {
+            ResolvedOption {
+                name: "user",
+                value: ResolvedValue::User(user, _),
+                ..
+            } => target_user_id = user.id.get(),
+            ResolvedOption {
+                name: "hours",
+                value: ResolvedValue::Integer(h),
+                ..
+            } => hours = *h,
+            _ => {}
+        }
+    }
+
+    if !handler.database.user_exists(target_user_id).await? {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "The specified user hasn't set up their Nightscout data yet.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let target_user_data = handler.database.get_user_info(target_user_id).await?;
+
+    if !target_user_data
+        .nightscout
+        .can_view(target_user_id, command_user_id)
+    {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "This user's Nightscout data is set to private.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let base_url = target_user_data
+        .nightscout
+        .nightscout_url
+        .as_deref()
+        .context("Nightscout URL missing")?;
+
+    if base_url.trim().is_empty() {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "Your Nightscout URL is empty. Please run `/setup` to configure it properly.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let token = target_user_data.nightscout.nightscout_token.as_deref();
+
+    let now = chrono::Utc::now();
+    let start_time = (now - chrono::Duration::hours(hours)).to_rfc3339();
+    let end_time = now.to_rfc3339();
+
+    let (announcements, profile) = tokio::join!(
+        handler
+            .nightscout_client
+            .get_announcements(base_url, &start_time, &end_time, token),
+        handler.nightscout_client.get_profile(base_url, token),
+    );
+
+    let announcements = match announcements {
+        Ok(announcements) => announcements,
+        Err(e) => {
+            eprintln!(
+                "Failed to get announcements for user {}: {}",
+                target_user_id, e
+            );
+            crate::commands::error::run(
+                context,
+                interaction,
+                "Could not fetch announcements from Nightscout. Please check the URL configuration with `/setup`.",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let timezone = target_user_data.nightscout.effective_timezone(
+        profile
+            .as_ref()
+            .ok()
+            .and_then(|profile| profile.default_store()),
+    );
+
+    let description = if announcements.is_empty() {
+        format!("No announcements in the last {}h.", hours)
+    } else {
+        announcements
+            .iter()
+            .take(MAX_ANNOUNCEMENTS)
+            .map(|announcement| {
+                let time = announcement.millis_to_user_timezone(timezone);
+                let notes = announcement
+                    .notes
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|notes| !notes.is_empty())
+                    .unwrap_or("(no message)");
+                let author = announcement
+                    .entered_by
+                    .as_deref()
+                    .filter(|author| !author.is_empty())
+                    .map(|author| format!(" — {}", author))
+                    .unwrap_or_default();
+
+                format!(
+                    "**{}** {}\n-# {}{}",
+                    time.format("%a %H:%M"),
+                    notes,
+                    time.format("%Y-%m-%d"),
+                    author
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let mut footer = format!("Last {}h • {}", hours, timezone);
+    if announcements.len() > MAX_ANNOUNCEMENTS {
+        footer.push_str(&format!(
+            " • showing {} of {}",
+            MAX_ANNOUNCEMENTS,
+            announcements.len()
+        ));
+    }
+
+    let embed = CreateEmbed::new()
+        .title("Announcements")
+        .description(description)
+        .footer(CreateEmbedFooter::new(footer))
+        .color(Colour::from_rgb(59, 130, 246));
+
+    let response = CreateInteractionResponseMessage::new().embed(embed);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("announcements")
+        .description("Shows recent announcements posted on a Nightscout site.")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::User, "user", "Target user.")
+                .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Integer, "hours", "1h to 168h back.")
+                .min_int_value(1)
+                .max_int_value(168)
+                .required(false),
+        )
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
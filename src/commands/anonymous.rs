@@ -0,0 +1,79 @@
+use crate::bot::Handler;
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext,
+    ResolvedOption, ResolvedValue,
+};
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let user_id = interaction.user.id.get();
+
+    let enabled = interaction
+        .data
+        .options()
+        .iter()
+        .find_map(|option| match option {
+            ResolvedOption {
+                name: "enabled",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => Some(*val),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow::anyhow!("Enabled parameter is required"))?;
+
+    handler
+        .database
+        .update_anonymous_mode(user_id, enabled)
+        .await?;
+    tracing::info!(
+        "[ANONYMOUS] User {} turned anonymous mode {}",
+        user_id,
+        if enabled { "on" } else { "off" }
+    );
+
+    let embed = if enabled {
+        CreateEmbed::new()
+            .title("Anonymous Mode On")
+            .description(
+                "`/bg` no longer shows your avatar, name or Nightscout site title, and your graphs leave out notes and the watermark.\n\nUse `/anonymous enabled:False` to turn it off.",
+            )
+            .color(Colour::from_rgb(34, 197, 94))
+    } else {
+        CreateEmbed::new()
+            .title("Anonymous Mode Off")
+            .description("`/bg` and your graphs show your name, avatar and notes again.")
+            .color(Colour::from_rgb(34, 197, 94))
+    };
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("anonymous")
+        .description("Hide your name, avatar and notes when your data is shared")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "enabled",
+                "Turn anonymous mode on or off",
+            )
+            .required(true),
+        )
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
@@ -1,27 +1,55 @@
 use crate::bot::Handler;
+use crate::utils::glucose::{GlucoseStatus, GlucoseThresholds};
+use crate::utils::nightscout::{
+    BOLUS_EVENT_TYPES, CARB_EVENT_TYPES, TEMP_BASAL_EVENT_TYPES, Treatment, Trend,
+};
 use anyhow::Context as AnyhowContext;
+use chrono::Timelike;
 use serenity::all::{
     Colour, CommandInteraction, CommandOptionType, Context, CreateAttachment, CreateEmbed,
-    CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
-    InteractionContext, ResolvedOption, ResolvedValue,
+    CreateEmbedFooter, EditInteractionResponse, InteractionContext, ResolvedOption, ResolvedValue,
 };
 use serenity::builder::{CreateCommand, CreateCommandOption};
 use std::str::FromStr;
 
+/// IOB/COB snapshots at least this old get their age shown under the value
+const LOOP_AGE_SHOWN_FROM_MINUTES: i64 = 5;
+/// Crossing a target boundary further out than this isn't worth projecting from the slope
+const PROJECTION_HORIZON_MINUTES: f32 = 60.0;
+
 pub async fn run(
     handler: &Handler,
     context: &Context,
     interaction: &CommandInteraction,
 ) -> anyhow::Result<()> {
-    let target_user_id = if let Some(ResolvedOption {
-        value: ResolvedValue::User(user, _),
-        ..
-    }) = interaction.data.options().first()
-    {
-        user.id.get()
-    } else {
-        interaction.user.id.get()
-    };
+    // Nightscout can take longer than Discord's 3 second reply window, so acknowledge first
+    // and edit the reply in once the data is in
+    interaction.defer(&context.http).await?;
+
+    let mut target_user_id = interaction.user.id.get();
+    let mut delta_interval: Option<i64> = None;
+    let mut vs_yesterday = false;
+
+    for option in &interaction.data.options() {
+        match option {
+            ResolvedOption {
+                name: "user",
+                value: ResolvedValue::User(user, _),
+                ..
+            } => target_user_id = user.id.get(),
+            ResolvedOption {
+                name: "delta_interval",
+                value: ResolvedValue::Integer(minutes),
+                ..
+            } => delta_interval = Some(*minutes),
+            ResolvedOption {
+                name: "vs_yesterday",
+                value: ResolvedValue::Boolean(value),
+                ..
+            } => vs_yesterday = *value,
+            _ => {}
+        }
+    }
 
     let command_user_id = interaction.user.id.get();
 
@@ -37,19 +65,10 @@ pub async fn run(
 
     let target_user_data = handler.database.get_user_info(target_user_id).await?;
 
-    #[allow(clippy::if_same_then_else)]
-    let can_access = if target_user_id == command_user_id {
-        true
-    } else if !target_user_data.nightscout.is_private {
-        true
-    } else {
-        target_user_data
-            .nightscout
-            .allowed_people
-            .contains(&command_user_id)
-    };
-
-    if !can_access {
+    if !target_user_data
+        .nightscout
+        .can_view(target_user_id, command_user_id)
+    {
         crate::commands::error::run(
             context,
             interaction,
@@ -76,25 +95,97 @@ pub async fn run(
     }
 
     let token = target_user_data.nightscout.nightscout_token.as_deref();
-    let entry = match handler.nightscout_client.get_entry(base_url, token).await {
+
+    let now_utc = chrono::Utc::now();
+    let thirty_min_ago = now_utc - chrono::Duration::minutes(30);
+    let start_time = thirty_min_ago.to_rfc3339();
+    let end_time = now_utc.to_rfc3339();
+
+    // None of these depend on each other, so fetch them all at once
+    let (
+        entry,
+        status,
+        profile,
+        (pebble_data, delta),
+        recent_entries,
+        recent_treatments,
+        last_bolus,
+        last_carbs,
+        last_temp_basal,
+        device_status,
+        yesterday_entry,
+    ) = tokio::join!(
+        handler.nightscout_client.get_entry(base_url, token),
+        handler.nightscout_client.get_status(base_url, token),
+        handler.nightscout_client.get_profile(base_url, token),
+        async {
+            let pebble_data = handler
+                .nightscout_client
+                .get_pebble_data(base_url, token)
+                .await
+                .ok()
+                .flatten();
+            // The pebble endpoint already carries the site's delta, so only work it out from
+            // the entries when it doesn't
+            let delta = match pebble_data.as_ref().and_then(|pebble| pebble.delta()) {
+                Some(delta) => Ok(delta),
+                None => {
+                    handler
+                        .nightscout_client
+                        .get_current_delta(base_url, token)
+                        .await
+                }
+            };
+            (pebble_data, delta)
+        },
+        handler
+            .nightscout_client
+            .get_entries_for_hours(base_url, 1, token),
+        handler
+            .nightscout_client
+            .fetch_treatments_between(base_url, &start_time, &end_time, token),
+        handler
+            .nightscout_client
+            .get_latest_treatment(base_url, token, BOLUS_EVENT_TYPES),
+        handler
+            .nightscout_client
+            .get_latest_treatment(base_url, token, CARB_EVENT_TYPES),
+        handler
+            .nightscout_client
+            .get_latest_treatment(base_url, token, TEMP_BASAL_EVENT_TYPES),
+        handler
+            .nightscout_client
+            .get_latest_device_status(base_url, token),
+        async {
+            if !vs_yesterday {
+                return None;
+            }
+            handler
+                .nightscout_client
+                .get_entry_near(base_url, now_utc - chrono::Duration::days(1), token)
+                .await
+                .ok()
+                .flatten()
+        },
+    );
+
+    let entry = match entry {
         Ok(entry) => entry,
         Err(e) => {
             eprintln!("Failed to get entry for user {}: {}", target_user_id, e);
-            crate::commands::error::run(
+            crate::commands::error::nightscout(
                 context,
                 interaction,
-                "Could not connect to your Nightscout site. Please check your URL configuration with `/setup`.",
+                &e,
+                target_user_id == command_user_id,
             )
             .await?;
             return Ok(());
         }
     };
 
-    let delta = match handler
-        .nightscout_client
-        .get_current_delta(base_url, token)
-        .await
-    {
+    let status = status.ok();
+    let delta = match delta {
         Ok(delta) => delta,
         Err(e) => {
             eprintln!("Failed to get delta for user {}: {}", target_user_id, e);
@@ -102,13 +193,7 @@ pub async fn run(
         }
     };
 
-    let status = handler
-        .nightscout_client
-        .get_status(base_url, token)
-        .await
-        .ok();
-
-    let profile = match handler.nightscout_client.get_profile(base_url, token).await {
+    let profile = match profile {
         Ok(profile) => profile,
         Err(e) => {
             eprintln!("Failed to get profile for user {}: {}", target_user_id, e);
@@ -119,44 +204,24 @@ pub async fn run(
         }
     };
 
-    let pebble_data = handler
-        .nightscout_client
-        .get_pebble_data(base_url, token)
-        .await
-        .ok()
-        .flatten();
-
-    let now_utc = chrono::Utc::now();
-    let thirty_min_ago = now_utc - chrono::Duration::minutes(30);
-    let start_time = thirty_min_ago.to_rfc3339();
-    let end_time = now_utc.to_rfc3339();
+    let recent_entries = recent_entries.unwrap_or_default();
+    let recent_treatments = recent_treatments.unwrap_or_default();
 
-    let recent_entries = handler
-        .nightscout_client
-        .get_entries_for_hours(base_url, 1, token)
-        .await
-        .unwrap_or_default();
-
-    let recent_treatments = handler
-        .nightscout_client
-        .fetch_treatments_between(base_url, &start_time, &end_time, token)
-        .await
-        .unwrap_or_default();
-
-    let default_profile_name = &profile.default_profile;
     let profile_store = profile
-        .store
-        .get(default_profile_name)
-        .context("Default profile not found")?;
+        .default_store()
+        .context("Nightscout profile has no stores")?;
 
     let thresholds = status
         .as_ref()
         .and_then(|s| s.settings.as_ref())
         .and_then(|settings| settings.thresholds.as_ref());
 
-    let user_timezone = &profile_store.timezone;
-    let target_low_mg = profile_store.get_target_low_mg(thresholds);
-    let target_high_mg = profile_store.get_target_high_mg(thresholds);
+    let user_timezone = target_user_data
+        .nightscout
+        .effective_timezone(Some(profile_store));
+    let user_range_mg = target_user_data.nightscout.target_range_mg;
+    let target_low_mg = profile_store.get_target_low_mg(user_range_mg, thresholds);
+    let target_high_mg = profile_store.get_target_high_mg(user_range_mg, thresholds);
 
     let entry_time = entry.millis_to_user_timezone(user_timezone);
     let now = chrono::Utc::now()
@@ -171,27 +236,29 @@ pub async fn run(
         format!("{} days ago", duration.num_days())
     };
 
-    let color = if entry.sgv > target_high_mg {
-        Colour::from_rgb(227, 177, 11)
-    } else if entry.sgv < target_low_mg {
-        Colour::from_rgb(235, 47, 47)
-    } else {
-        Colour::from_rgb(87, 189, 79)
+    let color = match GlucoseThresholds::new(target_low_mg, target_high_mg).classify(entry.sgv) {
+        GlucoseStatus::High => Colour::from_rgb(227, 177, 11),
+        GlucoseStatus::UrgentLow | GlucoseStatus::Low => Colour::from_rgb(235, 47, 47),
+        GlucoseStatus::InRange => Colour::from_rgb(87, 189, 79),
     };
 
-    let target_user = context.http.get_user(target_user_id.into()).await.ok();
-    let thumbnail_url = target_user
-        .as_ref()
-        .and_then(|u| u.avatar_url())
-        .unwrap_or_default();
+    let anonymous = target_user_data.nightscout.anonymous_mode;
+    let target_user = if anonymous {
+        None
+    } else {
+        context.http.get_user(target_user_id.into()).await.ok()
+    };
+    let thumbnail_url = target_user.as_ref().and_then(|u| u.avatar_url());
 
     let custom_title = status
         .as_ref()
         .and_then(|s| s.settings.as_ref())
         .and_then(|settings| settings.custom_title.as_deref())
-        .filter(|title| *title != "Nightscout");
+        .filter(|title| *title != "Nightscout" && !anonymous);
 
-    let title = if let Some(custom) = custom_title {
+    let title = if anonymous {
+        "Nightscout data".to_string()
+    } else if let Some(custom) = custom_title {
         custom.to_string()
     } else {
         format!(
@@ -206,12 +273,39 @@ pub async fn run(
     let icon_bytes = std::fs::read("assets/images/nightscout_icon.png")?;
     let icon_attachment = CreateAttachment::bytes(icon_bytes, "nightscout_icon.png");
 
-    let mut embed = CreateEmbed::new()
-        .thumbnail(thumbnail_url)
-        .title(title)
-        .color(color);
+    let mut embed = CreateEmbed::new().title(title).color(color);
+    if let Some(thumbnail_url) = thumbnail_url {
+        embed = embed.thumbnail(thumbnail_url);
+    }
+
+    let stale_minutes = target_user_data.nightscout.stale_minutes;
+    let is_data_old = duration.num_minutes() > stale_minutes;
 
-    let is_data_old = duration.num_minutes() > 15;
+    let cleaned_recent = handler
+        .nightscout_client
+        .filter_and_clean_entries(&recent_entries, 1, user_timezone)
+        .unwrap_or_default();
+
+    // Longer intervals are measured from the newest cleaned reading; fall back to the
+    // reading-to-reading delta when there's no reading that far back
+    let (delta, delta_suffix) = match delta_interval.filter(|minutes| *minutes > 5) {
+        Some(minutes) => match cleaned_recent
+            .first()
+            .and_then(|newest| newest.delta_over(&cleaned_recent, minutes))
+        {
+            Some(interval_delta) => (interval_delta, format!(" over {}m", minutes)),
+            None => (delta, String::new()),
+        },
+        None => (delta, String::new()),
+    };
+
+    if let Some(yesterday_entry) = &yesterday_entry {
+        embed = embed.field(
+            "vs 24h ago",
+            yesterday_comparison(entry.sgv, yesterday_entry.sgv),
+            true,
+        );
+    }
 
     if is_data_old {
         embed = embed.field(
@@ -223,41 +317,153 @@ pub async fn run(
 
     let (mgdl_value, mmol_value) = if is_data_old {
         (
-            format!("~~{} ({})~~", entry.sgv, delta.as_signed_str()),
             format!(
-                "~~{} ({})~~",
+                "~~{} ({}{})~~",
+                entry.sgv,
+                delta.as_signed_str(),
+                delta_suffix
+            ),
+            format!(
+                "~~{} ({}{})~~",
                 entry.svg_as_mmol(),
-                delta.as_mmol().as_signed_str()
+                delta.as_mmol().as_signed_str(),
+                delta_suffix
             ),
         )
     } else {
         (
-            format!("{} ({})", entry.sgv, delta.as_signed_str()),
+            format!("{} ({}{})", entry.sgv, delta.as_signed_str(), delta_suffix),
             format!(
-                "{} ({})",
+                "{} ({}{})",
                 entry.svg_as_mmol(),
-                delta.as_mmol().as_signed_str()
+                delta.as_mmol().as_signed_str(),
+                delta_suffix
             ),
         )
     };
 
+    // Some uploaders never send a direction, so work it out from the two newest readings
+    let trend = if entry.direction.is_some() {
+        entry.trend()
+    } else {
+        match cleaned_recent.as_slice() {
+            [newest, previous, ..] => newest.trend_from_slope(previous),
+            _ => Trend::Else,
+        }
+    };
+
     embed = embed
         .field("mg/dL", mgdl_value, true)
         .field("mmol/L", mmol_value, true)
-        .field("Trend", entry.trend().as_arrow(), true);
+        .field("Trend", trend.as_arrow(), true);
+
+    let (mut iob, mut cob, mut loop_time) = pebble_data
+        .map(|pebble| {
+            (
+                pebble
+                    .iob
+                    .as_deref()
+                    .and_then(|iob| iob.parse::<f32>().ok()),
+                pebble.cob,
+                pebble.timestamp(),
+            )
+        })
+        .unwrap_or((None, None, None));
 
-    if let Some(pebble) = pebble_data {
-        if let Some(iob_str) = pebble.iob
-            && let Ok(iob) = iob_str.parse::<f32>()
-            && iob > 0.0
-        {
-            embed = embed.field("IOB", format!("{:.2}u", iob), true);
-        }
-        if let Some(cob) = pebble.cob
-            && cob > 0.0
+    let device_status = device_status.ok().flatten();
+
+    // Some deployments disable the pebble endpoint, so fall back to the loop's device status
+    if iob.is_none()
+        && cob.is_none()
+        && let Some(device_status) = &device_status
+    {
+        iob = device_status.iob();
+        cob = device_status.cob();
+        loop_time = device_status.timestamp();
+    }
+
+    // An old IOB/COB snapshot is misleading, so only show it while it's recent
+    let loop_age_minutes = loop_time.map(|time| minutes_between(time, chrono::Utc::now()));
+    if loop_age_minutes.is_some_and(|age| age > stale_minutes) {
+        tracing::debug!(
+            "[BG] Hiding IOB/COB for user {}: snapshot is {:?} minutes old",
+            target_user_id,
+            loop_age_minutes
+        );
+        iob = None;
+        cob = None;
+    }
+    let loop_age = loop_age_minutes
+        .filter(|age| *age >= LOOP_AGE_SHOWN_FROM_MINUTES)
+        .map(|age| format!("\n-# {}", format_ago(age)))
+        .unwrap_or_default();
+
+    if let Some(iob) = iob
+        && iob > 0.0
+    {
+        embed = embed.field("IOB", format!("{:.2}u{}", iob, loop_age), true);
+    }
+    if let Some(cob) = cob
+        && cob > 0.0
+    {
+        embed = embed.field("COB", format!("{:.0}g{}", cob, loop_age), true);
+    }
+
+    // Predictions from an old suggestion are worse than none
+    let eventual_bg = device_status
+        .as_ref()
+        .filter(|status| {
+            status
+                .timestamp()
+                .is_some_and(|time| minutes_between(time, chrono::Utc::now()) <= stale_minutes)
+        })
+        .and_then(|status| status.eventual_bg());
+    if let Some(eventual_bg) = eventual_bg {
+        let mut value = format!("{:.0} ({:.1})", eventual_bg, eventual_bg / 18.0);
+        if let [newest, previous, ..] = cleaned_recent.as_slice()
+            && let Some(slope) = newest.slope_per_minute(previous)
+            && let Some(projection) =
+                time_to_threshold(newest.sgv, slope, target_low_mg, target_high_mg)
         {
-            embed = embed.field("COB", format!("{:.0}g", cob), true);
+            value.push_str(&format!("\n-# {}", projection));
         }
+        embed = embed.field("Eventual BG", value, true);
+    }
+
+    let scheduled_basal = profile_store.basal_at(now.num_seconds_from_midnight());
+    let temp_basal = last_temp_basal.ok().flatten().and_then(|temp| {
+        let minutes_left = temp.temp_basal_minutes_left(now_utc)?;
+        Some((temp.temp_basal_rate(scheduled_basal)?, minutes_left))
+    });
+    if let Some(basal) = basal_summary(scheduled_basal, temp_basal) {
+        embed = embed.field("Basal", basal, true);
+    }
+
+    let last_bolus = last_bolus
+        .ok()
+        .flatten()
+        .filter(Treatment::is_insulin)
+        .map(|bolus| {
+            format!(
+                "💉 {:.2}u {}",
+                bolus.insulin_amount().unwrap_or_default(),
+                format_ago(minutes_since(&bolus))
+            )
+        });
+    let last_carbs = last_carbs
+        .ok()
+        .flatten()
+        .filter(Treatment::is_carbs)
+        .map(|carbs| {
+            format!(
+                "🍞 {:.0}g {}",
+                carbs.carbs.unwrap_or_default(),
+                format_ago(minutes_since(&carbs))
+            )
+        });
+    let last_treatments: Vec<String> = last_bolus.into_iter().chain(last_carbs).collect();
+    if !last_treatments.is_empty() {
+        embed = embed.field("Last treatments", last_treatments.join("\n"), true);
     }
 
     let mut fingerprick_value: Option<(f32, u64)> = None;
@@ -265,7 +471,7 @@ pub async fn run(
 
     for entry in recent_entries.iter() {
         if entry.has_mbg()
-            && let Some(entry_time_millis) = entry.date
+            && let Some(entry_time_millis) = entry.timestamp_millis()
             && entry_time_millis >= thirty_min_ago_millis
             && let Some(mbg) = entry.mbg
         {
@@ -276,19 +482,10 @@ pub async fn run(
 
     if fingerprick_value.is_none() {
         for treatment in recent_treatments.iter() {
-            if treatment.event_type.as_deref() == Some("BG Check")
-                && let Some(glucose_str) = &treatment.glucose
-                && let Ok(glucose) = glucose_str.parse::<f32>()
+            if treatment.is_bg_check()
+                && let Some(glucose) = treatment.glucose_value()
             {
-                let treatment_time_millis = if let Some(time) = treatment.date.or(treatment.mills) {
-                    time
-                } else if let Some(created_at) = &treatment.created_at {
-                    if let Ok(parsed_time) = chrono::DateTime::parse_from_rfc3339(created_at) {
-                        parsed_time.timestamp_millis() as u64
-                    } else {
-                        continue;
-                    }
-                } else {
+                let Some(treatment_time_millis) = treatment.timestamp_millis() else {
                     continue;
                 };
 
@@ -339,17 +536,93 @@ pub async fn run(
             .icon_url("attachment://nightscout_icon.png"),
     );
 
-    let message = CreateInteractionResponseMessage::new()
+    let message = EditInteractionResponse::new()
         .add_embed(embed)
-        .add_file(icon_attachment);
+        .new_attachment(icon_attachment);
 
-    interaction
-        .create_response(&context.http, CreateInteractionResponse::Message(message))
-        .await?;
+    interaction.edit_response(&context.http, message).await?;
 
     Ok(())
 }
 
+/// Rough time until a reading at `sgv`, changing by `slope` mg/dL per minute, leaves the
+/// target range, if it's heading out within the projection horizon
+fn time_to_threshold(
+    sgv: f32,
+    slope: f32,
+    target_low_mg: f32,
+    target_high_mg: f32,
+) -> Option<String> {
+    let (label, distance) = if slope < 0.0 && sgv > target_low_mg {
+        ("Low", sgv - target_low_mg)
+    } else if slope > 0.0 && sgv < target_high_mg {
+        ("High", target_high_mg - sgv)
+    } else {
+        return None;
+    };
+
+    let minutes = distance / slope.abs();
+    (minutes <= PROJECTION_HORIZON_MINUTES)
+        .then(|| format!("{} in ~{:.0}m", label, minutes.max(1.0)))
+}
+
+fn minutes_since(treatment: &Treatment) -> i64 {
+    minutes_between(
+        treatment.millis_to_user_timezone("UTC").to_utc(),
+        chrono::Utc::now(),
+    )
+}
+
+fn minutes_between(then: chrono::DateTime<chrono::Utc>, now: chrono::DateTime<chrono::Utc>) -> i64 {
+    now.signed_duration_since(then).num_minutes().max(0)
+}
+
+/// The scheduled basal rate, with any running temp basal as a share of it and the minutes
+/// it has left
+fn basal_summary(scheduled: Option<f32>, temp: Option<(f32, i64)>) -> Option<String> {
+    match (scheduled, temp) {
+        (Some(scheduled), Some((rate, minutes_left))) if scheduled > 0.0 => Some(format!(
+            "{:.2} U/hr (temp {:.0}% {}m left)",
+            scheduled,
+            rate / scheduled * 100.0,
+            minutes_left
+        )),
+        (_, Some((rate, minutes_left))) => {
+            Some(format!("temp {:.2} U/hr ({}m left)", rate, minutes_left))
+        }
+        (Some(scheduled), None) => Some(format!("{:.2} U/hr", scheduled)),
+        (None, None) => None,
+    }
+}
+
+/// Yesterday's reading and how far today's differs from it, in both units
+fn yesterday_comparison(now_sgv: f32, yesterday_sgv: f32) -> String {
+    let difference = crate::utils::nightscout::Delta {
+        value: now_sgv - yesterday_sgv,
+    };
+    format!(
+        "{:.0} ({:.1})\n-# now {} ({})",
+        yesterday_sgv,
+        yesterday_sgv / 18.0,
+        difference.as_signed_str(),
+        difference.as_mmol().as_signed_str()
+    )
+}
+
+/// Compact relative time such as `42m ago` or `3h 5m ago`
+pub fn format_ago(minutes: i64) -> String {
+    if minutes < 60 {
+        format!("{}m ago", minutes)
+    } else if minutes < 24 * 60 {
+        match minutes % 60 {
+            0 => format!("{}h ago", minutes / 60),
+            rest => format!("{}h {}m ago", minutes / 60, rest),
+        }
+    } else {
+        format!("{}d ago", minutes / (24 * 60))
+    }
+}
+
 pub fn register() -> CreateCommand {
     CreateCommand::new("bg")
         .description("Sends your current blood glucose value.")
@@ -357,8 +630,82 @@ pub fn register() -> CreateCommand {
             CreateCommandOption::new(CommandOptionType::User, "user", "Target user.")
                 .required(false),
         )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "delta_interval",
+                "Show the change over a longer period than the last reading.",
+            )
+            .add_int_choice("5 minutes", 5)
+            .add_int_choice("15 minutes", 15)
+            .add_int_choice("30 minutes", 30)
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "vs_yesterday",
+                "Also show the reading from this time yesterday.",
+            )
+            .required(false),
+        )
         .contexts(vec![
             InteractionContext::Guild,
             InteractionContext::PrivateChannel,
         ])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_to_threshold() {
+        assert_eq!(
+            time_to_threshold(100.0, -1.2, 70.0, 180.0).as_deref(),
+            Some("Low in ~25m")
+        );
+        assert_eq!(
+            time_to_threshold(160.0, 2.0, 70.0, 180.0).as_deref(),
+            Some("High in ~10m")
+        );
+        // Too slow to matter within the hour, or already out of range
+        assert_eq!(time_to_threshold(150.0, -0.5, 70.0, 180.0), None);
+        assert_eq!(time_to_threshold(60.0, -1.0, 70.0, 180.0), None);
+        assert_eq!(time_to_threshold(120.0, 0.0, 70.0, 180.0), None);
+    }
+
+    #[test]
+    fn test_yesterday_comparison() {
+        assert_eq!(
+            yesterday_comparison(142.0, 130.0),
+            "130 (7.2)\n-# now +12 (+0.7)"
+        );
+        assert_eq!(
+            yesterday_comparison(100.0, 154.0),
+            "154 (8.6)\n-# now -54 (-3)"
+        );
+    }
+
+    #[test]
+    fn test_basal_summary() {
+        assert_eq!(
+            basal_summary(Some(0.75), Some((1.125, 20))).as_deref(),
+            Some("0.75 U/hr (temp 150% 20m left)")
+        );
+        assert_eq!(
+            basal_summary(None, Some((1.2, 5))).as_deref(),
+            Some("temp 1.20 U/hr (5m left)")
+        );
+        assert_eq!(basal_summary(Some(0.6), None).as_deref(), Some("0.60 U/hr"));
+        assert_eq!(basal_summary(None, None), None);
+    }
+
+    #[test]
+    fn test_format_ago() {
+        assert_eq!(format_ago(42), "42m ago");
+        assert_eq!(format_ago(120), "2h ago");
+        assert_eq!(format_ago(185), "3h 5m ago");
+        assert_eq!(format_ago(3 * 24 * 60 + 10), "3d ago");
+    }
+}
@@ -1,26 +1,99 @@
 use crate::bot::Handler;
+use crate::utils::stats::{a1c_ifcc_mmol_mol, estimated_a1c_percent};
 use serenity::all::{
     Colour, CommandInteraction, CommandOptionType, Context, CreateEmbed, CreateInteractionResponse,
     CreateInteractionResponseMessage, InteractionContext, ResolvedOption, ResolvedValue,
 };
 use serenity::builder::{CreateCommand, CreateCommandOption};
 
+/// With auto-detection, values below this are read as mmol/L and the rest as mg/dL
+const MMOL_INPUT_CEILING: f64 = 30.0;
+/// Maximum number of values converted in a single command
+const MAX_VALUES: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GlucoseUnit {
+    MgDl,
+    Mmol,
+}
+
+impl GlucoseUnit {
+    fn label(self) -> &'static str {
+        match self {
+            GlucoseUnit::MgDl => "mg/dL",
+            GlucoseUnit::Mmol => "mmol/L",
+        }
+    }
+
+    /// Guess the unit of a single value from its magnitude
+    fn detect(value: f64) -> Self {
+        if value < MMOL_INPUT_CEILING {
+            GlucoseUnit::Mmol
+        } else {
+            GlucoseUnit::MgDl
+        }
+    }
+
+    /// Convert a value in this unit to the other one
+    fn convert(self, value: f64) -> (f64, GlucoseUnit) {
+        match self {
+            GlucoseUnit::MgDl => (value / 18.0, GlucoseUnit::Mmol),
+            GlucoseUnit::Mmol => (value * 18.0, GlucoseUnit::MgDl),
+        }
+    }
+}
+
+/// Parse a comma and/or whitespace separated list of positive numbers
+fn parse_values(input: &str) -> Result<Vec<f64>, String> {
+    let values = input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| match part.parse::<f64>() {
+            Ok(value) if value.is_finite() && value > 0.0 => Ok(value),
+            _ => Err(format!("`{}` is not a valid glucose value.", part)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if values.is_empty() {
+        return Err("Please provide a value to convert.".to_string());
+    }
+    if values.len() > MAX_VALUES {
+        return Err(format!(
+            "You can convert up to {} values at once.",
+            MAX_VALUES
+        ));
+    }
+
+    Ok(values)
+}
+
+/// Estimated A1c for an average glucose in mg/dL, in both NGSP % and IFCC mmol/mol
+fn a1c_text(mean_mg: f64) -> String {
+    let percent = estimated_a1c_percent(mean_mg as f32);
+    format!(
+        "A1c {:.1}% ({:.0} mmol/mol)",
+        percent,
+        a1c_ifcc_mmol_mol(percent)
+    )
+}
+
 pub async fn run(
     _handler: &Handler,
     context: &Context,
     interaction: &CommandInteraction,
 ) -> anyhow::Result<()> {
     let mut unit: Option<String> = None;
-    let mut value: Option<f64> = None;
+    let mut input: Option<String> = None;
+    let mut as_average = false;
 
     for option in &interaction.data.options() {
         match option {
             ResolvedOption {
                 name: "value",
-                value: ResolvedValue::Number(v),
+                value: ResolvedValue::String(v),
                 ..
             } => {
-                value = Some(*v);
+                input = Some(v.to_string());
             }
             ResolvedOption {
                 name: "unit",
@@ -29,42 +102,99 @@ pub async fn run(
             } => {
                 unit = Some(u.to_string());
             }
+            ResolvedOption {
+                name: "as_average",
+                value: ResolvedValue::Boolean(a),
+                ..
+            } => {
+                as_average = *a;
+            }
             _ => {}
         }
     }
 
-    let value = if let Some(v) = value {
-        v
-    } else {
-        crate::commands::error::run(context, interaction, "Please provide a value to convert.")
-            .await?;
-        return Ok(());
-    };
-
-    let unit = if let Some(u) = unit {
-        u
-    } else {
-        crate::commands::error::run(context, interaction, "Please specify the unit.").await?;
-        return Ok(());
+    let values = match parse_values(input.as_deref().unwrap_or_default()) {
+        Ok(values) => values,
+        Err(message) => {
+            crate::commands::error::run(context, interaction, &message).await?;
+            return Ok(());
+        }
     };
 
-    let (result, from_unit, to_unit) = match unit.as_str() {
-        "mgdl_to_mmol" => (value / 18.0, "mg/dL", "mmol/L"),
-        "mmol_to_mgdl" => (value * 18.0, "mmol/L", "mg/dL"),
+    let fixed_unit = match unit.as_deref().unwrap_or("auto") {
+        "mgdl_to_mmol" => Some(GlucoseUnit::MgDl),
+        "mmol_to_mgdl" => Some(GlucoseUnit::Mmol),
+        "auto" => None,
         _ => {
             crate::commands::error::run(context, interaction, "Invalid conversion type.").await?;
             return Ok(());
         }
     };
 
-    let embed = CreateEmbed::new()
-        .title("Blood Glucose Conversion")
-        .description(format!(
+    let conversions: Vec<(f64, GlucoseUnit, f64, GlucoseUnit)> = values
+        .into_iter()
+        .map(|value| {
+            let from = fixed_unit.unwrap_or_else(|| GlucoseUnit::detect(value));
+            let (result, to) = from.convert(value);
+            (value, from, result, to)
+        })
+        .collect();
+
+    // The value in mg/dL, whichever side of the conversion it's on
+    let mean_mg = |value: f64, from: GlucoseUnit, result: f64| match from {
+        GlucoseUnit::MgDl => value,
+        GlucoseUnit::Mmol => result,
+    };
+
+    let description = if let [(value, from, result, to)] = conversions.as_slice() {
+        let mut description = format!(
             "**{:.1} {}** = **{:.1} {}**",
-            value, from_unit, result, to_unit
-        ))
+            value,
+            from.label(),
+            result,
+            to.label()
+        );
+        if as_average {
+            description.push_str(&format!(
+                "\nAs an average: estimated {}",
+                a1c_text(mean_mg(*value, *from, *result))
+            ));
+        }
+        description
+    } else {
+        let rows = conversions
+            .iter()
+            .map(|(value, from, result, to)| {
+                let row = format!(
+                    "{:>7.1} {:<6} = {:>7.1} {}",
+                    value,
+                    from.label(),
+                    result,
+                    to.label()
+                );
+                if as_average {
+                    format!("{:<31} {}", row, a1c_text(mean_mg(*value, *from, *result)))
+                } else {
+                    row
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!("```\n{}\n```", rows)
+    };
+
+    let mut embed = CreateEmbed::new()
+        .title("Blood Glucose Conversion")
+        .description(description)
         .color(Colour::BLUE);
 
+    if fixed_unit.is_none() {
+        embed = embed.footer(serenity::all::CreateEmbedFooter::new(format!(
+            "Units auto-detected: values under {} are read as mmol/L",
+            MMOL_INPUT_CEILING
+        )));
+    }
+
     let response = CreateInteractionResponseMessage::new()
         .embed(embed)
         .ephemeral(true);
@@ -81,24 +211,65 @@ pub fn register() -> CreateCommand {
         .description("Convert blood glucose units between mg/dL and mmol/L")
         .add_option(
             CreateCommandOption::new(
-                CommandOptionType::Number,
+                CommandOptionType::String,
                 "value",
-                "The glucose value to convert",
+                "One or more glucose values, e.g. 100 120 7.2",
             )
+            .max_length(200)
             .required(true),
         )
         .add_option(
             CreateCommandOption::new(
                 CommandOptionType::String,
                 "unit",
-                "Choose the conversion type",
+                "Choose the conversion type (auto-detects by default)",
             )
-            .required(true)
+            .required(false)
+            .add_string_choice("auto-detect", "auto")
             .add_string_choice("to mmol/L", "mgdl_to_mmol")
             .add_string_choice("to mg/dL", "mmol_to_mgdl"),
         )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "as_average",
+                "Treat the values as average glucose and also show the estimated A1c",
+            )
+            .required(false),
+        )
         .contexts(vec![
             InteractionContext::Guild,
             InteractionContext::PrivateChannel,
         ])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_unit_by_magnitude() {
+        assert_eq!(GlucoseUnit::detect(7.2), GlucoseUnit::Mmol);
+        assert_eq!(GlucoseUnit::detect(29.9), GlucoseUnit::Mmol);
+        assert_eq!(GlucoseUnit::detect(30.0), GlucoseUnit::MgDl);
+        assert_eq!(GlucoseUnit::detect(120.0), GlucoseUnit::MgDl);
+    }
+
+    #[test]
+    fn test_a1c_text_for_an_average() {
+        assert_eq!(a1c_text(154.0), "A1c 7.0% (53 mmol/mol)");
+        assert_eq!(a1c_text(126.0), "A1c 6.0% (42 mmol/mol)");
+    }
+
+    #[test]
+    fn test_parse_values_accepts_commas_and_spaces() {
+        assert_eq!(
+            parse_values("100, 120 7.2,,  5"),
+            Ok(vec![100.0, 120.0, 7.2, 5.0])
+        );
+        assert!(parse_values("100 abc").is_err());
+        assert!(parse_values("-4").is_err());
+        assert!(parse_values(" , ").is_err());
+        assert!(parse_values(&"100 ".repeat(MAX_VALUES + 1)).is_err());
+    }
+}
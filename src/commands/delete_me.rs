@@ -0,0 +1,115 @@
+use crate::bot::Handler;
+use serenity::all::{
+    ButtonStyle, Colour, CommandInteraction, ComponentInteraction, Context, CreateActionRow,
+    CreateButton, CreateCommand, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage, InteractionContext,
+};
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    if !handler
+        .database
+        .user_exists(interaction.user.id.get())
+        .await?
+    {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "Beetroot doesn't have any data stored for you, so there is nothing to delete.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let embed = CreateEmbed::new()
+        .title("Delete All Your Data?")
+        .description(
+            "This permanently removes your Nightscout URL and token, privacy settings, allowed users, graph settings, stickers and reminders.\n\n**This cannot be undone.** Use `/export` first if you want a copy.",
+        )
+        .color(Colour::RED);
+
+    let buttons = CreateActionRow::Buttons(vec![
+        CreateButton::new("delete_me_confirm")
+            .label("Delete everything")
+            .style(ButtonStyle::Danger),
+        CreateButton::new("delete_me_cancel")
+            .label("Cancel")
+            .style(ButtonStyle::Secondary),
+    ]);
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .components(vec![buttons])
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn handle_button(
+    handler: &Handler,
+    context: &Context,
+    interaction: &ComponentInteraction,
+) -> anyhow::Result<()> {
+    let user_id = interaction.user.id.get();
+
+    let embed = match interaction.data.custom_id.as_str() {
+        "delete_me_confirm" => {
+            if !handler.database.user_exists(user_id).await? {
+                CreateEmbed::new()
+                    .title("Nothing to Delete")
+                    .description("Your data has already been deleted.")
+                    .color(Colour::ORANGE)
+            } else {
+                match handler.database.delete_user(user_id).await {
+                    Ok(_) => {
+                        tracing::info!("[DELETE] User {} deleted all their data", user_id);
+                        CreateEmbed::new()
+                            .title("Data Deleted")
+                            .description(
+                                "All of your data has been removed from Beetroot. Run `/setup` any time to start again.",
+                            )
+                            .color(Colour::from_rgb(34, 197, 94))
+                    }
+                    Err(e) => {
+                        tracing::error!("[DELETE] Failed to delete user {}: {}", user_id, e);
+                        CreateEmbed::new()
+                            .title("Error")
+                            .description("Failed to delete your data. Please try again later.")
+                            .color(Colour::RED)
+                    }
+                }
+            }
+        }
+        "delete_me_cancel" => CreateEmbed::new()
+            .title("Deletion Cancelled")
+            .description("Your data has not been changed.")
+            .color(Colour::from_rgb(59, 130, 246)),
+        _ => return Ok(()),
+    };
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .components(vec![]);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::UpdateMessage(response))
+        .await?;
+
+    Ok(())
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("delete-me")
+        .description("Permanently delete all data Beetroot stores about you")
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
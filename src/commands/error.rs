@@ -1,6 +1,7 @@
+use crate::utils::nightscout::NightscoutError;
 use serenity::all::{
     Colour, CommandInteraction, Context, CreateEmbed, CreateInteractionResponse,
-    CreateInteractionResponseMessage,
+    CreateInteractionResponseMessage, EditInteractionResponse,
 };
 
 pub async fn run(
@@ -13,15 +14,202 @@ pub async fn run(
         .description(error_message)
         .color(Colour::RED);
 
+    send(context, interaction, embed).await
+}
+
+/// Explain a failed Nightscout request, telling a rejected token apart from an unreachable
+/// site or a site that simply has no recent readings
+pub async fn nightscout(
+    context: &Context,
+    interaction: &CommandInteraction,
+    error: &NightscoutError,
+    own_site: bool,
+) -> anyhow::Result<()> {
+    send(context, interaction, nightscout_embed(error, own_site)).await
+}
+
+/// The embed [`nightscout`] sends, for responses that don't go through a command interaction
+pub fn nightscout_embed(error: &NightscoutError, own_site: bool) -> CreateEmbed {
+    let (title, description, colour) = describe_nightscout_error(error, own_site);
+    CreateEmbed::new()
+        .title(title)
+        .description(description)
+        .color(colour)
+}
+
+fn describe_nightscout_error(
+    error: &NightscoutError,
+    own_site: bool,
+) -> (&'static str, String, Colour) {
+    let site = if own_site {
+        "your Nightscout site"
+    } else {
+        "this user's Nightscout site"
+    };
+
+    match error {
+        NightscoutError::InvalidToken => access_denied(site, own_site),
+        NightscoutError::Network(e)
+            if e.status().is_some_and(|status| {
+                status == reqwest::StatusCode::UNAUTHORIZED
+                    || status == reqwest::StatusCode::FORBIDDEN
+            }) =>
+        {
+            access_denied(site, own_site)
+        }
+        NightscoutError::Network(e) if e.status().is_some_and(|s| s.is_client_error()) => (
+            "Nightscout Request Failed",
+            format!(
+                "{} answered with {}.{}",
+                capitalize(site),
+                e.status().map(|s| s.to_string()).unwrap_or_default(),
+                if own_site {
+                    " Check your URL with `/get-nightscout-url` and update it with `/set-nightscout-url` if needed."
+                } else {
+                    ""
+                }
+            ),
+            Colour::RED,
+        ),
+        NightscoutError::Network(_) => (
+            "Nightscout Unreachable",
+            format!(
+                "Couldn't reach {}. It may be down or restarting, try again in a few minutes.{}",
+                site,
+                if own_site {
+                    " If it keeps happening, check your URL with `/get-nightscout-url`."
+                } else {
+                    ""
+                }
+            ),
+            Colour::RED,
+        ),
+        NightscoutError::NoEntries => (
+            "No Recent Data",
+            format!(
+                "{} is reachable but has no recent glucose readings. Check that your uploader is running.",
+                capitalize(site)
+            ),
+            Colour::from_rgb(234, 179, 8),
+        ),
+        NightscoutError::Url(_) => (
+            "Invalid Nightscout URL",
+            if own_site {
+                "Your Nightscout URL isn't valid. Update it with `/set-nightscout-url`.".to_string()
+            } else {
+                "This user's Nightscout URL isn't valid.".to_string()
+            },
+            Colour::RED,
+        ),
+        NightscoutError::MissingData | NightscoutError::Json(_) => (
+            "Unexpected Response",
+            format!(
+                "{} sent data Beetroot couldn't read. Make sure the URL points to a Nightscout site.",
+                capitalize(site)
+            ),
+            Colour::RED,
+        ),
+    }
+}
+
+fn access_denied(site: &str, own_site: bool) -> (&'static str, String, Colour) {
+    (
+        "Access Denied",
+        format!(
+            "{} rejected the access token.{}",
+            capitalize(site),
+            if own_site {
+                " Check your token with `/set-token`, or create a new one in Nightscout's Admin Tools with the `readable` role."
+            } else {
+                ""
+            }
+        ),
+        Colour::from_rgb(249, 115, 22),
+    )
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+async fn send(
+    context: &Context,
+    interaction: &CommandInteraction,
+    embed: CreateEmbed,
+) -> anyhow::Result<()> {
     let message = CreateInteractionResponseMessage::new()
-        .embed(embed)
+        .embed(embed.clone())
         .ephemeral(true);
 
     let builder = CreateInteractionResponse::Message(message);
-    interaction
+    if interaction
         .create_response(&context.http, builder)
         .await
+        .is_ok()
+    {
+        return Ok(());
+    }
+
+    // Slow commands defer before fetching anything, so the error replaces the loading message
+    interaction
+        .edit_response(&context.http, EditInteractionResponse::new().embed(embed))
+        .await
         .map_err(|e| anyhow::anyhow!("Failed to send error response: {}", e))?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nightscout_errors_get_distinct_titles() {
+        let (no_data, ..) = describe_nightscout_error(&NightscoutError::NoEntries, true);
+        let (bad_json, ..) = describe_nightscout_error(
+            &NightscoutError::Json(serde_json::from_str::<u8>("x").unwrap_err()),
+            true,
+        );
+        let (bad_url, description, _) = describe_nightscout_error(
+            &NightscoutError::Url(url::Url::parse("not a url").unwrap_err()),
+            false,
+        );
+
+        assert_eq!(no_data, "No Recent Data");
+        assert_eq!(bad_json, "Unexpected Response");
+        assert_eq!(bad_url, "Invalid Nightscout URL");
+        assert_eq!(
+            describe_nightscout_error(&NightscoutError::InvalidToken, true).0,
+            "Access Denied"
+        );
+        assert!(!description.contains("/set-nightscout-url"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_token_is_reported_as_auth_failure() {
+        use httpmock::prelude::*;
+
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/entries.json");
+                then.status(401);
+            })
+            .await;
+
+        let error = reqwest::get(server.url("/api/v1/entries.json"))
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap_err();
+        let (title, description, _) =
+            describe_nightscout_error(&NightscoutError::Network(error), true);
+
+        assert_eq!(title, "Access Denied");
+        assert!(description.contains("/set-token"));
+    }
+}
@@ -0,0 +1,47 @@
+use crate::bot::Handler;
+use serenity::all::{
+    Colour, CommandInteraction, Context, CreateAttachment, CreateCommand, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext,
+};
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let user_id = interaction.user.id.get();
+    let export = handler.database.export_user(user_id).await?;
+    let json = serde_json::to_string_pretty(&export)?;
+
+    tracing::info!("[EXPORT] User {} exported their stored data", user_id);
+
+    let embed = CreateEmbed::new()
+        .title("Your Beetroot Data")
+        .description(
+            "Attached is everything Beetroot stores about you as JSON.\n\nYour Nightscout token is never included in exports.",
+        )
+        .color(Colour::from_rgb(34, 197, 94));
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .add_file(CreateAttachment::bytes(
+            json.into_bytes(),
+            "beetroot-export.json",
+        ))
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("export")
+        .description("Download all data Beetroot stores about you as JSON")
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
@@ -1,18 +1,44 @@
 use crate::bot::Handler;
-use crate::utils::graph::draw_graph;
+use crate::utils::database::UserInfo;
+use crate::utils::graph::{
+    GraphOutput, GraphWindow, HiddenOverlays, draw_agp, draw_graph, draw_graph_svg, entries_to_csv,
+};
+use crate::utils::nightscout::{NightscoutError, NightscoutRequestOptions};
+use chrono::{NaiveDate, Utc};
+use chrono_tz::Tz;
 use serenity::all::{
-    CommandInteraction, CommandOptionType, Context, CreateInteractionResponse,
-    CreateInteractionResponseMessage, InteractionContext, ResolvedOption, ResolvedValue, User,
+    CommandInteraction, CommandOptionType, Context, EditInteractionResponse, InteractionContext,
+    ResolvedOption, ResolvedValue, User,
 };
 use serenity::builder::{CreateAttachment, CreateCommand, CreateCommandOption};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+pub const MIN_GRAPH_HOURS: i64 = 3;
+pub const MAX_GRAPH_HOURS: i64 = 72;
+const MIN_AGP_DAYS: i64 = 3;
+const MAX_AGP_DAYS: i64 = 30;
+const DEFAULT_AGP_DAYS: i64 = 14;
+/// Readings per day at the usual 5 minute CGM interval
+const READINGS_PER_DAY: i64 = 288;
 
 pub async fn run(
     handler: &Handler,
     context: &Context,
     interaction: &CommandInteraction,
 ) -> anyhow::Result<()> {
-    let mut hours = 3_i64;
+    // Nightscout can take longer than Discord's 3 second reply window, so acknowledge first
+    // and edit the reply in once the data is in
+    interaction.defer(&context.http).await?;
+
+    let mut hours: Option<i64> = None;
     let mut target_user: Option<&User> = None;
+    let mut export = false;
+    let mut svg = false;
+    let mut vs_yesterday = false;
+    let mut day: Option<&str> = None;
+    let mut hidden = HiddenOverlays::default();
+    let mut agp = false;
+    let mut days = DEFAULT_AGP_DAYS;
 
     for option in &interaction.data.options() {
         match option {
@@ -21,7 +47,7 @@ pub async fn run(
                 value: ResolvedValue::Integer(h),
                 ..
             } => {
-                hours = *h;
+                hours = Some(*h);
             }
             ResolvedOption {
                 name: "user",
@@ -30,6 +56,76 @@ pub async fn run(
             } => {
                 target_user = Some(user);
             }
+            ResolvedOption {
+                name: "export",
+                value: ResolvedValue::Boolean(e),
+                ..
+            } => {
+                export = *e;
+            }
+            ResolvedOption {
+                name: "svg",
+                value: ResolvedValue::Boolean(s),
+                ..
+            } => {
+                svg = *s;
+            }
+            ResolvedOption {
+                name: "vs_yesterday",
+                value: ResolvedValue::Boolean(v),
+                ..
+            } => {
+                vs_yesterday = *v;
+            }
+            ResolvedOption {
+                name: "day",
+                value: ResolvedValue::String(d),
+                ..
+            } => {
+                day = Some(d);
+            }
+            ResolvedOption {
+                name: "mode",
+                value: ResolvedValue::String(mode),
+                ..
+            } => {
+                agp = *mode == "agp";
+            }
+            ResolvedOption {
+                name: "days",
+                value: ResolvedValue::Integer(d),
+                ..
+            } => {
+                days = (*d).clamp(MIN_AGP_DAYS, MAX_AGP_DAYS);
+            }
+            ResolvedOption {
+                name: "hide_carbs",
+                value: ResolvedValue::Boolean(v),
+                ..
+            } => {
+                hidden.carbs = *v;
+            }
+            ResolvedOption {
+                name: "hide_insulin",
+                value: ResolvedValue::Boolean(v),
+                ..
+            } => {
+                hidden.insulin = *v;
+            }
+            ResolvedOption {
+                name: "hide_finger_sticks",
+                value: ResolvedValue::Boolean(v),
+                ..
+            } => {
+                hidden.finger_sticks = *v;
+            }
+            ResolvedOption {
+                name: "hide_stickers",
+                value: ResolvedValue::Boolean(v),
+                ..
+            } => {
+                hidden.stickers = *v;
+            }
             _ => {}
         }
     }
@@ -42,11 +138,9 @@ pub async fn run(
             .await
             .map_err(|_| anyhow::anyhow!("Target user not found in database"))?;
 
-        if !target_data.nightscout.is_private
-            || target_data
-                .nightscout
-                .allowed_people
-                .contains(&interaction.user.id.get())
+        if target_data
+            .nightscout
+            .can_view(target.id.get(), interaction.user.id.get())
         {
             (target_data, interaction.user.id.get(), true)
         } else {
@@ -66,6 +160,22 @@ pub async fn run(
         (data, interaction.user.id.get(), false)
     };
 
+    // The window is the viewer's preference, even when looking at someone else's graph
+    let default_hours = if is_viewing_other_user {
+        handler
+            .database
+            .get_user_info(interaction.user.id.get())
+            .await
+            .ok()
+            .and_then(|viewer| viewer.nightscout.default_graph_hours)
+    } else {
+        user_data.nightscout.default_graph_hours
+    };
+    let hours = hours
+        .or(default_hours)
+        .unwrap_or(MIN_GRAPH_HOURS)
+        .clamp(MIN_GRAPH_HOURS, MAX_GRAPH_HOURS);
+
     let base_url = user_data
         .nightscout
         .nightscout_url
@@ -85,25 +195,6 @@ pub async fn run(
     }
 
     let token = user_data.nightscout.nightscout_token.as_deref();
-    let entries = match handler
-        .nightscout_client
-        .get_entries_for_hours(base_url, hours as u16, token)
-        .await
-    {
-        Ok(entries) => entries,
-        Err(e) => {
-            eprintln!("Failed to get entries for graph: {}", e);
-            let error_msg = if is_viewing_other_user {
-                "Could not fetch glucose data from the target user's Nightscout site."
-            } else {
-                "Could not fetch glucose data from your Nightscout site. Please check your URL configuration with `/setup`."
-            };
-
-            crate::commands::error::run(context, interaction, error_msg).await?;
-            return Ok(());
-        }
-    };
-
     let profile = match handler.nightscout_client.get_profile(base_url, token).await {
         Ok(profile) => profile,
         Err(e) => {
@@ -115,16 +206,94 @@ pub async fn run(
         }
     };
 
+    let target_user_id = target_user.map_or(interaction.user.id.get(), |user| user.id.get());
+    if agp {
+        return run_agp(
+            handler,
+            context,
+            interaction,
+            &user_data,
+            target_user_id,
+            &profile,
+            days,
+            export,
+            !is_viewing_other_user,
+        )
+        .await;
+    }
+
+    let window = match day {
+        Some(day) => {
+            let timezone: Tz = user_data
+                .nightscout
+                .effective_timezone(profile.default_store())
+                .parse()
+                .unwrap_or(chrono_tz::UTC);
+            match parse_day(day, timezone) {
+                Ok(window) => window,
+                Err(message) => {
+                    crate::commands::error::run(context, interaction, &message).await?;
+                    return Ok(());
+                }
+            }
+        }
+        None => GraphWindow::last_hours(hours as u16),
+    };
+
+    let day_key = (!window.live).then_some(window.start);
+    let cache_key = graph_cache_key(
+        target_user_id,
+        hours,
+        day_key,
+        None,
+        vs_yesterday,
+        svg,
+        hidden,
+        &user_data,
+    );
+    if let Some(graph) = handler.cached_graph(cache_key).await {
+        tracing::debug!(
+            "[CACHE] Reusing graph for user {} ({}h)",
+            target_user_id,
+            window.hours()
+        );
+        return send_graph(context, interaction, graph, export).await;
+    }
+
+    // The comparison trace needs the same window from a day earlier as well
+    let fetch_start = if vs_yesterday {
+        window.start - chrono::Duration::days(1)
+    } else {
+        window.start
+    };
+    let options = NightscoutRequestOptions::default().between(
+        fetch_start.timestamp_millis() as u64,
+        window.end.timestamp_millis() as u64,
+    );
+    let entries = match handler
+        .nightscout_client
+        .get_entries(base_url, options, token)
+        .await
+    {
+        Ok(entries) => entries,
+        // Drawn as an empty graph rather than reported as an error
+        Err(NightscoutError::NoEntries) => Vec::new(),
+        Err(e) => {
+            eprintln!("Failed to get entries for graph: {}", e);
+            crate::commands::error::nightscout(context, interaction, &e, !is_viewing_other_user)
+                .await?;
+            return Ok(());
+        }
+    };
+
     let status = handler
         .nightscout_client
         .get_status(base_url, token)
         .await
         .ok();
 
-    let now = chrono::Utc::now();
-    let hours_ago = now - chrono::Duration::hours(hours);
-    let start_time = hours_ago.to_rfc3339();
-    let end_time = now.to_rfc3339();
+    let start_time = window.start.to_rfc3339();
+    let end_time = window.end.to_rfc3339();
 
     let treatments = match handler
         .nightscout_client
@@ -143,27 +312,204 @@ pub async fn run(
         .and_then(|s| s.settings.as_ref())
         .and_then(|settings| settings.thresholds.as_ref());
 
-    let buffer = draw_graph(
+    let mut graph = draw_graph(
         &entries,
         &treatments,
         &profile,
         &user_data.nightscout,
         &user_data.stickers,
         handler,
-        hours as u16,
+        window,
         None,
         thresholds,
+        vs_yesterday,
+        hidden,
     )
     .await?;
 
-    let graph_attachment = CreateAttachment::bytes(buffer, "graph.png");
+    if svg {
+        graph.svg = Some(draw_graph_svg(
+            &entries,
+            &treatments,
+            &profile,
+            &user_data.nightscout,
+            &handler.nightscout_client,
+            window,
+            thresholds,
+            hidden,
+        )?);
+    }
+
+    handler.cache_graph(cache_key, graph.clone()).await;
+
+    send_graph(context, interaction, graph, export).await
+}
+
+/// Send an Ambulatory Glucose Profile of the last `days` days instead of a timeline graph
+#[allow(clippy::too_many_arguments)]
+async fn run_agp(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+    user_data: &UserInfo,
+    target_user_id: u64,
+    profile: &crate::utils::nightscout::Profile,
+    days: i64,
+    export: bool,
+    own_site: bool,
+) -> anyhow::Result<()> {
+    let cache_key = graph_cache_key(
+        target_user_id,
+        0,
+        None,
+        Some(days),
+        false,
+        false,
+        HiddenOverlays::default(),
+        user_data,
+    );
+    if let Some(graph) = handler.cached_graph(cache_key).await {
+        tracing::debug!(
+            "[CACHE] Reusing glucose profile for user {} ({} days)",
+            target_user_id,
+            days
+        );
+        return send_graph(context, interaction, graph, export).await;
+    }
+
+    let base_url = user_data
+        .nightscout
+        .nightscout_url
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("Nightscout URL missing"))?;
+    let token = user_data.nightscout.nightscout_token.as_deref();
+
+    let end = Utc::now();
+    let start = end - chrono::Duration::days(days);
+    let options = NightscoutRequestOptions::default()
+        .between(
+            start.timestamp_millis() as u64,
+            end.timestamp_millis() as u64,
+        )
+        .count(((days + 1) * READINGS_PER_DAY) as u16);
+    let entries = match handler
+        .nightscout_client
+        .get_entries(base_url, options, token)
+        .await
+    {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Failed to get entries for glucose profile: {}", e);
+            crate::commands::error::nightscout(context, interaction, &e, own_site).await?;
+            return Ok(());
+        }
+    };
+
+    let status = handler
+        .nightscout_client
+        .get_status(base_url, token)
+        .await
+        .ok();
+    let thresholds = status
+        .as_ref()
+        .and_then(|s| s.settings.as_ref())
+        .and_then(|settings| settings.thresholds.as_ref());
 
-    // Send only the graph with no message
-    let message = CreateInteractionResponseMessage::new().add_file(graph_attachment);
+    let graph = match draw_agp(
+        &entries,
+        profile,
+        &user_data.nightscout,
+        handler,
+        days as u16,
+        thresholds,
+    ) {
+        Ok(graph) => graph,
+        Err(e) => {
+            eprintln!("Failed to draw glucose profile: {}", e);
+            crate::commands::error::run(
+                context,
+                interaction,
+                "There aren't enough readings in that period to build a glucose profile.",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    handler.cache_graph(cache_key, graph.clone()).await;
+
+    send_graph(context, interaction, graph, export).await
+}
+
+/// Parse a `YYYY-MM-DD` day into a midnight-to-midnight window in the user's timezone,
+/// refusing days that haven't started yet
+fn parse_day(day: &str, timezone: Tz) -> Result<GraphWindow, String> {
+    let date = NaiveDate::parse_from_str(day.trim(), "%Y-%m-%d").map_err(|_| {
+        format!(
+            "`{}` isn't a valid date. Use the `YYYY-MM-DD` format, like `2025-09-20`.",
+            day.trim()
+        )
+    })?;
+
+    let today = Utc::now().with_timezone(&timezone).date_naive();
+    if date > today {
+        return Err(format!(
+            "{} is in the future, so there's nothing to graph yet.",
+            date.format("%Y-%m-%d")
+        ));
+    }
+
+    GraphWindow::day(date, timezone).ok_or_else(|| format!("Couldn't graph {}.", date))
+}
 
-    interaction
-        .create_response(&context.http, CreateInteractionResponse::Message(message))
-        .await?;
+/// Identifies a graph by whose data it shows, the time window and every setting that
+/// changes how it is drawn, so a settings change never serves a stale image
+#[allow(clippy::too_many_arguments)]
+fn graph_cache_key(
+    target_user_id: u64,
+    hours: i64,
+    day: Option<chrono::DateTime<Utc>>,
+    agp_days: Option<i64>,
+    vs_yesterday: bool,
+    svg: bool,
+    hidden: HiddenOverlays,
+    user_data: &UserInfo,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    target_user_id.hash(&mut hasher);
+    hours.hash(&mut hasher);
+    day.hash(&mut hasher);
+    agp_days.hash(&mut hasher);
+    vs_yesterday.hash(&mut hasher);
+    svg.hash(&mut hasher);
+    hidden.hash(&mut hasher);
+    format!("{:?}{:?}", user_data.nightscout, user_data.stickers).hash(&mut hasher);
+    hasher.finish()
+}
+
+async fn send_graph(
+    context: &Context,
+    interaction: &CommandInteraction,
+    graph: GraphOutput,
+    export: bool,
+) -> anyhow::Result<()> {
+    let graph_attachment = CreateAttachment::bytes(graph.png, "graph.png");
+
+    let mut message = EditInteractionResponse::new().new_attachment(graph_attachment);
+    if let Some(summary) = &graph.summary {
+        message = message.content(summary.caption());
+    }
+
+    if export {
+        let csv = entries_to_csv(&graph.entries, &graph.timezone);
+        message = message.new_attachment(CreateAttachment::bytes(csv.into_bytes(), "graph.csv"));
+    }
+
+    if let Some(svg) = graph.svg {
+        message = message.new_attachment(CreateAttachment::bytes(svg.into_bytes(), "graph.svg"));
+    }
+
+    interaction.edit_response(&context.http, message).await?;
 
     Ok(())
 }
@@ -172,10 +518,14 @@ pub fn register() -> CreateCommand {
     CreateCommand::new("graph")
         .description("Sends a graph of blood glucose data.")
         .add_option(
-            CreateCommandOption::new(CommandOptionType::Integer, "hours", "3h to 24h of data.")
-                .min_int_value(3)
-                .max_int_value(24)
-                .required(false),
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "hours",
+                "3h to 72h of data (defaults to your /graph-settings default_hours).",
+            )
+            .min_int_value(MIN_GRAPH_HOURS as u64)
+            .max_int_value(MAX_GRAPH_HOURS as u64)
+            .required(false),
         )
         .add_option(
             CreateCommandOption::new(
@@ -185,8 +535,111 @@ pub fn register() -> CreateCommand {
             )
             .required(false),
         )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "export",
+                "Also attach the plotted readings as a CSV file.",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "svg",
+                "Also attach a simplified SVG (readings and treatments only) for printing or slides.",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "vs_yesterday",
+                "Overlay the same hours from yesterday as a faint line.",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "day",
+                "Graph a whole past day instead, as YYYY-MM-DD (overrides hours).",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "mode",
+                "Timeline of readings, or a glucose profile of typical days (AGP).",
+            )
+            .add_string_choice("Timeline", "timeline")
+            .add_string_choice("Glucose profile (AGP)", "agp")
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "days",
+                "Days the glucose profile covers, 3 to 30 (default 14).",
+            )
+            .min_int_value(MIN_AGP_DAYS as u64)
+            .max_int_value(MAX_AGP_DAYS as u64)
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "hide_carbs",
+                "Leave carb entries off this graph.",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "hide_insulin",
+                "Leave all boluses off this graph.",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "hide_finger_sticks",
+                "Leave finger-stick readings off this graph.",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "hide_stickers",
+                "Leave your stickers off this graph.",
+            )
+            .required(false),
+        )
         .contexts(vec![
             InteractionContext::Guild,
             InteractionContext::PrivateChannel,
         ])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_day_rejects_bad_and_future_dates() {
+        let window = parse_day(" 2025-09-20 ", chrono_tz::UTC).unwrap();
+        assert_eq!(window.start.to_rfc3339(), "2025-09-20T00:00:00+00:00");
+        assert_eq!(window.hours(), 24);
+
+        assert!(parse_day("20/09/2025", chrono_tz::UTC).is_err());
+        assert!(parse_day("2025-02-30", chrono_tz::UTC).is_err());
+
+        let tomorrow = Utc::now().date_naive() + chrono::Duration::days(2);
+        let error = parse_day(&tomorrow.to_string(), chrono_tz::UTC).unwrap_err();
+        assert!(error.contains("future"));
+    }
+}
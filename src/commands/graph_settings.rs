@@ -0,0 +1,499 @@
+use crate::bot::Handler;
+use crate::commands::graph::{MAX_GRAPH_HOURS, MIN_GRAPH_HOURS};
+use crate::utils::database::{GraphPalette, GraphThemeKind, GraphTint, TreatmentLayout};
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
+    InteractionContext, ResolvedOption, ResolvedValue,
+};
+
+/// Scale inputs below this are treated as mmol/L
+const MMOL_INPUT_CEILING: f32 = 30.0;
+const MIN_SCALE_SPAN_MG: f32 = 50.0;
+const MAX_SCALE_MG: f32 = 600.0;
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let user_id = interaction.user.id.get();
+
+    if !handler.database.user_exists(user_id).await? {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "You need to run `/setup` first to configure your Nightscout before changing graph settings.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let mut updated = false;
+    let mut scale_min: Option<f32> = None;
+    let mut scale_max: Option<f32> = None;
+
+    for option in &interaction.data.options() {
+        match option {
+            ResolvedOption {
+                name: "scale_min",
+                value: ResolvedValue::Number(val),
+                ..
+            } => scale_min = Some(*val as f32),
+            ResolvedOption {
+                name: "scale_max",
+                value: ResolvedValue::Number(val),
+                ..
+            } => scale_max = Some(*val as f32),
+            ResolvedOption {
+                name: "smooth_line",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => {
+                handler.database.update_smooth_line(user_id, *val).await?;
+                updated = true;
+            }
+            ResolvedOption {
+                name: "sd_band",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => {
+                handler.database.update_sd_band(user_id, *val).await?;
+                updated = true;
+            }
+            ResolvedOption {
+                name: "minor_gridlines",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => {
+                handler
+                    .database
+                    .update_minor_gridlines(user_id, *val)
+                    .await?;
+                updated = true;
+            }
+            ResolvedOption {
+                name: "right_axis",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => {
+                handler.database.update_right_axis(user_id, *val).await?;
+                updated = true;
+            }
+            ResolvedOption {
+                name: "treatment_legend",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => {
+                handler
+                    .database
+                    .update_treatment_legend(user_id, *val)
+                    .await?;
+                updated = true;
+            }
+            ResolvedOption {
+                name: "auto_scale",
+                value: ResolvedValue::Boolean(true),
+                ..
+            } => {
+                handler.database.update_fixed_scale(user_id, None).await?;
+                updated = true;
+            }
+            ResolvedOption {
+                name: "device_changes",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => {
+                handler
+                    .database
+                    .update_display_device_changes(user_id, *val)
+                    .await?;
+                updated = true;
+            }
+            ResolvedOption {
+                name: "theme",
+                value: ResolvedValue::String(val),
+                ..
+            } => {
+                let Some(theme) = GraphThemeKind::from_str(val) else {
+                    crate::commands::error::run(
+                        context,
+                        interaction,
+                        "Invalid theme. Use 'dark' or 'light'.",
+                    )
+                    .await?;
+                    return Ok(());
+                };
+                handler.database.update_graph_theme(user_id, theme).await?;
+                updated = true;
+            }
+            ResolvedOption {
+                name: "default_hours",
+                value: ResolvedValue::Integer(val),
+                ..
+            } => {
+                let hours = (*val).clamp(MIN_GRAPH_HOURS, MAX_GRAPH_HOURS);
+                handler
+                    .database
+                    .update_default_graph_hours(user_id, hours)
+                    .await?;
+                updated = true;
+            }
+            ResolvedOption {
+                name: "treatment_layout",
+                value: ResolvedValue::String(val),
+                ..
+            } => {
+                let Some(layout) = TreatmentLayout::from_str(val) else {
+                    crate::commands::error::run(
+                        context,
+                        interaction,
+                        "Invalid treatment layout. Use 'inline' or 'bars'.",
+                    )
+                    .await?;
+                    return Ok(());
+                };
+                handler
+                    .database
+                    .update_treatment_layout(user_id, layout)
+                    .await?;
+                updated = true;
+            }
+            ResolvedOption {
+                name: "palette",
+                value: ResolvedValue::String(val),
+                ..
+            } => {
+                let Some(palette) = GraphPalette::from_str(val) else {
+                    crate::commands::error::run(
+                        context,
+                        interaction,
+                        "Invalid palette. Use 'standard', 'blue_orange' or 'red_cyan'.",
+                    )
+                    .await?;
+                    return Ok(());
+                };
+                handler
+                    .database
+                    .update_graph_palette(user_id, palette)
+                    .await?;
+                updated = true;
+            }
+            ResolvedOption {
+                name: "background",
+                value: ResolvedValue::String(val),
+                ..
+            } => {
+                let Some(tint) = GraphTint::from_str(val) else {
+                    crate::commands::error::run(
+                        context,
+                        interaction,
+                        "Invalid background. Use 'none', 'blue', 'green', 'purple' or 'rose'.",
+                    )
+                    .await?;
+                    return Ok(());
+                };
+                handler.database.update_graph_tint(user_id, tint).await?;
+                updated = true;
+            }
+            ResolvedOption {
+                name: "marker_legend",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => {
+                handler.database.update_marker_legend(user_id, *val).await?;
+                updated = true;
+            }
+            ResolvedOption {
+                name: "watermark",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => {
+                handler
+                    .database
+                    .update_show_watermark(user_id, *val)
+                    .await?;
+                updated = true;
+            }
+            _ => {}
+        }
+    }
+
+    match (scale_min, scale_max) {
+        (None, None) => {}
+        (Some(min), Some(max)) => {
+            let (min_mg, max_mg) = (to_mg_dl(min), to_mg_dl(max));
+            if min_mg < 0.0 || max_mg > MAX_SCALE_MG || max_mg - min_mg < MIN_SCALE_SPAN_MG {
+                crate::commands::error::run(
+                    context,
+                    interaction,
+                    &format!(
+                        "The y-axis range must be at least {:.0} mg/dL ({:.1} mmol/L) wide and end at or below {:.0} mg/dL.",
+                        MIN_SCALE_SPAN_MG,
+                        MIN_SCALE_SPAN_MG / 18.0,
+                        MAX_SCALE_MG
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+
+            handler
+                .database
+                .update_fixed_scale(user_id, Some((min_mg, max_mg)))
+                .await?;
+            updated = true;
+        }
+        _ => {
+            crate::commands::error::run(
+                context,
+                interaction,
+                "Please provide both `scale_min` and `scale_max` to pin the y-axis.",
+            )
+            .await?;
+            return Ok(());
+        }
+    }
+
+    let settings = handler.database.get_user_info(user_id).await?.nightscout;
+
+    let yes_no = |value: bool| if value { "Shown" } else { "Hidden" };
+
+    let embed = CreateEmbed::new()
+        .title(if updated {
+            "Graph Settings Updated"
+        } else {
+            "Graph Settings"
+        })
+        .field(
+            "Default window",
+            format!(
+                "{}h",
+                settings.default_graph_hours.unwrap_or(MIN_GRAPH_HOURS)
+            ),
+            true,
+        )
+        .field("Theme", settings.graph_theme.display_name(), true)
+        .field("Background tint", settings.graph_tint.display_name(), true)
+        .field("Palette", settings.graph_palette.display_name(), true)
+        .field("Watermark", yes_no(settings.show_watermark), true)
+        .field(
+            "Sensor & site changes",
+            yes_no(settings.display_device_changes),
+            true,
+        )
+        .field(
+            "Microbolus markers",
+            format!(
+                "{} (≤ {:.1}u)",
+                yes_no(settings.display_microbolus),
+                settings.microbolus_threshold
+            ),
+            true,
+        )
+        .field("Smooth line", yes_no(settings.smooth_line), true)
+        .field("Variability band", yes_no(settings.sd_band), true)
+        .field("Right-side axis", yes_no(settings.right_axis), true)
+        .field(
+            "0.5 mmol/L gridlines",
+            yes_no(settings.minor_gridlines),
+            true,
+        )
+        .field("Treatment legend", yes_no(settings.treatment_legend), true)
+        .field("Treatments", settings.treatment_layout.display_name(), true)
+        .field("Marker legend", yes_no(settings.marker_legend), true)
+        .field(
+            "Y-axis",
+            match settings.fixed_scale_mg {
+                Some((min, max)) => format!(
+                    "Fixed: {:.0}–{:.0} mg/dL ({:.1}–{:.1} mmol/L)",
+                    min,
+                    max,
+                    min / 18.0,
+                    max / 18.0
+                ),
+                None => "Auto".to_string(),
+            },
+            false,
+        )
+        .footer(CreateEmbedFooter::new(
+            "Pass an option to change a setting, e.g. /graph-settings device_changes:False",
+        ))
+        .color(if updated {
+            Colour::from_rgb(34, 197, 94)
+        } else {
+            Colour::from_rgb(59, 130, 246)
+        });
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+/// Values this small can only be mmol/L, so accept either unit for the scale options
+fn to_mg_dl(value: f32) -> f32 {
+    if value < MMOL_INPUT_CEILING {
+        value * 18.0
+    } else {
+        value
+    }
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("graph-settings")
+        .description("View or change how your graphs are drawn")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "theme",
+                "Background and colour palette for your graphs",
+            )
+            .add_string_choice("Dark", "dark")
+            .add_string_choice("Light", "light")
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "background",
+                "Subtle colour tint for the graph background",
+            )
+            .add_string_choice("None", "none")
+            .add_string_choice("Blue", "blue")
+            .add_string_choice("Green", "green")
+            .add_string_choice("Purple", "purple")
+            .add_string_choice("Rose", "rose")
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "palette",
+                "High and low colours, including colour-blind friendly options",
+            )
+            .add_string_choice("Standard", "standard")
+            .add_string_choice("Blue/orange (red-green colour blindness)", "blue_orange")
+            .add_string_choice("Red/cyan (blue-yellow colour blindness)", "red_cyan")
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "watermark",
+                "Show the Beetroot watermark in the corner of your graphs",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "device_changes",
+                "Show sensor and site change markers on your graphs",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "smooth_line",
+                "Connect your readings with a smooth curve",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "sd_band",
+                "Shade ±1 standard deviation around a 1-hour moving average",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "right_axis",
+                "Also label the y-axis in your secondary unit on the right edge",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "minor_gridlines",
+                "Add faint gridlines every 0.5 mmol/L between the labelled ones (mmol/L graphs)",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "treatment_legend",
+                "List the time and amount of each bolus and carb entry under your graphs",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "treatment_layout",
+                "Draw boluses and carbs on the glucose line, or as bars along the plot edges",
+            )
+            .add_string_choice("On the glucose line", "inline")
+            .add_string_choice("Bars along the edges", "bars")
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "marker_legend",
+                "Show a key explaining the marker shapes and colours in the corner of your graphs",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "default_hours",
+                "Hours /graph shows when you don't pass hours (3 to 72)",
+            )
+            .min_int_value(MIN_GRAPH_HOURS as u64)
+            .max_int_value(MAX_GRAPH_HOURS as u64)
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Number,
+                "scale_min",
+                "Pin the bottom of the y-axis (mg/dL or mmol/L), used with scale_max",
+            )
+            .min_number_value(0.0)
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Number,
+                "scale_max",
+                "Pin the top of the y-axis (mg/dL or mmol/L), used with scale_min",
+            )
+            .min_number_value(1.0)
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "auto_scale",
+                "Go back to scaling the y-axis to your readings",
+            )
+            .required(false),
+        )
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
@@ -1,38 +1,234 @@
 use crate::bot::Handler;
+use crate::bot::command_registry;
 use crate::bot::helpers::pagination;
+use crate::commands::MAX_AUTOCOMPLETE_CHOICES;
 use serenity::all::{
     Colour, CommandInteraction, CommandOptionType, ComponentInteraction, Context, CreateActionRow,
-    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext,
-    ResolvedOption, ResolvedValue,
+    CreateAutocompleteResponse, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+    CreateInteractionResponseMessage, InteractionContext, ResolvedOption, ResolvedValue,
 };
 use serenity::builder::{CreateCommand, CreateCommandOption};
 
+/// One command's line in the help pages
+struct HelpEntry {
+    usage: &'static str,
+    description: &'static str,
+}
+
+impl HelpEntry {
+    /// The slash command name, or `None` for context menu entries
+    fn command_name(&self) -> Option<&'static str> {
+        self.usage
+            .strip_prefix('/')
+            .and_then(|usage| usage.split_whitespace().next())
+    }
+}
+
+struct HelpPage {
+    heading: &'static str,
+    colour: (u8, u8, u8),
+    entries: &'static [HelpEntry],
+}
+
+const HELP_PAGES: &[HelpPage] = &[
+    HelpPage {
+        heading: "🩸 **Main Commands** - Blood Glucose Monitoring",
+        colour: (139, 69, 19),
+        entries: &[
+            HelpEntry {
+                usage: "/bg [user] [delta_interval] [vs_yesterday]",
+                description: "Get current blood glucose reading. Optionally specify a user to view their data (requires permission), or a delta interval to see the change over the last 15 or 30 minutes. Set vs_yesterday to compare with the reading from this time yesterday. Shows your scheduled basal rate and any running temp basal when your Nightscout profile has a basal schedule.",
+            },
+            HelpEntry {
+                usage: "/graph [hours] [day] [user] [export] [svg] [vs_yesterday] [mode] [days] [hide_*]",
+                description: "Generate a blood glucose graph. Specify hours (3-72, defaulting to `default_hours` from `/graph-settings`) or a past day (`day:2025-09-20`, midnight to midnight in your timezone) and optionally a user to view their graph (requires permission). Set export to also get the readings as a CSV file, svg to also get a simplified vector copy of the graph (readings and treatments only, without stickers, bands or banners) that stays sharp when zoomed or printed, or vs_yesterday to compare with the same hours yesterday. Use hide_carbs, hide_insulin, hide_finger_sticks or hide_stickers for a cleaner trace. Set `mode:agp` for a glucose profile of the last 3-30 `days`: the median and the 25-75% and 10-90% ranges for each time of day.",
+            },
+            HelpEntry {
+                usage: "/tir [user]",
+                description: "Quick time-in-range check for the last 24 hours, with a low/in-range/high bar.",
+            },
+            HelpEntry {
+                usage: "/stats [user] [days]",
+                description: "Show average glucose, GMI (estimated A1c), variability and time in range, with a sparkline of daily averages.",
+            },
+            HelpEntry {
+                usage: "/leaderboard [join]",
+                description: "Rank this server's members by time in range over the last week. Only members who joined with `join:True` are ever shown.",
+            },
+            HelpEntry {
+                usage: "/nightscout-status [user]",
+                description: "Show pump battery, reservoir, phone battery, last pump contact and sensor age as reported to Nightscout by your loop or uploader.",
+            },
+            HelpEntry {
+                usage: "/announcements [user] [hours]",
+                description: "List announcements posted on a Nightscout site, with times in the site's timezone.",
+            },
+            HelpEntry {
+                usage: "/convert <values> [unit] [as_average]",
+                description: "Convert one or more blood glucose values between mg/dL and mmol/L. Units are auto-detected per value unless chosen. Set as_average to also see the estimated A1c (% and mmol/mol) for an average glucose. Example: `/convert 100 120 7.2`",
+            },
+        ],
+    },
+    HelpPage {
+        heading: "⚙️ **Setup & Configuration**",
+        colour: (252, 186, 0),
+        entries: &[
+            HelpEntry {
+                usage: "/setup",
+                description: "Configure your Nightscout URL and privacy settings. Required before using other commands.",
+            },
+            HelpEntry {
+                usage: "/token",
+                description: "Set or update your Nightscout API token for authentication (optional but recommended). Opens a modal for secure input.",
+            },
+            HelpEntry {
+                usage: "/set-token",
+                description: "Alternative command to set or update your Nightscout API token. Same as /token.",
+            },
+            HelpEntry {
+                usage: "/set-nightscout-url",
+                description: "Update your Nightscout URL. Tests the connection before saving changes.",
+            },
+            HelpEntry {
+                usage: "/get-nightscout-url",
+                description: "View your current Nightscout URL and token status (without revealing the token).",
+            },
+            HelpEntry {
+                usage: "/nightscout <add|list|switch|remove>",
+                description: "Save several Nightscout sites (for example your own and a child's) and switch which one is active. `/bg`, `/graph` and other commands use the active site.",
+            },
+            HelpEntry {
+                usage: "/set-visibility <public|private>",
+                description: "Set your profile visibility. Public = anyone can view, Private = only you and allowed users can view.",
+            },
+            HelpEntry {
+                usage: "/anonymous <enabled>",
+                description: "Hide your avatar, name and Nightscout site title on `/bg`, and notes and the watermark on your graphs, for sharing in public.",
+            },
+            HelpEntry {
+                usage: "/export",
+                description: "Download everything Beetroot stores about you as a JSON file. Your token is always redacted.",
+            },
+            HelpEntry {
+                usage: "/delete-me",
+                description: "Permanently delete all of your data from Beetroot after a confirmation.",
+            },
+            HelpEntry {
+                usage: "/allow <add|remove|list|clear>",
+                description: "Manage who can view your blood glucose data when your profile is private. Add or remove a user, list everyone you've allowed, or clear the whole list.",
+            },
+            HelpEntry {
+                usage: "/set-range <low> <high> [urgent_low]",
+                description: "Use your own target range (mg/dL or mmol/L) for `/bg` colours, graphs, stickers and time in range instead of your Nightscout targets. `urgent_low` sets where readings are highlighted as urgent on graphs. `reset:True` goes back to Nightscout's.",
+            },
+            HelpEntry {
+                usage: "/set-timezone <timezone>",
+                description: "Use a different IANA timezone (e.g. `Europe/Paris`) than your Nightscout profile's for graphs, `/bg` and reminders. `reset:True` goes back to the profile's.",
+            },
+            HelpEntry {
+                usage: "/set-stale-time [minutes]",
+                description: "Choose how many minutes (5-240) can pass before `/bg` and graphs warn that your data is old, and IOB, COB and predictions are hidden. Handy for sensors that upload less often. `reset:True` goes back to 15 minutes.",
+            },
+            HelpEntry {
+                usage: "/set-threshold <value> [display]",
+                description: "Configure microbolus threshold (in units) and whether to display them on graphs. Doses ≤ threshold are considered microbolus.",
+            },
+            HelpEntry {
+                usage: "/graph-settings [theme] [background] [palette] [watermark] [device_changes] [smooth_line] [sd_band] [right_axis] [minor_gridlines] [treatment_legend] [treatment_layout] [marker_legend] [default_hours] [scale_min] [scale_max] [auto_scale]",
+                description: "View or change your graph display settings, such as the dark/light theme, a background tint, colour-blind friendly high/low colours, the Beetroot watermark, sensor and site change markers, a smoothed glucose line, a shaded ±1 SD variability band, a right-side axis in your secondary unit, faint 0.5 mmol/L gridlines between the labelled ones, a bolus and carb time legend, boluses and carbs drawn as bars along the plot edges, a key explaining the marker shapes, how many hours `/graph` shows by default, and a fixed y-axis range.",
+            },
+        ],
+    },
+    HelpPage {
+        heading: "🎨 **Customization & Info**",
+        colour: (139, 69, 19),
+        entries: &[
+            HelpEntry {
+                usage: "/stickers",
+                description: "Manage your stickers - view and remove stickers from your graph. Makes your graphs more personalized!",
+            },
+            HelpEntry {
+                usage: "Add Sticker (Context Menu)",
+                description: "Right-click/long-press a message with a sticker → Apps → **Add Sticker** to add it to your graphs.",
+            },
+            HelpEntry {
+                usage: "Analyze Units (Context Menu)",
+                description: "Right-click/long-press a message → Apps → **Analyze Units** to detect and convert diabetes units in messages.",
+            },
+            HelpEntry {
+                usage: "/remind <in|at|list|cancel>",
+                description: "Get a DM reminder after some minutes or at a time of day (optionally repeating daily or weekly), list your reminders, or cancel one.",
+            },
+            HelpEntry {
+                usage: "/silence <minutes>",
+                description: "Silence Beetroot notifications for a while without turning them off. Use `0` to resume early.",
+            },
+            HelpEntry {
+                usage: "/weekly-digest <enabled>",
+                description: "Get a DM every Sunday with your week's time in range, average, CV, GMI and number of lows.",
+            },
+            HelpEntry {
+                usage: "/server-config",
+                description: "Server admins only: restrict commands that show glucose data (`/bg`, `/graph`, `/tir`, `/stats` and others) to certain channels, or make members' data private by default in the server.",
+            },
+            HelpEntry {
+                usage: "/help [page] [command]",
+                description: "Show this help message with all available commands. Use page parameter to navigate pages, or `command:bg` to see one command and its options.",
+            },
+            HelpEntry {
+                usage: "/info",
+                description: "Show information about Beetroot bot, GitHub repository, and how to report issues.",
+            },
+        ],
+    },
+];
+
 pub async fn run(
     _handler: &Handler,
     context: &Context,
     interaction: &CommandInteraction,
 ) -> anyhow::Result<()> {
     let mut page = 1;
+    let mut command: Option<&str> = None;
 
     for option in &interaction.data.options() {
-        if let ResolvedOption {
-            name: "page",
-            value: ResolvedValue::Integer(p),
-            ..
-        } = option
-        {
-            page = *p as u8;
+        match option {
+            ResolvedOption {
+                name: "page",
+                value: ResolvedValue::Integer(p),
+                ..
+            } => page = *p as u8,
+            ResolvedOption {
+                name: "command",
+                value: ResolvedValue::String(name),
+                ..
+            } => command = Some(name.trim()),
+            _ => {}
         }
     }
 
-    let (embed, components) = create_help_page(page);
-
-    let mut response = CreateInteractionResponseMessage::new()
-        .embed(embed)
-        .ephemeral(true);
+    let mut response = CreateInteractionResponseMessage::new().ephemeral(true);
 
-    if let Some(action_row) = components {
-        response = response.components(vec![action_row]);
+    if let Some(name) = command {
+        let Some(entry) = find_entry(name) else {
+            crate::commands::error::run(
+                context,
+                interaction,
+                &format!(
+                    "There's no command called `{}`. Use `/help` to see every command.",
+                    name
+                ),
+            )
+            .await?;
+            return Ok(());
+        };
+        response = response.embed(create_command_detail(entry));
+    } else {
+        let (embed, components) = create_help_page(page);
+        response = response.embed(embed);
+        if let Some(action_row) = components {
+            response = response.components(vec![action_row]);
+        }
     }
 
     interaction
@@ -43,106 +239,20 @@ pub async fn run(
 }
 
 fn create_help_page(page: u8) -> (CreateEmbed, Option<CreateActionRow>) {
-    let total_pages = 3;
+    let total_pages = HELP_PAGES.len() as u8;
     let page = page.clamp(1, total_pages);
+    let help_page = &HELP_PAGES[page as usize - 1];
+    let (r, g, b) = help_page.colour;
 
-    let embed = match page {
-        1 => CreateEmbed::new()
-            .title("Beetroot Commands (Page 1/3)")
-            .description("🩸 **Main Commands** - Blood Glucose Monitoring")
-            .color(Colour::from_rgb(139, 69, 19))
-            .field(
-                "/bg [user]",
-                "Get current blood glucose reading. Optionally specify a user to view their data (requires permission).",
-                false,
-            )
-            .field(
-                "/graph [hours] [user]",
-                "Generate a blood glucose graph. Specify hours (3-24) and optionally a user to view their graph (requires permission).",
-                false,
-            )
-            .field(
-                "/convert <value> <from_unit>",
-                "Convert blood glucose units between mg/dL and mmol/L. Example: `/convert 100 mg/dl`",
-                false,
-            ),
-        2 => CreateEmbed::new()
-            .title("Beetroot Commands (Page 2/3)")
-            .description("⚙️ **Setup & Configuration**")
-            .color(Colour::from_rgb(252, 186, 0))
-            .field(
-                "/setup",
-                "Configure your Nightscout URL and privacy settings. Required before using other commands.",
-                false,
-            )
-            .field(
-                "/token",
-                "Set or update your Nightscout API token for authentication (optional but recommended). Opens a modal for secure input.",
-                false,
-            )
-            .field(
-                "/set-token",
-                "Alternative command to set or update your Nightscout API token. Same as /token.",
-                false,
-            )
-            .field(
-                "/set-nightscout-url",
-                "Update your Nightscout URL. Tests the connection before saving changes.",
-                false,
-            )
-            .field(
-                "/get-nightscout-url",
-                "View your current Nightscout URL and token status (without revealing the token).",
-                false,
-            )
-            .field(
-                "/set-visibility <public|private>",
-                "Set your profile visibility. Public = anyone can view, Private = only you and allowed users can view.",
-                false,
-            )
-            .field(
-                "/allow @user [action]",
-                "Manage who can view your blood glucose data when your profile is private. Add or remove users from your allowed list.",
-                false,
-            )
-            .field(
-                "/set-threshold <value> [display]",
-                "Configure microbolus threshold (in units) and whether to display them on graphs. Doses ≤ threshold are considered microbolus.",
-                false,
-            ),
-        3 => CreateEmbed::new()
-            .title("Beetroot Commands (Page 3/3)")
-            .description("🎨 **Customization & Info**")
-            .color(Colour::from_rgb(139, 69, 19))
-            .field(
-                "/stickers",
-                "Manage your stickers - view and remove stickers from your graph. Makes your graphs more personalized!",
-                false,
-            )
-            .field(
-                "Add Sticker (Context Menu)",
-                "Right-click/long-press a message with a sticker → Apps → **Add Sticker** to add it to your graphs.",
-                false,
-            )
-            .field(
-                "Analyze Units (Context Menu)",
-                "Right-click/long-press a message → Apps → **Analyze Units** to detect and convert diabetes units in messages.",
-                false,
-            )
-            .field(
-                "/help [page]",
-                "Show this help message with all available commands. Use page parameter to navigate pages.",
-                false,
-            )
-            .field(
-                "/info",
-                "Show information about Beetroot bot, GitHub repository, and how to report issues.",
-                false,
-            ),
-        _ => unreachable!(),
-    };
+    let embed = help_page.entries.iter().fold(
+        CreateEmbed::new()
+            .title(format!("Beetroot Commands (Page {}/{})", page, total_pages))
+            .description(help_page.heading)
+            .color(Colour::from_rgb(r, g, b)),
+        |embed, entry| embed.field(entry.usage, entry.description, false),
+    );
 
-    let embed = embed.footer(serenity::all::CreateEmbedFooter::new(
+    let embed = embed.footer(CreateEmbedFooter::new(
         "Use /info for bot information and GitHub repository",
     ));
 
@@ -151,6 +261,121 @@ fn create_help_page(page: u8) -> (CreateEmbed, Option<CreateActionRow>) {
     (embed, components)
 }
 
+/// A single command's help, with the options it's registered with
+fn create_command_detail(entry: &HelpEntry) -> CreateEmbed {
+    let (r, g, b) = HELP_PAGES
+        .iter()
+        .find(|page| page.entries.iter().any(|e| std::ptr::eq(e, entry)))
+        .map(|page| page.colour)
+        .unwrap_or((139, 69, 19));
+
+    let mut embed = CreateEmbed::new()
+        .title(entry.usage)
+        .description(entry.description)
+        .color(Colour::from_rgb(r, g, b));
+
+    let options = entry
+        .command_name()
+        .map(command_options)
+        .unwrap_or_default();
+    if !options.is_empty() {
+        embed = embed.field("Options", options.join("\n"), false);
+    }
+
+    embed.footer(CreateEmbedFooter::new("Use /help to see every command"))
+}
+
+/// The help entry for a slash command, ignoring case and a leading `/`
+fn find_entry(name: &str) -> Option<&'static HelpEntry> {
+    let name = name.trim_start_matches('/');
+    HELP_PAGES
+        .iter()
+        .flat_map(|page| page.entries)
+        .find(|entry| {
+            entry
+                .command_name()
+                .is_some_and(|command| command.eq_ignore_ascii_case(name))
+        })
+}
+
+/// One line per option or subcommand of a registered command, read back from its
+/// registration so the list can't drift from what Discord shows
+fn command_options(name: &str) -> Vec<String> {
+    let Some(command) = command_registry::get_all_commands()
+        .into_iter()
+        .filter_map(|command| serde_json::to_value(command).ok())
+        .find(|command| command["name"] == name)
+    else {
+        return Vec::new();
+    };
+
+    command["options"]
+        .as_array()
+        .map(|options| {
+            options
+                .iter()
+                .map(|option| {
+                    let required = if option["required"].as_bool().unwrap_or(false) {
+                        " (required)"
+                    } else {
+                        ""
+                    };
+                    format!(
+                        "`{}`{} - {}",
+                        option["name"].as_str().unwrap_or_default(),
+                        required,
+                        option["description"].as_str().unwrap_or_default()
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Slash command names matching what's been typed so far, prefix matches first
+fn command_matches(input: &str) -> Vec<&'static str> {
+    let needle = input.trim().trim_start_matches('/').to_lowercase();
+    let mut matches: Vec<(bool, &'static str)> = HELP_PAGES
+        .iter()
+        .flat_map(|page| page.entries)
+        .filter_map(HelpEntry::command_name)
+        .filter(|name| name.contains(&needle))
+        .map(|name| (!name.starts_with(&needle), name))
+        .collect();
+
+    matches.sort();
+    matches
+        .into_iter()
+        .take(MAX_AUTOCOMPLETE_CHOICES)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// Suggest command names as the user types the `command` option
+pub async fn autocomplete(
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let input = interaction
+        .data
+        .autocomplete()
+        .filter(|option| option.name == "command")
+        .map(|option| option.value)
+        .unwrap_or_default();
+
+    let response = command_matches(input)
+        .into_iter()
+        .fold(CreateAutocompleteResponse::new(), |response, name| {
+            response.add_string_choice(name, name)
+        });
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Autocomplete(response))
+        .await?;
+
+    Ok(())
+}
+
 pub async fn handle_button(
     _handler: &Handler,
     context: &Context,
@@ -188,8 +413,52 @@ pub fn register() -> CreateCommand {
             .max_int_value(3)
             .required(false),
         )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "command",
+                "Show the details and options of a single command",
+            )
+            .set_autocomplete(true)
+            .required(false),
+        )
         .contexts(vec![
             InteractionContext::Guild,
             InteractionContext::PrivateChannel,
         ])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_entry_by_command_name() {
+        let entry = find_entry("bg").unwrap();
+        assert!(entry.usage.starts_with("/bg "));
+        assert!(find_entry("/GRAPH").is_some());
+        assert!(find_entry("set-timezone").is_some());
+        assert!(find_entry("Add").is_none());
+        assert!(find_entry("nope").is_none());
+
+        assert!(
+            command_options("bg")
+                .iter()
+                .any(|line| line.starts_with("`user`"))
+        );
+    }
+
+    #[test]
+    fn test_command_matches_prefix_first() {
+        assert_eq!(
+            command_matches("nightscout"),
+            vec![
+                "nightscout",
+                "nightscout-status",
+                "get-nightscout-url",
+                "set-nightscout-url"
+            ]
+        );
+        assert!(command_matches("").len() <= MAX_AUTOCOMPLETE_CHOICES);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::bot::Handler;
+use crate::bot::version_checker::current_version;
 use serenity::all::{
     Colour, CommandInteraction, Context, CreateEmbed, CreateInteractionResponse,
     CreateInteractionResponseMessage, InteractionContext,
@@ -6,14 +7,31 @@ use serenity::all::{
 use serenity::builder::CreateCommand;
 
 pub async fn run(
-    _handler: &Handler,
+    handler: &Handler,
     context: &Context,
     interaction: &CommandInteraction,
 ) -> anyhow::Result<()> {
+    let uptime = handler
+        .started_at
+        .get()
+        .map(|started_at| format_uptime(started_at.elapsed()))
+        .unwrap_or_else(|| "Starting up".to_string());
+
+    let user_count = match handler.database.user_count().await {
+        Ok(count) => count.to_string(),
+        Err(e) => {
+            tracing::warn!("[INFO] Couldn't count users: {}", e);
+            "Unknown".to_string()
+        }
+    };
+
     let embed = CreateEmbed::new()
         .title("Beetroot - Blood Glucose Discord Bot")
         .description("A Discord bot for sharing and viewing blood glucose data from Nightscout.")
         .color(Colour::from_rgb(34, 197, 94))
+        .field("Version", current_version(), true)
+        .field("Uptime", uptime, true)
+        .field("Users", user_count, true)
         .field(
             "GitHub Repository",
             "[ItsLimeNade/Beetroot](https://github.com/ItsLimeNade/Beetroot)",
@@ -45,6 +63,24 @@ pub async fn run(
     Ok(())
 }
 
+/// Format a duration as e.g. `3d 4h 12m`, dropping leading zero units
+fn format_uptime(elapsed: std::time::Duration) -> String {
+    let total_minutes = elapsed.as_secs() / 60;
+    let (days, hours, minutes) = (
+        total_minutes / (24 * 60),
+        total_minutes / 60 % 24,
+        total_minutes % 60,
+    );
+
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
 pub fn register() -> CreateCommand {
     CreateCommand::new("info")
         .description("Show information about Beetroot bot and GitHub repository")
@@ -53,3 +89,22 @@ pub fn register() -> CreateCommand {
             InteractionContext::PrivateChannel,
         ])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_format_uptime() {
+        assert_eq!(format_uptime(Duration::from_secs(59)), "0m");
+        assert_eq!(
+            format_uptime(Duration::from_secs(2 * 3600 + 5 * 60)),
+            "2h 5m"
+        );
+        assert_eq!(
+            format_uptime(Duration::from_secs(3 * 86400 + 4 * 3600 + 12 * 60)),
+            "3d 4h 12m"
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use crate::bot::Handler;
+use crate::commands::stats::fetch_stats_window;
+use crate::utils::stats::TimeInRange;
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
+    EditInteractionResponse, InteractionContext, ResolvedOption, ResolvedValue, UserId,
+};
+
+/// The leaderboard always covers the last week
+const LEADERBOARD_HOURS: i64 = 168;
+/// Participants need at least a day of CGM readings in the week to be ranked
+const MIN_READINGS: usize = 288;
+/// Upper bound on Nightscout sites fetched for a single leaderboard
+const MAX_PARTICIPANTS: usize = 25;
+/// Guild membership lookups sent to Discord at the same time
+const MEMBER_LOOKUP_CONCURRENCY: usize = 5;
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = interaction.guild_id else {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "`/leaderboard` can only be used inside a server.",
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let user_id = interaction.user.id.get();
+
+    let join = interaction
+        .data
+        .options()
+        .iter()
+        .find_map(|option| match option {
+            ResolvedOption {
+                name: "join",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => Some(*val),
+            _ => None,
+        });
+
+    if let Some(join) = join {
+        if !handler.database.user_exists(user_id).await? {
+            crate::commands::error::run(
+                context,
+                interaction,
+                "You need to run `/setup` first to configure your Nightscout before joining the leaderboard.",
+            )
+            .await?;
+            return Ok(());
+        }
+
+        handler
+            .database
+            .update_leaderboard_opt_in(user_id, join)
+            .await?;
+        tracing::info!(
+            "[LEADERBOARD] User {} {} the leaderboard",
+            user_id,
+            if join { "joined" } else { "left" }
+        );
+
+        let embed = if join {
+            CreateEmbed::new()
+                .title("Joined the Leaderboard")
+                .description(
+                    "Your weekly time in range will be ranked in `/leaderboard` in every server you share with Beetroot, even if your data is private.\n\nOnly your TIR percentage is shown. Use `/leaderboard join:False` to leave at any time.",
+                )
+                .color(Colour::from_rgb(34, 197, 94))
+        } else {
+            CreateEmbed::new()
+                .title("Left the Leaderboard")
+                .description("You won't appear in `/leaderboard` anymore.")
+                .color(Colour::from_rgb(34, 197, 94))
+        };
+
+        let response = CreateInteractionResponseMessage::new()
+            .embed(embed)
+            .ephemeral(true);
+        interaction
+            .create_response(context, CreateInteractionResponse::Message(response))
+            .await?;
+        return Ok(());
+    }
+
+    // Fetching a week of readings from every participant can take a while
+    interaction
+        .create_response(
+            context,
+            CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+        )
+        .await?;
+
+    let opted_in = handler.database.get_leaderboard_participants().await?;
+    let members = first_members(opted_in, MAX_PARTICIPANTS, |id| async move {
+        guild_id.member(context, UserId::new(id)).await.is_ok()
+    })
+    .await;
+
+    let results = futures::future::join_all(members.into_iter().map(|id| async move {
+        let user_info = handler.database.get_user_info(id).await.ok()?;
+        match fetch_stats_window(handler, &user_info.nightscout, LEADERBOARD_HOURS).await {
+            Ok(window) => Some((
+                id,
+                TimeInRange::from_entries(
+                    &window.entries,
+                    window.target_low_mg,
+                    window.target_high_mg,
+                ),
+            )),
+            Err(e) => {
+                tracing::warn!(
+                    "[LEADERBOARD] Couldn't fetch entries for user {}: {}",
+                    id,
+                    e
+                );
+                None
+            }
+        }
+    }))
+    .await;
+
+    let ranked = rank_participants(results.into_iter().flatten().collect());
+
+    let description = if ranked.is_empty() {
+        "Nobody in this server has joined the leaderboard with enough readings this week yet.\n\nUse `/leaderboard join:True` to take part.".to_string()
+    } else {
+        ranked
+            .iter()
+            .enumerate()
+            .map(|(i, (id, tir))| {
+                let place = match i {
+                    0 => "🥇".to_string(),
+                    1 => "🥈".to_string(),
+                    2 => "🥉".to_string(),
+                    _ => format!("**{}.**", i + 1),
+                };
+                format!("{} <@{}> — {:.0}%", place, id, tir.in_range_percent())
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let embed = CreateEmbed::new()
+        .title("Time in Range Leaderboard")
+        .description(description)
+        .footer(CreateEmbedFooter::new(
+            "Last 7 days, each against their own target range • Opt in with /leaderboard join:True",
+        ))
+        .color(Colour::from_rgb(59, 130, 246));
+
+    interaction
+        .edit_response(context, EditInteractionResponse::new().embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+/// The first `limit` of `ids` (in order) that `is_member` accepts. Only a few lookups run at
+/// once, and none are started after the limit is reached.
+async fn first_members<F, Fut>(ids: Vec<u64>, limit: usize, is_member: F) -> Vec<u64>
+where
+    F: Fn(u64) -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    use futures::StreamExt;
+
+    futures::stream::iter(ids)
+        .map(|id| {
+            let lookup = is_member(id);
+            async move { lookup.await.then_some(id) }
+        })
+        // `buffered` keeps the input order, so the same members are picked every time
+        .buffered(MEMBER_LOOKUP_CONCURRENCY)
+        .filter_map(std::future::ready)
+        .take(limit)
+        .collect()
+        .await
+}
+
+/// Rank participants by time in range, leaving out anyone without enough readings
+fn rank_participants(mut results: Vec<(u64, TimeInRange)>) -> Vec<(u64, TimeInRange)> {
+    results.retain(|(_, tir)| tir.total() >= MIN_READINGS);
+    results.sort_by(|(_, a), (_, b)| b.in_range_percent().total_cmp(&a.in_range_percent()));
+    results
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("leaderboard")
+        .description("Weekly time-in-range ranking of members who opted in")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "join",
+                "Join or leave the leaderboard",
+            )
+            .required(false),
+        )
+        .contexts(vec![InteractionContext::Guild])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tir(low: usize, in_range: usize, high: usize) -> TimeInRange {
+        TimeInRange {
+            low,
+            in_range,
+            high,
+        }
+    }
+
+    #[test]
+    fn test_rank_participants_by_tir() {
+        let ranked = rank_participants(vec![
+            (1, tir(100, 1000, 916)),
+            (2, tir(10, 1800, 206)),
+            (3, tir(0, 100, 0)),
+            (4, tir(50, 1500, 466)),
+        ]);
+
+        let ids: Vec<u64> = ranked.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![2, 4, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_first_members_stops_at_the_limit() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let lookups = AtomicUsize::new(0);
+        let members = first_members((1..=100).collect(), 3, |id| {
+            lookups.fetch_add(1, Ordering::SeqCst);
+            async move { id % 2 == 0 }
+        })
+        .await;
+
+        assert_eq!(members, vec![2, 4, 6]);
+        // Lookups stop shortly after the limit, not after all 100 ids
+        assert!(lookups.load(Ordering::SeqCst) <= 6 + MEMBER_LOOKUP_CONCURRENCY);
+    }
+}
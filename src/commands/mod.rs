@@ -1,18 +1,40 @@
 pub mod add_sticker;
+pub mod admin_stats;
 pub mod allow;
 pub mod analyze_units;
+pub mod announcements;
+pub mod anonymous;
 pub mod bg;
 pub mod convert;
+pub mod delete_me;
 pub mod error;
+pub mod export;
 pub mod get_nightscout_url;
 pub mod graph;
+pub mod graph_settings;
 pub mod help;
 pub mod info;
+pub mod leaderboard;
+pub mod nightscout;
+pub mod nightscout_status;
+pub mod remind;
+pub mod rotate_key;
+pub mod server_config;
 pub mod set_nightscout_url;
+pub mod set_range;
+pub mod set_stale_time;
 pub mod set_threshold;
+pub mod set_timezone;
 pub mod set_token;
 pub mod set_visibility;
 pub mod setup;
+pub mod silence;
+pub mod stats;
 pub mod stickers;
+pub mod tir;
 pub mod token;
 pub mod update_message;
+pub mod weekly_digest;
+
+/// Discord shows at most this many autocomplete choices
+pub const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
@@ -0,0 +1,381 @@
+use crate::bot::Handler;
+use crate::commands::set_nightscout_url::validate_and_fix_url;
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateInputText, CreateInteractionResponse, CreateInteractionResponseMessage,
+    CreateQuickModal, InputTextStyle, InteractionContext, ModalInteraction, ResolvedOption,
+    ResolvedValue,
+};
+
+/// Maximum number of Nightscout sites a user can save, including the active one
+const MAX_SITES_PER_USER: usize = 5;
+const MAX_NAME_LENGTH: usize = 32;
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let options = interaction.data.options();
+    let Some(ResolvedOption {
+        name: subcommand,
+        value: ResolvedValue::SubCommand(sub_options),
+        ..
+    }) = options.first()
+    else {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "Please choose a /nightscout subcommand.",
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let site_name = sub_options.iter().find_map(|option| match option {
+        ResolvedOption {
+            name: "name",
+            value: ResolvedValue::String(name),
+            ..
+        } => Some(name.trim()),
+        _ => None,
+    });
+
+    match (*subcommand, site_name) {
+        ("list", _) => list_sites(handler, context, interaction).await,
+        ("add", Some(name)) => add_site(handler, context, interaction, name).await,
+        ("switch", Some(name)) => switch_site(handler, context, interaction, name).await,
+        ("remove", Some(name)) => remove_site(handler, context, interaction, name).await,
+        ("add" | "switch" | "remove", None) => {
+            crate::commands::error::run(context, interaction, "Please provide a site name.").await
+        }
+        _ => {
+            crate::commands::error::run(context, interaction, "Unknown /nightscout subcommand.")
+                .await
+        }
+    }
+}
+
+async fn list_sites(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let sites = handler
+        .database
+        .get_nightscout_sites(interaction.user.id.get())
+        .await?;
+
+    let description = sites
+        .iter()
+        .map(|site| {
+            format!(
+                "{} **{}** — {}",
+                if site.is_active { "🟢" } else { "⚪" },
+                site.name,
+                site.nightscout_url.as_deref().unwrap_or("(no URL)")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let embed = CreateEmbed::new()
+        .title("Your Nightscout Sites")
+        .description(description)
+        .footer(serenity::all::CreateEmbedFooter::new(
+            "🟢 active • /bg and /graph use the active site. Change it with /nightscout switch.",
+        ))
+        .color(Colour::from_rgb(59, 130, 246));
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+async fn add_site(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+    name: &str,
+) -> anyhow::Result<()> {
+    let user_id = interaction.user.id.get();
+
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        crate::commands::error::run(
+            context,
+            interaction,
+            &format!(
+                "Site names must be between 1 and {} characters.",
+                MAX_NAME_LENGTH
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let sites = handler.database.get_nightscout_sites(user_id).await?;
+    if sites.iter().any(|site| site.name == name) {
+        crate::commands::error::run(
+            context,
+            interaction,
+            &format!("You already have a site called **{}**.", name),
+        )
+        .await?;
+        return Ok(());
+    }
+    if sites.len() >= MAX_SITES_PER_USER {
+        crate::commands::error::run(
+            context,
+            interaction,
+            &format!(
+                "You can save up to {} sites. Remove one with `/nightscout remove` first.",
+                MAX_SITES_PER_USER
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let modal = CreateQuickModal::new(format!("Add Nightscout site: {}", name))
+        .timeout(std::time::Duration::from_secs(300))
+        .short_field("Nightscout URL")
+        .field(
+            CreateInputText::new(InputTextStyle::Short, "Nightscout Token (optional)", "")
+                .required(false)
+                .placeholder("leave empty if the site is public"),
+        );
+
+    let Some(modal_response) = interaction.quick_modal(context, modal).await? else {
+        return Ok(());
+    };
+
+    let url_input = &modal_response.inputs[0];
+    let token_input = modal_response
+        .inputs
+        .get(1)
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty());
+
+    let validated_url = match validate_and_fix_url(url_input) {
+        Ok(url) => url,
+        Err(e) => {
+            return respond_to_modal(
+                context,
+                &modal_response.interaction,
+                "Invalid URL",
+                &format!("Please check your URL: {}", e),
+                Colour::RED,
+            )
+            .await;
+        }
+    };
+
+    tracing::info!(
+        "[TEST] Testing Nightscout connection for URL: {}",
+        validated_url
+    );
+    if let Err(e) = handler
+        .nightscout_client
+        .get_entry(&validated_url, token_input)
+        .await
+    {
+        tracing::error!("[ERROR] Nightscout connection test failed: {}", e);
+        return respond_to_modal(
+            context,
+            &modal_response.interaction,
+            "Connection Failed",
+            "Could not connect to that Nightscout site. Please check the URL and token.",
+            Colour::RED,
+        )
+        .await;
+    }
+
+    handler
+        .database
+        .add_nightscout_site(user_id, name, &validated_url, token_input)
+        .await?;
+
+    tracing::info!("[SITES] User {} saved Nightscout site '{}'", user_id, name);
+
+    respond_to_modal(
+        context,
+        &modal_response.interaction,
+        "Site Saved",
+        &format!(
+            "**{}** ({}) has been saved as a private site.\n\nUse `/nightscout switch name:{}` to make it active.",
+            name, validated_url, name
+        ),
+        Colour::from_rgb(34, 197, 94),
+    )
+    .await
+}
+
+async fn switch_site(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+    name: &str,
+) -> anyhow::Result<()> {
+    let user_id = interaction.user.id.get();
+
+    if !handler
+        .database
+        .switch_nightscout_site(user_id, name)
+        .await?
+    {
+        crate::commands::error::run(
+            context,
+            interaction,
+            &format!(
+                "You don't have an inactive site called **{}**. Use `/nightscout list` to see your sites.",
+                name
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    tracing::info!(
+        "[SITES] User {} switched to Nightscout site '{}'",
+        user_id,
+        name
+    );
+
+    let embed = CreateEmbed::new()
+        .title("Active Site Changed")
+        .description(format!(
+            "**{}** is now your active Nightscout site. `/bg`, `/graph` and other commands will use it.",
+            name
+        ))
+        .color(Colour::from_rgb(34, 197, 94));
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+async fn remove_site(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+    name: &str,
+) -> anyhow::Result<()> {
+    let user_id = interaction.user.id.get();
+
+    if !handler
+        .database
+        .remove_nightscout_site(user_id, name)
+        .await?
+    {
+        crate::commands::error::run(
+            context,
+            interaction,
+            &format!(
+                "You don't have an inactive site called **{}**. The active site can't be removed; switch to another one first.",
+                name
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    tracing::info!(
+        "[SITES] User {} removed Nightscout site '{}'",
+        user_id,
+        name
+    );
+
+    let embed = CreateEmbed::new()
+        .title("Site Removed")
+        .description(format!("**{}** has been removed.", name))
+        .color(Colour::from_rgb(34, 197, 94));
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+async fn respond_to_modal(
+    context: &Context,
+    interaction: &ModalInteraction,
+    title: &str,
+    description: &str,
+    colour: Colour,
+) -> anyhow::Result<()> {
+    let embed = CreateEmbed::new()
+        .title(title)
+        .description(description)
+        .color(colour);
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+pub fn register() -> CreateCommand {
+    let name_option = |description: &str| {
+        CreateCommandOption::new(CommandOptionType::String, "name", description)
+            .max_length(MAX_NAME_LENGTH as u16)
+            .required(true)
+    };
+
+    CreateCommand::new("nightscout")
+        .description("Manage several Nightscout sites and choose which one is active")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "add",
+                "Save another Nightscout site",
+            )
+            .add_sub_option(name_option("A name for the site, e.g. \"Sam\"")),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "Show your saved Nightscout sites",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "switch",
+                "Make a saved site the active one",
+            )
+            .add_sub_option(name_option("The site to switch to")),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "remove",
+                "Remove a saved site",
+            )
+            .add_sub_option(name_option("The site to remove")),
+        )
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
@@ -0,0 +1,286 @@
+use crate::bot::Handler;
+use crate::commands::bg::format_ago;
+use crate::utils::nightscout::{DeviceStatus, SENSOR_CHANGE_EVENT_TYPES};
+use anyhow::Context as AnyhowContext;
+use chrono::{DateTime, Utc};
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateEmbed, CreateEmbedFooter,
+    EditInteractionResponse, InteractionContext, ResolvedOption, ResolvedValue,
+};
+use serenity::builder::{CreateCommand, CreateCommandOption};
+
+/// Batteries at or below this percentage are flagged
+const LOW_BATTERY_PERCENT: f32 = 20.0;
+/// Reservoirs at or below this many units are flagged
+const LOW_RESERVOIR_UNITS: f32 = 20.0;
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    // Nightscout can take longer than Discord's 3 second reply window, so acknowledge first
+    // and edit the reply in once the data is in
+    interaction.defer(&context.http).await?;
+
+    let target_user_id = if let Some(ResolvedOption {
+        value: ResolvedValue::User(user, _),
+        ..
+    }) = interaction.data.options().first()
+    {
+        user.id.get()
+    } else {
+        interaction.user.id.get()
+    };
+
+    let command_user_id = interaction.user.id.get();
+
+    if !handler.database.user_exists(target_user_id).await? {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "The specified user hasn't set up their Nightscout data yet.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let target_user_data = handler.database.get_user_info(target_user_id).await?;
+
+    if !target_user_data
+        .nightscout
+        .can_view(target_user_id, command_user_id)
+    {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "This user's blood glucose data is set to private.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let base_url = target_user_data
+        .nightscout
+        .nightscout_url
+        .as_deref()
+        .context("Nightscout URL missing")?;
+
+    if base_url.trim().is_empty() {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "Your Nightscout URL is empty. Please run `/setup` to configure it properly.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let token = target_user_data.nightscout.nightscout_token.as_deref();
+
+    let (device_status, sensor_change) = tokio::join!(
+        handler
+            .nightscout_client
+            .get_latest_device_status(base_url, token),
+        handler
+            .nightscout_client
+            .get_latest_treatment(base_url, token, SENSOR_CHANGE_EVENT_TYPES),
+    );
+
+    let device_status = match device_status {
+        Ok(status) => status,
+        Err(e) => {
+            crate::commands::error::nightscout(
+                context,
+                interaction,
+                &e,
+                target_user_id == command_user_id,
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let sensor_started = sensor_change
+        .ok()
+        .flatten()
+        .map(|treatment| treatment.millis_to_user_timezone("UTC").to_utc());
+
+    let now = Utc::now();
+    let fields = status_fields(device_status.as_ref(), sensor_started, now);
+
+    if fields.is_empty() {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "This Nightscout site hasn't reported any pump, phone or sensor details. They're uploaded by apps like Loop, AndroidAPS or xDrip+.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let status_age = device_status
+        .as_ref()
+        .and_then(DeviceStatus::timestamp)
+        .map(|time| minutes_between(time, now));
+    let is_stale = status_age.is_some_and(|age| age > target_user_data.nightscout.stale_minutes);
+
+    let mut embed = CreateEmbed::new()
+        .title("Device Status")
+        .fields(fields)
+        .color(if is_stale {
+            Colour::from_rgb(234, 179, 8)
+        } else {
+            Colour::from_rgb(59, 130, 246)
+        });
+
+    if is_stale {
+        embed = embed.description(
+            "⚠️ The last device status is old, so the uploader may be offline and these values may be out of date.",
+        );
+    }
+
+    let mut footer = match status_age {
+        Some(age) => format!("Device status updated {}", format_ago(age)),
+        None => "No recent device status".to_string(),
+    };
+    if let Some(device) = device_status
+        .as_ref()
+        .and_then(|status| status.device.as_deref())
+    {
+        footer.push_str(&format!(" • {}", device));
+    }
+    embed = embed.footer(CreateEmbedFooter::new(footer));
+
+    interaction
+        .edit_response(context, EditInteractionResponse::new().embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+/// Embed fields for every hardware detail the site reported, flagging low values
+fn status_fields(
+    status: Option<&DeviceStatus>,
+    sensor_started: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Vec<(String, String, bool)> {
+    let mut fields = Vec::new();
+    let pump = status.and_then(|status| status.pump.as_ref());
+
+    if let Some(battery) = pump.and_then(|pump| pump.battery.as_ref()) {
+        let value = match (battery.percent, battery.voltage) {
+            (Some(percent), _) => Some(format!(
+                "{:.0}%{}",
+                percent,
+                low_marker(percent <= LOW_BATTERY_PERCENT)
+            )),
+            (None, Some(voltage)) => Some(format!("{:.2}V", voltage)),
+            (None, None) => None,
+        };
+        if let Some(value) = value {
+            fields.push(("Pump Battery".to_string(), value, true));
+        }
+    }
+
+    if let Some(reservoir) = pump.and_then(|pump| pump.reservoir) {
+        fields.push((
+            "Reservoir".to_string(),
+            format!(
+                "{:.1}u{}",
+                reservoir,
+                low_marker(reservoir <= LOW_RESERVOIR_UNITS)
+            ),
+            true,
+        ));
+    }
+
+    if let Some(battery) = status.and_then(DeviceStatus::uploader_battery) {
+        fields.push((
+            "Phone Battery".to_string(),
+            format!(
+                "{:.0}%{}",
+                battery,
+                low_marker(battery <= LOW_BATTERY_PERCENT)
+            ),
+            true,
+        ));
+    }
+
+    if let Some(clock) = pump.and_then(|pump| pump.clock_time()) {
+        fields.push((
+            "Last Pump Contact".to_string(),
+            format_ago(minutes_between(clock, now)),
+            true,
+        ));
+    }
+
+    if let Some(started) = sensor_started {
+        let minutes = minutes_between(started, now);
+        fields.push((
+            "Sensor Age".to_string(),
+            format!("{}d {}h", minutes / (24 * 60), minutes % (24 * 60) / 60),
+            true,
+        ));
+    }
+
+    fields
+}
+
+fn low_marker(is_low: bool) -> &'static str {
+    if is_low { " ⚠️" } else { "" }
+}
+
+fn minutes_between(then: DateTime<Utc>, now: DateTime<Utc>) -> i64 {
+    now.signed_duration_since(then).num_minutes().max(0)
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("nightscout-status")
+        .description("Show pump, phone and sensor status from Nightscout")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::User, "user", "Target user.")
+                .required(false),
+        )
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_fields_flag_low_values() {
+        let now = DateTime::parse_from_rfc3339("2025-09-23T12:00:00Z")
+            .unwrap()
+            .to_utc();
+        let status: DeviceStatus = serde_json::from_value(serde_json::json!({
+            "uploaderBattery": 15,
+            "pump": {
+                "battery": { "percent": 80 },
+                "reservoir": 12.5,
+                "clock": "2025-09-23T11:55:00Z"
+            }
+        }))
+        .unwrap();
+        let sensor_started = now - chrono::Duration::hours(3 * 24 + 5);
+
+        let fields = status_fields(Some(&status), Some(sensor_started), now);
+        let value = |name: &str| {
+            fields
+                .iter()
+                .find(|(field, ..)| field == name)
+                .map(|(_, value, _)| value.as_str())
+        };
+
+        assert_eq!(value("Pump Battery"), Some("80%"));
+        assert_eq!(value("Reservoir"), Some("12.5u ⚠️"));
+        assert_eq!(value("Phone Battery"), Some("15% ⚠️"));
+        assert_eq!(value("Last Pump Contact"), Some("5m ago"));
+        assert_eq!(value("Sensor Age"), Some("3d 5h"));
+
+        assert!(status_fields(None, None, now).is_empty());
+    }
+}
@@ -0,0 +1,379 @@
+use crate::bot::Handler;
+use crate::utils::database::ReminderRepeat;
+use crate::utils::reminders::{first_occurrence, parse_time_of_day, parse_weekday};
+use chrono::Utc;
+use chrono_tz::Tz;
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext,
+    ResolvedOption, ResolvedValue,
+};
+
+/// Maximum number of pending reminders a single user can have
+const MAX_REMINDERS_PER_USER: usize = 25;
+const MAX_MESSAGE_LENGTH: usize = 200;
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let options = interaction.data.options();
+    let Some(ResolvedOption {
+        name: subcommand,
+        value: ResolvedValue::SubCommand(sub_options),
+        ..
+    }) = options.first()
+    else {
+        crate::commands::error::run(context, interaction, "Please choose a /remind subcommand.")
+            .await?;
+        return Ok(());
+    };
+
+    match *subcommand {
+        "in" | "at" => {
+            create_reminder(handler, context, interaction, subcommand, sub_options).await
+        }
+        "list" => list_reminders(handler, context, interaction).await,
+        "cancel" => cancel_reminder(handler, context, interaction, sub_options).await,
+        _ => crate::commands::error::run(context, interaction, "Unknown /remind subcommand.").await,
+    }
+}
+
+async fn create_reminder(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+    subcommand: &str,
+    options: &[ResolvedOption<'_>],
+) -> anyhow::Result<()> {
+    let user_id = interaction.user.id.get();
+
+    let mut minutes: Option<i64> = None;
+    let mut time: Option<&str> = None;
+    let mut day: Option<&str> = None;
+    let mut message: Option<&str> = None;
+    let mut repeat = ReminderRepeat::Once;
+
+    for option in options {
+        match option {
+            ResolvedOption {
+                name: "minutes",
+                value: ResolvedValue::Integer(val),
+                ..
+            } => minutes = Some(*val),
+            ResolvedOption {
+                name: "time",
+                value: ResolvedValue::String(val),
+                ..
+            } => time = Some(val),
+            ResolvedOption {
+                name: "day",
+                value: ResolvedValue::String(val),
+                ..
+            } => day = Some(val),
+            ResolvedOption {
+                name: "message",
+                value: ResolvedValue::String(val),
+                ..
+            } => message = Some(val),
+            ResolvedOption {
+                name: "repeat",
+                value: ResolvedValue::String(val),
+                ..
+            } => repeat = ReminderRepeat::from_str(val).unwrap_or(ReminderRepeat::Once),
+            _ => {}
+        }
+    }
+
+    let message = message.unwrap_or_default().trim();
+    if message.is_empty() || message.chars().count() > MAX_MESSAGE_LENGTH {
+        crate::commands::error::run(
+            context,
+            interaction,
+            &format!(
+                "Reminder messages must be between 1 and {} characters.",
+                MAX_MESSAGE_LENGTH
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let existing = handler.database.get_user_reminders(user_id).await?;
+    if existing.len() >= MAX_REMINDERS_PER_USER {
+        crate::commands::error::run(
+            context,
+            interaction,
+            &format!(
+                "You already have {} reminders. Cancel some with `/remind cancel` first.",
+                MAX_REMINDERS_PER_USER
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let timezone = handler.user_timezone(user_id).await;
+    let tz: Tz = timezone.parse().unwrap_or(chrono_tz::UTC);
+    let now = Utc::now();
+
+    let fire_at = if subcommand == "in" {
+        let minutes = minutes.unwrap_or(0);
+        Some(now + chrono::Duration::minutes(minutes))
+    } else {
+        let Some(time) = time.and_then(parse_time_of_day) else {
+            crate::commands::error::run(
+                context,
+                interaction,
+                "Invalid time. Use 24-hour `HH:MM`, for example `09:00` or `21:30`.",
+            )
+            .await?;
+            return Ok(());
+        };
+
+        let weekday = match day {
+            Some(day) => match parse_weekday(day) {
+                Some(weekday) => Some(weekday),
+                None => {
+                    crate::commands::error::run(context, interaction, "Invalid day of the week.")
+                        .await?;
+                    return Ok(());
+                }
+            },
+            None => None,
+        };
+
+        first_occurrence(now, tz, time, weekday)
+    };
+
+    let Some(fire_at) = fire_at else {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "Couldn't work out when that reminder should fire. Please try a different time.",
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let reminder_id = handler
+        .database
+        .insert_reminder(user_id, message, fire_at.timestamp(), repeat, &timezone)
+        .await?;
+
+    tracing::info!(
+        "[REMIND] User {} created reminder #{} ({}) for {}",
+        user_id,
+        reminder_id,
+        repeat.to_str(),
+        fire_at
+    );
+
+    let embed = CreateEmbed::new()
+        .title("Reminder Set")
+        .description(format!(
+            "**#{}** {}\n\nI'll DM you <t:{}:R> (<t:{}:f>).",
+            reminder_id,
+            message,
+            fire_at.timestamp(),
+            fire_at.timestamp()
+        ))
+        .field("Repeats", repeat.display_name(), true)
+        .field("Timezone", &timezone, true)
+        .color(Colour::from_rgb(34, 197, 94));
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+async fn list_reminders(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let reminders = handler
+        .database
+        .get_user_reminders(interaction.user.id.get())
+        .await?;
+
+    let description = if reminders.is_empty() {
+        "You don't have any reminders. Create one with `/remind in` or `/remind at`.".to_string()
+    } else {
+        reminders
+            .iter()
+            .map(|reminder| {
+                format!(
+                    "**#{}** <t:{}:f> • {} — {}",
+                    reminder.id,
+                    reminder.next_fire,
+                    reminder.repeat.display_name(),
+                    reminder.message
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let embed = CreateEmbed::new()
+        .title("Your Reminders")
+        .description(description)
+        .color(Colour::from_rgb(59, 130, 246));
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+async fn cancel_reminder(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+    options: &[ResolvedOption<'_>],
+) -> anyhow::Result<()> {
+    let reminder_id = options.iter().find_map(|option| match option {
+        ResolvedOption {
+            name: "id",
+            value: ResolvedValue::Integer(id),
+            ..
+        } => Some(*id),
+        _ => None,
+    });
+
+    let Some(reminder_id) = reminder_id else {
+        crate::commands::error::run(context, interaction, "Please provide a reminder id.").await?;
+        return Ok(());
+    };
+
+    if !handler
+        .database
+        .delete_reminder(interaction.user.id.get(), reminder_id)
+        .await?
+    {
+        crate::commands::error::run(
+            context,
+            interaction,
+            &format!(
+                "Reminder #{} wasn't found. Use `/remind list` to see your reminders.",
+                reminder_id
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let embed = CreateEmbed::new()
+        .title("Reminder Cancelled")
+        .description(format!("Reminder **#{}** has been cancelled.", reminder_id))
+        .color(Colour::from_rgb(34, 197, 94));
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+pub fn register() -> CreateCommand {
+    let message_option = || {
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "message",
+            "What to remind you about",
+        )
+        .max_length(MAX_MESSAGE_LENGTH as u16)
+        .required(true)
+    };
+
+    CreateCommand::new("remind")
+        .description("Get a DM reminder for a bolus, meal, site change, or anything else")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "in",
+                "Remind me after a number of minutes",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::Integer, "minutes", "Minutes from now")
+                    .min_int_value(1)
+                    .max_int_value(10080)
+                    .required(true),
+            )
+            .add_sub_option(message_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "at",
+                "Remind me at a time of day in my Nightscout timezone",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "time",
+                    "24-hour time, e.g. 09:00",
+                )
+                .required(true),
+            )
+            .add_sub_option(message_option())
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::String, "day", "Day of the week")
+                    .add_string_choice("Monday", "monday")
+                    .add_string_choice("Tuesday", "tuesday")
+                    .add_string_choice("Wednesday", "wednesday")
+                    .add_string_choice("Thursday", "thursday")
+                    .add_string_choice("Friday", "friday")
+                    .add_string_choice("Saturday", "saturday")
+                    .add_string_choice("Sunday", "sunday")
+                    .required(false),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "repeat",
+                    "How often to repeat",
+                )
+                .add_string_choice("Once", "once")
+                .add_string_choice("Daily", "daily")
+                .add_string_choice("Weekly", "weekly")
+                .required(false),
+            ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "Show your pending reminders",
+        ))
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "cancel", "Cancel a reminder")
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Integer,
+                        "id",
+                        "Reminder number from /remind list",
+                    )
+                    .required(true),
+                ),
+        )
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
@@ -0,0 +1,89 @@
+use crate::bot::Handler;
+use crate::bot::helpers::owner::is_owner;
+use crate::utils::database::{configured_salt, next_salt};
+use serenity::all::{
+    Colour, CommandInteraction, Context, CreateCommand, CreateEmbed, CreateEmbedFooter,
+    CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext, Permissions,
+};
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    if !is_owner(interaction.user.id.get()) {
+        tracing::warn!(
+            "[ADMIN] User {} tried to use /rotate-key",
+            interaction.user.id.get()
+        );
+        crate::commands::error::run(
+            context,
+            interaction,
+            "This command is only available to the bot owner.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let old_salt = configured_salt();
+    let Some(new_salt) = next_salt() else {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "Set `ENCRYPTION_SALT_NEXT` on the server to the new salt, then run this again.",
+        )
+        .await?;
+        return Ok(());
+    };
+    if new_salt == old_salt {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "`ENCRYPTION_SALT_NEXT` is the same as the current `ENCRYPTION_SALT`.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    tracing::info!(
+        "[ADMIN] Owner {} started an encryption key rotation",
+        interaction.user.id.get()
+    );
+    let report = handler.database.reencrypt_all(&old_salt, &new_salt).await?;
+
+    let embed = CreateEmbed::new()
+        .title("Encryption Key Rotated")
+        .description(
+            "Stored tokens now use the key derived from the new salt, and the running bot has switched to it.\n\n**Set `ENCRYPTION_SALT` to the value of `ENCRYPTION_SALT_NEXT` before the next restart**, or tokens will become unreadable.",
+        )
+        .field("Re-encrypted", report.reencrypted.to_string(), true)
+        .field("Already current", report.already_current.to_string(), true)
+        .field("Unreadable", report.failed.to_string(), true)
+        .footer(CreateEmbedFooter::new(
+            "Unreadable tokens were left untouched; those users need to set their token again.",
+        ))
+        .color(Colour::from_rgb(34, 197, 94));
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("rotate-key")
+        .description(
+            "Bot owner only: re-encrypt stored tokens with the salt in ENCRYPTION_SALT_NEXT",
+        )
+        // Hidden from members by default; the owner check in `run` is what actually gates it
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
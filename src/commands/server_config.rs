@@ -0,0 +1,192 @@
+use crate::bot::Handler;
+use serenity::all::{
+    ChannelType, Colour, CommandInteraction, CommandOptionType, Context, CreateCommand,
+    CreateCommandOption, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+    InteractionContext, Permissions, ResolvedOption, ResolvedValue,
+};
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = interaction.guild_id else {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "`/server-config` can only be used inside a server.",
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let is_admin = interaction
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.manage_guild());
+
+    if !is_admin {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "You need the **Manage Server** permission to change Beetroot's server settings.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let mut settings = handler.database.get_guild_settings(guild_id.get()).await?;
+    let mut changes = Vec::new();
+
+    for option in &interaction.data.options() {
+        match option {
+            ResolvedOption {
+                name: "private_by_default",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => {
+                settings.force_private = *val;
+                changes.push(if *val {
+                    "Members' data is now private by default in this server".to_string()
+                } else {
+                    "Members' own visibility settings now apply in this server".to_string()
+                });
+            }
+            ResolvedOption {
+                name: "allow_channel",
+                value: ResolvedValue::Channel(channel),
+                ..
+            } => {
+                let channel_id = channel.id.get();
+                if !settings.allowed_channels.contains(&channel_id) {
+                    settings.allowed_channels.push(channel_id);
+                }
+                changes.push(format!("Allowed glucose commands in <#{}>", channel_id));
+            }
+            ResolvedOption {
+                name: "remove_channel",
+                value: ResolvedValue::Channel(channel),
+                ..
+            } => {
+                let channel_id = channel.id.get();
+                settings.allowed_channels.retain(|&id| id != channel_id);
+                changes.push(format!(
+                    "Removed <#{}> from the allowed channels",
+                    channel_id
+                ));
+            }
+            ResolvedOption {
+                name: "clear_channels",
+                value: ResolvedValue::Boolean(true),
+                ..
+            } => {
+                settings.allowed_channels.clear();
+                changes.push("Glucose commands can now be used in any channel".to_string());
+            }
+            _ => {}
+        }
+    }
+
+    if !changes.is_empty() {
+        handler
+            .database
+            .set_guild_settings(guild_id.get(), &settings)
+            .await?;
+
+        tracing::info!(
+            "[CONFIG] Guild {} settings updated by {}: force_private={}, allowed_channels={:?}",
+            guild_id,
+            interaction.user.id,
+            settings.force_private,
+            settings.allowed_channels
+        );
+    }
+
+    let channels = if settings.allowed_channels.is_empty() {
+        "Any channel".to_string()
+    } else {
+        settings
+            .allowed_channels
+            .iter()
+            .map(|id| format!("<#{}>", id))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let (title, description, colour) = if changes.is_empty() {
+        (
+            "Server Settings",
+            "Current Beetroot settings for this server.".to_string(),
+            Colour::from_rgb(59, 130, 246),
+        )
+    } else {
+        (
+            "Server Settings Updated",
+            changes.join("\n"),
+            Colour::from_rgb(34, 197, 94),
+        )
+    };
+
+    let embed = CreateEmbed::new()
+        .title(title)
+        .description(description)
+        .field(
+            "Private by default",
+            if settings.force_private { "Yes" } else { "No" },
+            true,
+        )
+        .field("Glucose command channels", channels, true)
+        .color(colour);
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("server-config")
+        .description("Configure Beetroot for this server (admins only)")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "private_by_default",
+                "Only let members see data from people who explicitly allowed them",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Channel,
+                "allow_channel",
+                "Allow /bg, /graph and other glucose commands in this channel",
+            )
+            .channel_types(vec![ChannelType::Text])
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Channel,
+                "remove_channel",
+                "Stop allowing glucose commands in this channel",
+            )
+            .channel_types(vec![ChannelType::Text])
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "clear_channels",
+                "Allow glucose commands in every channel again",
+            )
+            .required(false),
+        )
+        .contexts(vec![InteractionContext::Guild])
+}
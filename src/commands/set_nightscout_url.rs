@@ -78,14 +78,15 @@ pub async fn run(
             Ok(_) => {
                 tracing::info!("[OK] Nightscout connection test successful");
 
+                let previous_url = current_user_info.nightscout.nightscout_url.clone();
+
                 // Update the URL
                 let updated_nightscout_info = crate::utils::database::NightscoutInfo {
                     nightscout_url: Some(validated_url.clone()),
                     nightscout_token: current_user_info.nightscout.nightscout_token,
                     is_private: current_user_info.nightscout.is_private,
                     allowed_people: current_user_info.nightscout.allowed_people,
-                    microbolus_threshold: current_user_info.nightscout.microbolus_threshold,
-                    display_microbolus: current_user_info.nightscout.display_microbolus,
+                    ..current_user_info.nightscout
                 };
 
                 let user_id = interaction.user.id.get();
@@ -95,6 +96,17 @@ pub async fn run(
                     .await
                 {
                     Ok(_) => {
+                        if let Some(previous_url) = previous_url.as_deref() {
+                            handler
+                                .nightscout_client
+                                .invalidate_profile(previous_url)
+                                .await;
+                        }
+                        handler
+                            .nightscout_client
+                            .invalidate_profile(&validated_url)
+                            .await;
+
                         let success_embed = CreateEmbed::new()
                             .title("URL Updated")
                             .description(format!(
@@ -160,7 +172,7 @@ pub async fn run(
     Ok(())
 }
 
-fn validate_and_fix_url(input: &str) -> Result<String, String> {
+pub fn validate_and_fix_url(input: &str) -> Result<String, String> {
     let mut url = input.trim().to_string();
 
     // Check for empty or whitespace-only input
@@ -0,0 +1,236 @@
+use crate::bot::Handler;
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext,
+    ResolvedOption, ResolvedValue,
+};
+
+/// Range inputs below this are treated as mmol/L
+const MMOL_INPUT_CEILING: f32 = 30.0;
+const MIN_TARGET_MG: f32 = 40.0;
+const MAX_TARGET_MG: f32 = 400.0;
+const MIN_RANGE_SPAN_MG: f32 = 20.0;
+const MIN_URGENT_LOW_MG: f32 = 30.0;
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let user_id = interaction.user.id.get();
+
+    if !handler.database.user_exists(user_id).await? {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "You need to run `/setup` first to configure your Nightscout before setting a target range.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let mut low: Option<f32> = None;
+    let mut high: Option<f32> = None;
+    let mut urgent_low: Option<f32> = None;
+    let mut reset = false;
+
+    for option in &interaction.data.options() {
+        match option {
+            ResolvedOption {
+                name: "low",
+                value: ResolvedValue::Number(val),
+                ..
+            } => low = Some(*val as f32),
+            ResolvedOption {
+                name: "high",
+                value: ResolvedValue::Number(val),
+                ..
+            } => high = Some(*val as f32),
+            ResolvedOption {
+                name: "urgent_low",
+                value: ResolvedValue::Number(val),
+                ..
+            } => urgent_low = Some(*val as f32),
+            ResolvedOption {
+                name: "reset",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => reset = *val,
+            _ => {}
+        }
+    }
+
+    let embed = if reset {
+        handler.database.update_target_range(user_id, None).await?;
+        handler.database.update_urgent_low(user_id, None).await?;
+        tracing::info!("[RANGE] User {} reset their target range", user_id);
+
+        CreateEmbed::new()
+            .title("Target Range Reset")
+            .description("Beetroot will use the targets from your Nightscout site again.")
+            .color(Colour::from_rgb(34, 197, 94))
+    } else {
+        let range_mg = match (low, high, urgent_low) {
+            (Some(low), Some(high), _) => Some((to_mg_dl(low), to_mg_dl(high))),
+            (None, None, Some(_)) => None,
+            _ => {
+                crate::commands::error::run(
+                    context,
+                    interaction,
+                    "Please provide both `low` and `high`, an `urgent_low`, or use `reset:True` to go back to your Nightscout targets.",
+                )
+                .await?;
+                return Ok(());
+            }
+        };
+
+        if let Some((low_mg, high_mg)) = range_mg
+            && (low_mg < MIN_TARGET_MG
+                || high_mg > MAX_TARGET_MG
+                || high_mg - low_mg < MIN_RANGE_SPAN_MG)
+        {
+            crate::commands::error::run(
+                context,
+                interaction,
+                &format!(
+                    "Targets must be between {:.0} and {:.0} mg/dL ({:.1}–{:.1} mmol/L), with high at least {:.0} mg/dL above low.",
+                    MIN_TARGET_MG,
+                    MAX_TARGET_MG,
+                    MIN_TARGET_MG / 18.0,
+                    MAX_TARGET_MG / 18.0,
+                    MIN_RANGE_SPAN_MG
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+
+        let urgent_low_mg = urgent_low.map(to_mg_dl);
+        if let Some(urgent_mg) = urgent_low_mg {
+            let low_mg = match range_mg {
+                Some((low_mg, _)) => low_mg,
+                None => handler.user_target_range_mg(user_id).await.0,
+            };
+            if urgent_mg < MIN_URGENT_LOW_MG || urgent_mg >= low_mg {
+                crate::commands::error::run(
+                    context,
+                    interaction,
+                    &format!(
+                        "Your urgent low must be at least {:.0} mg/dL ({:.1} mmol/L) and below your low target of {:.0} mg/dL ({:.1} mmol/L).",
+                        MIN_URGENT_LOW_MG,
+                        MIN_URGENT_LOW_MG / 18.0,
+                        low_mg,
+                        low_mg / 18.0
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+        }
+
+        let mut lines = Vec::new();
+        if let Some((low_mg, high_mg)) = range_mg {
+            handler
+                .database
+                .update_target_range(user_id, Some((low_mg, high_mg)))
+                .await?;
+            tracing::info!(
+                "[RANGE] User {} set target range {:.0}-{:.0} mg/dL",
+                user_id,
+                low_mg,
+                high_mg
+            );
+            lines.push(format!(
+                "Your target range is now **{:.0}–{:.0} mg/dL** ({:.1}–{:.1} mmol/L).\n`/bg` colours, graphs, stickers and time in range will use it instead of your Nightscout targets.",
+                low_mg,
+                high_mg,
+                low_mg / 18.0,
+                high_mg / 18.0
+            ));
+        }
+        if let Some(urgent_mg) = urgent_low_mg {
+            handler
+                .database
+                .update_urgent_low(user_id, Some(urgent_mg))
+                .await?;
+            tracing::info!(
+                "[RANGE] User {} set urgent low {:.0} mg/dL",
+                user_id,
+                urgent_mg
+            );
+            lines.push(format!(
+                "Your urgent low is now **{:.0} mg/dL** ({:.1} mmol/L). Readings below it stand out on your graphs.",
+                urgent_mg,
+                urgent_mg / 18.0
+            ));
+        }
+
+        CreateEmbed::new()
+            .title("Target Range Updated")
+            .description(lines.join("\n\n"))
+            .color(Colour::from_rgb(34, 197, 94))
+    };
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+/// Values this small can only be mmol/L, so accept either unit
+fn to_mg_dl(value: f32) -> f32 {
+    if value < MMOL_INPUT_CEILING {
+        value * 18.0
+    } else {
+        value
+    }
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("set-range")
+        .description("Set your own low and high targets instead of using Nightscout's")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Number,
+                "low",
+                "Bottom of your target range (mg/dL or mmol/L)",
+            )
+            .min_number_value(0.0)
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Number,
+                "high",
+                "Top of your target range (mg/dL or mmol/L)",
+            )
+            .min_number_value(0.0)
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Number,
+                "urgent_low",
+                "Readings below this are highlighted as urgent on graphs (mg/dL or mmol/L)",
+            )
+            .min_number_value(0.0)
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "reset",
+                "Go back to the targets from your Nightscout site",
+            )
+            .required(false),
+        )
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
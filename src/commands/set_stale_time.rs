@@ -0,0 +1,138 @@
+use crate::bot::Handler;
+use crate::utils::nightscout::STALE_DATA_MINUTES;
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext,
+    ResolvedOption, ResolvedValue,
+};
+
+const MIN_STALE_MINUTES: i64 = 5;
+const MAX_STALE_MINUTES: i64 = 240;
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let user_id = interaction.user.id.get();
+
+    if !handler.database.user_exists(user_id).await? {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "You need to run `/setup` first to configure your Nightscout before changing when data counts as old.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let mut minutes: Option<i64> = None;
+    let mut reset = false;
+
+    for option in &interaction.data.options() {
+        match option {
+            ResolvedOption {
+                name: "minutes",
+                value: ResolvedValue::Integer(val),
+                ..
+            } => minutes = Some(*val),
+            ResolvedOption {
+                name: "reset",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => reset = *val,
+            _ => {}
+        }
+    }
+
+    let embed = if reset {
+        handler.database.update_stale_minutes(user_id, None).await?;
+        tracing::info!("[STALE] User {} reset their stale data threshold", user_id);
+
+        CreateEmbed::new()
+            .title("Stale Data Threshold Reset")
+            .description(format!(
+                "Readings and loop data older than {} minutes will be flagged as old again.",
+                STALE_DATA_MINUTES
+            ))
+            .color(Colour::from_rgb(34, 197, 94))
+    } else {
+        let Some(minutes) = minutes else {
+            crate::commands::error::run(
+                context,
+                interaction,
+                "Please provide `minutes`, or use `reset:True` to go back to the default.",
+            )
+            .await?;
+            return Ok(());
+        };
+
+        if !(MIN_STALE_MINUTES..=MAX_STALE_MINUTES).contains(&minutes) {
+            crate::commands::error::run(
+                context,
+                interaction,
+                &format!(
+                    "The threshold must be between {} and {} minutes.",
+                    MIN_STALE_MINUTES, MAX_STALE_MINUTES
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+
+        handler
+            .database
+            .update_stale_minutes(user_id, Some(minutes))
+            .await?;
+        tracing::info!(
+            "[STALE] User {} set their stale data threshold to {} minutes",
+            user_id,
+            minutes
+        );
+
+        CreateEmbed::new()
+            .title("Stale Data Threshold Updated")
+            .description(format!(
+                "`/bg` and your graphs will warn when the latest reading is more than {} minutes old, and IOB, COB and predictions older than that will be hidden.",
+                minutes
+            ))
+            .color(Colour::from_rgb(34, 197, 94))
+    };
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("set-stale-time")
+        .description("Choose how old your data can get before Beetroot warns that it's stale")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "minutes",
+                "Minutes after which readings and loop data count as old (default 15)",
+            )
+            .min_int_value(MIN_STALE_MINUTES as u64)
+            .max_int_value(MAX_STALE_MINUTES as u64)
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "reset",
+                "Go back to the default of 15 minutes",
+            )
+            .required(false),
+        )
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
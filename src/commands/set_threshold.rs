@@ -1,10 +1,14 @@
 use crate::bot::Handler;
+use crate::utils::nightscout::Treatment;
 use serenity::all::{
     Colour, CommandInteraction, CommandOptionType, Context, CreateEmbed, CreateInteractionResponse,
     CreateInteractionResponseMessage, InteractionContext, ResolvedOption, ResolvedValue,
 };
 use serenity::builder::{CreateCommand, CreateCommandOption};
 
+/// How far back `/set-threshold` looks to preview the effect of the new threshold
+const PREVIEW_HOURS: i64 = 6;
+
 pub async fn run(
     handler: &Handler,
     context: &Context,
@@ -51,12 +55,37 @@ pub async fn run(
         return Ok(());
     }
 
+    let user_id = interaction.user.id.get();
+    let previous = handler.database.get_user_info(user_id).await?.nightscout;
+
     handler
         .database
-        .update_microbolus_settings(interaction.user.id.get(), threshold, display)
+        .update_microbolus_settings(user_id, threshold, display)
         .await?;
 
-    let embed = CreateEmbed::new()
+    // Show how the new threshold reclassifies recent doses; the update doesn't depend on it
+    let preview = match previous.nightscout_url.as_deref() {
+        Some(base_url) if !base_url.trim().is_empty() => {
+            let now = chrono::Utc::now();
+            let start_time = (now - chrono::Duration::hours(PREVIEW_HOURS)).to_rfc3339();
+            handler
+                .nightscout_client
+                .fetch_treatments_between(
+                    base_url,
+                    &start_time,
+                    &now.to_rfc3339(),
+                    previous.nightscout_token.as_deref(),
+                )
+                .await
+                .ok()
+                .map(|treatments| {
+                    preview_text(&treatments, previous.microbolus_threshold, threshold)
+                })
+        }
+        _ => None,
+    };
+
+    let mut embed = CreateEmbed::new()
         .title("Microbolus Threshold Updated")
         .description(format!(
             "**Threshold:** {:.1} units\n**Display on graph:** {}\n\nInsulin doses {} {:.1}u will be considered microbolus injections.",
@@ -66,6 +95,9 @@ pub async fn run(
             threshold
         ))
         .color(Colour::from_rgb(34, 197, 94));
+    if let Some(preview) = preview {
+        embed = embed.field(format!("Last {} hours", PREVIEW_HOURS), preview, false);
+    }
 
     let response = CreateInteractionResponseMessage::new()
         .embed(embed)
@@ -78,6 +110,38 @@ pub async fn run(
     Ok(())
 }
 
+/// Summarize how recent boluses are split between micro and manual at the new threshold
+fn preview_text(treatments: &[Treatment], old_threshold: f32, new_threshold: f32) -> String {
+    let boluses: Vec<&Treatment> = treatments.iter().filter(|t| t.is_insulin()).collect();
+    if boluses.is_empty() {
+        return "No boluses to reclassify.".to_string();
+    }
+
+    let count_micro = |threshold: f32| {
+        boluses
+            .iter()
+            .filter(|bolus| bolus.is_microbolus(threshold))
+            .count()
+    };
+    let (micro, was_micro) = (count_micro(new_threshold), count_micro(old_threshold));
+
+    let mut text = format!(
+        "{} microbolus{} and {} manual bolus{}",
+        micro,
+        if micro == 1 { "" } else { "es" },
+        boluses.len() - micro,
+        if boluses.len() - micro == 1 { "" } else { "es" }
+    );
+    if micro != was_micro {
+        text.push_str(&format!(
+            " (was {} micro and {} manual)",
+            was_micro,
+            boluses.len() - was_micro
+        ));
+    }
+    text
+}
+
 pub fn register() -> CreateCommand {
     CreateCommand::new("set-threshold")
         .description("Set microbolus threshold and display preferences")
@@ -104,3 +168,29 @@ pub fn register() -> CreateCommand {
             InteractionContext::PrivateChannel,
         ])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_counts_reclassified_boluses() {
+        let treatments: Vec<Treatment> = serde_json::from_value(serde_json::json!([
+            { "insulin": 0.2, "type": "SMB" },
+            { "insulin": 0.8 },
+            { "insulin": 4.0 },
+            { "carbs": 30 }
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            preview_text(&treatments, 0.5, 1.0),
+            "2 microboluses and 1 manual bolus (was 1 micro and 2 manual)"
+        );
+        assert_eq!(
+            preview_text(&treatments, 1.0, 1.0),
+            "2 microboluses and 1 manual bolus"
+        );
+        assert_eq!(preview_text(&[], 0.5, 1.0), "No boluses to reclassify.");
+    }
+}
@@ -0,0 +1,256 @@
+use crate::bot::Handler;
+use crate::commands::MAX_AUTOCOMPLETE_CHOICES;
+use chrono::Utc;
+use chrono_tz::{TZ_VARIANTS, Tz};
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateAutocompleteResponse,
+    CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage, InteractionContext, ResolvedOption, ResolvedValue,
+};
+
+/// How many similar timezone names to suggest for an invalid input
+const MAX_SUGGESTIONS: usize = 3;
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let user_id = interaction.user.id.get();
+
+    if !handler.database.user_exists(user_id).await? {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "You need to run `/setup` first to configure your Nightscout before setting a timezone.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let mut timezone: Option<&str> = None;
+    let mut reset = false;
+
+    for option in &interaction.data.options() {
+        match option {
+            ResolvedOption {
+                name: "timezone",
+                value: ResolvedValue::String(val),
+                ..
+            } => timezone = Some(val.trim()),
+            ResolvedOption {
+                name: "reset",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => reset = *val,
+            _ => {}
+        }
+    }
+
+    let embed = if reset {
+        handler
+            .database
+            .update_timezone_override(user_id, None)
+            .await?;
+        tracing::info!("[TIMEZONE] User {} reset their timezone override", user_id);
+
+        CreateEmbed::new()
+            .title("Timezone Reset")
+            .description("Beetroot will use the timezone from your Nightscout profile again.")
+            .color(Colour::from_rgb(34, 197, 94))
+    } else {
+        let Some(input) = timezone.filter(|tz| !tz.is_empty()) else {
+            crate::commands::error::run(
+                context,
+                interaction,
+                "Please provide a timezone such as `Europe/Paris`, or use `reset:True` to go back to your Nightscout profile's timezone.",
+            )
+            .await?;
+            return Ok(());
+        };
+
+        let Some(tz) = parse_timezone(input) else {
+            let suggestions = suggest_timezones(input);
+            let hint = if suggestions.is_empty() {
+                String::new()
+            } else {
+                format!(" Did you mean {}?", suggestions.join(", "))
+            };
+            crate::commands::error::run(
+                context,
+                interaction,
+                &format!(
+                    "`{}` isn't a valid IANA timezone. Use a `Region/City` name like `America/New_York` or `Europe/Paris`.{}",
+                    input, hint
+                ),
+            )
+            .await?;
+            return Ok(());
+        };
+
+        handler
+            .database
+            .update_timezone_override(user_id, Some(tz.name()))
+            .await?;
+        tracing::info!(
+            "[TIMEZONE] User {} set timezone override to {}",
+            user_id,
+            tz.name()
+        );
+
+        CreateEmbed::new()
+            .title("Timezone Updated")
+            .description(format!(
+                "Your timezone is now **{}** (currently {}).\n\nGraphs, `/bg` and reminders will use it instead of your Nightscout profile's timezone.",
+                tz.name(),
+                Utc::now().with_timezone(&tz).format("%a %H:%M")
+            ))
+            .color(Colour::from_rgb(34, 197, 94))
+    };
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+/// Suggest timezone names as the user types the `timezone` option
+pub async fn autocomplete(
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let input = interaction
+        .data
+        .autocomplete()
+        .filter(|option| option.name == "timezone")
+        .map(|option| option.value)
+        .unwrap_or_default();
+
+    let response = timezone_matches(input)
+        .into_iter()
+        .fold(CreateAutocompleteResponse::new(), |response, name| {
+            response.add_string_choice(name, name)
+        });
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Autocomplete(response))
+        .await?;
+
+    Ok(())
+}
+
+/// Timezone names matching what's been typed so far. Names with a part starting with the
+/// input come before names that only contain it somewhere.
+fn timezone_matches(input: &str) -> Vec<&'static str> {
+    let needle = input.trim().to_lowercase().replace(' ', "_");
+    let mut matches: Vec<(bool, &'static str)> = TZ_VARIANTS
+        .iter()
+        .map(|tz| tz.name())
+        .filter_map(|name| {
+            let lower = name.to_lowercase();
+            if !lower.contains(&needle) {
+                return None;
+            }
+            let segment_start = lower
+                .split(['/', '_'])
+                .any(|segment| segment.starts_with(&needle));
+            Some((!segment_start, name))
+        })
+        .collect();
+
+    matches.sort();
+    matches
+        .into_iter()
+        .take(MAX_AUTOCOMPLETE_CHOICES)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// Parse an IANA timezone name, ignoring case
+fn parse_timezone(input: &str) -> Option<Tz> {
+    input.parse::<Tz>().ok().or_else(|| {
+        TZ_VARIANTS
+            .iter()
+            .find(|tz| tz.name().eq_ignore_ascii_case(input))
+            .copied()
+    })
+}
+
+/// Timezone names containing the input, e.g. `paris` suggests `Europe/Paris`
+fn suggest_timezones(input: &str) -> Vec<String> {
+    let needle = input.to_lowercase().replace(' ', "_");
+    TZ_VARIANTS
+        .iter()
+        .map(|tz| tz.name())
+        .filter(|name| name.to_lowercase().contains(&needle))
+        .take(MAX_SUGGESTIONS)
+        .map(|name| format!("`{}`", name))
+        .collect()
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("set-timezone")
+        .description("Use a different timezone than the one in your Nightscout profile")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "timezone",
+                "IANA timezone name, e.g. Europe/Paris",
+            )
+            .set_autocomplete(true)
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "reset",
+                "Go back to the timezone from your Nightscout profile",
+            )
+            .required(false),
+        )
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timezone_ignores_case() {
+        assert_eq!(
+            parse_timezone("Europe/Paris"),
+            Some(chrono_tz::Europe::Paris)
+        );
+        assert_eq!(
+            parse_timezone("europe/paris"),
+            Some(chrono_tz::Europe::Paris)
+        );
+        assert_eq!(parse_timezone("Mars/Olympus"), None);
+    }
+
+    #[test]
+    fn test_timezone_matches_for_autocomplete() {
+        let matches = timezone_matches("paris");
+        assert_eq!(matches.first(), Some(&"Europe/Paris"));
+
+        let matches = timezone_matches("york");
+        assert!(matches.contains(&"America/New_York"));
+
+        assert_eq!(timezone_matches("").len(), MAX_AUTOCOMPLETE_CHOICES);
+        assert!(timezone_matches("zzz").is_empty());
+    }
+
+    #[test]
+    fn test_suggest_timezones_by_city() {
+        assert_eq!(suggest_timezones("new york"), vec!["`America/New_York`"]);
+        assert!(suggest_timezones("zzz").is_empty());
+    }
+}
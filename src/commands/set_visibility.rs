@@ -76,8 +76,7 @@ pub async fn run(
         nightscout_token: current_user_info.nightscout.nightscout_token,
         is_private,
         allowed_people: current_user_info.nightscout.allowed_people,
-        microbolus_threshold: current_user_info.nightscout.microbolus_threshold,
-        display_microbolus: current_user_info.nightscout.display_microbolus,
+        ..current_user_info.nightscout
     };
 
     let user_id = interaction.user.id.get();
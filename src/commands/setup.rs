@@ -134,8 +134,7 @@ pub async fn handle_button(
         nightscout_token: token,
         allowed_people: vec![],
         is_private,
-        microbolus_threshold: 0.5,
-        display_microbolus: true,
+        ..Default::default()
     };
 
     let user_id = interaction.user.id.get();
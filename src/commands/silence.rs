@@ -0,0 +1,111 @@
+use crate::bot::Handler;
+use chrono::Utc;
+use chrono_tz::Tz;
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext,
+    ResolvedOption, ResolvedValue,
+};
+
+/// Longest a user can silence notifications for in one go (one week)
+const MAX_SILENCE_MINUTES: i64 = 7 * 24 * 60;
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let user_id = interaction.user.id.get();
+
+    let minutes = interaction
+        .data
+        .options()
+        .iter()
+        .find_map(|option| match option {
+            ResolvedOption {
+                name: "minutes",
+                value: ResolvedValue::Integer(val),
+                ..
+            } => Some(*val),
+            _ => None,
+        })
+        .unwrap_or(0);
+
+    if !(0..=MAX_SILENCE_MINUTES).contains(&minutes) {
+        crate::commands::error::run(
+            context,
+            interaction,
+            &format!(
+                "You can silence notifications for up to {} minutes.",
+                MAX_SILENCE_MINUTES
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let embed = if minutes == 0 {
+        handler.database.update_snooze_until(user_id, None).await?;
+        tracing::info!("[SILENCE] User {} resumed notifications", user_id);
+
+        CreateEmbed::new()
+            .title("Notifications Resumed")
+            .description("Beetroot notifications are no longer silenced.")
+            .color(Colour::from_rgb(34, 197, 94))
+    } else {
+        let resume_at = Utc::now() + chrono::Duration::minutes(minutes);
+        handler
+            .database
+            .update_snooze_until(user_id, Some(resume_at.timestamp()))
+            .await?;
+
+        let timezone = handler.user_timezone(user_id).await;
+        let tz: Tz = timezone.parse().unwrap_or(chrono_tz::UTC);
+        let local_resume = resume_at.with_timezone(&tz);
+
+        tracing::info!(
+            "[SILENCE] User {} silenced notifications until {}",
+            user_id,
+            resume_at
+        );
+
+        CreateEmbed::new()
+            .title("Notifications Silenced")
+            .description(format!(
+                "Notifications will resume at **{}** ({}), <t:{}:R>.\n\nRun `/silence` with `0` minutes to resume early.",
+                local_resume.format("%a %H:%M"),
+                timezone,
+                resume_at.timestamp()
+            ))
+            .color(Colour::from_rgb(59, 130, 246))
+    };
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("silence")
+        .description("Temporarily silence Beetroot notifications")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "minutes",
+                "How long to stay silent (0 resumes notifications now)",
+            )
+            .min_int_value(0)
+            .max_int_value(MAX_SILENCE_MINUTES as u64)
+            .required(true),
+        )
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
@@ -0,0 +1,298 @@
+use crate::bot::Handler;
+use crate::utils::database::NightscoutInfo;
+use crate::utils::nightscout::{Entry, NightscoutError, NightscoutRequestOptions};
+use crate::utils::stats::{GlucoseSummary, daily_means, sparkline};
+use anyhow::Context as AnyhowContext;
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateEmbed, CreateEmbedFooter,
+    EditInteractionResponse, InteractionContext, ResolvedOption, ResolvedValue,
+};
+use serenity::builder::{CreateCommand, CreateCommandOption};
+
+const DEFAULT_DAYS: i64 = 7;
+const MAX_DAYS: i64 = 14;
+/// CGM readings per hour, used to size the entries request for the window
+const READINGS_PER_HOUR: u16 = 12;
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    // Nightscout can take longer than Discord's 3 second reply window, so acknowledge first
+    // and edit the reply in once the data is in
+    interaction.defer(&context.http).await?;
+
+    let command_user_id = interaction.user.id.get();
+    let mut target_user_id = command_user_id;
+    let mut days = DEFAULT_DAYS;
+
+    for option in &interaction.data.options() {
+        match option {
+            ResolvedOption {
+                name: "user",
+                value: ResolvedValue::User(user, _),
+                ..
+            } => target_user_id = user.id.get(),
+            ResolvedOption {
+                name: "days",
+                value: ResolvedValue::Integer(d),
+                ..
+            } => days = (*d).clamp(1, MAX_DAYS),
+            _ => {}
+        }
+    }
+
+    if !handler.database.user_exists(target_user_id).await? {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "The specified user hasn't set up their Nightscout data yet.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let target_user_data = handler.database.get_user_info(target_user_id).await?;
+
+    if !target_user_data
+        .nightscout
+        .can_view(target_user_id, command_user_id)
+    {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "This user's blood glucose data is set to private.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let base_url = target_user_data
+        .nightscout
+        .nightscout_url
+        .as_deref()
+        .context("Nightscout URL missing")?;
+
+    if base_url.trim().is_empty() {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "Your Nightscout URL is empty. Please run `/setup` to configure it properly.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let window = match fetch_stats_window(handler, &target_user_data.nightscout, days * 24).await {
+        Ok(window) => window,
+        Err(e) => {
+            eprintln!("Failed to get entries for user {}: {}", target_user_id, e);
+            match e.downcast_ref::<NightscoutError>() {
+                Some(error) => {
+                    crate::commands::error::nightscout(
+                        context,
+                        interaction,
+                        error,
+                        target_user_id == command_user_id,
+                    )
+                    .await?
+                }
+                None => {
+                    crate::commands::error::run(
+                        context,
+                        interaction,
+                        "Could not fetch glucose data from Nightscout. Please check the URL configuration with `/setup`.",
+                    )
+                    .await?
+                }
+            }
+            return Ok(());
+        }
+    };
+
+    let Some(summary) =
+        GlucoseSummary::from_entries(&window.entries, window.target_low_mg, window.target_high_mg)
+    else {
+        crate::commands::error::run(
+            context,
+            interaction,
+            &format!("No glucose readings were found in the last {} days.", days),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let daily = daily_means(&window.entries, &window.timezone);
+    let daily_values: Vec<f32> = daily.iter().map(|(_, mean)| *mean).collect();
+
+    let trend = match (daily.first(), daily.last()) {
+        (Some((first_day, _)), Some((last_day, _))) if daily.len() > 1 => format!(
+            "`{}`\n-# Daily average, {} → {}",
+            sparkline(&daily_values),
+            first_day.format("%m/%d"),
+            last_day.format("%m/%d")
+        ),
+        _ => "-# Not enough days for a trend yet".to_string(),
+    };
+
+    let embed = summary_embed(&summary, window.target_low_mg, window.target_high_mg)
+        .title(format!("Last {} days", days))
+        .description(trend);
+
+    interaction
+        .edit_response(context, EditInteractionResponse::new().embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+/// Cleaned readings from the last `hours` with the user's target range and timezone
+pub struct StatsWindow {
+    pub entries: Vec<Entry>,
+    pub target_low_mg: f32,
+    pub target_high_mg: f32,
+    pub timezone: String,
+}
+
+/// Fetch everything needed for glucose statistics over the last `hours`, resolving targets
+/// and timezone the same way as `/bg`
+pub async fn fetch_stats_window(
+    handler: &Handler,
+    nightscout: &NightscoutInfo,
+    hours: i64,
+) -> anyhow::Result<StatsWindow> {
+    let base_url = nightscout
+        .nightscout_url
+        .as_deref()
+        .filter(|url| !url.trim().is_empty())
+        .context("Nightscout URL missing")?;
+    let token = nightscout.nightscout_token.as_deref();
+    let hours = hours.clamp(1, MAX_DAYS * 24) as u16;
+    let options = NightscoutRequestOptions::default()
+        .hours_back(hours)
+        .count(hours * READINGS_PER_HOUR + 100);
+
+    let (entries, profile, status) = tokio::join!(
+        handler
+            .nightscout_client
+            .get_entries(base_url, options, token),
+        handler.nightscout_client.get_profile(base_url, token),
+        handler.nightscout_client.get_status(base_url, token),
+    );
+    let entries = entries?;
+
+    let profile_store = profile
+        .as_ref()
+        .ok()
+        .and_then(|profile| profile.default_store());
+    let thresholds = status
+        .as_ref()
+        .ok()
+        .and_then(|s| s.settings.as_ref())
+        .and_then(|settings| settings.thresholds.as_ref());
+
+    let user_range_mg = nightscout.target_range_mg;
+    let (target_low_mg, target_high_mg) = match (profile_store, user_range_mg) {
+        (Some(store), _) => (
+            store.get_target_low_mg(user_range_mg, thresholds),
+            store.get_target_high_mg(user_range_mg, thresholds),
+        ),
+        (None, Some(range)) => range,
+        (None, None) => (
+            thresholds
+                .map(|t| t.bg_target_bottom as f32)
+                .filter(|v| *v > 0.0)
+                .unwrap_or(70.0),
+            thresholds
+                .map(|t| t.bg_target_top as f32)
+                .filter(|v| *v > 0.0)
+                .unwrap_or(180.0),
+        ),
+    };
+    let timezone = nightscout.effective_timezone(profile_store);
+
+    let entries = handler
+        .nightscout_client
+        .filter_and_clean_entries(&entries, hours, timezone)
+        .unwrap_or_default();
+
+    Ok(StatsWindow {
+        entries,
+        target_low_mg,
+        target_high_mg,
+        timezone: timezone.to_string(),
+    })
+}
+
+/// Embed fields for a glucose summary: average, GMI, variability, time in range and lows
+pub fn summary_embed(
+    summary: &GlucoseSummary,
+    target_low_mg: f32,
+    target_high_mg: f32,
+) -> CreateEmbed {
+    let tir = &summary.tir;
+    CreateEmbed::new()
+        .field(
+            "Average",
+            format!(
+                "{:.0} mg/dL\n{:.1} mmol/L",
+                summary.mean,
+                summary.mean / 18.0
+            ),
+            true,
+        )
+        .field(
+            "GMI (est. A1c)",
+            format!("{:.1}%", summary.gmi_percent()),
+            true,
+        )
+        .field(
+            "Variability",
+            format!(
+                "SD {:.0} mg/dL\nCV {:.0}%",
+                summary.sd,
+                summary.cv_percent()
+            ),
+            true,
+        )
+        .field(
+            "Time in range",
+            format!(
+                "🔴 {:.0}% • 🟢 {:.0}% • 🟡 {:.0}%",
+                tir.low_percent(),
+                tir.in_range_percent(),
+                tir.high_percent()
+            ),
+            true,
+        )
+        .field("Lows", summary.low_episodes.to_string(), true)
+        .footer(CreateEmbedFooter::new(format!(
+            "{} readings • range {:.0}-{:.0} mg/dL ({:.1}-{:.1} mmol/L)",
+            tir.total(),
+            target_low_mg,
+            target_high_mg,
+            target_low_mg / 18.0,
+            target_high_mg / 18.0
+        )))
+        .color(Colour::from_rgb(59, 130, 246))
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("stats")
+        .description("Shows glucose statistics and a daily trend.")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::User, "user", "Target user.")
+                .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Integer, "days", "1 to 14 days.")
+                .min_int_value(1)
+                .max_int_value(MAX_DAYS as u64)
+                .required(false),
+        )
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
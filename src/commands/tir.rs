@@ -0,0 +1,256 @@
+use crate::bot::Handler;
+use crate::utils::stats::TimeInRange;
+use anyhow::Context as AnyhowContext;
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateEmbed, CreateEmbedFooter,
+    EditInteractionResponse, InteractionContext, ResolvedOption, ResolvedValue,
+};
+use serenity::builder::{CreateCommand, CreateCommandOption};
+
+/// Number of blocks in the time-in-range bar
+const BAR_WIDTH: usize = 20;
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    // Nightscout can take longer than Discord's 3 second reply window, so acknowledge first
+    // and edit the reply in once the data is in
+    interaction.defer(&context.http).await?;
+
+    let target_user_id = if let Some(ResolvedOption {
+        value: ResolvedValue::User(user, _),
+        ..
+    }) = interaction.data.options().first()
+    {
+        user.id.get()
+    } else {
+        interaction.user.id.get()
+    };
+
+    let command_user_id = interaction.user.id.get();
+
+    if !handler.database.user_exists(target_user_id).await? {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "The specified user hasn't set up their Nightscout data yet.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let target_user_data = handler.database.get_user_info(target_user_id).await?;
+
+    if !target_user_data
+        .nightscout
+        .can_view(target_user_id, command_user_id)
+    {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "This user's blood glucose data is set to private.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let base_url = target_user_data
+        .nightscout
+        .nightscout_url
+        .as_deref()
+        .context("Nightscout URL missing")?;
+
+    if base_url.trim().is_empty() {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "Your Nightscout URL is empty. Please run `/setup` to configure it properly.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let token = target_user_data.nightscout.nightscout_token.as_deref();
+
+    let (entries, profile, status) = tokio::join!(
+        handler
+            .nightscout_client
+            .get_entries_for_hours(base_url, 24, token),
+        handler.nightscout_client.get_profile(base_url, token),
+        handler.nightscout_client.get_status(base_url, token),
+    );
+
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Failed to get entries for user {}: {}", target_user_id, e);
+            crate::commands::error::nightscout(
+                context,
+                interaction,
+                &e,
+                target_user_id == command_user_id,
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let profile_store = profile
+        .as_ref()
+        .ok()
+        .and_then(|profile| profile.default_store());
+    let thresholds = status
+        .as_ref()
+        .ok()
+        .and_then(|s| s.settings.as_ref())
+        .and_then(|settings| settings.thresholds.as_ref());
+
+    let user_range_mg = target_user_data.nightscout.target_range_mg;
+    let (target_low_mg, target_high_mg) = match (profile_store, user_range_mg) {
+        (Some(store), _) => (
+            store.get_target_low_mg(user_range_mg, thresholds),
+            store.get_target_high_mg(user_range_mg, thresholds),
+        ),
+        (None, Some(range)) => range,
+        (None, None) => (
+            thresholds
+                .map(|t| t.bg_target_bottom as f32)
+                .filter(|v| *v > 0.0)
+                .unwrap_or(70.0),
+            thresholds
+                .map(|t| t.bg_target_top as f32)
+                .filter(|v| *v > 0.0)
+                .unwrap_or(180.0),
+        ),
+    };
+    let timezone = target_user_data
+        .nightscout
+        .effective_timezone(profile_store);
+
+    let entries = handler
+        .nightscout_client
+        .filter_and_clean_entries(&entries, 24, timezone)
+        .unwrap_or_default();
+    let tir = TimeInRange::from_entries(&entries, target_low_mg, target_high_mg);
+
+    if tir.total() == 0 {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "No glucose readings were found in the last 24 hours.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let in_range = tir.in_range_percent();
+    let color = if in_range >= 70.0 {
+        Colour::from_rgb(87, 189, 79)
+    } else if in_range >= 50.0 {
+        Colour::from_rgb(227, 177, 11)
+    } else {
+        Colour::from_rgb(235, 47, 47)
+    };
+
+    let embed = CreateEmbed::new()
+        .title(format!("{:.0}% in range", in_range))
+        .description(format!(
+            "{}\n🔴 Low {:.0}% • 🟢 In range {:.0}% • 🟡 High {:.0}%",
+            tir_bar(&tir),
+            tir.low_percent(),
+            in_range,
+            tir.high_percent()
+        ))
+        .footer(CreateEmbedFooter::new(format!(
+            "Last 24h • {} readings • range {:.0}-{:.0} mg/dL ({:.1}-{:.1} mmol/L)",
+            tir.total(),
+            target_low_mg,
+            target_high_mg,
+            target_low_mg / 18.0,
+            target_high_mg / 18.0
+        )))
+        .color(color);
+
+    interaction
+        .edit_response(context, EditInteractionResponse::new().embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+/// Splits `BAR_WIDTH` blocks between the buckets by largest remainder, so the blocks always
+/// add up to the bar width. Any non-empty bucket gets at least one block so small lows stay
+/// visible, with the extra blocks taken from the largest bucket.
+fn bar_blocks(counts: [usize; 3]) -> [usize; 3] {
+    let total: usize = counts.iter().sum();
+    if total == 0 {
+        return [0; 3];
+    }
+
+    let mut blocks = counts.map(|count| count * BAR_WIDTH / total);
+    let mut by_remainder = [0, 1, 2];
+    by_remainder.sort_by_key(|&i| std::cmp::Reverse((counts[i] * BAR_WIDTH) % total));
+    let assigned: usize = blocks.iter().sum();
+    for &i in by_remainder.iter().take(BAR_WIDTH - assigned) {
+        blocks[i] += 1;
+    }
+
+    for i in 0..blocks.len() {
+        if counts[i] > 0 && blocks[i] == 0 {
+            let largest = (0..blocks.len()).max_by_key(|&j| blocks[j]).unwrap_or(i);
+            blocks[largest] -= 1;
+            blocks[i] = 1;
+        }
+    }
+    blocks
+}
+
+/// Renders the low/in-range/high split as a coloured block bar using an ANSI code block.
+fn tir_bar(tir: &TimeInRange) -> String {
+    let [low, in_range, high] = bar_blocks([tir.low, tir.in_range, tir.high]);
+
+    format!(
+        "```ansi\n\u{1b}[31m{}\u{1b}[32m{}\u{1b}[33m{}\u{1b}[0m\n```",
+        "▇".repeat(low),
+        "▇".repeat(in_range),
+        "▇".repeat(high)
+    )
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("tir")
+        .description("Shows time-in-range for the last 24 hours.")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::User, "user", "Target user.")
+                .required(false),
+        )
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bar_blocks_fill_the_bar_exactly() {
+        assert_eq!(bar_blocks([0, 10, 0]), [0, BAR_WIDTH, 0]);
+        assert_eq!(bar_blocks([1, 2, 1]), [5, 10, 5]);
+
+        // Tiny lows and highs each keep a block without overflowing the bar
+        let blocks = bar_blocks([1, 998, 1]);
+        assert_eq!(blocks, [1, BAR_WIDTH - 2, 1]);
+
+        for counts in [[1, 1, 1], [1, 0, 1000], [3, 3, 994], [7, 6, 7]] {
+            let blocks = bar_blocks(counts);
+            assert_eq!(blocks.iter().sum::<usize>(), BAR_WIDTH, "{:?}", counts);
+            for (count, block) in counts.iter().zip(blocks) {
+                assert_eq!(*count > 0, block > 0, "{:?}", counts);
+            }
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use crate::bot::Handler;
+use crate::utils::nightscout::NightscoutError;
 use serenity::all::{
-    Colour, CommandInteraction, Context, CreateCommand, CreateEmbed, CreateInputText,
-    CreateInteractionResponse, CreateInteractionResponseMessage, CreateQuickModal,
+    Colour, CommandInteraction, Context, CreateCommand, CreateEmbed, CreateEmbedFooter,
+    CreateInputText, CreateInteractionResponse, CreateInteractionResponseMessage, CreateQuickModal,
     InteractionContext,
 };
 
@@ -55,13 +56,34 @@ pub async fn run(
         .get_user_info(interaction.user.id.get())
         .await?;
 
+    if let Err(e) = verify_token(
+        handler,
+        current_user_info.nightscout.nightscout_url.as_deref(),
+        token.as_deref(),
+    )
+    .await
+    {
+        tracing::warn!("[TOKEN] Token check failed, not saving: {}", e);
+        let error_embed = crate::commands::error::nightscout_embed(&e, true)
+            .footer(CreateEmbedFooter::new("Your token was not saved."));
+
+        let error_response = CreateInteractionResponseMessage::new()
+            .embed(error_embed)
+            .ephemeral(true);
+
+        modal_response
+            .interaction
+            .create_response(context, CreateInteractionResponse::Message(error_response))
+            .await?;
+        return Ok(());
+    }
+
     let updated_nightscout_info = crate::utils::database::NightscoutInfo {
         nightscout_url: current_user_info.nightscout.nightscout_url,
         nightscout_token: token.clone(),
         is_private: current_user_info.nightscout.is_private,
         allowed_people: current_user_info.nightscout.allowed_people,
-        microbolus_threshold: current_user_info.nightscout.microbolus_threshold,
-        display_microbolus: current_user_info.nightscout.display_microbolus,
+        ..current_user_info.nightscout
     };
 
     let user_id = interaction.user.id.get();
@@ -123,6 +145,24 @@ pub async fn run(
     Ok(())
 }
 
+/// Checks that `token` can read entries from the user's saved site before it is stored.
+/// A site that accepts the token but has no readings yet still passes.
+pub async fn verify_token(
+    handler: &Handler,
+    nightscout_url: Option<&str>,
+    token: Option<&str>,
+) -> Result<(), NightscoutError> {
+    let (Some(url), Some(token)) = (nightscout_url, token) else {
+        return Ok(());
+    };
+
+    tracing::info!("[TEST] Testing token against {}", url);
+    match handler.nightscout_client.get_entry(url, Some(token)).await {
+        Ok(_) | Err(NightscoutError::NoEntries) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
 pub fn register() -> CreateCommand {
     CreateCommand::new("token")
         .description("Set or update your Nightscout API token for authentication")
@@ -0,0 +1,89 @@
+use crate::bot::Handler;
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext,
+    ResolvedOption, ResolvedValue,
+};
+
+pub async fn run(
+    handler: &Handler,
+    context: &Context,
+    interaction: &CommandInteraction,
+) -> anyhow::Result<()> {
+    let user_id = interaction.user.id.get();
+
+    if !handler.database.user_exists(user_id).await? {
+        crate::commands::error::run(
+            context,
+            interaction,
+            "You need to run `/setup` first to configure your Nightscout before subscribing to the weekly digest.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let enabled = interaction
+        .data
+        .options()
+        .iter()
+        .find_map(|option| match option {
+            ResolvedOption {
+                name: "enabled",
+                value: ResolvedValue::Boolean(val),
+                ..
+            } => Some(*val),
+            _ => None,
+        })
+        .unwrap_or(true);
+
+    handler
+        .database
+        .update_weekly_digest(user_id, enabled)
+        .await?;
+    tracing::info!(
+        "[DIGEST] User {} {} the weekly digest",
+        user_id,
+        if enabled { "enabled" } else { "disabled" }
+    );
+
+    let embed = if enabled {
+        CreateEmbed::new()
+            .title("Weekly Digest Enabled")
+            .description(
+                "Every Sunday evening I'll DM you a summary of your week (Monday to Sunday in your timezone): time in range, average, CV, GMI and number of lows.\n\nMake sure your DMs are open to this server's members.",
+            )
+            .color(Colour::from_rgb(34, 197, 94))
+    } else {
+        CreateEmbed::new()
+            .title("Weekly Digest Disabled")
+            .description("You won't receive the weekly digest anymore.")
+            .color(Colour::from_rgb(34, 197, 94))
+    };
+
+    let response = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    interaction
+        .create_response(context, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("weekly-digest")
+        .description("Get a weekly summary of your glucose statistics by DM every Sunday")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "enabled",
+                "Whether to receive the weekly digest",
+            )
+            .required(true),
+        )
+        .contexts(vec![
+            InteractionContext::Guild,
+            InteractionContext::PrivateChannel,
+        ])
+}
@@ -3,11 +3,15 @@ use aes_gcm::{
     aead::{Aead, AeadCore, KeyInit, OsRng},
 };
 use base64::{Engine as _, engine::general_purpose};
+use serde::Serialize;
 use serde_json;
 use sqlx::{
     Row, SqlitePool as Pool,
     sqlite::{SqliteConnectOptions, SqlitePool},
 };
+use std::sync::Arc;
+
+use crate::utils::nightscout::{ProfileStore, STALE_DATA_MINUTES};
 
 /// Secure token encryption/decryption module
 struct TokenCrypto {
@@ -15,18 +19,20 @@ struct TokenCrypto {
 }
 
 impl TokenCrypto {
-    /// Create a new TokenCrypto instance with a derived key
+    /// Create a new TokenCrypto instance with a key derived from the configured salt
     fn new() -> Self {
-        let key = Self::derive_key();
+        Self::with_salt(&configured_salt())
+    }
+
+    /// Create a TokenCrypto instance with a key derived from the given salt
+    fn with_salt(salt: &str) -> Self {
+        let key = Self::derive_key(salt);
         let cipher = Aes256Gcm::new(&key);
         Self { cipher }
     }
 
-    /// Derive a deterministic encryption key from environment
-    fn derive_key() -> Key<Aes256Gcm> {
-        let salt = dotenvy::var("ENCRYPTION_SALT")
-            .unwrap_or_else(|_| "beetroot_default_salt_change_in_production".to_string());
-
+    /// Derive a deterministic encryption key from a salt
+    fn derive_key(salt: &str) -> Key<Aes256Gcm> {
         let key_material = format!("beetroot_token_encryption_v1_{}", salt);
         let hash = blake3::hash(key_material.as_bytes());
 
@@ -67,11 +73,50 @@ impl TokenCrypto {
     }
 }
 
-/// Global encryption instance - safely managed with OnceLock
-static CRYPTO_INSTANCE: std::sync::OnceLock<TokenCrypto> = std::sync::OnceLock::new();
+/// The salt from `ENCRYPTION_SALT`, or the built-in default
+pub fn configured_salt() -> String {
+    dotenvy::var("ENCRYPTION_SALT")
+        .unwrap_or_else(|_| "beetroot_default_salt_change_in_production".to_string())
+}
+
+/// The salt `/rotate-key` moves stored tokens to, from `ENCRYPTION_SALT_NEXT`. It's read on
+/// the server so the secret never passes through Discord.
+pub fn next_salt() -> Option<String> {
+    dotenvy::var("ENCRYPTION_SALT_NEXT")
+        .ok()
+        .filter(|salt| !salt.trim().is_empty())
+}
+
+/// Global encryption instance - swapped out when the key is rotated with `reencrypt_all`
+static CRYPTO_INSTANCE: std::sync::OnceLock<std::sync::RwLock<Arc<TokenCrypto>>> =
+    std::sync::OnceLock::new();
 
-fn get_crypto() -> &'static TokenCrypto {
-    CRYPTO_INSTANCE.get_or_init(TokenCrypto::new)
+fn crypto_lock() -> &'static std::sync::RwLock<Arc<TokenCrypto>> {
+    CRYPTO_INSTANCE.get_or_init(|| std::sync::RwLock::new(Arc::new(TokenCrypto::new())))
+}
+
+fn get_crypto() -> Arc<TokenCrypto> {
+    crypto_lock()
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+fn set_crypto(crypto: TokenCrypto) {
+    *crypto_lock()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(crypto);
+}
+
+/// Outcome of re-encrypting every stored token with `Database::reencrypt_all`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReencryptReport {
+    /// Tokens decrypted with the old key and encrypted with the new one
+    pub reencrypted: u32,
+    /// Tokens that already decrypt with the new key, e.g. from an earlier interrupted run
+    pub already_current: u32,
+    /// Tokens neither key can decrypt; they are left untouched
+    pub failed: u32,
 }
 
 #[derive(Clone, Debug)]
@@ -82,6 +127,244 @@ pub struct NightscoutInfo {
     pub is_private: bool,
     pub microbolus_threshold: f32,
     pub display_microbolus: bool,
+    pub display_device_changes: bool,
+    pub graph_theme: GraphThemeKind,
+    /// Unix timestamp (seconds) until which notifications are silenced
+    pub snooze_until: Option<i64>,
+    /// Pinned y-axis range in mg/dL, or `None` to scale the axis to the data
+    pub fixed_scale_mg: Option<(f32, f32)>,
+    pub smooth_line: bool,
+    /// Repeat the secondary unit scale on the right edge of graphs
+    pub right_axis: bool,
+    /// List bolus and carb times under graphs
+    pub treatment_legend: bool,
+    /// Custom target range in mg/dL set with `/set-range`, overriding Nightscout's thresholds
+    pub target_range_mg: Option<(f32, f32)>,
+    /// IANA timezone set with `/set-timezone`, used instead of the Nightscout profile's
+    pub timezone_override: Option<String>,
+    /// Urgent low threshold in mg/dL set with `/set-range`, overriding Nightscout's `bgLow`
+    pub urgent_low_mg: Option<f32>,
+    /// Draw the "Beetroot" watermark in the top-left corner of graphs
+    pub show_watermark: bool,
+    pub graph_tint: GraphTint,
+    /// Set with `/anonymous`: leave names, avatars and free text off `/bg` and graphs
+    pub anonymous_mode: bool,
+    /// Shade ±1 standard deviation around an hourly moving average on graphs
+    pub sd_band: bool,
+    /// Draw faint unlabelled gridlines every 0.5 mmol/L between the labelled ones
+    pub minor_gridlines: bool,
+    /// Show a key explaining the marker shapes in the corner of graphs
+    pub marker_legend: bool,
+    /// Colours used for high and low readings on graphs
+    pub graph_palette: GraphPalette,
+    /// Where boluses and carbs are drawn on graphs
+    pub treatment_layout: TreatmentLayout,
+    /// Minutes after which readings and loop data are flagged as old
+    pub stale_minutes: i64,
+    /// Hours `/graph` shows when no `hours` option is given, or `None` for the built-in default
+    pub default_graph_hours: Option<i64>,
+}
+
+impl NightscoutInfo {
+    /// Whether notifications are currently silenced with `/silence`
+    pub fn is_snoozed(&self, now: i64) -> bool {
+        self.snooze_until.is_some_and(|until| until > now)
+    }
+
+    /// Whether `requester_id` may see this data, which belongs to `owner_id`: the owner always
+    /// can, anyone can while it's public, and otherwise only people added with `/allow`
+    pub fn can_view(&self, owner_id: u64, requester_id: u64) -> bool {
+        owner_id == requester_id || !self.is_private || self.allowed_people.contains(&requester_id)
+    }
+
+    /// The `/set-timezone` override, or else the timezone from the Nightscout profile,
+    /// falling back to UTC
+    pub fn effective_timezone<'a>(&'a self, profile_store: Option<&'a ProfileStore>) -> &'a str {
+        self.timezone_override
+            .as_deref()
+            .or(profile_store.map(|store| store.timezone.as_str()))
+            .unwrap_or("UTC")
+    }
+}
+
+impl Default for NightscoutInfo {
+    fn default() -> Self {
+        Self {
+            nightscout_url: None,
+            nightscout_token: None,
+            allowed_people: vec![],
+            is_private: true,
+            microbolus_threshold: 0.5,
+            display_microbolus: true,
+            display_device_changes: true,
+            graph_theme: GraphThemeKind::Dark,
+            snooze_until: None,
+            fixed_scale_mg: None,
+            smooth_line: false,
+            right_axis: false,
+            treatment_legend: false,
+            target_range_mg: None,
+            timezone_override: None,
+            urgent_low_mg: None,
+            show_watermark: true,
+            graph_tint: GraphTint::None,
+            anonymous_mode: false,
+            sd_band: false,
+            minor_gridlines: false,
+            marker_legend: true,
+            graph_palette: GraphPalette::Standard,
+            treatment_layout: TreatmentLayout::Inline,
+            stale_minutes: STALE_DATA_MINUTES,
+            default_graph_hours: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum GraphThemeKind {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl GraphThemeKind {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "dark" => Some(Self::Dark),
+            "light" => Some(Self::Light),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Dark => "dark",
+            Self::Light => "light",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Dark => "Dark",
+            Self::Light => "Light",
+        }
+    }
+}
+
+/// Subtle colour blended into the graph background, on top of the theme
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GraphTint {
+    #[default]
+    None,
+    Blue,
+    Green,
+    Purple,
+    Rose,
+}
+
+impl GraphTint {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Some(Self::None),
+            "blue" => Some(Self::Blue),
+            "green" => Some(Self::Green),
+            "purple" => Some(Self::Purple),
+            "rose" => Some(Self::Rose),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Blue => "blue",
+            Self::Green => "green",
+            Self::Purple => "purple",
+            Self::Rose => "rose",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Blue => "Blue",
+            Self::Green => "Green",
+            Self::Purple => "Purple",
+            Self::Rose => "Rose",
+        }
+    }
+}
+
+/// Where boluses and carbs are drawn on graphs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TreatmentLayout {
+    /// Triangles and circles next to the glucose trace
+    #[default]
+    Inline,
+    /// Bars along the bottom (insulin) and top (carbs) edges of the plot
+    Bars,
+}
+
+impl TreatmentLayout {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "inline" => Some(Self::Inline),
+            "bars" => Some(Self::Bars),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Inline => "inline",
+            Self::Bars => "bars",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Inline => "On the glucose line",
+            Self::Bars => "Bars along the edges",
+        }
+    }
+}
+
+/// High/low colours for the graph, with options that stay distinct for colour-blind users
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GraphPalette {
+    #[default]
+    Standard,
+    /// Blue lows and orange highs, for red-green colour blindness
+    BlueOrange,
+    /// Red lows and cyan highs, for blue-yellow colour blindness
+    RedCyan,
+}
+
+impl GraphPalette {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "standard" => Some(Self::Standard),
+            "blue_orange" => Some(Self::BlueOrange),
+            "red_cyan" => Some(Self::RedCyan),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::BlueOrange => "blue_orange",
+            Self::RedCyan => "red_cyan",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Standard => "Standard",
+            Self::BlueOrange => "Blue/orange (red-green safe)",
+            Self::RedCyan => "Red/cyan (blue-yellow safe)",
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -131,12 +414,197 @@ impl StickerCategory {
     }
 }
 
+/// Size choices offered when adding a sticker, stored as a `scale` factor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StickerSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl StickerSize {
+    pub const ALL: [Self; 3] = [Self::Small, Self::Medium, Self::Large];
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "small" => Some(Self::Small),
+            "medium" => Some(Self::Medium),
+            "large" => Some(Self::Large),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Small => "small",
+            Self::Medium => "medium",
+            Self::Large => "large",
+        }
+    }
+
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Self::Small => "Small",
+            Self::Medium => "Medium",
+            Self::Large => "Large",
+        }
+    }
+
+    pub fn scale(self) -> f32 {
+        match self {
+            Self::Small => 0.7,
+            Self::Medium => 1.0,
+            Self::Large => 1.4,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Sticker {
     pub id: i32,
     pub file_name: String,
     pub display_name: String,
     pub category: StickerCategory,
+    /// Tilt in degrees (clockwise) applied when the sticker is drawn
+    pub rotation: f32,
+    /// Size relative to the default sticker size
+    pub scale: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReminderRepeat {
+    Once,
+    Daily,
+    Weekly,
+}
+
+impl ReminderRepeat {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "once" => Some(Self::Once),
+            "daily" => Some(Self::Daily),
+            "weekly" => Some(Self::Weekly),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Once => "once",
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Once => "Once",
+            Self::Daily => "Daily",
+            Self::Weekly => "Weekly",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Reminder {
+    pub id: i64,
+    pub discord_id: u64,
+    pub message: String,
+    /// Unix timestamp (seconds) of the next time this reminder fires
+    pub next_fire: i64,
+    pub repeat: ReminderRepeat,
+    pub timezone: String,
+}
+
+/// A named Nightscout site saved with `/nightscout add`. Every command reads the URL, token
+/// and privacy settings of the user's active site.
+#[derive(Clone, Debug)]
+pub struct NightscoutSite {
+    pub name: String,
+    pub nightscout_url: Option<String>,
+    pub is_active: bool,
+}
+
+/// Per-server configuration set by a guild's admins with `/server-config`
+#[derive(Clone, Debug, Default)]
+pub struct GuildSettings {
+    /// Channels where commands showing glucose data may be used. Empty means any channel.
+    pub allowed_channels: Vec<u64>,
+    /// Treat every member's data as private in this guild, whatever their own visibility is
+    pub force_private: bool,
+}
+
+impl GuildSettings {
+    pub fn allows_channel(&self, channel_id: u64) -> bool {
+        self.allowed_channels.is_empty() || self.allowed_channels.contains(&channel_id)
+    }
+}
+
+/// Everything stored about a user, in a portable form. The Nightscout token is never included.
+#[derive(Debug, Serialize)]
+pub struct UserExport {
+    pub discord_id: String,
+    pub exported_at: String,
+    pub nightscout_url: Option<String>,
+    pub nightscout_token: Option<&'static str>,
+    pub is_private: bool,
+    pub allowed_people: Vec<String>,
+    pub microbolus_threshold: f32,
+    pub display_microbolus: bool,
+    pub display_device_changes: bool,
+    pub graph_theme: &'static str,
+    pub snooze_until: Option<String>,
+    pub fixed_scale_mg: Option<(f32, f32)>,
+    pub smooth_line: bool,
+    pub right_axis: bool,
+    pub treatment_legend: bool,
+    pub target_range_mg: Option<(f32, f32)>,
+    pub timezone_override: Option<String>,
+    pub urgent_low_mg: Option<f32>,
+    pub show_watermark: bool,
+    pub graph_tint: &'static str,
+    pub anonymous_mode: bool,
+    pub sd_band: bool,
+    pub minor_gridlines: bool,
+    pub marker_legend: bool,
+    pub graph_palette: &'static str,
+    pub treatment_layout: &'static str,
+    pub stale_minutes: i64,
+    pub default_graph_hours: Option<i64>,
+    pub weekly_digest: bool,
+    pub digest_sent_week: Option<String>,
+    pub leaderboard_opt_in: bool,
+    pub last_seen_version: Option<String>,
+    pub nightscout_sites: Vec<SiteExport>,
+    pub stickers: Vec<StickerExport>,
+    pub reminders: Vec<ReminderExport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SiteExport {
+    pub name: String,
+    pub nightscout_url: Option<String>,
+    pub nightscout_token: Option<&'static str>,
+    pub is_private: bool,
+    pub allowed_people: Vec<String>,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StickerExport {
+    pub file_name: String,
+    pub display_name: String,
+    pub category: &'static str,
+    pub rotation: f32,
+    pub scale: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReminderExport {
+    pub message: String,
+    pub next_fire: String,
+    pub repeat: &'static str,
+    pub timezone: String,
 }
 
 #[derive(Clone, Debug)]
@@ -146,6 +614,7 @@ pub struct UserInfo {
     pub stickers: Vec<Sticker>,
 }
 
+#[derive(Clone)]
 pub struct Database {
     pool: Pool,
 }
@@ -157,7 +626,11 @@ impl Database {
             .create_if_missing(true);
 
         let pool = SqlitePool::connect_with(opts).await?;
+        Self::with_pool(pool).await
+    }
 
+    /// Create the tables and run every migration on an open pool
+    async fn with_pool(pool: Pool) -> Result<Self, sqlx::Error> {
         Self::setup_tables(&pool).await?;
 
         let migration = crate::utils::migration::Migration::new(pool.clone());
@@ -166,6 +639,32 @@ impl Database {
         migration.add_sticker_display_name_field().await?;
         migration.add_last_seen_version_field().await?;
         migration.add_sticker_category_field().await?;
+        migration.add_device_change_display_field().await?;
+        migration.add_graph_theme_field().await?;
+        migration.add_snooze_until_field().await?;
+        migration.add_fixed_scale_fields().await?;
+        migration.add_smooth_line_field().await?;
+        migration.create_reminders_table().await?;
+        migration.create_guild_settings_table().await?;
+        migration.create_nightscout_sites_table().await?;
+        migration.move_connection_to_sites().await?;
+        migration.add_right_axis_field().await?;
+        migration.add_treatment_legend_field().await?;
+        migration.add_target_range_fields().await?;
+        migration.add_timezone_override_field().await?;
+        migration.add_weekly_digest_fields().await?;
+        migration.add_urgent_low_field().await?;
+        migration.add_graph_background_fields().await?;
+        migration.add_leaderboard_field().await?;
+        migration.add_sticker_scale_field().await?;
+        migration.add_anonymous_mode_field().await?;
+        migration.add_sd_band_field().await?;
+        migration.add_marker_legend_field().await?;
+        migration.add_graph_palette_field().await?;
+        migration.add_treatment_layout_field().await?;
+        migration.add_stale_minutes_field().await?;
+        migration.add_default_graph_hours_field().await?;
+        migration.add_minor_gridlines_field().await?;
 
         Ok(Database { pool })
     }
@@ -216,6 +715,149 @@ impl Database {
         })
     }
 
+    /// Collect a user's stored data for export, with the Nightscout token redacted
+    pub async fn export_user(&self, discord_id: u64) -> Result<UserExport, sqlx::Error> {
+        let user_info = self.get_user_info(discord_id).await?;
+        let reminders = self.get_user_reminders(discord_id).await?;
+
+        // Columns that only the background tasks read, so they aren't part of NightscoutInfo
+        let extra = sqlx::query(
+            "SELECT weekly_digest, digest_sent_week, leaderboard_opt_in, last_seen_version FROM users WHERE discord_id = ?",
+        )
+        .bind(discord_id as i64)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let site_rows = sqlx::query(
+            "SELECT name, nightscout_url, nightscout_token, is_private, allowed_people, is_active
+             FROM nightscout_sites WHERE discord_id = ? ORDER BY id",
+        )
+        .bind(discord_id as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Destructured in full so a new setting can't be left out of the export by accident
+        let NightscoutInfo {
+            nightscout_url,
+            nightscout_token,
+            allowed_people,
+            is_private,
+            microbolus_threshold,
+            display_microbolus,
+            display_device_changes,
+            graph_theme,
+            snooze_until,
+            fixed_scale_mg,
+            smooth_line,
+            right_axis,
+            treatment_legend,
+            target_range_mg,
+            timezone_override,
+            urgent_low_mg,
+            show_watermark,
+            graph_tint,
+            anonymous_mode,
+            sd_band,
+            minor_gridlines,
+            marker_legend,
+            graph_palette,
+            treatment_layout,
+            stale_minutes,
+            default_graph_hours,
+        } = user_info.nightscout;
+
+        // Discord ids exceed JavaScript's safe integer range, so export them as strings
+        let id_strings = |ids: &[u64]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        let timestamp = |secs: i64| {
+            chrono::DateTime::from_timestamp(secs, 0)
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_default()
+        };
+
+        Ok(UserExport {
+            discord_id: discord_id.to_string(),
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            nightscout_url,
+            nightscout_token: nightscout_token.map(|_| "[REDACTED]"),
+            is_private,
+            allowed_people: id_strings(&allowed_people),
+            microbolus_threshold,
+            display_microbolus,
+            display_device_changes,
+            graph_theme: graph_theme.to_str(),
+            snooze_until: snooze_until.map(timestamp),
+            fixed_scale_mg,
+            smooth_line,
+            right_axis,
+            treatment_legend,
+            target_range_mg,
+            timezone_override,
+            urgent_low_mg,
+            show_watermark,
+            graph_tint: graph_tint.to_str(),
+            anonymous_mode,
+            sd_band,
+            minor_gridlines,
+            marker_legend,
+            graph_palette: graph_palette.to_str(),
+            treatment_layout: treatment_layout.to_str(),
+            stale_minutes,
+            default_graph_hours,
+            weekly_digest: extra.get::<Option<i32>, _>("weekly_digest").unwrap_or(0) != 0,
+            digest_sent_week: extra.get("digest_sent_week"),
+            leaderboard_opt_in: extra
+                .get::<Option<i32>, _>("leaderboard_opt_in")
+                .unwrap_or(0)
+                != 0,
+            last_seen_version: extra.get("last_seen_version"),
+            nightscout_sites: site_rows
+                .iter()
+                .map(|row| {
+                    let allowed: Vec<u64> = row
+                        .get::<Option<String>, _>("allowed_people")
+                        .and_then(|json| serde_json::from_str(&json).ok())
+                        .unwrap_or_default();
+                    SiteExport {
+                        name: row.get("name"),
+                        nightscout_url: row.get("nightscout_url"),
+                        nightscout_token: row
+                            .get::<Option<String>, _>("nightscout_token")
+                            .map(|_| "[REDACTED]"),
+                        is_private: row.get::<i32, _>("is_private") != 0,
+                        allowed_people: id_strings(&allowed),
+                        is_active: row.get::<i32, _>("is_active") != 0,
+                    }
+                })
+                .collect(),
+            stickers: user_info
+                .stickers
+                .into_iter()
+                .map(|sticker| StickerExport {
+                    file_name: sticker.file_name,
+                    display_name: sticker.display_name,
+                    category: sticker.category.to_str(),
+                    rotation: sticker.rotation,
+                    scale: sticker.scale,
+                })
+                .collect(),
+            reminders: reminders
+                .into_iter()
+                .map(|reminder| ReminderExport {
+                    message: reminder.message,
+                    next_fire: timestamp(reminder.next_fire),
+                    repeat: reminder.repeat.to_str(),
+                    timezone: reminder.timezone,
+                })
+                .collect(),
+        })
+    }
+
+    /// Check the database still answers queries, for the health endpoint
+    pub async fn ping(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
     pub async fn user_exists(&self, discord_id: u64) -> Result<bool, sqlx::Error> {
         let result = sqlx::query("SELECT 1 FROM users WHERE discord_id = ? LIMIT 1")
             .bind(discord_id as i64)
@@ -225,6 +867,35 @@ impl Database {
         Ok(result.is_some())
     }
 
+    /// Number of registered users
+    pub async fn user_count(&self) -> Result<i64, sqlx::Error> {
+        let row = sqlx::query("SELECT COUNT(*) AS count FROM users")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get("count"))
+    }
+
+    /// Number of stickers across all users
+    pub async fn count_stickers_total(&self) -> Result<i64, sqlx::Error> {
+        let row = sqlx::query("SELECT COUNT(*) AS count FROM stickers")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get("count"))
+    }
+
+    /// Number of users with a Nightscout token configured
+    pub async fn count_users_with_token(&self) -> Result<i64, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) AS count FROM nightscout_sites WHERE is_active = 1 AND nightscout_token IS NOT NULL AND nightscout_token != ''",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.get("count"))
+    }
+
     pub async fn insert_user(
         &self,
         discord_id: u64,
@@ -252,19 +923,30 @@ impl Database {
             None
         };
 
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO users (discord_id, microbolus_threshold, display_microbolus) VALUES (?, ?, ?)",
+        )
+        .bind(discord_id as i64)
+        .bind(nightscout_info.microbolus_threshold)
+        .bind(nightscout_info.display_microbolus as i32)
+        .execute(&mut *tx)
+        .await?;
+
         sqlx::query(
-            "INSERT INTO users (discord_id, nightscout_url, nightscout_token, is_private, allowed_people, microbolus_threshold, display_microbolus) VALUES (?, ?, ?, ?, ?, ?, ?)"
+            "INSERT INTO nightscout_sites (discord_id, name, nightscout_url, nightscout_token, is_private, allowed_people, is_active) VALUES (?, 'default', ?, ?, ?, ?, 1)",
         )
         .bind(discord_id as i64)
         .bind(&nightscout_info.nightscout_url)
         .bind(&encrypted_token)
         .bind(nightscout_info.is_private as i32)
         .bind(allowed_people_json)
-        .bind(nightscout_info.microbolus_threshold)
-        .bind(nightscout_info.display_microbolus as i32)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        tx.commit().await?;
+
         tracing::info!(
             "[SECURITY] User {} token stored with encryption",
             discord_id
@@ -299,55 +981,100 @@ impl Database {
             None
         };
 
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "UPDATE users SET microbolus_threshold = ?, display_microbolus = ? WHERE discord_id = ?",
+        )
+        .bind(nightscout_info.microbolus_threshold)
+        .bind(nightscout_info.display_microbolus as i32)
+        .bind(discord_id as i64)
+        .execute(&mut *tx)
+        .await?;
+
         sqlx::query(
-            "UPDATE users SET nightscout_url = ?, nightscout_token = ?, is_private = ?, allowed_people = ?, microbolus_threshold = ?, display_microbolus = ? WHERE discord_id = ?"
+            "UPDATE nightscout_sites SET nightscout_url = ?, nightscout_token = ?, is_private = ?, allowed_people = ? WHERE discord_id = ? AND is_active = 1",
         )
         .bind(&nightscout_info.nightscout_url)
         .bind(&encrypted_token)
         .bind(nightscout_info.is_private as i32)
         .bind(allowed_people_json)
-        .bind(nightscout_info.microbolus_threshold)
-        .bind(nightscout_info.display_microbolus as i32)
         .bind(discord_id as i64)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        tx.commit().await?;
+
         tracing::info!(
             "[SECURITY] User {} token updated with encryption",
             discord_id
         );
         Ok(())
     }
-    #[allow(dead_code)]
+    /// Remove the user and everything stored for them, all at once so a failure partway
+    /// through doesn't leave their stickers or sites behind without the user
     pub async fn delete_user(&self, discord_id: u64) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
         sqlx::query("DELETE FROM stickers WHERE discord_id = ?")
             .bind(discord_id as i64)
-            .execute(&self.pool)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM reminders WHERE discord_id = ?")
+            .bind(discord_id as i64)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM nightscout_sites WHERE discord_id = ?")
+            .bind(discord_id as i64)
+            .execute(&mut *tx)
             .await?;
 
         sqlx::query("DELETE FROM users WHERE discord_id = ?")
             .bind(discord_id as i64)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
 
-        Ok(())
+        tx.commit().await
     }
+
     pub async fn insert_sticker(
         &self,
         discord_id: u64,
         file_name: &str,
         display_name: &str,
         category: StickerCategory,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query("INSERT INTO stickers (file_name, display_name, discord_id, category) VALUES (?, ?, ?, ?)")
+        rotation: f32,
+    ) -> Result<i64, sqlx::Error> {
+        let result = sqlx::query("INSERT INTO stickers (file_name, display_name, discord_id, category, rotation) VALUES (?, ?, ?, ?, ?)")
             .bind(file_name)
             .bind(display_name)
             .bind(discord_id as i64)
             .bind(category.to_str())
+            .bind(rotation)
             .execute(&self.pool)
             .await?;
 
-        Ok(())
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Resize one of the user's stickers. Returns `false` if they don't own a sticker with
+    /// that id.
+    pub async fn update_sticker_scale(
+        &self,
+        discord_id: u64,
+        sticker_id: i64,
+        scale: f32,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE stickers SET scale = ? WHERE id = ? AND discord_id = ?")
+            .bind(scale)
+            .bind(sticker_id)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
     }
 
     #[allow(dead_code)]
@@ -394,23 +1121,312 @@ impl Database {
         Ok(())
     }
 
+    pub async fn insert_reminder(
+        &self,
+        discord_id: u64,
+        message: &str,
+        next_fire: i64,
+        repeat: ReminderRepeat,
+        timezone: &str,
+    ) -> Result<i64, sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO reminders (discord_id, message, next_fire, repeat, timezone) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(discord_id as i64)
+        .bind(message)
+        .bind(next_fire)
+        .bind(repeat.to_str())
+        .bind(timezone)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn get_user_reminders(&self, discord_id: u64) -> Result<Vec<Reminder>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, discord_id, message, next_fire, repeat, timezone FROM reminders WHERE discord_id = ? ORDER BY next_fire",
+        )
+        .bind(discord_id as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(Self::reminder_from_row).collect())
+    }
+
+    /// Reminders whose fire time is at or before `now` (Unix seconds)
+    pub async fn get_due_reminders(&self, now: i64) -> Result<Vec<Reminder>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, discord_id, message, next_fire, repeat, timezone FROM reminders WHERE next_fire <= ? ORDER BY next_fire",
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(Self::reminder_from_row).collect())
+    }
+
+    pub async fn reschedule_reminder(
+        &self,
+        reminder_id: i64,
+        next_fire: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE reminders SET next_fire = ? WHERE id = ?")
+            .bind(next_fire)
+            .bind(reminder_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Delete one of a user's reminders. Returns `false` if it didn't exist or isn't theirs.
+    pub async fn delete_reminder(
+        &self,
+        discord_id: u64,
+        reminder_id: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM reminders WHERE id = ? AND discord_id = ?")
+            .bind(reminder_id)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn complete_reminder(&self, reminder_id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM reminders WHERE id = ?")
+            .bind(reminder_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_nightscout_sites(
+        &self,
+        discord_id: u64,
+    ) -> Result<Vec<NightscoutSite>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT name, nightscout_url, is_active FROM nightscout_sites WHERE discord_id = ? ORDER BY id",
+        )
+        .bind(discord_id as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| NightscoutSite {
+                name: row.get("name"),
+                nightscout_url: row.get("nightscout_url"),
+                is_active: row.get::<i32, _>("is_active") != 0,
+            })
+            .collect())
+    }
+
+    /// Save an additional, inactive Nightscout site. New sites start private.
+    pub async fn add_nightscout_site(
+        &self,
+        discord_id: u64,
+        name: &str,
+        nightscout_url: &str,
+        nightscout_token: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let encrypted_token = match nightscout_token {
+            Some(token) => Some(get_crypto().encrypt(token).map_err(|e| {
+                tracing::error!(
+                    "[ENCRYPTION] Failed to encrypt site token for user {}: {}",
+                    discord_id,
+                    e
+                );
+                sqlx::Error::Protocol("Token encryption failed".to_string())
+            })?),
+            None => None,
+        };
+
+        sqlx::query(
+            "INSERT INTO nightscout_sites (discord_id, name, nightscout_url, nightscout_token, is_active) VALUES (?, ?, ?, ?, 0)",
+        )
+        .bind(discord_id as i64)
+        .bind(name)
+        .bind(nightscout_url)
+        .bind(encrypted_token)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Make a saved site the active one. Returns `false` if the user has no inactive site
+    /// with that name.
+    pub async fn switch_nightscout_site(
+        &self,
+        discord_id: u64,
+        name: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let target_exists = sqlx::query(
+            "SELECT 1 FROM nightscout_sites WHERE discord_id = ? AND name = ? AND is_active = 0",
+        )
+        .bind(discord_id as i64)
+        .bind(name)
+        .fetch_optional(&mut *tx)
+        .await?
+        .is_some();
+
+        if !target_exists {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "UPDATE nightscout_sites SET is_active = 0 WHERE discord_id = ? AND is_active = 1",
+        )
+        .bind(discord_id as i64)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE nightscout_sites SET is_active = 1 WHERE discord_id = ? AND name = ?")
+            .bind(discord_id as i64)
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Remove a saved site. The active site can't be removed; returns `false` if no inactive
+    /// site has that name.
+    pub async fn remove_nightscout_site(
+        &self,
+        discord_id: u64,
+        name: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "DELETE FROM nightscout_sites WHERE discord_id = ? AND name = ? AND is_active = 0",
+        )
+        .bind(discord_id as i64)
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Settings for a guild, or the defaults if its admins haven't configured anything
+    pub async fn get_guild_settings(&self, guild_id: u64) -> Result<GuildSettings, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT allowed_channels, force_private FROM guild_settings WHERE guild_id = ?",
+        )
+        .bind(guild_id as i64)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row
+            .map(|row| GuildSettings {
+                allowed_channels: serde_json::from_str(&row.get::<String, _>("allowed_channels"))
+                    .unwrap_or_default(),
+                force_private: row.get::<i32, _>("force_private") != 0,
+            })
+            .unwrap_or_default())
+    }
+
+    pub async fn set_guild_settings(
+        &self,
+        guild_id: u64,
+        settings: &GuildSettings,
+    ) -> Result<(), sqlx::Error> {
+        let allowed_channels_json =
+            serde_json::to_string(&settings.allowed_channels).unwrap_or("[]".to_string());
+
+        sqlx::query(
+            "INSERT INTO guild_settings (guild_id, allowed_channels, force_private) VALUES (?, ?, ?)
+             ON CONFLICT(guild_id) DO UPDATE SET allowed_channels = excluded.allowed_channels, force_private = excluded.force_private",
+        )
+        .bind(guild_id as i64)
+        .bind(allowed_channels_json)
+        .bind(settings.force_private as i32)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    fn reminder_from_row(row: &sqlx::sqlite::SqliteRow) -> Reminder {
+        Reminder {
+            id: row.get("id"),
+            discord_id: row.get::<i64, _>("discord_id") as u64,
+            message: row.get("message"),
+            next_fire: row.get("next_fire"),
+            repeat: ReminderRepeat::from_str(&row.get::<String, _>("repeat"))
+                .unwrap_or(ReminderRepeat::Once),
+            timezone: row.get("timezone"),
+        }
+    }
+
     async fn get_nightscout_info(&self, user_id: u64) -> Result<NightscoutInfo, sqlx::Error> {
         let row = sqlx::query(
-            "SELECT nightscout_url, nightscout_token, is_private, allowed_people, microbolus_threshold, display_microbolus FROM users WHERE discord_id = ?"
+            "SELECT s.nightscout_url, s.nightscout_token, s.is_private, s.allowed_people, microbolus_threshold, display_microbolus, display_device_changes, graph_theme, snooze_until, fixed_scale_min, fixed_scale_max, smooth_line, right_axis, treatment_legend, target_low, target_high, timezone_override, urgent_low, show_watermark, graph_tint, anonymous_mode, sd_band, marker_legend, graph_palette, treatment_layout, stale_minutes, default_graph_hours, minor_gridlines FROM users u LEFT JOIN nightscout_sites s ON s.discord_id = u.discord_id AND s.is_active = 1 WHERE u.discord_id = ?"
         )
         .bind(user_id as i64)
         .fetch_one(&self.pool).await?;
 
         let nightscout_url: Option<String> = row.get("nightscout_url");
         let encrypted_token: Option<String> = row.get("nightscout_token");
-        let is_private: bool = row.get::<i32, _>("is_private") != 0;
-        let allowed_people: Vec<u64> =
-            serde_json::from_str(&row.get::<String, _>("allowed_people")).unwrap_or_default();
+        let is_private: bool = row.get::<Option<i32>, _>("is_private").unwrap_or(1) != 0;
+        let allowed_people: Vec<u64> = row
+            .get::<Option<String>, _>("allowed_people")
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
         let microbolus_threshold: f32 = row
             .get::<Option<f32>, _>("microbolus_threshold")
             .unwrap_or(0.5);
         let display_microbolus: bool =
             row.get::<Option<i32>, _>("display_microbolus").unwrap_or(1) != 0;
+        let display_device_changes: bool = row
+            .get::<Option<i32>, _>("display_device_changes")
+            .unwrap_or(1)
+            != 0;
+        let graph_theme = row
+            .get::<Option<String>, _>("graph_theme")
+            .and_then(|theme| GraphThemeKind::from_str(&theme))
+            .unwrap_or_default();
+        let snooze_until: Option<i64> = row.get("snooze_until");
+        let fixed_scale_mg = row
+            .get::<Option<f32>, _>("fixed_scale_min")
+            .zip(row.get::<Option<f32>, _>("fixed_scale_max"));
+        let smooth_line: bool = row.get::<Option<i32>, _>("smooth_line").unwrap_or(0) != 0;
+        let right_axis: bool = row.get::<Option<i32>, _>("right_axis").unwrap_or(0) != 0;
+        let treatment_legend: bool =
+            row.get::<Option<i32>, _>("treatment_legend").unwrap_or(0) != 0;
+        let target_range_mg = row
+            .get::<Option<f32>, _>("target_low")
+            .zip(row.get::<Option<f32>, _>("target_high"));
+        let timezone_override: Option<String> = row.get("timezone_override");
+        let urgent_low_mg: Option<f32> = row.get("urgent_low");
+        let show_watermark: bool = row.get::<Option<i32>, _>("show_watermark").unwrap_or(1) != 0;
+        let graph_tint = row
+            .get::<Option<String>, _>("graph_tint")
+            .and_then(|tint| GraphTint::from_str(&tint))
+            .unwrap_or_default();
+        let anonymous_mode: bool = row.get::<Option<i32>, _>("anonymous_mode").unwrap_or(0) != 0;
+        let sd_band: bool = row.get::<Option<i32>, _>("sd_band").unwrap_or(0) != 0;
+        let marker_legend: bool = row.get::<Option<i32>, _>("marker_legend").unwrap_or(1) != 0;
+        let graph_palette = row
+            .get::<Option<String>, _>("graph_palette")
+            .and_then(|palette| GraphPalette::from_str(&palette))
+            .unwrap_or_default();
+        let treatment_layout = row
+            .get::<Option<String>, _>("treatment_layout")
+            .and_then(|layout| TreatmentLayout::from_str(&layout))
+            .unwrap_or_default();
+        let stale_minutes: i64 = row
+            .get::<Option<i64>, _>("stale_minutes")
+            .unwrap_or(STALE_DATA_MINUTES);
+        let default_graph_hours: Option<i64> = row.get("default_graph_hours");
+        let minor_gridlines: bool = row.get::<Option<i32>, _>("minor_gridlines").unwrap_or(0) != 0;
 
         let nightscout_token = if let Some(encrypted) = encrypted_token {
             match get_crypto().decrypt(&encrypted) {
@@ -442,6 +1458,26 @@ impl Database {
             allowed_people,
             microbolus_threshold,
             display_microbolus,
+            display_device_changes,
+            graph_theme,
+            snooze_until,
+            fixed_scale_mg,
+            smooth_line,
+            right_axis,
+            treatment_legend,
+            target_range_mg,
+            timezone_override,
+            urgent_low_mg,
+            show_watermark,
+            graph_tint,
+            anonymous_mode,
+            sd_band,
+            minor_gridlines,
+            marker_legend,
+            graph_palette,
+            treatment_layout,
+            stale_minutes,
+            default_graph_hours,
         };
 
         Ok(info)
@@ -454,7 +1490,7 @@ impl Database {
         tracing::info!("[MIGRATION] Starting token encryption migration");
 
         let rows = sqlx::query(
-            "SELECT discord_id, nightscout_token FROM users WHERE nightscout_token IS NOT NULL",
+            "SELECT id, discord_id, nightscout_token FROM nightscout_sites WHERE nightscout_token IS NOT NULL",
         )
         .fetch_all(&self.pool)
         .await?;
@@ -462,6 +1498,7 @@ impl Database {
         let mut migrated_count = 0;
 
         for row in rows {
+            let site_id: i64 = row.get("id");
             let discord_id: i64 = row.get("discord_id");
             let current_token: String = row.get("nightscout_token");
 
@@ -476,9 +1513,9 @@ impl Database {
 
             match get_crypto().encrypt(&current_token) {
                 Ok(encrypted_token) => {
-                    sqlx::query("UPDATE users SET nightscout_token = ? WHERE discord_id = ?")
+                    sqlx::query("UPDATE nightscout_sites SET nightscout_token = ? WHERE id = ?")
                         .bind(&encrypted_token)
-                        .bind(discord_id)
+                        .bind(site_id)
                         .execute(&self.pool)
                         .await?;
 
@@ -502,9 +1539,72 @@ impl Database {
         Ok(migrated_count)
     }
 
+    /// Re-encrypt every stored token (active and saved sites) from the key derived from
+    /// `old_salt` to the one derived from `new_salt`, in a single transaction. Tokens that
+    /// already use the new key are skipped, and tokens neither key can read are left as they
+    /// are. On success the running bot switches to the new key.
+    pub async fn reencrypt_all(
+        &self,
+        old_salt: &str,
+        new_salt: &str,
+    ) -> Result<ReencryptReport, sqlx::Error> {
+        tracing::info!("[ENCRYPTION] Starting token re-encryption");
+
+        let old_crypto = TokenCrypto::with_salt(old_salt);
+        let new_crypto = TokenCrypto::with_salt(new_salt);
+        let mut report = ReencryptReport::default();
+
+        let mut tx = self.pool.begin().await?;
+
+        let rows = sqlx::query(
+            "SELECT id, nightscout_token FROM nightscout_sites WHERE nightscout_token IS NOT NULL AND nightscout_token != ''",
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for row in rows {
+            let site_id: i64 = row.get("id");
+            let token: String = row.get("nightscout_token");
+
+            let Ok(plaintext) = old_crypto.decrypt(&token) else {
+                if new_crypto.decrypt(&token).is_ok() {
+                    report.already_current += 1;
+                } else {
+                    tracing::warn!(
+                        "[ENCRYPTION] Couldn't decrypt token for site {} with either key, leaving it",
+                        site_id
+                    );
+                    report.failed += 1;
+                }
+                continue;
+            };
+
+            let encrypted = new_crypto
+                .encrypt(&plaintext)
+                .map_err(|e| sqlx::Error::Protocol(format!("Token encryption failed: {}", e)))?;
+            sqlx::query("UPDATE nightscout_sites SET nightscout_token = ? WHERE id = ?")
+                .bind(encrypted)
+                .bind(site_id)
+                .execute(&mut *tx)
+                .await?;
+            report.reencrypted += 1;
+        }
+
+        tx.commit().await?;
+        set_crypto(new_crypto);
+
+        tracing::info!(
+            "[ENCRYPTION] Re-encryption complete: {} re-encrypted, {} already current, {} unreadable",
+            report.reencrypted,
+            report.already_current,
+            report.failed
+        );
+        Ok(report)
+    }
+
     pub async fn get_user_stickers(&self, user_id: u64) -> Result<Vec<Sticker>, sqlx::Error> {
         let rows = sqlx::query(
-            "SELECT id, file_name, display_name, category FROM stickers WHERE discord_id = ?",
+            "SELECT id, file_name, display_name, category, rotation, scale FROM stickers WHERE discord_id = ?",
         )
         .bind(user_id as i64)
         .fetch_all(&self.pool)
@@ -520,6 +1620,8 @@ impl Database {
                     display_name: row.get("display_name"),
                     category: StickerCategory::from_str(&category_str)
                         .unwrap_or(StickerCategory::Any),
+                    rotation: row.get::<Option<f32>, _>("rotation").unwrap_or(0.0),
+                    scale: row.get::<Option<f32>, _>("scale").unwrap_or(1.0),
                 }
             })
             .collect();
@@ -532,7 +1634,7 @@ impl Database {
         user_id: u64,
         category: StickerCategory,
     ) -> Result<Vec<Sticker>, sqlx::Error> {
-        let rows = sqlx::query("SELECT id, file_name, display_name, category FROM stickers WHERE discord_id = ? AND category = ?")
+        let rows = sqlx::query("SELECT id, file_name, display_name, category, rotation, scale FROM stickers WHERE discord_id = ? AND category = ?")
             .bind(user_id as i64)
             .bind(category.to_str())
             .fetch_all(&self.pool)
@@ -548,6 +1650,8 @@ impl Database {
                     display_name: row.get("display_name"),
                     category: StickerCategory::from_str(&category_str)
                         .unwrap_or(StickerCategory::Any),
+                    rotation: row.get::<Option<f32>, _>("rotation").unwrap_or(0.0),
+                    scale: row.get::<Option<f32>, _>("scale").unwrap_or(1.0),
                 }
             })
             .collect();
@@ -589,6 +1693,380 @@ impl Database {
         Ok(())
     }
 
+    pub async fn update_display_device_changes(
+        &self,
+        discord_id: u64,
+        display: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET display_device_changes = ? WHERE discord_id = ?")
+            .bind(display as i32)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Pin the graph's y-axis to a range in mg/dL, or return to auto-scaling with `None`
+    pub async fn update_fixed_scale(
+        &self,
+        discord_id: u64,
+        scale_mg: Option<(f32, f32)>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE users SET fixed_scale_min = ?, fixed_scale_max = ? WHERE discord_id = ?",
+        )
+        .bind(scale_mg.map(|(min, _)| min))
+        .bind(scale_mg.map(|(_, max)| max))
+        .bind(discord_id as i64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_target_range(
+        &self,
+        discord_id: u64,
+        range_mg: Option<(f32, f32)>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET target_low = ?, target_high = ? WHERE discord_id = ?")
+            .bind(range_mg.map(|(low, _)| low))
+            .bind(range_mg.map(|(_, high)| high))
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_urgent_low(
+        &self,
+        discord_id: u64,
+        urgent_low_mg: Option<f32>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET urgent_low = ? WHERE discord_id = ?")
+            .bind(urgent_low_mg)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_stale_minutes(
+        &self,
+        discord_id: u64,
+        minutes: Option<i64>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET stale_minutes = ? WHERE discord_id = ?")
+            .bind(minutes)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_timezone_override(
+        &self,
+        discord_id: u64,
+        timezone: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET timezone_override = ? WHERE discord_id = ?")
+            .bind(timezone)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_weekly_digest(
+        &self,
+        discord_id: u64,
+        enabled: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET weekly_digest = ? WHERE discord_id = ?")
+            .bind(enabled as i32)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Users who opted into the weekly digest, with the week it was last sent for
+    pub async fn get_digest_subscribers(&self) -> Result<Vec<(u64, Option<String>)>, sqlx::Error> {
+        let rows =
+            sqlx::query("SELECT discord_id, digest_sent_week FROM users WHERE weekly_digest = 1")
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get::<i64, _>("discord_id") as u64,
+                    row.get("digest_sent_week"),
+                )
+            })
+            .collect())
+    }
+
+    pub async fn update_leaderboard_opt_in(
+        &self,
+        discord_id: u64,
+        opt_in: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET leaderboard_opt_in = ? WHERE discord_id = ?")
+            .bind(opt_in as i32)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Users who explicitly opted into `/leaderboard`; nobody else may ever be ranked
+    pub async fn get_leaderboard_participants(&self) -> Result<Vec<u64>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT discord_id FROM users WHERE leaderboard_opt_in = 1 ORDER BY discord_id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| row.get::<i64, _>("discord_id") as u64)
+            .collect())
+    }
+
+    pub async fn mark_digest_sent(&self, discord_id: u64, week: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET digest_sent_week = ? WHERE discord_id = ?")
+            .bind(week)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_smooth_line(
+        &self,
+        discord_id: u64,
+        smooth_line: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET smooth_line = ? WHERE discord_id = ?")
+            .bind(smooth_line as i32)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_right_axis(
+        &self,
+        discord_id: u64,
+        right_axis: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET right_axis = ? WHERE discord_id = ?")
+            .bind(right_axis as i32)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_treatment_legend(
+        &self,
+        discord_id: u64,
+        treatment_legend: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET treatment_legend = ? WHERE discord_id = ?")
+            .bind(treatment_legend as i32)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_marker_legend(
+        &self,
+        discord_id: u64,
+        marker_legend: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET marker_legend = ? WHERE discord_id = ?")
+            .bind(marker_legend as i32)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_sd_band(&self, discord_id: u64, sd_band: bool) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET sd_band = ? WHERE discord_id = ?")
+            .bind(sd_band as i32)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_minor_gridlines(
+        &self,
+        discord_id: u64,
+        minor_gridlines: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET minor_gridlines = ? WHERE discord_id = ?")
+            .bind(minor_gridlines as i32)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_graph_theme(
+        &self,
+        discord_id: u64,
+        theme: GraphThemeKind,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET graph_theme = ? WHERE discord_id = ?")
+            .bind(theme.to_str())
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_show_watermark(
+        &self,
+        discord_id: u64,
+        show_watermark: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET show_watermark = ? WHERE discord_id = ?")
+            .bind(show_watermark as i32)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_graph_tint(
+        &self,
+        discord_id: u64,
+        tint: GraphTint,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET graph_tint = ? WHERE discord_id = ?")
+            .bind(tint.to_str())
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_default_graph_hours(
+        &self,
+        discord_id: u64,
+        hours: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET default_graph_hours = ? WHERE discord_id = ?")
+            .bind(hours)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_treatment_layout(
+        &self,
+        discord_id: u64,
+        layout: TreatmentLayout,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET treatment_layout = ? WHERE discord_id = ?")
+            .bind(layout.to_str())
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_graph_palette(
+        &self,
+        discord_id: u64,
+        palette: GraphPalette,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET graph_palette = ? WHERE discord_id = ?")
+            .bind(palette.to_str())
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_anonymous_mode(
+        &self,
+        discord_id: u64,
+        anonymous_mode: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET anonymous_mode = ? WHERE discord_id = ?")
+            .bind(anonymous_mode as i32)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Silence notifications until the given Unix timestamp, or resume them with `None`
+    pub async fn update_snooze_until(
+        &self,
+        discord_id: u64,
+        snooze_until: Option<i64>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET snooze_until = ? WHERE discord_id = ?")
+            .bind(snooze_until)
+            .bind(discord_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_allowed_people(&self, owner_id: u64) -> Result<Vec<u64>, sqlx::Error> {
+        Ok(self
+            .get_user_info(owner_id)
+            .await?
+            .nightscout
+            .allowed_people)
+    }
+
+    /// Empty a user's allowed list, returning how many people were on it
+    pub async fn clear_allowed(&self, owner_id: u64) -> Result<usize, sqlx::Error> {
+        let removed = self.get_allowed_people(owner_id).await?.len();
+
+        sqlx::query(
+            "UPDATE nightscout_sites SET allowed_people = '[]' WHERE discord_id = ? AND is_active = 1",
+        )
+            .bind(owner_id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(removed)
+    }
+
     pub async fn add_allowed_user(
         &self,
         owner_id: u64,
@@ -605,11 +2083,13 @@ impl Database {
         let allowed_people_json =
             serde_json::to_string(&allowed_people).unwrap_or("[]".to_string());
 
-        sqlx::query("UPDATE users SET allowed_people = ? WHERE discord_id = ?")
-            .bind(allowed_people_json)
-            .bind(owner_id as i64)
-            .execute(&self.pool)
-            .await?;
+        sqlx::query(
+            "UPDATE nightscout_sites SET allowed_people = ? WHERE discord_id = ? AND is_active = 1",
+        )
+        .bind(allowed_people_json)
+        .bind(owner_id as i64)
+        .execute(&self.pool)
+        .await?;
 
         Ok(true)
     }
@@ -630,11 +2110,13 @@ impl Database {
         let allowed_people_json =
             serde_json::to_string(&allowed_people).unwrap_or("[]".to_string());
 
-        sqlx::query("UPDATE users SET allowed_people = ? WHERE discord_id = ?")
-            .bind(allowed_people_json)
-            .bind(owner_id as i64)
-            .execute(&self.pool)
-            .await?;
+        sqlx::query(
+            "UPDATE nightscout_sites SET allowed_people = ? WHERE discord_id = ? AND is_active = 1",
+        )
+        .bind(allowed_people_json)
+        .bind(owner_id as i64)
+        .execute(&self.pool)
+        .await?;
 
         Ok(true)
     }
@@ -662,3 +2144,249 @@ impl Database {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[test]
+    fn test_can_view_owner_public_or_allowed() {
+        let mut info = NightscoutInfo {
+            is_private: false,
+            ..NightscoutInfo::default()
+        };
+        assert!(info.can_view(1, 2));
+
+        info.is_private = true;
+        assert!(info.can_view(1, 1));
+        assert!(!info.can_view(1, 2));
+
+        info.allowed_people = vec![2];
+        assert!(info.can_view(1, 2));
+        assert!(!info.can_view(1, 3));
+    }
+
+    #[test]
+    fn test_effective_timezone_prefers_the_override() {
+        let store = ProfileStore {
+            timezone: "Europe/Warsaw".to_string(),
+            units: None,
+            target_low: None,
+            target_high: None,
+            basal: None,
+        };
+        let mut info = NightscoutInfo::default();
+        assert_eq!(info.effective_timezone(None), "UTC");
+        assert_eq!(info.effective_timezone(Some(&store)), "Europe/Warsaw");
+
+        info.timezone_override = Some("America/Chicago".to_string());
+        assert_eq!(info.effective_timezone(Some(&store)), "America/Chicago");
+    }
+
+    /// A fully migrated database that lives in memory. A single connection keeps every
+    /// query on the same in-memory database.
+    async fn memory_database() -> Database {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        Database::with_pool(pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_delete_user_removes_their_sites() {
+        let database = memory_database().await;
+        let user_id = 1_100_000_000_000_000_003u64;
+
+        database
+            .insert_user(
+                user_id,
+                NightscoutInfo {
+                    nightscout_url: Some("https://main.example.com".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        database.delete_user(user_id).await.unwrap();
+        assert!(!database.user_exists(user_id).await.unwrap());
+        let sites: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM nightscout_sites WHERE discord_id = ?")
+                .bind(user_id as i64)
+                .fetch_one(&database.pool)
+                .await
+                .unwrap();
+        assert_eq!(sites, 0);
+    }
+
+    #[tokio::test]
+    async fn test_export_round_trips_every_setting() {
+        let database = memory_database().await;
+        let user_id = 1_100_000_000_000_000_001u64;
+        let friend_id = 1_100_000_000_000_000_002u64;
+
+        database
+            .insert_user(
+                user_id,
+                NightscoutInfo {
+                    nightscout_url: Some("https://main.example.com".to_string()),
+                    nightscout_token: Some("secret-token".to_string()),
+                    allowed_people: vec![friend_id],
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        database
+            .update_target_range(user_id, Some((80.0, 160.0)))
+            .await
+            .unwrap();
+        database
+            .update_timezone_override(user_id, Some("Europe/Paris"))
+            .await
+            .unwrap();
+        database.update_weekly_digest(user_id, true).await.unwrap();
+        database
+            .update_leaderboard_opt_in(user_id, true)
+            .await
+            .unwrap();
+        database
+            .update_snooze_until(user_id, Some(1_700_000_000))
+            .await
+            .unwrap();
+        database.update_anonymous_mode(user_id, true).await.unwrap();
+        database
+            .update_minor_gridlines(user_id, true)
+            .await
+            .unwrap();
+        let sticker_id = database
+            .insert_sticker(user_id, "beet.png", "Beet", StickerCategory::High, 10.0)
+            .await
+            .unwrap();
+        database
+            .update_sticker_scale(user_id, sticker_id, 1.5)
+            .await
+            .unwrap();
+        database
+            .add_nightscout_site(user_id, "kid", "https://kid.example.com", Some("kid-token"))
+            .await
+            .unwrap();
+
+        let export = database.export_user(user_id).await.unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&export).unwrap()).unwrap();
+
+        assert_eq!(json["discord_id"], user_id.to_string());
+        assert_eq!(json["nightscout_token"], "[REDACTED]");
+        assert_eq!(json["allowed_people"][0], friend_id.to_string());
+        assert_eq!(json["target_range_mg"], serde_json::json!([80.0, 160.0]));
+        assert_eq!(json["timezone_override"], "Europe/Paris");
+        assert_eq!(json["weekly_digest"], true);
+        assert_eq!(json["leaderboard_opt_in"], true);
+        assert_eq!(json["snooze_until"], "2023-11-14T22:13:20+00:00");
+        assert_eq!(json["anonymous_mode"], true);
+        assert_eq!(json["minor_gridlines"], true);
+        assert_eq!(json["stickers"][0]["scale"], 1.5);
+
+        let sites = json["nightscout_sites"].as_array().unwrap();
+        assert_eq!(sites.len(), 2);
+        assert_eq!(sites[0]["name"], "default");
+        assert_eq!(sites[0]["nightscout_url"], "https://main.example.com");
+        assert_eq!(sites[0]["is_active"], true);
+        assert_eq!(sites[1]["nightscout_url"], "https://kid.example.com");
+        assert_eq!(sites[1]["nightscout_token"], "[REDACTED]");
+        assert!(!json.to_string().contains("kid-token"));
+        assert!(!json.to_string().contains("secret-token"));
+    }
+
+    #[tokio::test]
+    async fn test_switching_sites_keeps_each_sites_settings() {
+        let database = memory_database().await;
+        let user_id = 1_100_000_000_000_000_004u64;
+        let friend_id = 1_100_000_000_000_000_005u64;
+
+        database
+            .insert_user(
+                user_id,
+                NightscoutInfo {
+                    nightscout_url: Some("https://main.example.com".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        database
+            .add_nightscout_site(user_id, "kid", "https://kid.example.com", None)
+            .await
+            .unwrap();
+        database.add_allowed_user(user_id, friend_id).await.unwrap();
+
+        assert!(
+            database
+                .switch_nightscout_site(user_id, "kid")
+                .await
+                .unwrap()
+        );
+        let info = database.get_user_info(user_id).await.unwrap().nightscout;
+        assert_eq!(
+            info.nightscout_url.as_deref(),
+            Some("https://kid.example.com")
+        );
+        assert!(info.allowed_people.is_empty());
+
+        assert!(
+            database
+                .switch_nightscout_site(user_id, "default")
+                .await
+                .unwrap()
+        );
+        let info = database.get_user_info(user_id).await.unwrap().nightscout;
+        assert_eq!(
+            info.nightscout_url.as_deref(),
+            Some("https://main.example.com")
+        );
+        assert_eq!(info.allowed_people, vec![friend_id]);
+    }
+
+    #[tokio::test]
+    async fn test_connection_on_users_row_moves_to_active_site() {
+        let database = memory_database().await;
+        let user_id = 1_100_000_000_000_000_006u64;
+
+        // A user stored before the connection moved off the users row
+        sqlx::query(
+            "INSERT INTO users (discord_id, nightscout_url, is_private, allowed_people) VALUES (?, 'https://old.example.com', 0, '[7]')",
+        )
+        .bind(user_id as i64)
+        .execute(&database.pool)
+        .await
+        .unwrap();
+
+        let migration = crate::utils::migration::Migration::new(database.pool.clone());
+        migration.move_connection_to_sites().await.unwrap();
+        migration.move_connection_to_sites().await.unwrap();
+
+        let info = database.get_user_info(user_id).await.unwrap().nightscout;
+        assert_eq!(
+            info.nightscout_url.as_deref(),
+            Some("https://old.example.com")
+        );
+        assert!(!info.is_private);
+        assert_eq!(info.allowed_people, vec![7]);
+
+        let sites = database.get_nightscout_sites(user_id).await.unwrap();
+        assert_eq!(sites.len(), 1);
+        assert!(sites[0].is_active);
+
+        let leftover: Option<String> =
+            sqlx::query_scalar("SELECT nightscout_url FROM users WHERE discord_id = ?")
+                .bind(user_id as i64)
+                .fetch_one(&database.pool)
+                .await
+                .unwrap();
+        assert_eq!(leftover, None);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::utils::database::StickerCategory;
+
+/// Where a reading falls against a user's thresholds
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GlucoseStatus {
+    UrgentLow,
+    Low,
+    InRange,
+    High,
+}
+
+impl GlucoseStatus {
+    /// Low or urgent low
+    pub fn is_low(self) -> bool {
+        matches!(self, Self::UrgentLow | Self::Low)
+    }
+
+    pub fn to_sticker_category(self) -> StickerCategory {
+        match self {
+            Self::UrgentLow | Self::Low => StickerCategory::Low,
+            Self::InRange => StickerCategory::InRange,
+            Self::High => StickerCategory::High,
+        }
+    }
+}
+
+/// The mg/dL boundaries readings are classified against. The target range is inclusive, so
+/// a reading exactly on a boundary counts as in range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlucoseThresholds {
+    pub target_low_mg: f32,
+    pub target_high_mg: f32,
+    /// Readings below this are urgent lows; without it every low is a plain low
+    pub urgent_low_mg: Option<f32>,
+}
+
+impl GlucoseThresholds {
+    pub fn new(target_low_mg: f32, target_high_mg: f32) -> Self {
+        Self {
+            target_low_mg,
+            target_high_mg,
+            urgent_low_mg: None,
+        }
+    }
+
+    pub fn with_urgent_low(mut self, urgent_low_mg: f32) -> Self {
+        self.urgent_low_mg = Some(urgent_low_mg);
+        self
+    }
+
+    /// Classify a reading in mg/dL
+    pub fn classify(&self, sgv: f32) -> GlucoseStatus {
+        if sgv > self.target_high_mg {
+            GlucoseStatus::High
+        } else if self
+            .urgent_low_mg
+            .is_some_and(|urgent_low| sgv < urgent_low)
+        {
+            GlucoseStatus::UrgentLow
+        } else if sgv < self.target_low_mg {
+            GlucoseStatus::Low
+        } else {
+            GlucoseStatus::InRange
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_boundaries() {
+        let thresholds = GlucoseThresholds::new(70.0, 180.0);
+        assert_eq!(thresholds.classify(69.9), GlucoseStatus::Low);
+        assert_eq!(thresholds.classify(70.0), GlucoseStatus::InRange);
+        assert_eq!(thresholds.classify(180.0), GlucoseStatus::InRange);
+        assert_eq!(thresholds.classify(180.1), GlucoseStatus::High);
+        assert_eq!(thresholds.classify(40.0), GlucoseStatus::Low);
+
+        let thresholds = thresholds.with_urgent_low(54.0);
+        assert_eq!(thresholds.classify(53.0), GlucoseStatus::UrgentLow);
+        assert_eq!(thresholds.classify(54.0), GlucoseStatus::Low);
+        assert!(thresholds.classify(53.0).is_low());
+        assert_eq!(
+            GlucoseStatus::UrgentLow.to_sticker_category(),
+            StickerCategory::Low
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use super::drawing::{BucketPercentiles, agp_percentiles, draw_sd_band, draw_smooth_line};
+use super::helpers::{
+    draw_dashed_horizontal_line, draw_dashed_vertical_line, draw_y_gridlines, y_axis_range,
+    y_axis_ticks,
+};
+use super::layout::GraphLayout;
+use super::text::draw_text;
+use super::theme::GraphTheme;
+use super::types::PrefUnit;
+use super::{GraphOutput, encode_png};
+use crate::bot::Handler;
+use crate::utils::database::NightscoutInfo;
+use crate::utils::nightscout::{Entry, Profile, StatusThresholds};
+use crate::utils::stats::GlucoseSummary;
+use ab_glyph::PxScale;
+use anyhow::{Result, anyhow};
+use chrono::{Timelike, Utc};
+use image::RgbaImage;
+use imageproc::drawing::draw_line_segment_mut;
+
+/// Width of each time-of-day bucket the percentiles are computed over
+const AGP_BUCKET_MINUTES: u32 = 30;
+/// Hours between x-axis labels on the 24h axis
+const AGP_LABEL_INTERVAL_HOURS: u32 = 3;
+
+/// Draw an Ambulatory Glucose Profile: the last `days` days of readings folded onto a single
+/// 24 hour axis, with the median and the 25-75% and 10-90% ranges of each time of day.
+pub fn draw_agp(
+    entries: &[Entry],
+    profile: &Profile,
+    user_settings: &NightscoutInfo,
+    handler: &Handler,
+    days: u16,
+    status_thresholds: Option<&StatusThresholds>,
+) -> Result<GraphOutput> {
+    tracing::info!(
+        "[AGP] Starting glucose profile for {} days from {} entries",
+        days,
+        entries.len()
+    );
+
+    let profile_store = profile.default_store().ok_or_else(|| {
+        tracing::error!("[AGP] Profile has no stores");
+        anyhow!("Nightscout profile has no stores")
+    })?;
+    let user_timezone = user_settings.effective_timezone(Some(profile_store));
+
+    let target_low_mg =
+        profile_store.get_target_low_mg(user_settings.target_range_mg, status_thresholds);
+    let target_high_mg =
+        profile_store.get_target_high_mg(user_settings.target_range_mg, status_thresholds);
+
+    let end = Utc::now();
+    let start = end - chrono::Duration::days(days as i64);
+    let entries = handler
+        .nightscout_client
+        .filter_and_clean_entries_between(entries, start, end, user_timezone)
+        .map_err(|e| anyhow!("No entries found in the last {} days: {}", days, e))?;
+
+    let samples: Vec<(u32, f32)> = entries
+        .iter()
+        .filter(|entry| entry.has_sgv())
+        .map(|entry| {
+            let time = entry.millis_to_user_timezone(user_timezone);
+            (time.hour() * 60 + time.minute(), entry.sgv)
+        })
+        .collect();
+    let buckets = agp_percentiles(&samples, AGP_BUCKET_MINUTES);
+    if buckets.iter().all(Option::is_none) {
+        return Err(anyhow!("Not enough readings to build a glucose profile"));
+    }
+
+    let pref = if profile_store.uses_mmol() {
+        PrefUnit::Mmol
+    } else {
+        PrefUnit::MgDl
+    };
+
+    let layout = GraphLayout::new(false);
+    let (width, height) = (layout.width, layout.height);
+    let num_y_labels = 8;
+
+    let theme = GraphTheme::from(user_settings.graph_theme)
+        .with_tint(user_settings.graph_tint)
+        .with_palette(user_settings.graph_palette);
+
+    let plot_left = layout.plot_left;
+    let plot_top = layout.plot_top;
+    let plot_right = layout.plot_right;
+    let plot_bottom = layout.plot_bottom;
+    let inner_plot_left = layout.inner_left;
+    let inner_plot_right = layout.inner_right;
+    let inner_plot_top = layout.inner_top;
+    let inner_plot_bottom = layout.inner_bottom;
+    let inner_plot_w = inner_plot_right - inner_plot_left;
+
+    let highest_mg = buckets
+        .iter()
+        .flatten()
+        .map(|bucket| bucket.p90)
+        .fold(0.0_f32, f32::max);
+    let (y_min, y_max) = y_axis_range(highest_mg, user_settings.fixed_scale_mg, pref);
+
+    // The x axis runs over a single day, in seconds since midnight
+    let projection = layout.projection(y_min, y_max, pref, 0, 24 * 3600);
+    let project_y = |value_mg: f32| projection.y(projection.clamp_mg(value_mg));
+    let bucket_x = |index: usize| {
+        let bucket_secs = AGP_BUCKET_MINUTES as i64 * 60;
+        projection.x(index as i64 * bucket_secs + bucket_secs / 2)
+    };
+    // Missing buckets break the bands and median instead of being bridged
+    let max_gap_px = inner_plot_w / buckets.len() as f32 * 1.5;
+
+    let mut img = RgbaImage::from_pixel(width, height, theme.bg);
+
+    draw_line_segment_mut(
+        &mut img,
+        (plot_left, plot_top),
+        (plot_left, plot_bottom),
+        theme.axis,
+    );
+    draw_line_segment_mut(
+        &mut img,
+        (plot_left, plot_bottom),
+        (plot_right, plot_bottom),
+        theme.axis,
+    );
+
+    let y_values = y_axis_ticks(y_min, y_max, pref, num_y_labels);
+    draw_y_gridlines(
+        &mut img,
+        &projection,
+        &y_values,
+        user_settings.minor_gridlines,
+        &theme,
+    );
+
+    for y_val in y_values {
+        let y_px = projection.y_display(y_val);
+
+        let (primary, secondary) = match pref {
+            PrefUnit::MgDl => (format!("{}", y_val as i32), format!("{:.1}", y_val / 18.0)),
+            PrefUnit::Mmol => (
+                format!("{:.1}", y_val),
+                format!("{}", (y_val * 18.0) as i32),
+            ),
+        };
+        let label_x = (plot_left - 136.0) as i32;
+        draw_text(
+            &mut img,
+            theme.bright,
+            label_x,
+            (y_px - 16.0) as i32,
+            PxScale::from(40.0),
+            handler,
+            &primary,
+        );
+        draw_text(
+            &mut img,
+            theme.dim,
+            label_x,
+            (y_px + 12.0) as i32,
+            PxScale::from(36.0),
+            handler,
+            &secondary,
+        );
+    }
+
+    for hour in (0..=24).step_by(AGP_LABEL_INTERVAL_HOURS as usize) {
+        let x = projection.x(hour as i64 * 3600);
+        if hour > 0 && hour < 24 {
+            draw_dashed_vertical_line(
+                &mut img,
+                x,
+                inner_plot_top,
+                inner_plot_bottom,
+                theme.faint_grid,
+                6,
+                12,
+            );
+        }
+        let label = format!("{:02}:00", hour % 24);
+        let label_w = label.len() as f32 * 24.0;
+        draw_text(
+            &mut img,
+            theme.bright,
+            (x - label_w / 2.0) as i32,
+            (plot_bottom + 16.0) as i32,
+            PxScale::from(40.0),
+            handler,
+            &label,
+        );
+    }
+
+    for (target, color) in [
+        (target_high_mg, theme.target_high_line),
+        (target_low_mg, theme.target_low_line),
+    ] {
+        let y = project_y(target);
+        if y >= inner_plot_top && y <= inner_plot_bottom {
+            draw_dashed_horizontal_line(
+                &mut img,
+                y,
+                inner_plot_left,
+                inner_plot_right,
+                color,
+                10,
+                5,
+            );
+        }
+    }
+
+    let band = |upper: fn(&BucketPercentiles) -> f32, lower: fn(&BucketPercentiles) -> f32| {
+        buckets
+            .iter()
+            .enumerate()
+            .filter_map(|(i, bucket)| {
+                bucket.map(|b| (bucket_x(i), project_y(upper(&b)), project_y(lower(&b))))
+            })
+            .collect::<Vec<_>>()
+    };
+    draw_sd_band(
+        &mut img,
+        &band(|b| b.p90, |b| b.p10),
+        max_gap_px,
+        theme.insulin,
+        0.15,
+    );
+    draw_sd_band(
+        &mut img,
+        &band(|b| b.p75, |b| b.p25),
+        max_gap_px,
+        theme.insulin,
+        0.3,
+    );
+
+    let median_px: Vec<(f32, f32)> = buckets
+        .iter()
+        .enumerate()
+        .filter_map(|(i, bucket)| bucket.map(|b| (bucket_x(i), project_y(b.p50))))
+        .collect();
+    draw_smooth_line(&mut img, &median_px, max_gap_px, theme.bright);
+
+    let title = format!(
+        "Glucose profile · last {} days · median, 25-75% and 10-90%",
+        days
+    );
+    draw_text(
+        &mut img,
+        theme.dim,
+        (plot_left + 40.0) as i32,
+        (plot_top - 36.0) as i32,
+        PxScale::from(32.0),
+        handler,
+        &title,
+    );
+
+    let (primary_unit, secondary_unit) = match pref {
+        PrefUnit::MgDl => ("mg/dL", "mmol/L"),
+        PrefUnit::Mmol => ("mmol/L", "mg/dL"),
+    };
+    let header_x = (plot_left - 144.0) as i32;
+    let header_y = (plot_bottom + 60.0) as i32;
+    draw_text(
+        &mut img,
+        theme.bright,
+        header_x,
+        header_y,
+        PxScale::from(40.0),
+        handler,
+        primary_unit,
+    );
+    draw_text(
+        &mut img,
+        theme.dim,
+        header_x,
+        header_y + 36,
+        PxScale::from(36.0),
+        handler,
+        secondary_unit,
+    );
+
+    if user_settings.show_watermark && !user_settings.anonymous_mode {
+        draw_text(
+            &mut img,
+            theme.dim,
+            20,
+            10,
+            PxScale::from(36.0),
+            handler,
+            "Beetroot",
+        );
+    }
+
+    let png = encode_png(img)?;
+    tracing::info!("[AGP] Generated glucose profile ({} bytes)", png.len());
+
+    let summary = GlucoseSummary::from_entries(&entries, target_low_mg, target_high_mg);
+    Ok(GraphOutput {
+        png,
+        svg: None,
+        entries,
+        timezone: user_timezone.to_string(),
+        summary,
+    })
+}
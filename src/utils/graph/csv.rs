@@ -0,0 +1,78 @@
+use chrono::SecondsFormat;
+
+use crate::utils::nightscout::Entry;
+
+/// Serialize entries as CSV (timestamp in the user's timezone, mg/dL, mmol/L, direction),
+/// oldest reading first.
+pub fn entries_to_csv(entries: &[Entry], user_timezone: &str) -> String {
+    let mut rows: Vec<(chrono::DateTime<chrono_tz::Tz>, &Entry)> = entries
+        .iter()
+        .map(|entry| (entry.millis_to_user_timezone(user_timezone), entry))
+        .collect();
+    rows.sort_by_key(|(time, _)| *time);
+
+    let mut csv = String::from("timestamp,mg_dl,mmol_l,direction\n");
+    for (time, entry) in rows {
+        let mg_dl = if entry.has_sgv() {
+            entry.sgv
+        } else {
+            entry.mbg.unwrap_or(0.0)
+        };
+
+        csv.push_str(&format!(
+            "{},{:.0},{:.1},{}\n",
+            time.to_rfc3339_opts(SecondsFormat::Secs, false),
+            mg_dl,
+            mg_dl / 18.0,
+            escape_field(entry.direction.as_deref().unwrap_or(""))
+        ));
+    }
+
+    csv
+}
+
+fn escape_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(sgv: f32, date: u64, direction: Option<&str>) -> Entry {
+        Entry {
+            id: None,
+            sgv,
+            direction: direction.map(str::to_string),
+            entry_type: Some("sgv".to_string()),
+            date_string: None,
+            date: Some(date),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_entries_to_csv_sorted_in_user_timezone() {
+        let entries = vec![
+            entry(126.0, 1_700_000_300_000, Some("Flat")),
+            entry(120.0, 1_700_000_000_000, Some("FortyFiveUp")),
+        ];
+
+        let csv = entries_to_csv(&entries, "Europe/Paris");
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines[0], "timestamp,mg_dl,mmol_l,direction");
+        assert_eq!(lines[1], "2023-11-14T23:13:20+01:00,120,6.7,FortyFiveUp");
+        assert_eq!(lines[2], "2023-11-14T23:18:20+01:00,126,7.0,Flat");
+    }
+
+    #[test]
+    fn test_entries_to_csv_escapes_fields() {
+        let csv = entries_to_csv(&[entry(100.0, 1_700_000_000_000, Some("a,b"))], "UTC");
+        assert!(csv.ends_with(",\"a,b\"\n"));
+    }
+}
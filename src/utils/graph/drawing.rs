@@ -1,40 +1,125 @@
 use ab_glyph::PxScale;
 use image::{Rgba, RgbaImage};
-use imageproc::drawing::{draw_filled_circle_mut, draw_polygon_mut, draw_text_mut};
+use imageproc::drawing::{
+    draw_filled_circle_mut, draw_filled_rect_mut, draw_hollow_circle_mut, draw_hollow_rect_mut,
+    draw_line_segment_mut, draw_polygon_mut,
+};
 use imageproc::point::Point;
+use imageproc::rect::Rect;
 
+use super::helpers::{draw_dashed_vertical_line, shade_region};
+use super::text::draw_text;
+use super::theme::GraphTheme;
 use super::types::PrefUnit;
 use crate::bot::Handler;
-use crate::utils::nightscout::Entry;
+use crate::utils::glucose::{GlucoseStatus, GlucoseThresholds};
+use crate::utils::nightscout::{Entry, Trend};
 
-/// Draw insulin treatment (triangle)
+pub const MICROBOLUS_TRIANGLE_SIZE: f32 = 8.0;
+const MIN_BOLUS_TRIANGLE_SIZE: f32 = 12.0;
+const MAX_BOLUS_TRIANGLE_SIZE: f32 = 30.0;
+
+/// The smallest dose that gets the largest triangle, so a window with only a few small
+/// boluses doesn't draw them all at full size
+const MIN_TYPICAL_MAX_BOLUS: f32 = 5.0;
+
+/// The 95th percentile of the given bolus amounts, used as the dose that gets the largest
+/// triangle so the scale adapts to the user's own dosing range. Never below
+/// [`MIN_TYPICAL_MAX_BOLUS`].
+pub fn typical_max_bolus(amounts: &[f32]) -> Option<f32> {
+    let mut amounts: Vec<f32> = amounts.iter().copied().filter(|a| *a > 0.0).collect();
+    if amounts.is_empty() {
+        return None;
+    }
+
+    amounts.sort_by(|a, b| a.total_cmp(b));
+    let index = ((amounts.len() - 1) as f32 * 0.95).round() as usize;
+    Some(amounts[index].max(MIN_TYPICAL_MAX_BOLUS))
+}
+
+/// Half-width of a bolus triangle. The triangle's area grows with the dose between the
+/// microbolus threshold and `typical_max_bolus`, clamped to a readable pixel range.
+pub fn insulin_triangle_size(
+    insulin_amount: f32,
+    microbolus_threshold: f32,
+    typical_max_bolus: f32,
+) -> f32 {
+    let span = typical_max_bolus - microbolus_threshold;
+    let ratio = if span > 0.0 {
+        ((insulin_amount - microbolus_threshold) / span).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+
+    MIN_BOLUS_TRIANGLE_SIZE + ratio.sqrt() * (MAX_BOLUS_TRIANGLE_SIZE - MIN_BOLUS_TRIANGLE_SIZE)
+}
+
+/// How far insulin and carb markers sit below and above the reading they belong to
+pub const TREATMENT_MARKER_OFFSET: f32 = 70.0;
+
+/// Centre of a treatment marker `offset` pixels from `anchor_y`, keeping the `above` and
+/// `below` extents of the marker and its label between `top` and `bottom`. Flips to the
+/// other side of the reading when there's no room, and clamps when neither side fits.
+pub fn place_marker(
+    anchor_y: f32,
+    offset: f32,
+    above: f32,
+    below: f32,
+    top: f32,
+    bottom: f32,
+) -> f32 {
+    let fits = |y: f32| y - above >= top && y + below <= bottom;
+    let preferred = anchor_y + offset;
+    if fits(preferred) {
+        return preferred;
+    }
+    let flipped = anchor_y - offset;
+    if fits(flipped) {
+        return flipped;
+    }
+    preferred.clamp(top + above, (bottom - below).max(top + above))
+}
+
+/// Draw insulin treatment (triangle), naming the insulins under the amount for
+/// multi-insulin treatments
 #[allow(clippy::too_many_arguments)]
 pub fn draw_insulin_treatment(
     img: &mut RgbaImage,
     insulin_amount: f32,
+    insulin_names: Option<&str>,
     is_microbolus: bool,
     microbolus_threshold: f32,
+    typical_max_bolus: f32,
     x: f32,
     y: f32,
-    insulin_col: Rgba<u8>,
-    bg: Rgba<u8>,
-    bright: Rgba<u8>,
+    plot_top: f32,
+    plot_bottom: f32,
+    theme: &GraphTheme,
     handler: &Handler,
 ) {
     let triangle_size = if is_microbolus {
-        8
-    } else if insulin_amount <= microbolus_threshold + 1.0 {
-        12
-    } else if insulin_amount <= microbolus_threshold + 5.0 {
-        18
+        MICROBOLUS_TRIANGLE_SIZE
     } else {
-        30
+        insulin_triangle_size(insulin_amount, microbolus_threshold, typical_max_bolus)
     };
 
-    let triangle_y = y + 70.0;
+    // The amount and insulin names hang below the triangle
+    let label_height = match (is_microbolus, insulin_names) {
+        (true, _) => 0.0,
+        (false, None) => 52.0,
+        (false, Some(_)) => 84.0,
+    };
+    let triangle_y = place_marker(
+        y,
+        TREATMENT_MARKER_OFFSET,
+        triangle_size,
+        triangle_size + label_height,
+        plot_top,
+        plot_bottom,
+    );
 
     tracing::trace!(
-        "[GRAPH] Drawing insulin: {:.1}u at ({:.1}, {:.1}) - size: {}",
+        "[GRAPH] Drawing insulin: {:.1}u at ({:.1}, {:.1}) - size: {:.1}",
         insulin_amount,
         x,
         triangle_y,
@@ -43,61 +128,95 @@ pub fn draw_insulin_treatment(
 
     let triangle_points = vec![
         Point::new(
-            (x - triangle_size as f32) as i32,
-            (triangle_y - triangle_size as f32) as i32,
+            (x - triangle_size) as i32,
+            (triangle_y - triangle_size) as i32,
         ),
         Point::new(
-            (x + triangle_size as f32) as i32,
-            (triangle_y - triangle_size as f32) as i32,
+            (x + triangle_size) as i32,
+            (triangle_y - triangle_size) as i32,
         ),
-        Point::new(x as i32, (triangle_y + triangle_size as f32) as i32),
+        Point::new(x as i32, (triangle_y + triangle_size) as i32),
     ];
 
-    draw_polygon_mut(img, &triangle_points, insulin_col);
+    draw_polygon_mut(img, &triangle_points, theme.insulin);
 
     if !is_microbolus {
         let insulin_text = format!("{:.1}u", insulin_amount);
         let text_width = insulin_text.len() as f32 * 18.0;
         let text_x = (x - text_width / 2.0) as i32;
-        let text_y = (triangle_y + triangle_size as f32 + 16.0) as i32;
+        let text_y = (triangle_y + triangle_size + 16.0) as i32;
         let scale = PxScale::from(36.0);
 
         for dx in [-1, 0, 1] {
             for dy in [-1, 0, 1] {
                 if dx != 0 || dy != 0 {
-                    draw_text_mut(
+                    draw_text(
                         img,
-                        bg,
+                        theme.bg,
                         text_x + dx,
                         text_y + dy,
                         scale,
-                        &handler.font,
+                        handler,
                         &insulin_text,
                     );
                 }
             }
         }
 
-        draw_text_mut(
+        draw_text(
             img,
-            bright,
+            theme.bright,
             text_x,
             text_y,
             scale,
-            &handler.font,
+            handler,
             &insulin_text,
         );
+
+        if let Some(names) = insulin_names {
+            let names_width = names.chars().count() as f32 * 14.0;
+            draw_text(
+                img,
+                theme.dim,
+                (x - names_width / 2.0) as i32,
+                text_y + 40,
+                PxScale::from(28.0),
+                handler,
+                names,
+            );
+        }
+    }
+}
+
+/// Carbs at or above this amount are drawn at full colour
+const FULL_COLOR_CARBS: f32 = 60.0;
+/// Share of the carbs colour used for the smallest snacks, blended into the background
+const MIN_CARBS_COLOR_STRENGTH: f32 = 0.45;
+
+/// Carbs colour scaled by amount, so a snack reads fainter than a large meal
+pub fn carbs_color(carbs_amount: f32, theme: &GraphTheme) -> Rgba<u8> {
+    let strength = MIN_CARBS_COLOR_STRENGTH
+        + (1.0 - MIN_CARBS_COLOR_STRENGTH) * (carbs_amount / FULL_COLOR_CARBS).clamp(0.0, 1.0);
+    let mut color = theme.carbs;
+    for channel in 0..3 {
+        color[channel] = (theme.bg[channel] as f32 * (1.0 - strength)
+            + theme.carbs[channel] as f32 * strength)
+            .round() as u8;
     }
+    color
 }
 
-/// Draw carbs treatment (circle)
+/// Draw carbs treatment (circle), with fat-protein units appended to the label when logged
+#[allow(clippy::too_many_arguments)]
 pub fn draw_carbs_treatment(
     img: &mut RgbaImage,
     carbs_amount: f32,
+    fat_protein_units: Option<f32>,
     x: f32,
     y: f32,
-    carbs_col: Rgba<u8>,
-    bg: Rgba<u8>,
+    plot_top: f32,
+    plot_bottom: f32,
+    theme: &GraphTheme,
     handler: &Handler,
 ) {
     let circle_radius = if carbs_amount < 0.5 {
@@ -115,11 +234,27 @@ pub fn draw_carbs_treatment(
         y
     );
 
-    let carbs_y = y - 70.0;
+    // The amount sits above the circle
+    let carbs_y = place_marker(
+        y,
+        -TREATMENT_MARKER_OFFSET,
+        circle_radius as f32 + 50.0,
+        circle_radius as f32,
+        plot_top,
+        plot_bottom,
+    );
 
-    draw_filled_circle_mut(img, (x as i32, carbs_y as i32), circle_radius, carbs_col);
+    draw_filled_circle_mut(
+        img,
+        (x as i32, carbs_y as i32),
+        circle_radius,
+        carbs_color(carbs_amount, theme),
+    );
 
-    let carbs_text = format!("{}g", carbs_amount as i32);
+    let carbs_text = match fat_protein_units {
+        Some(fpu) => format!("{}g +{:.1} FPU", carbs_amount as i32, fpu),
+        None => format!("{}g", carbs_amount as i32),
+    };
     let text_width = carbs_text.len() as f32 * 18.0;
     let text_x = (x - text_width / 2.0) as i32;
     let text_y = (carbs_y - circle_radius as f32 - 50.0) as i32;
@@ -128,40 +263,131 @@ pub fn draw_carbs_treatment(
     for dx in [-1, 0, 1] {
         for dy in [-1, 0, 1] {
             if dx != 0 || dy != 0 {
-                draw_text_mut(
+                draw_text(
                     img,
-                    bg,
+                    theme.bg,
                     text_x + dx,
                     text_y + dy,
                     scale,
-                    &handler.font,
+                    handler,
                     &carbs_text,
                 );
             }
         }
     }
 
-    draw_text_mut(
+    draw_text(
         img,
-        carbs_col,
+        theme.carbs,
         text_x,
         text_y,
         scale,
-        &handler.font,
+        handler,
         &carbs_text,
     );
 }
 
-/// Draw glucose reading treatment (dual circle)
+/// Tallest a treatment bar gets, for doses at or above the reference amount
+const MAX_TREATMENT_BAR_HEIGHT: f32 = 90.0;
+/// Shortest a treatment bar gets, so tiny doses stay visible
+const MIN_TREATMENT_BAR_HEIGHT: f32 = 4.0;
+const TREATMENT_BAR_WIDTH: i32 = 8;
+const MICROBOLUS_BAR_WIDTH: i32 = 4;
+
+/// Bar height proportional to `amount`, reaching the full height at `reference`
+fn treatment_bar_height(amount: f32, reference: f32) -> f32 {
+    let ratio = if reference > 0.0 {
+        (amount / reference).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    (ratio * MAX_TREATMENT_BAR_HEIGHT).max(MIN_TREATMENT_BAR_HEIGHT)
+}
+
+/// Draw a bolus as a bar rising from `base_y`, labelled with its amount unless it's a
+/// microbolus
 #[allow(clippy::too_many_arguments)]
+pub fn draw_insulin_bar(
+    img: &mut RgbaImage,
+    insulin_amount: f32,
+    is_microbolus: bool,
+    typical_max_bolus: f32,
+    x: f32,
+    base_y: f32,
+    theme: &GraphTheme,
+    handler: &Handler,
+) {
+    let height = treatment_bar_height(insulin_amount, typical_max_bolus);
+    let width = if is_microbolus {
+        MICROBOLUS_BAR_WIDTH
+    } else {
+        TREATMENT_BAR_WIDTH
+    };
+    let top = base_y - height;
+    draw_filled_rect_mut(
+        img,
+        Rect::at(x as i32 - width / 2, top as i32).of_size(width as u32, height as u32),
+        theme.insulin,
+    );
+
+    if !is_microbolus {
+        let text = format!("{:.1}u", insulin_amount);
+        let text_width = text.len() as f32 * 14.0;
+        draw_text(
+            img,
+            theme.insulin,
+            (x - text_width / 2.0) as i32,
+            (top - 32.0) as i32,
+            PxScale::from(28.0),
+            handler,
+            &text,
+        );
+    }
+}
+
+/// Draw a carb entry as a bar hanging from `top_y`, labelled with its amount
+pub fn draw_carbs_bar(
+    img: &mut RgbaImage,
+    carbs_amount: f32,
+    fat_protein_units: Option<f32>,
+    x: f32,
+    top_y: f32,
+    theme: &GraphTheme,
+    handler: &Handler,
+) {
+    let height = treatment_bar_height(carbs_amount, FULL_COLOR_CARBS);
+    let color = carbs_color(carbs_amount, theme);
+    draw_filled_rect_mut(
+        img,
+        Rect::at(x as i32 - TREATMENT_BAR_WIDTH / 2, top_y as i32)
+            .of_size(TREATMENT_BAR_WIDTH as u32, height as u32),
+        color,
+    );
+
+    let text = match fat_protein_units {
+        Some(fpu) => format!("{}g +{:.1} FPU", carbs_amount as i32, fpu),
+        None => format!("{}g", carbs_amount as i32),
+    };
+    let text_width = text.len() as f32 * 14.0;
+    draw_text(
+        img,
+        theme.carbs,
+        (x - text_width / 2.0) as i32,
+        (top_y + height + 4.0) as i32,
+        PxScale::from(28.0),
+        handler,
+        &text,
+    );
+}
+
+/// Draw glucose reading treatment (dual circle)
 pub fn draw_glucose_reading(
     img: &mut RgbaImage,
     glucose_value: f32,
     x: f32,
     y: f32,
     pref: PrefUnit,
-    bg: Rgba<u8>,
-    bright: Rgba<u8>,
+    theme: &GraphTheme,
     handler: &Handler,
 ) {
     tracing::trace!(
@@ -172,11 +398,19 @@ pub fn draw_glucose_reading(
     );
 
     let bg_check_radius = 12;
-    let grey_outline = Rgba([128u8, 128u8, 128u8, 255u8]);
-    let red_inside = Rgba([220u8, 38u8, 27u8, 255u8]);
 
-    draw_filled_circle_mut(img, (x as i32, y as i32), bg_check_radius, grey_outline);
-    draw_filled_circle_mut(img, (x as i32, y as i32), bg_check_radius - 4, red_inside);
+    draw_filled_circle_mut(
+        img,
+        (x as i32, y as i32),
+        bg_check_radius,
+        theme.bg_check_outline,
+    );
+    draw_filled_circle_mut(
+        img,
+        (x as i32, y as i32),
+        bg_check_radius - 4,
+        theme.bg_check_fill,
+    );
 
     let glucose_text = match pref {
         PrefUnit::MgDl => format!("{:.0}", glucose_value),
@@ -190,52 +424,905 @@ pub fn draw_glucose_reading(
     for dx in [-1, 0, 1] {
         for dy in [-1, 0, 1] {
             if dx != 0 || dy != 0 {
-                draw_text_mut(
+                draw_text(
                     img,
-                    bg,
+                    theme.bg,
                     text_x + dx,
                     text_y + dy,
                     scale,
-                    &handler.font,
+                    handler,
                     &glucose_text,
                 );
             }
         }
     }
 
-    draw_text_mut(
+    draw_text(
         img,
-        bright,
+        theme.bright,
         text_x,
         text_y,
         scale,
-        &handler.font,
+        handler,
         &glucose_text,
     );
 }
 
+/// Draw a sensor or site change marker (dashed line with a short label at the top)
+#[allow(clippy::too_many_arguments)]
+pub fn draw_device_change_marker(
+    img: &mut RgbaImage,
+    label: &str,
+    x: f32,
+    plot_top: f32,
+    plot_bottom: f32,
+    marker_col: Rgba<u8>,
+    theme: &GraphTheme,
+    handler: &Handler,
+) {
+    tracing::trace!("[GRAPH] Drawing {} change marker at x={:.1}", label, x);
+
+    draw_dashed_vertical_line(img, x, plot_top, plot_bottom, marker_col, 14, 8);
+
+    let flag_points = vec![
+        Point::new(x as i32, plot_top as i32),
+        Point::new(x as i32 + 14, plot_top as i32 + 7),
+        Point::new(x as i32, plot_top as i32 + 14),
+    ];
+    draw_polygon_mut(img, &flag_points, marker_col);
+
+    let scale = PxScale::from(28.0);
+    let text_x = x as i32 + 18;
+    let text_y = plot_top as i32 - 4;
+
+    for dx in [-1, 0, 1] {
+        for dy in [-1, 0, 1] {
+            if dx != 0 || dy != 0 {
+                draw_text(
+                    img,
+                    theme.bg,
+                    text_x + dx,
+                    text_y + dy,
+                    scale,
+                    handler,
+                    label,
+                );
+            }
+        }
+    }
+
+    draw_text(img, marker_col, text_x, text_y, scale, handler, label);
+}
+
+/// Longest note text drawn on the graph before it is ellipsized
+const NOTE_MAX_CHARS: usize = 18;
+
+/// Shorten `text` to at most `max_chars` characters, ending with an ellipsis when cut
+fn ellipsize(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    format!("{}…", kept.trim_end())
+}
+
+/// Shade the span of an exercise treatment and label it along the bottom of the plot
+pub fn draw_exercise_band(
+    img: &mut RgbaImage,
+    x_start: f32,
+    x_end: f32,
+    plot_top: f32,
+    plot_bottom: f32,
+    theme: &GraphTheme,
+    handler: &Handler,
+) {
+    tracing::trace!(
+        "[GRAPH] Drawing exercise band from x={:.1} to x={:.1}",
+        x_start,
+        x_end
+    );
+
+    shade_region(
+        img,
+        x_start,
+        x_end,
+        plot_top,
+        plot_bottom,
+        theme.exercise,
+        0.12,
+    );
+
+    let label = "Exercise";
+    let label_width = label.len() as f32 * 14.0;
+    if x_end - x_start >= label_width + 8.0 {
+        draw_text(
+            img,
+            theme.exercise,
+            (x_start + 4.0) as i32,
+            (plot_bottom - 32.0) as i32,
+            PxScale::from(28.0),
+            handler,
+            label,
+        );
+    }
+}
+
+/// Draw a small diamond at the bottom of the plot with the (ellipsized) note text above it
+pub fn draw_note_marker(
+    img: &mut RgbaImage,
+    text: &str,
+    x: f32,
+    plot_bottom: f32,
+    theme: &GraphTheme,
+    handler: &Handler,
+) {
+    tracing::trace!("[GRAPH] Drawing note marker at x={:.1}", x);
+
+    let y = plot_bottom - 10.0;
+    let diamond = vec![
+        Point::new(x as i32, y as i32 - 8),
+        Point::new(x as i32 + 8, y as i32),
+        Point::new(x as i32, y as i32 + 8),
+        Point::new(x as i32 - 8, y as i32),
+    ];
+    draw_polygon_mut(img, &diamond, theme.note);
+
+    let label = ellipsize(text, NOTE_MAX_CHARS);
+    let scale = PxScale::from(26.0);
+    let text_width = label.chars().count() as f32 * 26.0 * 0.6;
+    let text_x = (x - text_width / 2.0).max(0.0) as i32;
+    let text_y = (y - 44.0) as i32;
+
+    for dx in [-1, 0, 1] {
+        for dy in [-1, 0, 1] {
+            if dx != 0 || dy != 0 {
+                draw_text(
+                    img,
+                    theme.bg,
+                    text_x + dx,
+                    text_y + dy,
+                    scale,
+                    handler,
+                    &label,
+                );
+            }
+        }
+    }
+
+    draw_text(img, theme.note, text_x, text_y, scale, handler, &label);
+}
+
+/// Draw a caret pointing off the plot for a reading beyond the top (`above`) or bottom of
+/// the y-axis, optionally labelled with its true value
+#[allow(clippy::too_many_arguments)]
+pub fn draw_off_scale_marker(
+    img: &mut RgbaImage,
+    x: f32,
+    y: f32,
+    radius: i32,
+    above: bool,
+    label: Option<&str>,
+    plot_right: f32,
+    theme: &GraphTheme,
+    handler: &Handler,
+) {
+    let (tip_y, base_y, color) = if above {
+        let tip_y = y - radius as f32 - 14.0;
+        (tip_y, tip_y + 8.0, theme.high)
+    } else {
+        let tip_y = y + radius as f32 + 14.0;
+        (tip_y, tip_y - 8.0, theme.low)
+    };
+
+    draw_line_segment_mut(img, (x - 7.0, base_y), (x, tip_y), color);
+    draw_line_segment_mut(img, (x, tip_y), (x + 7.0, base_y), color);
+    draw_line_segment_mut(img, (x - 7.0, base_y + 1.0), (x, tip_y + 1.0), color);
+    draw_line_segment_mut(img, (x, tip_y + 1.0), (x + 7.0, base_y + 1.0), color);
+
+    let Some(label) = label else {
+        return;
+    };
+
+    tracing::trace!(
+        "[GRAPH] Annotating off-scale reading {} at x={:.1}",
+        label,
+        x
+    );
+
+    let scale = PxScale::from(28.0);
+    let text_width = label.len() as f32 * 14.0;
+    let text_x = (x + 12.0).min(plot_right - text_width) as i32;
+    let text_y = if above { y - 6.0 } else { y - 22.0 } as i32;
+
+    for dx in [-1, 0, 1] {
+        for dy in [-1, 0, 1] {
+            if dx != 0 || dy != 0 {
+                draw_text(
+                    img,
+                    theme.bg,
+                    text_x + dx,
+                    text_y + dy,
+                    scale,
+                    handler,
+                    label,
+                );
+            }
+        }
+    }
+
+    draw_text(img, color, text_x, text_y, scale, handler, label);
+}
+
 /// Draw glucose data points on the graph
+/// Urgent lows are drawn this many pixels larger than other readings
+pub const URGENT_LOW_RADIUS_BOOST: i32 = 2;
+
 #[allow(clippy::too_many_arguments)]
 pub fn draw_glucose_points(
     img: &mut RgbaImage,
     entries: &[Entry],
     points_px: &[(f32, f32)],
     svg_radius: i32,
-    high_col: Rgba<u8>,
-    low_col: Rgba<u8>,
-    axis_col: Rgba<u8>,
-    target_high: f32,
-    target_low: f32,
+    theme: &GraphTheme,
+    thresholds: &GlucoseThresholds,
 ) {
     for (i, e) in entries.iter().enumerate() {
         let (x, y) = points_px[i];
-        let color = if e.sgv > target_high {
-            high_col
-        } else if e.sgv < target_low {
-            low_col
-        } else {
-            axis_col
+        let status = thresholds.classify(e.sgv);
+        let (color, radius) = match status {
+            GlucoseStatus::High => (theme.high, svg_radius),
+            GlucoseStatus::UrgentLow => (theme.urgent_low, svg_radius + URGENT_LOW_RADIUS_BOOST),
+            GlucoseStatus::Low => (theme.low, svg_radius),
+            GlucoseStatus::InRange => (theme.in_range, svg_radius),
         };
-        draw_filled_circle_mut(img, (x.round() as i32, y.round() as i32), svg_radius, color);
+        let center = (x.round() as i32, y.round() as i32);
+
+        if theme.square_highs && status == GlucoseStatus::High {
+            let side = (2 * radius).max(1) as u32;
+            let square = Rect::at(center.0 - radius, center.1 - radius).of_size(side, side);
+            if e.is_noisy() {
+                draw_hollow_rect_mut(img, square, color);
+            } else {
+                draw_filled_rect_mut(img, square, color);
+            }
+        } else if e.is_noisy() {
+            // Hollow ring for unreliable readings: two strokes so it stays visible at small radii
+            draw_hollow_circle_mut(img, center, radius, color);
+            draw_hollow_circle_mut(img, center, radius - 1, color);
+        } else {
+            draw_filled_circle_mut(img, center, radius, color);
+        }
+    }
+}
+
+const TREND_ARROW_LENGTH: f32 = 34.0;
+const TREND_ARROW_HEAD: f32 = 12.0;
+
+/// Draw a small arrow starting just right of the newest reading, tilted by the trend.
+/// Double trends get a second arrowhead.
+pub fn draw_trend_arrow(
+    img: &mut RgbaImage,
+    x: f32,
+    y: f32,
+    radius: i32,
+    trend: Trend,
+    color: Rgba<u8>,
+) {
+    let Some(degrees) = trend.as_degrees() else {
+        return;
+    };
+    // Image y grows downwards, so flip the angle's vertical component
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (dx, dy) = (cos, -sin);
+    let (px, py) = (-dy, dx);
+
+    let start = (x + radius as f32 + 10.0, y);
+    let tip = (
+        start.0 + dx * TREND_ARROW_LENGTH,
+        start.1 + dy * TREND_ARROW_LENGTH,
+    );
+
+    for offset in [-1.0, 0.0, 1.0] {
+        draw_line_segment_mut(
+            img,
+            (start.0 + px * offset, start.1 + py * offset),
+            (tip.0 + px * offset, tip.1 + py * offset),
+            color,
+        );
+    }
+
+    let heads = if trend.is_double() { 2 } else { 1 };
+    for head in 0..heads {
+        let head_tip = (
+            tip.0 - dx * TREND_ARROW_HEAD * head as f32,
+            tip.1 - dy * TREND_ARROW_HEAD * head as f32,
+        );
+        let base = (
+            head_tip.0 - dx * TREND_ARROW_HEAD,
+            head_tip.1 - dy * TREND_ARROW_HEAD,
+        );
+        let half = TREND_ARROW_HEAD * 0.6;
+        draw_polygon_mut(
+            img,
+            &[
+                Point::new(head_tip.0.round() as i32, head_tip.1.round() as i32),
+                Point::new(
+                    (base.0 + px * half).round() as i32,
+                    (base.1 + py * half).round() as i32,
+                ),
+                Point::new(
+                    (base.0 - px * half).round() as i32,
+                    (base.1 - py * half).round() as i32,
+                ),
+            ],
+            color,
+        );
+    }
+}
+
+/// Interpolated points per segment of the smoothed glucose line
+const SPLINE_SAMPLES_PER_SEGMENT: usize = 8;
+
+/// Split pixel points (sorted left to right) into runs wherever consecutive points are more
+/// than `max_gap_px` apart horizontally, so lines never bridge a gap in the data
+pub fn split_at_gaps(points: &[(f32, f32)], max_gap_px: f32) -> Vec<Vec<(f32, f32)>> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut runs: Vec<Vec<(f32, f32)>> = Vec::new();
+    for point in sorted {
+        match runs.last_mut() {
+            Some(run)
+                if run
+                    .last()
+                    .is_some_and(|last| point.0 - last.0 <= max_gap_px) =>
+            {
+                run.push(point)
+            }
+            _ => runs.push(vec![point]),
+        }
+    }
+    runs
+}
+
+/// Uniform Catmull-Rom interpolation through every point of a run. The
+/// curve passes through each input point; the first and last points are duplicated as the
+/// outer control points.
+pub fn catmull_rom(points: &[(f32, f32)], samples_per_segment: usize) -> Vec<(f32, f32)> {
+    if points.len() < 3 || samples_per_segment == 0 {
+        return points.to_vec();
+    }
+
+    let mut curve = Vec::with_capacity((points.len() - 1) * samples_per_segment + 1);
+    for i in 0..points.len() - 1 {
+        let p0 = points[i.saturating_sub(1)];
+        let p1 = points[i];
+        let p2 = points[i + 1];
+        let p3 = points[(i + 2).min(points.len() - 1)];
+
+        for step in 0..samples_per_segment {
+            let t = step as f32 / samples_per_segment as f32;
+            let t2 = t * t;
+            let t3 = t2 * t;
+            let blend = |a: f32, b: f32, c: f32, d: f32| {
+                0.5 * (2.0 * b
+                    + (c - a) * t
+                    + (2.0 * a - 5.0 * b + 4.0 * c - d) * t2
+                    + (3.0 * b - a - 3.0 * c + d) * t3)
+            };
+            curve.push((blend(p0.0, p1.0, p2.0, p3.0), blend(p0.1, p1.1, p2.1, p3.1)));
+        }
+    }
+    curve.push(points[points.len() - 1]);
+    curve
+}
+
+/// Draw a smoothed line through the glucose points, broken wherever readings are missing
+pub fn draw_smooth_line(
+    img: &mut RgbaImage,
+    points_px: &[(f32, f32)],
+    max_gap_px: f32,
+    color: Rgba<u8>,
+) {
+    for run in split_at_gaps(points_px, max_gap_px) {
+        let curve = catmull_rom(&run, SPLINE_SAMPLES_PER_SEGMENT);
+        for pair in curve.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            draw_line_segment_mut(img, start, end, color);
+            draw_line_segment_mut(img, (start.0, start.1 + 1.0), (end.0, end.1 + 1.0), color);
+        }
+    }
+}
+
+/// Centred moving mean and standard deviation of time-stamped samples, one pair per sample.
+/// Each sample's window covers everything within `window_ms / 2` either side of it.
+pub fn rolling_mean_sd(samples: &[(i64, f32)], window_ms: i64) -> Vec<(f32, f32)> {
+    let half = window_ms / 2;
+    samples
+        .iter()
+        .map(|&(time, _)| {
+            let values: Vec<f32> = samples
+                .iter()
+                .filter(|(other, _)| (other - time).abs() <= half)
+                .map(|&(_, value)| value)
+                .collect();
+            let n = values.len() as f32;
+            let mean = values.iter().sum::<f32>() / n;
+            let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / n;
+            (mean, variance.sqrt())
+        })
+        .collect()
+}
+
+/// Shade between an upper and lower pixel edge, given as `(x, upper_y, lower_y)` sorted left
+/// to right. Edges are interpolated per pixel column and the band breaks at gaps in the data.
+pub fn draw_sd_band(
+    img: &mut RgbaImage,
+    band_px: &[(f32, f32, f32)],
+    max_gap_px: f32,
+    color: Rgba<u8>,
+    opacity: f32,
+) {
+    for pair in band_px.windows(2) {
+        let ((x0, top0, bottom0), (x1, top1, bottom1)) = (pair[0], pair[1]);
+        let span = x1 - x0;
+        if span <= 0.0 || span > max_gap_px {
+            continue;
+        }
+
+        let mut x = x0.round();
+        while x < x1.round() {
+            let t = ((x - x0) / span).clamp(0.0, 1.0);
+            let top = top0 + (top1 - top0) * t;
+            let bottom = bottom0 + (bottom1 - bottom0) * t;
+            shade_region(img, x, x + 1.0, top, bottom, color, opacity);
+            x += 1.0;
+        }
+    }
+}
+
+/// Buckets with fewer readings than this are left out of the glucose profile
+const MIN_AGP_BUCKET_READINGS: usize = 3;
+
+/// The 10th, 25th, 50th, 75th and 90th percentile of one time-of-day bucket
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BucketPercentiles {
+    pub p10: f32,
+    pub p25: f32,
+    pub p50: f32,
+    pub p75: f32,
+    pub p90: f32,
+}
+
+/// Linearly interpolated percentile `p` (0 to 100) of values sorted in ascending order
+pub fn percentile(sorted: &[f32], p: f32) -> Option<f32> {
+    let (first, last) = (sorted.first()?, sorted.last()?);
+    let rank = (p / 100.0).clamp(0.0, 1.0) * (sorted.len() - 1) as f32;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    if lower == upper {
+        return Some(sorted[lower]);
+    }
+    let value = sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f32);
+    Some(value.clamp(*first, *last))
+}
+
+/// Group `(minute_of_day, value)` samples into `bucket_minutes`-wide buckets across a day and
+/// compute each bucket's percentiles. Buckets without enough readings are `None`.
+pub fn agp_percentiles(
+    samples: &[(u32, f32)],
+    bucket_minutes: u32,
+) -> Vec<Option<BucketPercentiles>> {
+    let bucket_minutes = bucket_minutes.clamp(1, 24 * 60);
+    let bucket_count = (24 * 60_u32).div_ceil(bucket_minutes) as usize;
+    let mut buckets: Vec<Vec<f32>> = vec![Vec::new(); bucket_count];
+    for &(minute, value) in samples {
+        let index = ((minute % (24 * 60)) / bucket_minutes) as usize;
+        buckets[index].push(value);
+    }
+
+    buckets
+        .into_iter()
+        .map(|mut values| {
+            if values.len() < MIN_AGP_BUCKET_READINGS {
+                return None;
+            }
+            values.sort_by(f32::total_cmp);
+            Some(BucketPercentiles {
+                p10: percentile(&values, 10.0)?,
+                p25: percentile(&values, 25.0)?,
+                p50: percentile(&values, 50.0)?,
+                p75: percentile(&values, 75.0)?,
+                p90: percentile(&values, 90.0)?,
+            })
+        })
+        .collect()
+}
+
+const MARKER_KEY_FONT_SIZE: f32 = 26.0;
+/// Space taken by a key icon, including the gap before its label
+const MARKER_KEY_ICON_WIDTH: i32 = 26;
+/// Gap between one key entry and the next
+const MARKER_KEY_SPACING: i32 = 22;
+
+/// Icon drawn next to each entry in the marker key
+enum KeyIcon {
+    Triangle(Rgba<u8>),
+    Circle(Rgba<u8>),
+    Square(Rgba<u8>),
+    Ringed,
+}
+
+/// Left edge of each key entry, laid out so the key ends at `right_x`
+fn marker_key_offsets(labels: &[&str], right_x: i32) -> Vec<i32> {
+    let widths: Vec<i32> = labels
+        .iter()
+        .map(|label| {
+            MARKER_KEY_ICON_WIDTH
+                + (label.chars().count() as f32 * MARKER_KEY_FONT_SIZE * 0.55) as i32
+        })
+        .collect();
+    let total: i32 =
+        widths.iter().sum::<i32>() + MARKER_KEY_SPACING * (widths.len() as i32 - 1).max(0);
+
+    let mut x = right_x - total;
+    widths
+        .iter()
+        .map(|width| {
+            let start = x;
+            x += width + MARKER_KEY_SPACING;
+            start
+        })
+        .collect()
+}
+
+/// Draw a one-line key of the graph's marker shapes and colours, ending at `right_x`
+pub fn draw_marker_key(
+    img: &mut RgbaImage,
+    right_x: i32,
+    y: i32,
+    theme: &GraphTheme,
+    handler: &Handler,
+) {
+    let entries = [
+        (KeyIcon::Triangle(theme.insulin), "Insulin"),
+        (KeyIcon::Circle(theme.carbs), "Carbs"),
+        (KeyIcon::Ringed, "Finger-stick"),
+        (KeyIcon::Circle(theme.low), "Low"),
+        (KeyIcon::Circle(theme.in_range), "In range"),
+        (
+            if theme.square_highs {
+                KeyIcon::Square(theme.high)
+            } else {
+                KeyIcon::Circle(theme.high)
+            },
+            "High",
+        ),
+    ];
+    let labels: Vec<&str> = entries.iter().map(|(_, label)| *label).collect();
+    let scale = PxScale::from(MARKER_KEY_FONT_SIZE);
+    let icon_y = y + (MARKER_KEY_FONT_SIZE / 2.0) as i32;
+
+    for ((icon, label), x) in entries.iter().zip(marker_key_offsets(&labels, right_x)) {
+        let icon_x = x + 8;
+        match icon {
+            KeyIcon::Triangle(color) => draw_polygon_mut(
+                img,
+                &[
+                    Point::new(icon_x - 8, icon_y + 7),
+                    Point::new(icon_x + 8, icon_y + 7),
+                    Point::new(icon_x, icon_y - 8),
+                ],
+                *color,
+            ),
+            KeyIcon::Circle(color) => draw_filled_circle_mut(img, (icon_x, icon_y), 7, *color),
+            KeyIcon::Square(color) => draw_filled_rect_mut(
+                img,
+                Rect::at(icon_x - 7, icon_y - 7).of_size(14, 14),
+                *color,
+            ),
+            KeyIcon::Ringed => {
+                draw_filled_circle_mut(img, (icon_x, icon_y), 8, theme.bg_check_outline);
+                draw_filled_circle_mut(img, (icon_x, icon_y), 4, theme.bg_check_fill);
+            }
+        }
+        draw_text(
+            img,
+            theme.dim,
+            x + MARKER_KEY_ICON_WIDTH,
+            y,
+            scale,
+            handler,
+            label,
+        );
+    }
+}
+
+/// Width of one `HH:MM amount` cell in the treatment legend
+const LEGEND_COLUMN_WIDTH: u32 = 250;
+const LEGEND_ROW_HEIGHT: u32 = 44;
+const LEGEND_PADDING: u32 = 24;
+const LEGEND_FONT_SIZE: f32 = 32.0;
+
+/// A bolus or carb entry listed in the treatment legend
+pub struct LegendItem {
+    pub time: String,
+    pub amount: String,
+    pub color: Rgba<u8>,
+}
+
+/// Columns and rows needed to lay out `item_count` legend cells across `width` pixels
+fn legend_layout(item_count: usize, width: u32) -> (usize, usize) {
+    let columns =
+        ((width.saturating_sub(2 * LEGEND_PADDING) / LEGEND_COLUMN_WIDTH) as usize).max(1);
+    (columns, item_count.div_ceil(columns))
+}
+
+/// Return a copy of the graph extended downwards with the time and amount of each
+/// treatment, laid out in rows, since the image itself can't show exact times on hover
+pub fn draw_treatment_legend(
+    img: &RgbaImage,
+    items: &[LegendItem],
+    theme: &GraphTheme,
+    handler: &Handler,
+) -> RgbaImage {
+    let (columns, rows) = legend_layout(items.len(), img.width());
+    let legend_height = 2 * LEGEND_PADDING + rows as u32 * LEGEND_ROW_HEIGHT;
+
+    let mut extended = RgbaImage::from_pixel(img.width(), img.height() + legend_height, theme.bg);
+    image::imageops::replace(&mut extended, img, 0, 0);
+
+    let scale = PxScale::from(LEGEND_FONT_SIZE);
+    let time_width = (6.0 * LEGEND_FONT_SIZE * 0.6) as i32;
+
+    for (i, item) in items.iter().enumerate() {
+        let x = (LEGEND_PADDING + (i % columns) as u32 * LEGEND_COLUMN_WIDTH) as i32;
+        let y = (img.height() + LEGEND_PADDING + (i / columns) as u32 * LEGEND_ROW_HEIGHT) as i32;
+
+        draw_text(&mut extended, theme.dim, x, y, scale, handler, &item.time);
+        draw_text(
+            &mut extended,
+            item.color,
+            x + time_width,
+            y,
+            scale,
+            handler,
+            &item.amount,
+        );
+    }
+
+    extended
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_place_marker_flips_and_clamps() {
+        // Room below the reading
+        assert_eq!(place_marker(300.0, 70.0, 10.0, 60.0, 100.0, 900.0), 370.0);
+        // Too close to the bottom, so it goes above instead
+        assert_eq!(place_marker(850.0, 70.0, 10.0, 60.0, 100.0, 900.0), 780.0);
+        // Carbs above a reading at the top edge flip below it
+        assert_eq!(place_marker(120.0, -70.0, 74.0, 24.0, 100.0, 900.0), 190.0);
+        // Neither side fits in a cramped plot, so it's clamped inside
+        assert_eq!(place_marker(150.0, 70.0, 10.0, 60.0, 100.0, 200.0), 140.0);
+    }
+
+    #[test]
+    fn test_trend_arrow_points_with_trend() {
+        let blank = RgbaImage::from_pixel(120, 120, Rgba([0, 0, 0, 255]));
+        let color = Rgba([255, 255, 255, 255]);
+        let lit = |img: &RgbaImage, x: u32, y: u32| img.get_pixel(x, y) == &color;
+
+        let mut up = blank.clone();
+        draw_trend_arrow(&mut up, 50.0, 80.0, 4, Trend::SingleUp, color);
+        assert!(lit(&up, 64, 60));
+        assert!(!lit(&up, 80, 80));
+
+        let mut flat = blank.clone();
+        draw_trend_arrow(&mut flat, 50.0, 80.0, 4, Trend::Flat, color);
+        assert!(lit(&flat, 80, 80));
+
+        let mut none = blank.clone();
+        draw_trend_arrow(&mut none, 50.0, 80.0, 4, Trend::Else, color);
+        assert_eq!(none, blank);
+    }
+
+    #[test]
+    fn test_ellipsize_long_notes() {
+        assert_eq!(ellipsize("Run", 18), "Run");
+        assert_eq!(ellipsize("Pizza with extra cheese", 10), "Pizza wit…");
+        assert_eq!(ellipsize("Pizza and pasta", 7), "Pizza…");
+        assert_eq!(ellipsize("ééééé", 5), "ééééé");
+    }
+
+    #[test]
+    fn test_legend_layout_wraps_into_rows() {
+        assert_eq!(legend_layout(0, 1700), (6, 0));
+        assert_eq!(legend_layout(6, 1700), (6, 1));
+        assert_eq!(legend_layout(7, 1700), (6, 2));
+        assert_eq!(legend_layout(3, 100), (1, 3));
+    }
+
+    #[test]
+    fn test_split_at_gaps_breaks_runs() {
+        let points = [
+            (30.0, 5.0),
+            (0.0, 1.0),
+            (10.0, 2.0),
+            (20.0, 3.0),
+            (100.0, 4.0),
+        ];
+        let runs = split_at_gaps(&points, 15.0);
+
+        assert_eq!(runs.len(), 2);
+        assert_eq!(
+            runs[0],
+            vec![(0.0, 1.0), (10.0, 2.0), (20.0, 3.0), (30.0, 5.0)]
+        );
+        assert_eq!(runs[1], vec![(100.0, 4.0)]);
+    }
+
+    #[test]
+    fn test_catmull_rom_passes_through_points() {
+        let points = [(0.0, 0.0), (10.0, 20.0), (20.0, 5.0), (30.0, 15.0)];
+        let curve = catmull_rom(&points, 4);
+
+        assert_eq!(curve.len(), 3 * 4 + 1);
+        for (i, point) in points.iter().enumerate() {
+            let sampled = curve[i * 4];
+            assert!((sampled.0 - point.0).abs() < 1e-4);
+            assert!((sampled.1 - point.1).abs() < 1e-4);
+        }
+
+        // Straight input stays straight
+        let line = catmull_rom(&[(0.0, 0.0), (10.0, 10.0), (20.0, 20.0)], 5);
+        assert!(line.iter().all(|(x, y)| (x - y).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_carbs_color_scales_with_amount() {
+        let theme = GraphTheme::dark();
+        let snack = carbs_color(5.0, &theme);
+        let meal = carbs_color(90.0, &theme);
+
+        assert_eq!(meal, theme.carbs);
+        assert_ne!(snack, theme.carbs);
+        // The snack sits between the background and the full carbs colour
+        for channel in 0..3 {
+            let (lo, hi) = (
+                theme.bg[channel].min(theme.carbs[channel]),
+                theme.bg[channel].max(theme.carbs[channel]),
+            );
+            assert!((lo..=hi).contains(&snack[channel]));
+        }
+    }
+
+    #[test]
+    fn test_marker_key_ends_at_right_edge() {
+        let labels = ["Insulin", "Carbs", "In range"];
+        let offsets = marker_key_offsets(&labels, 1500);
+
+        assert_eq!(offsets.len(), 3);
+        assert!(offsets.windows(2).all(|pair| pair[0] < pair[1]));
+        let last_width = MARKER_KEY_ICON_WIDTH + (8.0 * MARKER_KEY_FONT_SIZE * 0.55) as i32;
+        assert_eq!(offsets[2] + last_width, 1500);
+        assert!(marker_key_offsets(&[], 1500).is_empty());
+    }
+
+    #[test]
+    fn test_percentile_interpolates() {
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(percentile(&values, 50.0), Some(3.0));
+        assert_eq!(percentile(&values, 0.0), Some(1.0));
+        assert_eq!(percentile(&values, 100.0), Some(5.0));
+        assert_eq!(percentile(&values, 25.0), Some(2.0));
+        assert_eq!(percentile(&values, 10.0), Some(1.4));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn test_agp_percentiles_bucket_by_time_of_day() {
+        // Three days of readings at 08:10, 08:20 and 08:40, and a lone reading at 20:00
+        let mut samples: Vec<(u32, f32)> = (0..3)
+            .flat_map(|day| {
+                let offset = day as f32 * 10.0;
+                [(490, 100.0 + offset), (500, 110.0 + offset), (520, 200.0)]
+            })
+            .collect();
+        samples.push((20 * 60, 150.0));
+
+        let buckets = agp_percentiles(&samples, 30);
+        assert_eq!(buckets.len(), 48);
+
+        let morning = buckets[16].unwrap();
+        assert_eq!(morning.p50, 115.0);
+        assert!(morning.p10 <= morning.p25 && morning.p75 <= morning.p90);
+        assert_eq!(buckets[17].unwrap().p50, 200.0);
+        assert!(buckets[40].is_none());
+        assert!(buckets[0].is_none());
+    }
+
+    #[test]
+    fn test_treatment_bar_height_is_proportional() {
+        assert_eq!(treatment_bar_height(10.0, 10.0), MAX_TREATMENT_BAR_HEIGHT);
+        assert_eq!(
+            treatment_bar_height(5.0, 10.0),
+            MAX_TREATMENT_BAR_HEIGHT / 2.0
+        );
+        assert_eq!(treatment_bar_height(25.0, 10.0), MAX_TREATMENT_BAR_HEIGHT);
+        assert_eq!(treatment_bar_height(0.05, 10.0), MIN_TREATMENT_BAR_HEIGHT);
+        assert_eq!(treatment_bar_height(1.0, 0.0), MAX_TREATMENT_BAR_HEIGHT);
+    }
+
+    #[test]
+    fn test_rolling_mean_sd() {
+        let minute = 60_000;
+        let flat: Vec<(i64, f32)> = (0..12).map(|i| (i * 5 * minute, 120.0)).collect();
+        for (mean, sd) in rolling_mean_sd(&flat, 60 * minute) {
+            assert_eq!(mean, 120.0);
+            assert_eq!(sd, 0.0);
+        }
+
+        // Alternating 100/140 around a mean of 120 has a standard deviation of 20
+        let alternating: Vec<(i64, f32)> = (0..4)
+            .map(|i| (i * 5 * minute, if i % 2 == 0 { 100.0 } else { 140.0 }))
+            .collect();
+        for (mean, sd) in rolling_mean_sd(&alternating, 60 * minute) {
+            assert!((mean - 120.0).abs() < 0.01, "mean {}", mean);
+            assert!((sd - 20.0).abs() < 0.01, "sd {}", sd);
+        }
+
+        // Samples outside the window don't pull the edges of the series
+        let step: Vec<(i64, f32)> = (0..24)
+            .map(|i| (i * 5 * minute, if i < 12 { 100.0 } else { 200.0 }))
+            .collect();
+        let stats = rolling_mean_sd(&step, 60 * minute);
+        assert_eq!(stats[0], (100.0, 0.0));
+        assert_eq!(stats[23], (200.0, 0.0));
+
+        assert!(rolling_mean_sd(&[], 60 * minute).is_empty());
+    }
+
+    #[test]
+    fn test_sd_band_breaks_at_gaps() {
+        let mut img = RgbaImage::from_pixel(100, 100, Rgba([0, 0, 0, 255]));
+        let band = [(10.0, 40.0, 60.0), (20.0, 40.0, 60.0), (80.0, 40.0, 60.0)];
+        draw_sd_band(&mut img, &band, 15.0, Rgba([255, 255, 255, 255]), 0.5);
+
+        assert_ne!(img.get_pixel(15, 50)[0], 0);
+        assert_eq!(img.get_pixel(15, 30)[0], 0);
+        assert_eq!(img.get_pixel(50, 50)[0], 0);
+    }
+
+    #[test]
+    fn test_insulin_triangle_size_scales_with_typical_max() {
+        let typical = typical_max_bolus(&[1.0, 2.0, 3.0, 4.0, 20.0]).unwrap();
+        assert_eq!(typical, 20.0);
+
+        assert_eq!(
+            insulin_triangle_size(0.5, 0.5, 4.0),
+            MIN_BOLUS_TRIANGLE_SIZE
+        );
+        assert_eq!(
+            insulin_triangle_size(4.0, 0.5, 4.0),
+            MAX_BOLUS_TRIANGLE_SIZE
+        );
+        assert_eq!(
+            insulin_triangle_size(12.0, 0.5, 4.0),
+            MAX_BOLUS_TRIANGLE_SIZE
+        );
+
+        // The same dose is drawn smaller for someone who usually takes larger boluses
+        assert!(insulin_triangle_size(2.0, 0.5, 4.0) > insulin_triangle_size(2.0, 0.5, 15.0));
+        assert_eq!(typical_max_bolus(&[]), None);
+    }
+
+    #[test]
+    fn test_lone_small_bolus_is_not_drawn_at_full_size() {
+        let typical = typical_max_bolus(&[1.0]).unwrap();
+        assert_eq!(typical, MIN_TYPICAL_MAX_BOLUS);
+        assert!(insulin_triangle_size(1.0, 0.5, typical) < MAX_BOLUS_TRIANGLE_SIZE);
     }
 }
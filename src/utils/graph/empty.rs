@@ -0,0 +1,213 @@
+use super::helpers::{draw_dashed_horizontal_line, draw_y_gridlines, y_axis_range, y_axis_ticks};
+use super::layout::GraphLayout;
+use super::text::draw_text;
+use super::theme::GraphTheme;
+use super::types::{GraphWindow, PrefUnit};
+use super::{GraphOutput, encode_png};
+use crate::bot::Handler;
+use crate::utils::database::NightscoutInfo;
+use ab_glyph::PxScale;
+use anyhow::Result;
+use chrono_tz::Tz;
+use image::RgbaImage;
+use imageproc::drawing::draw_line_segment_mut;
+
+pub(super) const EMPTY_MESSAGE_FONT_SIZE: f32 = 48.0;
+
+/// The message shown in place of readings, naming the window that came up empty
+pub(super) fn empty_message(window: GraphWindow, timezone: Tz) -> String {
+    if window.live {
+        format!("No data in the last {} hours", window.hours())
+    } else {
+        format!(
+            "No data on {}",
+            window.start.with_timezone(&timezone).format("%a %-d %b %Y")
+        )
+    }
+}
+
+/// Draw the graph's axes, gridlines and target lines with a centred message instead of
+/// readings, for a window with no data
+#[allow(clippy::too_many_arguments)]
+pub fn draw_empty_graph(
+    user_settings: &NightscoutInfo,
+    handler: &Handler,
+    window: GraphWindow,
+    user_timezone: &str,
+    pref: PrefUnit,
+    target_low_mg: f32,
+    target_high_mg: f32,
+) -> Result<GraphOutput> {
+    tracing::info!(
+        "[GRAPH] No entries between {} and {}, drawing an empty graph",
+        window.start,
+        window.end
+    );
+
+    let layout = GraphLayout::new(user_settings.right_axis);
+    let (width, height) = (layout.width, layout.height);
+    let num_y_labels = 8;
+
+    let theme = GraphTheme::from(user_settings.graph_theme)
+        .with_tint(user_settings.graph_tint)
+        .with_palette(user_settings.graph_palette);
+
+    let plot_left = layout.plot_left;
+    let plot_top = layout.plot_top;
+    let plot_right = layout.plot_right;
+    let plot_bottom = layout.plot_bottom;
+    let inner_plot_left = layout.inner_left;
+    let inner_plot_right = layout.inner_right;
+    let inner_plot_top = layout.inner_top;
+    let inner_plot_bottom = layout.inner_bottom;
+
+    let (y_min, y_max) = y_axis_range(0.0, user_settings.fixed_scale_mg, pref);
+    let projection = layout.projection(
+        y_min,
+        y_max,
+        pref,
+        window.start.timestamp(),
+        window.end.timestamp(),
+    );
+
+    let mut img = RgbaImage::from_pixel(width, height, theme.bg);
+
+    draw_line_segment_mut(
+        &mut img,
+        (plot_left, plot_top),
+        (plot_left, plot_bottom),
+        theme.axis,
+    );
+    draw_line_segment_mut(
+        &mut img,
+        (plot_left, plot_bottom),
+        (plot_right, plot_bottom),
+        theme.axis,
+    );
+
+    let y_values = y_axis_ticks(y_min, y_max, pref, num_y_labels);
+    draw_y_gridlines(
+        &mut img,
+        &projection,
+        &y_values,
+        user_settings.minor_gridlines,
+        &theme,
+    );
+
+    for y_val in y_values {
+        let y_px = projection.y_display(y_val);
+
+        let (primary, secondary) = match pref {
+            PrefUnit::MgDl => (format!("{}", y_val as i32), format!("{:.1}", y_val / 18.0)),
+            PrefUnit::Mmol => (
+                format!("{:.1}", y_val),
+                format!("{}", (y_val * 18.0) as i32),
+            ),
+        };
+        let label_x = (plot_left - 136.0) as i32;
+        draw_text(
+            &mut img,
+            theme.bright,
+            label_x,
+            (y_px - 16.0) as i32,
+            PxScale::from(40.0),
+            handler,
+            &primary,
+        );
+        draw_text(
+            &mut img,
+            theme.dim,
+            label_x,
+            (y_px + 12.0) as i32,
+            PxScale::from(36.0),
+            handler,
+            &secondary,
+        );
+        if user_settings.right_axis {
+            draw_text(
+                &mut img,
+                theme.dim,
+                (plot_right + 16.0) as i32,
+                (y_px - 18.0) as i32,
+                PxScale::from(36.0),
+                handler,
+                &secondary,
+            );
+        }
+    }
+
+    for (target, color) in [
+        (target_high_mg, theme.target_high_line),
+        (target_low_mg, theme.target_low_line),
+    ] {
+        let y = projection.y(target);
+        if y >= inner_plot_top && y <= inner_plot_bottom {
+            draw_dashed_horizontal_line(
+                &mut img,
+                y,
+                inner_plot_left,
+                inner_plot_right,
+                color,
+                10,
+                5,
+            );
+        }
+    }
+
+    let timezone: Tz = user_timezone.parse().unwrap_or(chrono_tz::UTC);
+    let message = empty_message(window, timezone);
+    let message_width = message.chars().count() as f32 * EMPTY_MESSAGE_FONT_SIZE * 0.5;
+    draw_text(
+        &mut img,
+        theme.dim,
+        ((inner_plot_left + inner_plot_right - message_width) / 2.0) as i32,
+        ((inner_plot_top + inner_plot_bottom - EMPTY_MESSAGE_FONT_SIZE) / 2.0) as i32,
+        PxScale::from(EMPTY_MESSAGE_FONT_SIZE),
+        handler,
+        &message,
+    );
+
+    if user_settings.show_watermark && !user_settings.anonymous_mode {
+        draw_text(
+            &mut img,
+            theme.dim,
+            20,
+            10,
+            PxScale::from(36.0),
+            handler,
+            "Beetroot",
+        );
+    }
+
+    Ok(GraphOutput {
+        png: encode_png(img)?,
+        svg: None,
+        entries: Vec::new(),
+        timezone: user_timezone.to_string(),
+        summary: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_empty_message_names_the_window() {
+        assert_eq!(
+            empty_message(GraphWindow::last_hours(6), chrono_tz::UTC),
+            "No data in the last 6 hours"
+        );
+
+        let day = GraphWindow::day(
+            NaiveDate::from_ymd_opt(2025, 9, 20).unwrap(),
+            chrono_tz::UTC,
+        )
+        .unwrap();
+        assert_eq!(
+            empty_message(day, chrono_tz::UTC),
+            "No data on Sat 20 Sep 2025"
+        );
+    }
+}
@@ -1,5 +1,10 @@
 use anyhow::{Result, anyhow};
 use image::RgbaImage;
+use imageproc::drawing::draw_line_segment_mut;
+
+use super::theme::GraphTheme;
+use super::types::{PlotProjection, PrefUnit};
+use crate::utils::nightscout::Entry;
 
 /// Download a sticker image from a URL
 pub async fn download_sticker_image(url: &str) -> Result<image::DynamicImage> {
@@ -89,3 +94,322 @@ pub fn draw_dashed_horizontal_line(
         drawing_dash = !drawing_dash;
     }
 }
+
+/// Find sensor dropouts: pairs of indices of consecutive sensor readings (in the order given)
+/// that are more than `threshold` apart. Meter-only entries are ignored.
+pub fn find_gaps(
+    entries: &[Entry],
+    user_timezone: &str,
+    threshold: chrono::Duration,
+) -> Vec<(usize, usize)> {
+    let readings: Vec<(usize, i64)> = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| entry.has_sgv())
+        .map(|(i, entry)| (i, entry.millis_to_user_timezone(user_timezone).timestamp()))
+        .collect();
+
+    readings
+        .windows(2)
+        .filter(|pair| (pair[1].1 - pair[0].1).abs() > threshold.num_seconds())
+        .map(|pair| (pair[0].0, pair[1].0))
+        .collect()
+}
+
+/// Evenly spaced mmol/L y-axis ticks between `y_min` and `y_max`, using the smallest
+/// 1, 2, 5 or 10 mmol step that fits in `max_ticks` labels. Every tick lies within the range.
+pub fn mmol_axis_ticks(y_min: f32, y_max: f32, max_ticks: usize) -> Vec<f32> {
+    const NICE_STEPS: [f32; 4] = [1.0, 2.0, 5.0, 10.0];
+    const EPSILON: f32 = 1e-4;
+
+    let tick_range = |step: f32| {
+        let first = (y_min / step - EPSILON).ceil() as i32;
+        let last = (y_max / step + EPSILON).floor() as i32;
+        (first, last)
+    };
+
+    let step = NICE_STEPS
+        .into_iter()
+        .find(|&step| {
+            let (first, last) = tick_range(step);
+            (last - first + 1) as usize <= max_ticks.max(2)
+        })
+        .unwrap_or(NICE_STEPS[NICE_STEPS.len() - 1]);
+
+    let (first, last) = tick_range(step);
+    (first..=last).map(|k| k as f32 * step).collect()
+}
+
+/// Faint mmol/L gridlines every half mmol between `y_min` and `y_max`, skipping the values
+/// already drawn as labelled `major_ticks`
+pub fn mmol_minor_ticks(y_min: f32, y_max: f32, major_ticks: &[f32]) -> Vec<f32> {
+    const MINOR_STEP: f32 = 0.5;
+    const EPSILON: f32 = 1e-4;
+
+    let first = (y_min / MINOR_STEP - EPSILON).ceil() as i32;
+    let last = (y_max / MINOR_STEP + EPSILON).floor() as i32;
+    (first..=last)
+        .map(|k| k as f32 * MINOR_STEP)
+        .filter(|value| {
+            !major_ticks
+                .iter()
+                .any(|major| (major - value).abs() < EPSILON)
+        })
+        .collect()
+}
+
+/// Pixel rows of the horizontal gridlines inside the plot, paired with whether each is a faint
+/// minor line. Minor lines come first so the labelled ones are drawn over them.
+pub fn y_gridlines(
+    projection: &PlotProjection,
+    major_ticks: &[f32],
+    minor: bool,
+) -> Vec<(f32, bool)> {
+    let minor_ticks = if minor && matches!(projection.pref, PrefUnit::Mmol) {
+        mmol_minor_ticks(projection.y_min, projection.y_max, major_ticks)
+    } else {
+        Vec::new()
+    };
+
+    minor_ticks
+        .into_iter()
+        .map(|value| (value, true))
+        .chain(major_ticks.iter().map(|value| (*value, false)))
+        .map(|(value, is_minor)| (projection.y_display(value), is_minor))
+        .filter(|(y_px, _)| *y_px > projection.top && *y_px < projection.bottom)
+        .collect()
+}
+
+/// Draw the horizontal gridlines from [`y_gridlines`] across the plot
+pub fn draw_y_gridlines(
+    img: &mut RgbaImage,
+    projection: &PlotProjection,
+    major_ticks: &[f32],
+    minor: bool,
+    theme: &GraphTheme,
+) {
+    for (y_px, is_minor) in y_gridlines(projection, major_ticks, minor) {
+        let color = if is_minor {
+            theme.faint_grid
+        } else {
+            theme.grid
+        };
+        draw_line_segment_mut(
+            img,
+            (projection.left, y_px),
+            (projection.right, y_px),
+            color,
+        );
+    }
+}
+
+/// The y-axis range in the display unit: the user's fixed scale, or one fitted to the highest
+/// reading within sensible bounds
+pub fn y_axis_range(
+    max_sgv_mg: f32,
+    fixed_scale_mg: Option<(f32, f32)>,
+    pref: PrefUnit,
+) -> (f32, f32) {
+    match (fixed_scale_mg, pref) {
+        (Some((min_mg, max_mg)), PrefUnit::MgDl) => (min_mg, max_mg),
+        (Some((min_mg, max_mg)), PrefUnit::Mmol) => (min_mg / 18.0, max_mg / 18.0),
+        (None, PrefUnit::MgDl) => (
+            40.0,
+            ((max_sgv_mg / 10.0).ceil() * 10.0).clamp(200.0, 400.0),
+        ),
+        (None, PrefUnit::Mmol) => (2.0, (max_sgv_mg / 18.0).ceil().clamp(11.0, 22.0)),
+    }
+}
+
+/// Y-axis tick values in the display unit, at most `max_ticks` of them
+pub fn y_axis_ticks(y_min: f32, y_max: f32, pref: PrefUnit, max_ticks: usize) -> Vec<f32> {
+    match pref {
+        PrefUnit::MgDl => {
+            let step = ((y_max - y_min) / (max_ticks - 1) as f32 / 10.0).ceil() * 10.0;
+            (0..max_ticks)
+                .map(|i| (y_min + step * i as f32).round())
+                .filter(|&val| val <= y_max)
+                .collect()
+        }
+        PrefUnit::Mmol => mmol_axis_ticks(y_min, y_max, max_ticks),
+    }
+}
+
+/// Radius of each reading's dot, shrinking as more readings share the plot width
+pub fn point_radius(reading_count: usize) -> i32 {
+    match reading_count {
+        0..100 => 8,
+        100..300 => 6,
+        300..600 => 4,
+        _ => 3,
+    }
+}
+
+/// The same wall-clock time one day later, so yesterday's readings line up with today's by
+/// time of day even across a daylight saving change
+pub fn same_time_next_day(
+    time: chrono::DateTime<chrono_tz::Tz>,
+) -> Option<chrono::DateTime<chrono_tz::Tz>> {
+    use chrono::TimeZone;
+
+    let next = time.naive_local() + chrono::Duration::days(1);
+    time.timezone().from_local_datetime(&next).earliest()
+}
+
+/// Blend a colour over a rectangular region, leaving whatever is underneath visible
+pub fn shade_region(
+    img: &mut RgbaImage,
+    x_start: f32,
+    x_end: f32,
+    y_start: f32,
+    y_end: f32,
+    color: image::Rgba<u8>,
+    opacity: f32,
+) {
+    let clamp_x = |x: f32| (x.round().max(0.0) as u32).min(img.width());
+    let clamp_y = |y: f32| (y.round().max(0.0) as u32).min(img.height());
+    let (x0, x1) = (clamp_x(x_start.min(x_end)), clamp_x(x_start.max(x_end)));
+    let (y0, y1) = (clamp_y(y_start.min(y_end)), clamp_y(y_start.max(y_end)));
+
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let pixel = img.get_pixel_mut(x, y);
+            for channel in 0..3 {
+                pixel[channel] = (pixel[channel] as f32 * (1.0 - opacity)
+                    + color[channel] as f32 * opacity)
+                    .round() as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(sgv: f32, minutes: u64) -> Entry {
+        Entry {
+            sgv,
+            date: Some(1_700_000_000_000 + minutes * 60_000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_point_radius_shrinks_for_dense_graphs() {
+        // 3h, 24h and 72h of 5-minute readings
+        assert_eq!(point_radius(36), 8);
+        assert_eq!(point_radius(288), 6);
+        assert_eq!(point_radius(864), 3);
+        assert!((1..2000).all(|n| point_radius(n) <= point_radius(n - 1)));
+    }
+
+    #[test]
+    fn test_same_time_next_day_keeps_wall_clock_across_dst() {
+        use chrono::{TimeZone, Timelike};
+
+        let paris: chrono_tz::Tz = "Europe/Paris".parse().unwrap();
+        // Clocks go forward overnight, so the next day's noon is only 23 hours later
+        let saturday_noon = paris.with_ymd_and_hms(2025, 3, 29, 12, 0, 0).unwrap();
+        let sunday_noon = same_time_next_day(saturday_noon).unwrap();
+
+        assert_eq!(sunday_noon.hour(), 12);
+        assert_eq!(sunday_noon.date_naive().to_string(), "2025-03-30");
+        assert_eq!((sunday_noon - saturday_noon).num_hours(), 23);
+    }
+
+    #[test]
+    fn test_find_gaps_between_sensor_readings() {
+        // Newest first, as returned by Nightscout
+        let entries = vec![
+            entry(120.0, 60),
+            entry(118.0, 55),
+            entry(0.0, 40),
+            entry(110.0, 20),
+            entry(108.0, 15),
+        ];
+
+        let gaps = find_gaps(&entries, "UTC", chrono::Duration::minutes(15));
+        assert_eq!(gaps, vec![(1, 3)]);
+
+        assert!(find_gaps(&entries, "UTC", chrono::Duration::minutes(40)).is_empty());
+    }
+
+    #[test]
+    fn test_mmol_minor_ticks_fill_between_major_ticks() {
+        let major = mmol_axis_ticks(2.0, 11.0, 8);
+        let minor = mmol_minor_ticks(2.0, 11.0, &major);
+
+        assert_eq!(minor.first(), Some(&2.5));
+        assert_eq!(minor.last(), Some(&11.0));
+        assert!(minor.iter().all(|value| !major.contains(value)));
+        assert_eq!(minor.len() + major.len(), 19);
+    }
+
+    #[test]
+    fn test_minor_gridlines_only_on_mmol_axes() {
+        let projection = |pref, y_min, y_max| PlotProjection {
+            left: 180.0,
+            right: 1600.0,
+            top: 100.0,
+            bottom: 920.0,
+            y_min,
+            y_max,
+            pref,
+            start_secs: 0,
+            end_secs: 3600,
+        };
+
+        let mmol = projection(PrefUnit::Mmol, 2.0, 11.0);
+        let major = y_axis_ticks(2.0, 11.0, PrefUnit::Mmol, 8);
+        let with_minor = y_gridlines(&mmol, &major, true);
+        assert!(with_minor.iter().any(|(_, is_minor)| *is_minor));
+        assert!(!y_gridlines(&mmol, &major, false).iter().any(|(_, m)| *m));
+        assert!(
+            with_minor
+                .iter()
+                .all(|(y_px, _)| *y_px > mmol.top && *y_px < mmol.bottom)
+        );
+
+        let mg = projection(PrefUnit::MgDl, 40.0, 200.0);
+        let major = y_axis_ticks(40.0, 200.0, PrefUnit::MgDl, 8);
+        assert!(!y_gridlines(&mg, &major, true).iter().any(|(_, m)| *m));
+    }
+
+    #[test]
+    fn test_mmol_axis_ticks_are_even_unique_and_in_range() {
+        for (min, max) in [
+            (2.0, 11.0),
+            (2.0, 22.0),
+            (3.9, 10.0),
+            (2.2, 5.0),
+            (2.0, 15.0),
+        ] {
+            let ticks = mmol_axis_ticks(min, max, 8);
+
+            assert!(
+                ticks.len() >= 2,
+                "too few ticks for {min}..{max}: {ticks:?}"
+            );
+            assert!(
+                ticks.len() <= 8,
+                "too many ticks for {min}..{max}: {ticks:?}"
+            );
+            assert!(ticks.iter().all(|t| *t >= min && *t <= max));
+
+            let steps: Vec<f32> = ticks.windows(2).map(|w| w[1] - w[0]).collect();
+            assert!(
+                steps
+                    .iter()
+                    .all(|s| *s > 0.0 && (s - steps[0]).abs() < 1e-4)
+            );
+            // The labels reach within one step of both ends of the axis
+            assert!(ticks[0] - min < steps[0] && max - ticks[ticks.len() - 1] < steps[0]);
+        }
+
+        assert_eq!(
+            mmol_axis_ticks(2.0, 11.0, 8),
+            vec![2.0, 4.0, 6.0, 8.0, 10.0]
+        );
+    }
+}
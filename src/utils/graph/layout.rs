@@ -0,0 +1,113 @@
+use super::types::{PlotProjection, PrefUnit};
+
+const WIDTH: u32 = 1700;
+const HEIGHT: u32 = 1100;
+const LEFT_MARGIN: f32 = 160.0;
+const RIGHT_MARGIN: f32 = 80.0;
+/// Right margin with room for the secondary unit labels mirrored on the right edge
+const RIGHT_AXIS_MARGIN: f32 = 150.0;
+const TOP_MARGIN: f32 = 80.0;
+const BOTTOM_MARGIN: f32 = 160.0;
+/// Gap between the axes and the area readings are drawn in
+const PLOT_PADDING: f32 = 20.0;
+
+/// Canvas size and plot area shared by every renderer, so the PNG and SVG graphs line up
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GraphLayout {
+    pub width: u32,
+    pub height: u32,
+    /// Where the axes are drawn
+    pub plot_left: f32,
+    pub plot_top: f32,
+    pub plot_right: f32,
+    pub plot_bottom: f32,
+    /// The padded area inside the axes that readings are drawn in
+    pub inner_left: f32,
+    pub inner_top: f32,
+    pub inner_right: f32,
+    pub inner_bottom: f32,
+}
+
+impl GraphLayout {
+    pub fn new(right_axis: bool) -> Self {
+        let right_margin = if right_axis {
+            RIGHT_AXIS_MARGIN
+        } else {
+            RIGHT_MARGIN
+        };
+        let plot_left = LEFT_MARGIN;
+        let plot_top = TOP_MARGIN;
+        let plot_right = WIDTH as f32 - right_margin;
+        let plot_bottom = HEIGHT as f32 - BOTTOM_MARGIN;
+
+        Self {
+            width: WIDTH,
+            height: HEIGHT,
+            plot_left,
+            plot_top,
+            plot_right,
+            plot_bottom,
+            inner_left: plot_left + PLOT_PADDING,
+            inner_top: plot_top + PLOT_PADDING,
+            inner_right: plot_right - PLOT_PADDING,
+            inner_bottom: plot_bottom - PLOT_PADDING,
+        }
+    }
+
+    /// A projection onto the inner plot area for the given axis range and time span
+    pub fn projection(
+        &self,
+        y_min: f32,
+        y_max: f32,
+        pref: PrefUnit,
+        start_secs: i64,
+        end_secs: i64,
+    ) -> PlotProjection {
+        PlotProjection {
+            left: self.inner_left,
+            right: self.inner_right,
+            top: self.inner_top,
+            bottom: self.inner_bottom,
+            y_min,
+            y_max,
+            pref,
+            start_secs,
+            end_secs,
+        }
+    }
+}
+
+/// Minutes between x-axis time labels for a window of `hours`
+pub fn label_interval_minutes(hours: u16) -> u32 {
+    match hours {
+        0..=3 => 30,
+        4..=6 => 60,
+        7..=12 => 120,
+        13..=24 => 180,
+        25..=48 => 360,
+        _ => 720,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_leaves_room_for_the_right_axis() {
+        let plain = GraphLayout::new(false);
+        let with_axis = GraphLayout::new(true);
+        assert_eq!(plain.plot_right, 1620.0);
+        assert_eq!(with_axis.plot_right, 1550.0);
+        assert_eq!(plain.inner_left, with_axis.inner_left);
+        assert_eq!(plain.inner_bottom, 920.0);
+    }
+
+    #[test]
+    fn test_label_interval_grows_with_window() {
+        assert_eq!(label_interval_minutes(3), 30);
+        assert_eq!(label_interval_minutes(12), 120);
+        assert_eq!(label_interval_minutes(24), 180);
+        assert_eq!(label_interval_minutes(72), 720);
+    }
+}
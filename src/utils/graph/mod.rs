@@ -1,29 +1,72 @@
+mod agp;
+mod csv;
 mod drawing;
+mod empty;
 mod helpers;
+mod layout;
 mod stickers;
+mod svg;
+mod text;
+mod theme;
 mod types;
 
 use drawing::{
-    draw_carbs_treatment, draw_glucose_points, draw_glucose_reading, draw_insulin_treatment,
+    LegendItem, draw_carbs_bar, draw_carbs_treatment, draw_device_change_marker,
+    draw_exercise_band, draw_glucose_points, draw_glucose_reading, draw_insulin_bar,
+    draw_insulin_treatment, draw_marker_key, draw_note_marker, draw_off_scale_marker, draw_sd_band,
+    draw_smooth_line, draw_treatment_legend, draw_trend_arrow, rolling_mean_sd, typical_max_bolus,
 };
-use helpers::{draw_dashed_horizontal_line, draw_dashed_vertical_line};
+use empty::draw_empty_graph;
+use helpers::{
+    draw_dashed_horizontal_line, draw_dashed_vertical_line, draw_y_gridlines, find_gaps,
+    point_radius, same_time_next_day, shade_region, y_axis_range, y_axis_ticks,
+};
+use layout::{GraphLayout, label_interval_minutes};
 use stickers::{
     StickerConfig, draw_sticker, filter_ranges_by_duration, find_sticker_position,
-    identify_status_ranges, select_stickers_to_place,
+    identify_status_ranges, prefetch_sticker_images, select_stickers_to_place,
+    sticker_collision_radius,
 };
+use text::draw_text;
 use types::PrefUnit;
 
-use super::database::{NightscoutInfo, Sticker};
-use super::nightscout::{Entry, Profile, Treatment};
+pub use agp::draw_agp;
+pub use csv::entries_to_csv;
+pub use stickers::random_sticker_rotation;
+pub use svg::draw_graph_svg;
+pub use text::GlyphCache;
+pub use theme::GraphTheme;
+pub use types::{GraphWindow, HiddenOverlays};
+
+use super::database::{NightscoutInfo, Sticker, TreatmentLayout};
+use super::glucose::GlucoseThresholds;
+use super::nightscout::{Entry, Profile, Treatment, get_urgent_low_mg};
+use super::stats::GlucoseSummary;
 use crate::bot::Handler;
 use ab_glyph::PxScale;
 use anyhow::{Result, anyhow};
-use chrono::Utc;
 use chrono_tz::Tz;
-use image::{DynamicImage, Rgba, RgbaImage};
-use imageproc::drawing::{draw_line_segment_mut, draw_text_mut};
+use image::{DynamicImage, RgbaImage};
+use imageproc::drawing::draw_line_segment_mut;
 use std::io::Cursor;
 
+/// Width of the moving window the variability band is computed over
+const SD_BAND_WINDOW_MINUTES: i64 = 60;
+
+/// A rendered graph along with the filtered entries that were plotted on it.
+#[derive(Clone)]
+pub struct GraphOutput {
+    pub png: Vec<u8>,
+    /// The same graph as vector SVG, when it was asked for
+    pub svg: Option<String>,
+    pub entries: Vec<Entry>,
+    pub timezone: String,
+    /// Stats for the plotted readings against the same target range the graph shades
+    pub summary: Option<GlucoseSummary>,
+}
+
+/// Draw a glucose graph of `window`. With `vs_yesterday`, `entries` should also cover the same
+/// window one day earlier, which is drawn as a faint trace aligned by time of day.
 #[allow(dead_code)]
 #[allow(clippy::too_many_arguments)]
 pub async fn draw_graph(
@@ -33,13 +76,18 @@ pub async fn draw_graph(
     user_settings: &NightscoutInfo,
     stickers: &[Sticker],
     handler: &Handler,
-    hours: u16,
+    window: GraphWindow,
     save_path: Option<&str>,
     status_thresholds: Option<&super::nightscout::StatusThresholds>,
-) -> Result<Vec<u8>> {
+    vs_yesterday: bool,
+    hidden: HiddenOverlays,
+) -> Result<GraphOutput> {
+    let hours = window.hours();
     tracing::info!(
-        "[GRAPH] Starting graph generation for {} hours of data",
-        hours
+        "[GRAPH] Starting graph generation for {} hours of data ({} to {})",
+        hours,
+        window.start,
+        window.end
     );
     tracing::debug!(
         "[GRAPH] Received {} entries and {} treatments",
@@ -47,41 +95,67 @@ pub async fn draw_graph(
         treatments.len()
     );
 
-    if entries.is_empty() {
-        tracing::error!("[GRAPH] No entries provided");
-        return Err(anyhow!("No entries provided"));
-    }
+    let stickers = if hidden.stickers { &[] } else { stickers };
 
-    let default_profile_name = &profile.default_profile;
-    let profile_store: &crate::utils::nightscout::ProfileStore =
-        profile.store.get(default_profile_name).ok_or_else(|| {
-            tracing::error!(
-                "[GRAPH] Default profile '{}' not found",
-                default_profile_name
-            );
-            anyhow!("Default profile not found")
-        })?;
+    let profile_store = profile.default_store().ok_or_else(|| {
+        tracing::error!("[GRAPH] Profile has no stores");
+        anyhow!("Nightscout profile has no stores")
+    })?;
 
-    let user_timezone = &profile_store.timezone;
+    let user_timezone = user_settings.effective_timezone(Some(profile_store));
     tracing::info!("[GRAPH] Using timezone: {}", user_timezone);
 
-    let target_low_mg = profile_store.get_target_low_mg(status_thresholds);
-    let target_high_mg = profile_store.get_target_high_mg(status_thresholds);
+    let target_low_mg =
+        profile_store.get_target_low_mg(user_settings.target_range_mg, status_thresholds);
+    let target_high_mg =
+        profile_store.get_target_high_mg(user_settings.target_range_mg, status_thresholds);
+    let urgent_low_mg = get_urgent_low_mg(user_settings.urgent_low_mg, status_thresholds);
     tracing::info!(
-        "[GRAPH] Using target ranges: {:.1} - {:.1} mg/dL",
+        "[GRAPH] Using target ranges: {:.1} - {:.1} mg/dL (urgent low {:.1})",
         target_low_mg,
-        target_high_mg
+        target_high_mg,
+        urgent_low_mg
     );
 
-    let nightscout_client = crate::utils::nightscout::Nightscout::new();
-    let entries = match nightscout_client.filter_and_clean_entries(entries, hours, user_timezone) {
+    let pref = if profile_store.uses_mmol() {
+        PrefUnit::Mmol
+    } else {
+        PrefUnit::MgDl
+    };
+
+    let nightscout_client = &handler.nightscout_client;
+    let yesterday_entries = if vs_yesterday {
+        nightscout_client
+            .filter_and_clean_entries_between(
+                entries,
+                window.start - chrono::Duration::days(1),
+                window.end,
+                user_timezone,
+            )
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let entries = match nightscout_client.filter_and_clean_entries_between(
+        entries,
+        window.start,
+        window.end,
+        user_timezone,
+    ) {
         Ok(filtered) => filtered,
         Err(e) => {
-            tracing::error!("[GRAPH] Failed to filter entries: {}", e);
-            return Err(anyhow!(
-                "No entries found within the requested {} hour time range",
-                hours
-            ));
+            // An empty window still gets a graph, so the reply reads as "nothing yet" rather
+            // than a failure
+            tracing::warn!("[GRAPH] No entries to plot: {}", e);
+            return draw_empty_graph(
+                user_settings,
+                handler,
+                window,
+                user_timezone,
+                pref,
+                target_low_mg,
+                target_high_mg,
+            );
         }
     };
 
@@ -90,55 +164,45 @@ pub async fn draw_graph(
         entries.len()
     );
 
-    let units_str = profile_store
-        .units
-        .clone()
-        .unwrap_or_else(|| "mg/dl".to_string())
-        .to_lowercase();
-
-    tracing::info!("[GRAPH] Using units: {}", units_str);
-
-    let pref = if units_str == "mmol/l" || units_str == "mmol" {
-        PrefUnit::Mmol
-    } else {
-        PrefUnit::MgDl
-    };
+    tracing::info!(
+        "[GRAPH] Profile units {:?}, drawing in {}",
+        profile_store.units,
+        if matches!(pref, PrefUnit::Mmol) {
+            "mmol/L"
+        } else {
+            "mg/dL"
+        }
+    );
 
     let num_y_labels = 8;
     let approximation = false;
-    let width = 1700u32;
-    let height = 1100u32;
-
-    let bg = Rgba([17u8, 24u8, 28u8, 255u8]);
-    let grid_col = Rgba([30u8, 41u8, 47u8, 255u8]);
-    let axis_col = Rgba([148u8, 163u8, 184u8, 255u8]);
-    let bright = Rgba([248u8, 250u8, 252u8, 255u8]);
-    let dim = Rgba([148u8, 163u8, 184u8, 255u8]);
-    let darker_dim = Rgba([98u8, 113u8, 134u8, 255u8]);
-    let high_col = Rgba([255u8, 159u8, 10u8, 255u8]);
-    let low_col = Rgba([255u8, 69u8, 58u8, 255u8]);
-    let insulin_col = Rgba([96u8, 165u8, 250u8, 255u8]);
-    let carbs_col = Rgba([251u8, 191u8, 36u8, 255u8]);
-    let _glucose_reading_col = Rgba([52u8, 211u8, 153u8, 255u8]);
-
-    let left_margin = 160.0_f32;
-    let right_margin = 80.0_f32;
-    let top_margin = 80.0_f32;
-    let bottom_margin = 160.0_f32;
-
-    let plot_w = (width as f32) - left_margin - right_margin;
-    let plot_h = (height as f32) - top_margin - bottom_margin;
-    let plot_left = left_margin;
-    let plot_top = top_margin;
-    let plot_right = plot_left + plot_w;
-    let plot_bottom = plot_top + plot_h;
-
-    let plot_padding = 20.0;
-
-    let inner_plot_left = plot_left + plot_padding;
-    let inner_plot_right = plot_right - plot_padding;
-    let inner_plot_top = plot_top + plot_padding;
-    let inner_plot_bottom = plot_bottom - plot_padding;
+    let layout = GraphLayout::new(user_settings.right_axis);
+    let width = layout.width;
+    let height = layout.height;
+
+    let theme = GraphTheme::from(user_settings.graph_theme)
+        .with_tint(user_settings.graph_tint)
+        .with_palette(user_settings.graph_palette);
+    tracing::info!(
+        "[GRAPH] Using {} theme",
+        user_settings.graph_theme.display_name()
+    );
+
+    let bg = theme.bg;
+    let axis_col = theme.axis;
+    let bright = theme.bright;
+    let dim = theme.dim;
+    let darker_dim = theme.darker_dim;
+
+    let plot_left = layout.plot_left;
+    let plot_top = layout.plot_top;
+    let plot_right = layout.plot_right;
+    let plot_bottom = layout.plot_bottom;
+
+    let inner_plot_left = layout.inner_left;
+    let inner_plot_right = layout.inner_right;
+    let inner_plot_top = layout.inner_top;
+    let inner_plot_bottom = layout.inner_bottom;
     let inner_plot_w = inner_plot_right - inner_plot_left;
     let inner_plot_h = inner_plot_bottom - inner_plot_top;
 
@@ -149,21 +213,10 @@ pub async fn draw_graph(
     let primary_legend_font_size: f32 = 40.0_f32;
     let secondary_legend_font_size: f32 = 36.0_f32;
 
-    let svg_radius: i32 = if entries.len() < 100 { 8 } else { 6 };
+    let svg_radius = point_radius(entries.len());
 
-    let (y_min, y_max) = match pref {
-        PrefUnit::MgDl => {
-            let max_mg = entries.iter().map(|e| e.sgv).fold(0.0_f32, |a, b| a.max(b));
-            let calculated_max = ((max_mg / 10.0).ceil() * 10.0).clamp(200.0, 400.0);
-            (40.0_f32, calculated_max)
-        }
-        PrefUnit::Mmol => {
-            let max_mg = entries.iter().map(|e| e.sgv).fold(0.0_f32, |a, b| a.max(b));
-            let max_mmol = max_mg / 18.0;
-            let calculated_max_mmol = (max_mmol.ceil()).clamp(11.0, 22.0);
-            (2.0_f32, calculated_max_mmol)
-        }
-    };
+    let max_sgv = entries.iter().map(|e| e.sgv).fold(0.0_f32, f32::max);
+    let (y_min, y_max) = y_axis_range(max_sgv, user_settings.fixed_scale_mg, pref);
 
     tracing::info!(
         "[GRAPH] Y-axis range: {:.1} to {:.1} ({})",
@@ -188,13 +241,14 @@ pub async fn draw_graph(
         }
     };
 
-    let project_y = |value: f32| -> f32 {
-        let normalized_value = match pref {
-            PrefUnit::MgDl => value,
-            PrefUnit::Mmol => value / 18.0,
-        };
-        inner_plot_bottom - ((normalized_value - y_min) / (y_max - y_min)) * inner_plot_h
-    };
+    let projection = layout.projection(
+        y_min,
+        y_max,
+        pref,
+        window.start.timestamp(),
+        window.end.timestamp(),
+    );
+    let project_y = |value: f32| -> f32 { projection.y(value) };
 
     let mut img = RgbaImage::from_pixel(width, height, bg);
 
@@ -211,128 +265,95 @@ pub async fn draw_graph(
         axis_col,
     );
 
-    let y_values: Vec<f32> = match pref {
-        PrefUnit::MgDl => {
-            let step = ((y_max - y_min) / (num_y_labels - 1) as f32 / 10.0).ceil() * 10.0;
-            (0..num_y_labels)
-                .map(|i| (y_min + step * i as f32).round())
-                .filter(|&val| val <= y_max)
-                .collect()
-        }
-        PrefUnit::Mmol => {
-            let step = ((y_max - y_min) / (num_y_labels - 1) as f32).ceil();
-            (0..num_y_labels)
-                .map(|i| (y_min + step * i as f32).floor())
-                .filter(|&val| val <= y_max)
-                .collect()
-        }
-    };
+    let y_values = y_axis_ticks(y_min, y_max, pref, num_y_labels);
 
-    for y_val in y_values.iter() {
-        let y_px = match pref {
-            PrefUnit::MgDl => project_y(*y_val),
-            PrefUnit::Mmol => {
-                inner_plot_bottom - ((*y_val - y_min) / (y_max - y_min)) * inner_plot_h
-            }
-        };
+    draw_y_gridlines(
+        &mut img,
+        &projection,
+        &y_values,
+        user_settings.minor_gridlines,
+        &theme,
+    );
 
-        if y_px > inner_plot_top && y_px < inner_plot_bottom {
-            draw_line_segment_mut(
-                &mut img,
-                (inner_plot_left, y_px),
-                (inner_plot_right, y_px),
-                grid_col,
-            );
-        }
+    for y_val in y_values.iter() {
+        let y_px = projection.y_display(*y_val);
 
         let label_x = (plot_left - 136.0) as i32;
 
-        match pref {
+        let (primary_display, secondary_display) = match pref {
             PrefUnit::MgDl => {
-                draw_text_mut(
-                    &mut img,
-                    bright,
-                    label_x,
-                    (y_px - 16.0) as i32,
-                    PxScale::from(y_label_size_primary),
-                    &handler.font,
-                    &format!("{}", (*y_val as i32)),
-                );
-
                 let mmol_v = y_val / 18.0;
                 let mmol_display = if approximation {
                     format!("±{:.1}", (mmol_v * 2.0).round() / 2.0)
                 } else {
                     format!("{:.1}", mmol_v)
                 };
-                draw_text_mut(
-                    &mut img,
-                    dim,
-                    label_x,
-                    (y_px + 12.0) as i32,
-                    PxScale::from(y_label_size_secondary),
-                    &handler.font,
-                    &mmol_display,
-                );
+                (format!("{}", (*y_val as i32)), mmol_display)
             }
             PrefUnit::Mmol => {
-                draw_text_mut(
-                    &mut img,
-                    bright,
-                    label_x,
-                    (y_px - 16.0) as i32,
-                    PxScale::from(y_label_size_primary),
-                    &handler.font,
-                    &format!("{:.1}", y_val),
-                );
-
                 let mg_val = y_val * 18.0;
                 let mg_display = if approximation {
                     format!("±{}", ((mg_val / 10.0).round() * 10.0) as i32)
                 } else {
                     format!("{}", mg_val as i32)
                 };
-                draw_text_mut(
-                    &mut img,
-                    dim,
-                    label_x,
-                    (y_px + 12.0) as i32,
-                    PxScale::from(y_label_size_secondary),
-                    &handler.font,
-                    &mg_display,
-                );
+                (format!("{:.1}", y_val), mg_display)
             }
+        };
+
+        draw_text(
+            &mut img,
+            bright,
+            label_x,
+            (y_px - 16.0) as i32,
+            PxScale::from(y_label_size_primary),
+            handler,
+            &primary_display,
+        );
+        draw_text(
+            &mut img,
+            dim,
+            label_x,
+            (y_px + 12.0) as i32,
+            PxScale::from(y_label_size_secondary),
+            handler,
+            &secondary_display,
+        );
+
+        if user_settings.right_axis {
+            draw_text(
+                &mut img,
+                dim,
+                (plot_right + 16.0) as i32,
+                (y_px - 18.0) as i32,
+                PxScale::from(y_label_size_secondary),
+                handler,
+                &secondary_display,
+            );
         }
     }
 
     if let Some(&last_y_val) = y_values.last() {
-        let y_px = match pref {
-            PrefUnit::MgDl => project_y(last_y_val),
-            PrefUnit::Mmol => {
-                inner_plot_bottom - ((last_y_val - y_min) / (y_max - y_min)) * inner_plot_h
-            }
-        };
+        let y_px = projection.y_display(last_y_val);
 
         if y_px >= inner_plot_top && y_px <= inner_plot_bottom {
-            let faint_grid_col = Rgba([25u8, 35u8, 41u8, 255u8]);
             draw_line_segment_mut(
                 &mut img,
                 (inner_plot_left, y_px),
                 (inner_plot_right, y_px),
-                faint_grid_col,
+                theme.faint_grid,
             );
         }
     }
 
     let target_high_y = project_y(target_high_mg);
     if target_high_y >= inner_plot_top && target_high_y <= inner_plot_bottom {
-        let faint_orange = Rgba([255u8, 159u8, 10u8, 80u8]);
         draw_dashed_horizontal_line(
             &mut img,
             target_high_y,
             inner_plot_left,
             inner_plot_right,
-            faint_orange,
+            theme.target_high_line,
             10,
             5,
         );
@@ -340,23 +361,23 @@ pub async fn draw_graph(
 
     let target_low_y = project_y(target_low_mg);
     if target_low_y >= inner_plot_top && target_low_y <= inner_plot_bottom {
-        let faint_red = Rgba([255u8, 69u8, 58u8, 80u8]);
         draw_dashed_horizontal_line(
             &mut img,
             target_low_y,
             inner_plot_left,
             inner_plot_right,
-            faint_red,
+            theme.target_low_line,
             10,
             5,
         );
     }
 
     let user_tz: Tz = user_timezone.parse().unwrap_or(chrono_tz::UTC);
-    let now = Utc::now().with_timezone(&user_tz);
+    // The right edge of the graph, which is the present for live windows
+    let now = window.end.with_timezone(&user_tz);
 
-    let newest_time = now;
-    let oldest_time = now - chrono::Duration::hours(hours as i64);
+    let oldest_time = window.start.with_timezone(&user_tz);
+    let time_range_seconds = (window.end - window.start).num_seconds() as f32;
 
     let total_hours = hours as f32;
     tracing::info!(
@@ -365,22 +386,10 @@ pub async fn draw_graph(
     );
 
     let max_x_labels = 6;
-    let time_interval = if total_hours <= 3.0 {
-        0.5
-    } else if total_hours <= 6.0 {
-        1.0
-    } else if total_hours <= 12.0 {
-        2.0
-    } else {
-        3.0
-    };
-
-    let time_range_seconds = (newest_time.timestamp() - oldest_time.timestamp()) as f32;
+    let time_interval = label_interval_minutes(hours) as f32 / 60.0;
 
     let calculate_x_position = |entry_time: chrono::DateTime<chrono_tz::Tz>| -> f32 {
-        let time_from_oldest = (entry_time.timestamp() - oldest_time.timestamp()) as f32;
-        let time_ratio = time_from_oldest / time_range_seconds;
-        inner_plot_left + (time_ratio * inner_plot_w)
+        projection.x(entry_time.timestamp())
     };
 
     let mut label_entries = Vec::new();
@@ -476,19 +485,165 @@ pub async fn draw_graph(
 
             let date_text = entry_time.format("%m/%d").to_string();
             let text_width = (date_text.len() as f32) * 14.0;
-            draw_text_mut(
+            draw_text(
                 &mut img,
                 dim,
                 (x_center - text_width / 2.0) as i32,
                 (plot_top - 30.) as i32,
                 PxScale::from(28.0),
-                &handler.font,
+                handler,
                 &date_text,
             );
         }
         prev_date = Some(current_date);
     }
 
+    // Shade sensor dropouts such as warmups and disconnects so they read as missing data
+    for (a, b) in find_gaps(&entries, user_timezone, chrono::Duration::minutes(15)) {
+        let x_a = calculate_x_position(entries[a].millis_to_user_timezone(user_timezone));
+        let x_b = calculate_x_position(entries[b].millis_to_user_timezone(user_timezone));
+        let (gap_left, gap_right) = (x_a.min(x_b), x_a.max(x_b));
+
+        shade_region(
+            &mut img,
+            gap_left,
+            gap_right,
+            inner_plot_top,
+            inner_plot_bottom,
+            dim,
+            0.08,
+        );
+
+        let gap_text = "gap";
+        let gap_text_width = (gap_text.len() as f32) * 14.0;
+        if gap_right - gap_left >= gap_text_width + 8.0 {
+            draw_text(
+                &mut img,
+                darker_dim,
+                ((gap_left + gap_right - gap_text_width) / 2.0) as i32,
+                (inner_plot_top + 4.0) as i32,
+                PxScale::from(28.0),
+                handler,
+                gap_text,
+            );
+        }
+    }
+
+    // Shade exercise behind the readings so activity lines up with its effect on glucose
+    for treatment in treatments.iter().filter(|t| t.is_exercise()) {
+        let Some(duration) = treatment.duration.filter(|minutes| *minutes > 0.0) else {
+            continue;
+        };
+        let start = treatment.millis_to_user_timezone(user_timezone);
+        let end = start + chrono::Duration::seconds((duration * 60.0) as i64);
+        let x_start = calculate_x_position(start).max(inner_plot_left);
+        let x_end = calculate_x_position(end).min(inner_plot_right);
+
+        if x_end > x_start {
+            draw_exercise_band(
+                &mut img,
+                x_start,
+                x_end,
+                inner_plot_top,
+                inner_plot_bottom,
+                &theme,
+                handler,
+            );
+        }
+    }
+
+    // Yesterday's trace sits under everything else, shifted forward a day by wall-clock time
+    if vs_yesterday {
+        let yesterday_px: Vec<(f32, f32)> = yesterday_entries
+            .iter()
+            .filter(|entry| entry.has_sgv())
+            .filter_map(|entry| {
+                let shifted = same_time_next_day(entry.millis_to_user_timezone(user_timezone))?;
+                (shifted >= oldest_time && shifted <= now).then(|| {
+                    (
+                        calculate_x_position(shifted),
+                        project_y(projection.clamp_mg(entry.sgv)),
+                    )
+                })
+            })
+            .collect();
+
+        if !yesterday_px.is_empty() {
+            let max_gap_px = 10.0 * 60.0 / time_range_seconds * inner_plot_w;
+            draw_smooth_line(&mut img, &yesterday_px, max_gap_px, darker_dim);
+            draw_text(
+                &mut img,
+                darker_dim,
+                inner_plot_left as i32,
+                (plot_top - 30.) as i32,
+                PxScale::from(28.0),
+                handler,
+                "Yesterday",
+            );
+        }
+    }
+
+    if window.live {
+        // Mark the present at the right edge so any gap since the last reading is visible
+        let now_x = calculate_x_position(now);
+        draw_dashed_vertical_line(
+            &mut img,
+            now_x,
+            inner_plot_top,
+            inner_plot_bottom,
+            dim,
+            4,
+            6,
+        );
+
+        let now_text = "now";
+        let now_text_width = (now_text.len() as f32) * 14.0;
+        draw_text(
+            &mut img,
+            dim,
+            (now_x - now_text_width) as i32,
+            (plot_top - 30.) as i32,
+            PxScale::from(28.0),
+            handler,
+            now_text,
+        );
+    } else {
+        // A past day has no "now", so say which day it is instead
+        let day_text = oldest_time.format("%a %-d %b %Y").to_string();
+        let day_text_width = (day_text.chars().count() as f32) * 14.0;
+        draw_text(
+            &mut img,
+            dim,
+            (inner_plot_right - day_text_width) as i32,
+            (plot_top - 30.) as i32,
+            PxScale::from(28.0),
+            handler,
+            &day_text,
+        );
+    }
+
+    // Warn when the newest reading is stale, matching the /bg warning
+    if window.live
+        && let Some(newest_entry) = entries.first()
+    {
+        let age_minutes = now
+            .signed_duration_since(newest_entry.millis_to_user_timezone(user_timezone))
+            .num_minutes();
+        if age_minutes > user_settings.stale_minutes {
+            let stale_text = format!("Data {} min old", age_minutes);
+            let stale_text_width = (stale_text.chars().count() as f32) * 14.0;
+            draw_text(
+                &mut img,
+                theme.high,
+                ((plot_left + plot_right - stale_text_width) / 2.0) as i32,
+                (plot_top - 30.) as i32,
+                PxScale::from(28.0),
+                handler,
+                &stale_text,
+            );
+        }
+    }
+
     for entry in final_label_entries.iter() {
         let entry_time = entry.millis_to_user_timezone(user_timezone);
         let x_center = calculate_x_position(entry_time);
@@ -498,16 +653,21 @@ pub async fn draw_graph(
         let text_w = (time_label.chars().count() as f32) * approx_char_width;
         let x_text = (x_center - text_w / 2.0).round() as i32;
 
-        draw_text_mut(
+        draw_text(
             &mut img,
             bright,
             x_text,
             (plot_bottom + 16.0) as i32,
             PxScale::from(x_label_size_primary),
-            &handler.font,
+            handler,
             &time_label,
         );
 
+        // Times relative to now only mean something on a live graph
+        if !window.live {
+            continue;
+        }
+
         let diff = now.signed_duration_since(entry_time);
         let hours_ago = diff.num_hours();
         let minutes_ago = diff.num_minutes();
@@ -527,13 +687,13 @@ pub async fn draw_graph(
 
         let approx_w2 = (rel.chars().count() as f32) * (x_label_size_secondary * 0.6);
         let x_text2 = (x_center - approx_w2 / 2.0).round() as i32;
-        draw_text_mut(
+        draw_text(
             &mut img,
             dim,
             x_text2,
             (plot_bottom + 56.0) as i32,
             PxScale::from(x_label_size_secondary),
-            &handler.font,
+            handler,
             &rel,
         );
     }
@@ -542,19 +702,54 @@ pub async fn draw_graph(
     for entry in &entries {
         let entry_time = entry.millis_to_user_timezone(user_timezone);
         let x = calculate_x_position(entry_time);
-        let y = project_y(entry.sgv.clamp(
-            match pref {
-                PrefUnit::MgDl => y_min,
-                PrefUnit::Mmol => y_min * 18.0,
-            },
-            match pref {
-                PrefUnit::MgDl => y_max,
-                PrefUnit::Mmol => y_max * 18.0,
-            },
-        ));
+        let y = project_y(projection.clamp_mg(entry.sgv));
         points_px.push((x, y));
     }
 
+    // The band and smoothed line sit under the stickers and treatment markers
+    if user_settings.sd_band {
+        let mut samples: Vec<(i64, f32, f32)> = entries
+            .iter()
+            .zip(&points_px)
+            .filter(|(entry, _)| entry.has_sgv())
+            .map(|(entry, &(x, _))| {
+                let time = entry
+                    .millis_to_user_timezone(user_timezone)
+                    .timestamp_millis();
+                (time, entry.sgv, x)
+            })
+            .collect();
+        samples.sort_by_key(|(time, ..)| *time);
+
+        let stats = rolling_mean_sd(
+            &samples
+                .iter()
+                .map(|&(time, sgv, _)| (time, sgv))
+                .collect::<Vec<_>>(),
+            SD_BAND_WINDOW_MINUTES * 60 * 1000,
+        );
+        let band_px: Vec<(f32, f32, f32)> = samples
+            .iter()
+            .zip(stats)
+            .map(|(&(_, _, x), (mean, sd))| {
+                (
+                    x,
+                    project_y(projection.clamp_mg(mean + sd)),
+                    project_y(projection.clamp_mg(mean - sd)),
+                )
+            })
+            .collect();
+
+        let max_gap_px = 10.0 * 60.0 / time_range_seconds * inner_plot_w;
+        draw_sd_band(&mut img, &band_px, max_gap_px, theme.dim, 0.18);
+    }
+
+    if user_settings.smooth_line {
+        // Don't interpolate across gaps longer than two missed readings
+        let max_gap_px = 10.0 * 60.0 / time_range_seconds * inner_plot_w;
+        draw_smooth_line(&mut img, &points_px, max_gap_px, theme.dim);
+    }
+
     tracing::info!("[GRAPH] Drawing contextual stickers");
 
     let status_ranges =
@@ -564,16 +759,11 @@ pub async fn draw_graph(
     let mut treatment_positions: Vec<(f32, f32)> = Vec::new();
 
     for treatment in treatments {
-        let treatment_time = if let Some(created_at) = &treatment.created_at {
-            match chrono::DateTime::parse_from_rfc3339(created_at) {
-                Ok(dt) => dt.with_timezone(&user_tz),
-                Err(_) => continue,
-            }
-        } else if let Some(ts) = treatment.date.or(treatment.mills) {
-            chrono::DateTime::from_timestamp_millis(ts as i64)
-                .map(|dt| dt.with_timezone(&user_tz))
-                .unwrap_or(now)
-        } else {
+        let Some(treatment_time) = treatment
+            .timestamp_millis()
+            .and_then(|ms| chrono::DateTime::from_timestamp_millis(ms as i64))
+            .map(|dt| dt.with_timezone(&user_tz))
+        else {
             continue;
         };
 
@@ -602,12 +792,24 @@ pub async fn draw_graph(
     }
 
     let stickers_to_place = select_stickers_to_place(stickers, &status_ranges);
+    let sticker_images = prefetch_sticker_images(
+        &stickers_to_place
+            .iter()
+            .map(|(sticker, _)| *sticker)
+            .collect::<Vec<_>>(),
+    )
+    .await;
 
     let config = StickerConfig::default();
     let mut occupied_areas: Vec<(f32, f32, f32)> = Vec::new();
 
-    for (sticker, range) in stickers_to_place {
+    for ((sticker, range), sticker_img) in stickers_to_place.into_iter().zip(sticker_images) {
+        let Some(sticker_img) = sticker_img else {
+            continue;
+        };
+
         if let Some((x, y)) = find_sticker_position(
+            sticker,
             range,
             &entries,
             &points_px,
@@ -621,11 +823,12 @@ pub async fn draw_graph(
         ) {
             let abs_x = inner_plot_left + x * (inner_plot_right - inner_plot_left);
             let abs_y = inner_plot_top + y * (inner_plot_bottom - inner_plot_top);
-            occupied_areas.push((abs_x, abs_y, config.sticker_radius));
+            occupied_areas.push((abs_x, abs_y, sticker_collision_radius(sticker, &config)));
 
-            if let Err(e) = draw_sticker(
+            draw_sticker(
                 &mut img,
                 sticker,
+                &sticker_img,
                 x,
                 y,
                 inner_plot_left,
@@ -633,15 +836,7 @@ pub async fn draw_graph(
                 inner_plot_top,
                 inner_plot_bottom,
                 handler,
-            )
-            .await
-            {
-                tracing::warn!(
-                    "[GRAPH] Failed to draw sticker {}: {}",
-                    sticker.display_name,
-                    e
-                );
-            }
+            );
         } else {
             tracing::info!(
                 "[GRAPH] Skipping sticker {} due to no available space",
@@ -649,6 +844,16 @@ pub async fn draw_graph(
             );
         }
     }
+    let bolus_amounts: Vec<f32> = treatments
+        .iter()
+        .filter(|t| t.is_insulin() && t.type_.as_deref() != Some("SMB"))
+        .filter_map(Treatment::insulin_amount)
+        .filter(|amount| *amount > user_settings.microbolus_threshold)
+        .collect();
+    let typical_max_bolus = typical_max_bolus(&bolus_amounts).unwrap_or(10.0);
+
+    let mut legend_items: Vec<(chrono::DateTime<Tz>, LegendItem)> = Vec::new();
+
     tracing::debug!("[GRAPH] Drawing {} treatments", treatments.len());
     for treatment in treatments {
         tracing::debug!(
@@ -657,24 +862,16 @@ pub async fn draw_graph(
             treatment.created_at,
             treatment.date,
             treatment.mills,
-            treatment.insulin,
+            treatment.insulin_amount(),
             treatment.carbs
         );
 
-        let treatment_time = if let Some(created_at) = &treatment.created_at {
-            match chrono::DateTime::parse_from_rfc3339(created_at) {
-                Ok(dt) => dt.with_timezone(&user_tz),
-                Err(e) => {
-                    tracing::warn!("[GRAPH] Failed to parse created_at '{}': {}", created_at, e);
-                    continue;
-                }
-            }
-        } else if let Some(ts) = treatment.date.or(treatment.mills) {
-            chrono::DateTime::from_timestamp_millis(ts as i64)
-                .map(|dt| dt.with_timezone(&user_tz))
-                .unwrap_or(now)
-        } else {
-            tracing::warn!("[GRAPH] Treatment has no timestamp, skipping");
+        let Some(treatment_time) = treatment
+            .timestamp_millis()
+            .and_then(|ms| chrono::DateTime::from_timestamp_millis(ms as i64))
+            .map(|dt| dt.with_timezone(&user_tz))
+        else {
+            tracing::warn!("[GRAPH] Treatment has no usable timestamp, skipping");
             continue;
         };
 
@@ -694,45 +891,137 @@ pub async fn draw_graph(
 
         let closest_x = treatment_x;
 
-        if treatment.is_insulin() {
-            let insulin_amount = treatment.insulin.unwrap_or(0.0);
-            let is_smb_type = treatment.type_.as_deref() == Some("SMB");
-            let is_microbolus = is_smb_type || insulin_amount <= user_settings.microbolus_threshold;
-
-            if is_microbolus && !user_settings.display_microbolus {
-                continue;
-            }
-
-            draw_insulin_treatment(
+        if (treatment.is_sensor_change() || treatment.is_site_change())
+            && user_settings.display_device_changes
+            && (inner_plot_left..=inner_plot_right).contains(&closest_x)
+        {
+            let (label, marker_col) = if treatment.is_sensor_change() {
+                ("Sensor", theme.sensor_change)
+            } else {
+                ("Site", theme.site_change)
+            };
+            draw_device_change_marker(
                 &mut img,
-                insulin_amount,
-                is_microbolus,
-                user_settings.microbolus_threshold,
+                label,
                 closest_x,
-                closest_y,
-                insulin_col,
-                bg,
-                bright,
+                inner_plot_top,
+                inner_plot_bottom,
+                marker_col,
+                &theme,
                 handler,
             );
         }
 
-        if treatment.is_carbs() {
-            let carbs_amount = treatment.carbs.unwrap_or(0.0);
-            draw_carbs_treatment(
+        // Notes are free text and may name people, so anonymous graphs leave them off
+        if let Some(note) = treatment.note_text()
+            && !user_settings.anonymous_mode
+            && (inner_plot_left..=inner_plot_right).contains(&closest_x)
+        {
+            draw_note_marker(
                 &mut img,
-                carbs_amount,
+                note,
                 closest_x,
-                closest_y,
-                carbs_col,
-                bg,
+                inner_plot_bottom,
+                &theme,
                 handler,
             );
         }
 
-        if treatment.is_glucose_reading()
-            && let Some(glucose_str) = &treatment.glucose
-            && let Ok(glucose_value) = glucose_str.parse::<f32>()
+        if treatment.is_insulin() && !hidden.insulin {
+            let insulin_amount = treatment.insulin_amount().unwrap_or(0.0);
+            let is_microbolus = treatment.is_microbolus(user_settings.microbolus_threshold);
+
+            if is_microbolus && !user_settings.display_microbolus {
+                continue;
+            }
+
+            if !is_microbolus && (inner_plot_left..=inner_plot_right).contains(&closest_x) {
+                legend_items.push((
+                    treatment_time,
+                    LegendItem {
+                        time: treatment_time.format("%H:%M").to_string(),
+                        amount: format!("{:.1}u", insulin_amount),
+                        color: theme.insulin,
+                    },
+                ));
+            }
+
+            match user_settings.treatment_layout {
+                TreatmentLayout::Inline => draw_insulin_treatment(
+                    &mut img,
+                    insulin_amount,
+                    treatment.insulin_names().as_deref(),
+                    is_microbolus,
+                    user_settings.microbolus_threshold,
+                    typical_max_bolus,
+                    closest_x,
+                    closest_y,
+                    inner_plot_top,
+                    inner_plot_bottom,
+                    &theme,
+                    handler,
+                ),
+                TreatmentLayout::Bars => {
+                    if (inner_plot_left..=inner_plot_right).contains(&closest_x) {
+                        draw_insulin_bar(
+                            &mut img,
+                            insulin_amount,
+                            is_microbolus,
+                            typical_max_bolus,
+                            closest_x,
+                            plot_bottom,
+                            &theme,
+                            handler,
+                        );
+                    }
+                }
+            }
+        }
+
+        if treatment.is_carbs() && !hidden.carbs {
+            let carbs_amount = treatment.carbs.unwrap_or(0.0);
+            if carbs_amount > 0.0 && (inner_plot_left..=inner_plot_right).contains(&closest_x) {
+                legend_items.push((
+                    treatment_time,
+                    LegendItem {
+                        time: treatment_time.format("%H:%M").to_string(),
+                        amount: format!("{}g", carbs_amount as i32),
+                        color: theme.carbs,
+                    },
+                ));
+            }
+
+            match user_settings.treatment_layout {
+                TreatmentLayout::Inline => draw_carbs_treatment(
+                    &mut img,
+                    carbs_amount,
+                    treatment.fat_protein_units(),
+                    closest_x,
+                    closest_y,
+                    inner_plot_top,
+                    inner_plot_bottom,
+                    &theme,
+                    handler,
+                ),
+                TreatmentLayout::Bars => {
+                    if (inner_plot_left..=inner_plot_right).contains(&closest_x) {
+                        draw_carbs_bar(
+                            &mut img,
+                            carbs_amount,
+                            treatment.fat_protein_units(),
+                            closest_x,
+                            plot_top,
+                            &theme,
+                            handler,
+                        );
+                    }
+                }
+            }
+        }
+
+        if treatment.is_bg_check()
+            && !hidden.finger_sticks
+            && let Some(glucose_value) = treatment.glucose_value()
         {
             let glucose_y = project_y(glucose_value);
             draw_glucose_reading(
@@ -741,8 +1030,7 @@ pub async fn draw_graph(
                 closest_x,
                 glucose_y,
                 pref,
-                bg,
-                bright,
+                &theme,
                 handler,
             );
         }
@@ -753,18 +1041,91 @@ pub async fn draw_graph(
         &entries,
         &points_px,
         svg_radius,
-        high_col,
-        low_col,
-        axis_col,
-        target_high_mg,
-        target_low_mg,
+        &theme,
+        &GlucoseThresholds::new(target_low_mg, target_high_mg).with_urgent_low(urgent_low_mg),
     );
 
+    // Show where the newest reading is heading, deriving the trend from the slope when the
+    // uploader sent no direction
+    let mut by_x: Vec<usize> = (0..entries.len())
+        .filter(|&i| entries[i].has_sgv())
+        .collect();
+    by_x.sort_by(|&a, &b| points_px[b].0.total_cmp(&points_px[a].0));
+    if let [newest, rest @ ..] = by_x.as_slice() {
+        let trend = match (entries[*newest].direction.is_some(), rest.first()) {
+            (false, Some(previous)) => entries[*newest].trend_from_slope(&entries[*previous]),
+            _ => entries[*newest].trend(),
+        };
+        let (x, y) = points_px[*newest];
+        draw_trend_arrow(&mut img, x, y, svg_radius, trend, theme.bright);
+    }
+
+    // Readings beyond the y-axis are clamped to its edge, so mark them with a caret and
+    // label the most extreme reading of each off-scale run with its real value.
+    let (y_min_mg, y_max_mg) = match pref {
+        PrefUnit::MgDl => (y_min, y_max),
+        PrefUnit::Mmol => (y_min * 18.0, y_max * 18.0),
+    };
+    for above in [true, false] {
+        let is_off_scale = |entry: &Entry| {
+            if above {
+                entry.sgv > y_max_mg
+            } else {
+                entry.has_sgv() && entry.sgv < y_min_mg
+            }
+        };
+
+        let mut i = 0;
+        while i < entries.len() {
+            if !is_off_scale(&entries[i]) {
+                i += 1;
+                continue;
+            }
+
+            let run_start = i;
+            while i < entries.len() && is_off_scale(&entries[i]) {
+                i += 1;
+            }
+            let peak = (run_start..i)
+                .max_by(|&a, &b| {
+                    let ordering = entries[a].sgv.total_cmp(&entries[b].sgv);
+                    if above { ordering } else { ordering.reverse() }
+                })
+                .unwrap_or(run_start);
+
+            tracing::debug!(
+                "[GRAPH] {} readings {} the y-axis (extreme {:.0} mg/dL)",
+                i - run_start,
+                if above { "above" } else { "below" },
+                entries[peak].sgv
+            );
+
+            for idx in run_start..i {
+                let (x, y) = points_px[idx];
+                let label = (idx == peak).then(|| match pref {
+                    PrefUnit::MgDl => format!("{:.0}", entries[idx].sgv),
+                    PrefUnit::Mmol => format!("{:.1}", entries[idx].sgv / 18.0),
+                });
+                draw_off_scale_marker(
+                    &mut img,
+                    x,
+                    y,
+                    svg_radius,
+                    above,
+                    label.as_deref(),
+                    inner_plot_right,
+                    &theme,
+                    handler,
+                );
+            }
+        }
+    }
+
     let mbg_count = entries.iter().filter(|e| e.has_mbg()).count();
     tracing::info!("[GRAPH] Found {} entries with MBG values", mbg_count);
 
     for (i, entry) in entries.iter().enumerate() {
-        if entry.has_mbg() {
+        if entry.has_mbg() && !hidden.finger_sticks {
             let mbg_value = entry.mbg.unwrap_or(0.0);
             let (x, _) = points_px[i];
             let mbg_y = project_y(mbg_value);
@@ -777,7 +1138,7 @@ pub async fn draw_graph(
                 entry.entry_type
             );
 
-            draw_glucose_reading(&mut img, mbg_value, x, mbg_y, pref, bg, bright, handler);
+            draw_glucose_reading(&mut img, mbg_value, x, mbg_y, pref, &theme, handler);
         }
     }
 
@@ -785,64 +1146,70 @@ pub async fn draw_graph(
     let header_y = (plot_bottom + 60.) as i32;
     match pref {
         PrefUnit::MgDl => {
-            draw_text_mut(
+            draw_text(
                 &mut img,
                 bright,
                 header_x,
                 header_y,
                 PxScale::from(primary_legend_font_size),
-                &handler.font,
+                handler,
                 "mg/dL",
             );
-            draw_text_mut(
+            draw_text(
                 &mut img,
                 dim,
                 header_x,
                 header_y + 36,
                 PxScale::from(secondary_legend_font_size),
-                &handler.font,
+                handler,
                 "mmol/L",
             );
         }
         PrefUnit::Mmol => {
-            draw_text_mut(
+            draw_text(
                 &mut img,
                 bright,
                 header_x,
                 header_y,
                 PxScale::from(primary_legend_font_size),
-                &handler.font,
+                handler,
                 "mmol/L",
             );
-            draw_text_mut(
+            draw_text(
                 &mut img,
                 dim,
                 header_x,
                 header_y + 36,
                 PxScale::from(secondary_legend_font_size),
-                &handler.font,
+                handler,
                 "mg/dL",
             );
         }
     }
 
-    draw_text_mut(
-        &mut img,
-        dim,
-        20,
-        10,
-        PxScale::from(secondary_legend_font_size),
-        &handler.font,
-        "Beetroot",
-    );
+    if user_settings.show_watermark && !user_settings.anonymous_mode {
+        draw_text(
+            &mut img,
+            dim,
+            20,
+            10,
+            PxScale::from(secondary_legend_font_size),
+            handler,
+            "Beetroot",
+        );
+    }
 
-    let dyna = DynamicImage::ImageRgba8(img);
-    let mut out_buf: Vec<u8> = Vec::new();
-    dyna.write_to(&mut Cursor::new(&mut out_buf), image::ImageFormat::Png)
-        .map_err(|e| {
-            tracing::error!("[GRAPH] Failed to encode PNG: {}", e);
-            anyhow!("Failed to encode PNG: {}", e)
-        })?;
+    if user_settings.marker_legend {
+        draw_marker_key(&mut img, plot_right as i32, 10, &theme, handler);
+    }
+
+    if user_settings.treatment_legend && !legend_items.is_empty() {
+        legend_items.sort_by_key(|(time, _)| *time);
+        let items: Vec<LegendItem> = legend_items.into_iter().map(|(_, item)| item).collect();
+        img = draw_treatment_legend(&img, &items, &theme, handler);
+    }
+
+    let out_buf = encode_png(img)?;
 
     if let Some(path) = save_path {
         std::fs::write(path, &out_buf).map_err(|e| {
@@ -855,5 +1222,24 @@ pub async fn draw_graph(
         "[GRAPH] Successfully generated graph ({} bytes)",
         out_buf.len()
     );
+    let summary = GlucoseSummary::from_entries(&entries, target_low_mg, target_high_mg);
+    Ok(GraphOutput {
+        png: out_buf,
+        svg: None,
+        entries,
+        timezone: user_timezone.to_string(),
+        summary,
+    })
+}
+
+/// Encode a finished graph as PNG bytes
+fn encode_png(img: RgbaImage) -> Result<Vec<u8>> {
+    let mut out_buf: Vec<u8> = Vec::new();
+    DynamicImage::ImageRgba8(img)
+        .write_to(&mut Cursor::new(&mut out_buf), image::ImageFormat::Png)
+        .map_err(|e| {
+            tracing::error!("[GRAPH] Failed to encode PNG: {}", e);
+            anyhow!("Failed to encode PNG: {}", e)
+        })?;
     Ok(out_buf)
 }
@@ -1,15 +1,41 @@
 use anyhow::Result;
-use image::{Rgba, RgbaImage};
+use image::{DynamicImage, Rgba, RgbaImage};
+use imageproc::geometric_transformations::{Interpolation, rotate_about_center};
 
 use super::helpers::download_sticker_image;
-use super::types::GlucoseStatus;
 use crate::bot::Handler;
 use crate::utils::database::{Sticker, StickerCategory};
+use crate::utils::glucose::{GlucoseStatus, GlucoseThresholds};
 use crate::utils::nightscout::Entry;
 
 /// Maximum number of stickers to show per graph
 pub const MAX_STICKERS_PER_GRAPH: usize = 3;
 
+/// New stickers are tilted by a random angle up to this many degrees either way
+const MAX_STICKER_ROTATION_DEGREES: f32 = 15.0;
+
+/// Longest side of a sticker drawn at scale 1.0, in pixels
+const BASE_STICKER_SIZE: f32 = 200.0;
+/// Stored scales outside this range are clamped so a bad value can't cover the graph
+const STICKER_SCALE_RANGE: (f32, f32) = (0.5, 2.0);
+
+/// The sticker's stored scale, clamped to a sensible range
+fn sticker_scale(sticker: &Sticker) -> f32 {
+    sticker
+        .scale
+        .clamp(STICKER_SCALE_RANGE.0, STICKER_SCALE_RANGE.1)
+}
+
+/// Radius around a placed sticker that other stickers keep clear of
+pub fn sticker_collision_radius(sticker: &Sticker, config: &StickerConfig) -> f32 {
+    config.sticker_radius * sticker_scale(sticker)
+}
+
+/// Random tilt for a newly added sticker, in degrees
+pub fn random_sticker_rotation() -> f32 {
+    (rand::random::<f32>() * 2.0 - 1.0) * MAX_STICKER_ROTATION_DEGREES
+}
+
 /// Configuration for sticker placement
 pub struct StickerConfig {
     pub sticker_radius: f32,
@@ -48,11 +74,12 @@ pub fn identify_status_ranges(
         return status_ranges;
     }
 
-    let mut current_status = GlucoseStatus::from_sgv(entries[0].sgv, target_low, target_high);
+    let thresholds = GlucoseThresholds::new(target_low, target_high);
+    let mut current_status = thresholds.classify(entries[0].sgv);
     let mut range_start = 0;
 
     for (i, entry) in entries.iter().enumerate().skip(1) {
-        let status = GlucoseStatus::from_sgv(entry.sgv, target_low, target_high);
+        let status = thresholds.classify(entry.sgv);
         if status != current_status {
             status_ranges.push((current_status, range_start, i - 1));
             current_status = status;
@@ -84,7 +111,7 @@ pub fn filter_ranges_by_duration(
             let duration_minutes = ((end_time.timestamp() - start_time.timestamp()).abs()) / 60;
 
             let min_duration = match status {
-                GlucoseStatus::Low => 0,
+                GlucoseStatus::UrgentLow | GlucoseStatus::Low => 0,
                 GlucoseStatus::InRange | GlucoseStatus::High => 30,
             };
 
@@ -190,9 +217,11 @@ pub fn select_stickers_to_place<'a>(
     stickers_to_place
 }
 
-/// Find a valid position for a sticker
+/// Find a valid position for a sticker. Bigger stickers keep further away from other
+/// stickers, the glucose curve and treatments.
 #[allow(clippy::too_many_arguments)]
 pub fn find_sticker_position(
+    sticker: &Sticker,
     range: Option<(usize, usize)>,
     entries: &[Entry],
     points_px: &[(f32, f32)],
@@ -206,6 +235,8 @@ pub fn find_sticker_position(
 ) -> Option<(f32, f32)> {
     let inner_plot_w = inner_plot_right - inner_plot_left;
     let inner_plot_h = inner_plot_bottom - inner_plot_top;
+    let scale = sticker_scale(sticker);
+    let sticker_radius = sticker_collision_radius(sticker, config);
 
     let target_entry_idx = if let Some((start_idx, end_idx)) = range {
         let range_size = end_idx - start_idx + 1;
@@ -269,17 +300,17 @@ pub fn find_sticker_position(
 
         let has_collision = occupied_areas.iter().any(|(ox, oy, r)| {
             let distance = ((abs_x - ox).powi(2) + (abs_y - oy).powi(2)).sqrt();
-            distance < (config.sticker_radius + r)
+            distance < (sticker_radius + r)
         });
 
         let too_close_to_curve = points_px.iter().any(|(px, py)| {
             let distance = ((abs_x - px).powi(2) + (abs_y - py).powi(2)).sqrt();
-            distance < config.curve_avoidance_distance
+            distance < config.curve_avoidance_distance * scale
         });
 
         let too_close_to_treatments = treatment_positions.iter().any(|(tx, ty)| {
             let distance = ((abs_x - tx).powi(2) + (abs_y - ty).powi(2)).sqrt();
-            distance < config.treatment_avoidance_distance
+            distance < config.treatment_avoidance_distance * scale
         });
 
         if !has_collision && !too_close_to_curve && !too_close_to_treatments {
@@ -290,11 +321,40 @@ pub fn find_sticker_position(
     None
 }
 
-/// Draw a single sticker on the graph
+/// Load a sticker's image from the CDN or from disk
+async fn load_sticker_image(sticker: &Sticker) -> Result<DynamicImage> {
+    if sticker.file_name.starts_with("http") {
+        download_sticker_image(&sticker.file_name).await
+    } else {
+        Ok(image::open(&sticker.file_name)?)
+    }
+}
+
+/// Fetch all sticker images concurrently. A sticker that fails to load yields `None`
+/// so the rest of the graph can still be drawn.
+pub async fn prefetch_sticker_images(stickers: &[&Sticker]) -> Vec<Option<DynamicImage>> {
+    futures::future::join_all(stickers.iter().map(|sticker| async move {
+        match load_sticker_image(sticker).await {
+            Ok(image) => Some(image),
+            Err(e) => {
+                tracing::warn!(
+                    "[GRAPH] Failed to load sticker {}: {}",
+                    sticker.display_name,
+                    e
+                );
+                None
+            }
+        }
+    }))
+    .await
+}
+
+/// Draw a single, already loaded sticker on the graph
 #[allow(clippy::too_many_arguments)]
-pub async fn draw_sticker(
+pub fn draw_sticker(
     img: &mut RgbaImage,
     sticker: &Sticker,
+    sticker_img: &DynamicImage,
     x: f32,
     y: f32,
     inner_plot_left: f32,
@@ -302,7 +362,7 @@ pub async fn draw_sticker(
     inner_plot_top: f32,
     inner_plot_bottom: f32,
     _handler: &Handler,
-) -> Result<()> {
+) {
     let inner_plot_w = inner_plot_right - inner_plot_left;
     let inner_plot_h = inner_plot_bottom - inner_plot_top;
 
@@ -313,23 +373,17 @@ pub async fn draw_sticker(
         y
     );
 
-    let sticker_img = if sticker.file_name.starts_with("http") {
-        download_sticker_image(&sticker.file_name).await?
-    } else {
-        image::open(&sticker.file_name)?
-    };
-
     let sticker_rgba = sticker_img.to_rgba8();
     let (sticker_w, sticker_h) = sticker_rgba.dimensions();
 
     let sticker_x = (inner_plot_left + x * inner_plot_w) as i32;
     let sticker_y = (inner_plot_top + y * inner_plot_h) as i32;
 
-    let max_size = 200;
+    let max_size = BASE_STICKER_SIZE * sticker_scale(sticker);
     let scale_factor = if sticker_w > sticker_h {
-        max_size as f32 / sticker_w as f32
+        max_size / sticker_w as f32
     } else {
-        max_size as f32 / sticker_h as f32
+        max_size / sticker_h as f32
     };
     let new_w = (sticker_w as f32 * scale_factor) as u32;
     let new_h = (sticker_h as f32 * scale_factor) as u32;
@@ -341,6 +395,9 @@ pub async fn draw_sticker(
         image::imageops::FilterType::Lanczos3,
     );
 
+    let resized_sticker = rotate_sticker(&resized_sticker, sticker.rotation);
+    let (new_w, new_h) = resized_sticker.dimensions();
+
     let start_x = (sticker_x - new_w as i32 / 2).max(0);
     let start_y = (sticker_y - new_h as i32 / 2).max(0);
 
@@ -401,6 +458,68 @@ pub async fn draw_sticker(
     //     &handler.font,
     //     debug_label,
     // );
+}
+
+/// Rotate a sticker clockwise by `degrees` on a canvas grown to its diagonal, so the
+/// corners aren't clipped. The added margin is transparent.
+fn rotate_sticker(sticker: &RgbaImage, degrees: f32) -> RgbaImage {
+    if degrees.abs() < f32::EPSILON {
+        return sticker.clone();
+    }
+
+    let (w, h) = sticker.dimensions();
+    let side = ((w * w + h * h) as f32).sqrt().ceil() as u32;
+    let mut canvas = RgbaImage::from_pixel(side, side, Rgba([0, 0, 0, 0]));
+    image::imageops::overlay(
+        &mut canvas,
+        sticker,
+        ((side - w) / 2) as i64,
+        ((side - h) / 2) as i64,
+    );
 
-    Ok(())
+    rotate_about_center(
+        &canvas,
+        degrees.to_radians(),
+        Interpolation::Bilinear,
+        Rgba([0, 0, 0, 0]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collision_radius_follows_sticker_scale() {
+        let config = StickerConfig::default();
+        let sticker = |scale: f32| Sticker {
+            id: 1,
+            file_name: "images/stickers/beet.png".to_string(),
+            display_name: "beet".to_string(),
+            category: StickerCategory::Any,
+            rotation: 0.0,
+            scale,
+        };
+
+        let medium = sticker_collision_radius(&sticker(1.0), &config);
+        assert!(sticker_collision_radius(&sticker(1.4), &config) > medium);
+        assert!(sticker_collision_radius(&sticker(0.7), &config) < medium);
+        assert_eq!(
+            sticker_collision_radius(&sticker(50.0), &config),
+            config.sticker_radius * STICKER_SCALE_RANGE.1
+        );
+    }
+
+    #[test]
+    fn test_rotated_sticker_is_not_clipped() {
+        let sticker = RgbaImage::from_pixel(200, 80, Rgba([255, 0, 0, 255]));
+        let opaque = |img: &RgbaImage| img.pixels().filter(|p| p[3] > 128).count();
+
+        assert_eq!(rotate_sticker(&sticker, 0.0), sticker);
+
+        let rotated = rotate_sticker(&sticker, 45.0);
+        assert!(rotated.width() >= 216 && rotated.height() >= 216);
+        let ratio = opaque(&rotated) as f32 / opaque(&sticker) as f32;
+        assert!((0.97..1.03).contains(&ratio), "ratio {}", ratio);
+    }
 }
@@ -0,0 +1,612 @@
+use super::drawing::{
+    MICROBOLUS_TRIANGLE_SIZE, TREATMENT_MARKER_OFFSET, URGENT_LOW_RADIUS_BOOST, carbs_color,
+    insulin_triangle_size, place_marker, typical_max_bolus,
+};
+use super::empty::{EMPTY_MESSAGE_FONT_SIZE, empty_message};
+use super::helpers::{point_radius, y_axis_range, y_axis_ticks, y_gridlines};
+use super::layout::{GraphLayout, label_interval_minutes};
+use super::theme::GraphTheme;
+use super::types::{GraphWindow, HiddenOverlays, PrefUnit};
+use crate::utils::database::NightscoutInfo;
+use crate::utils::glucose::{GlucoseStatus, GlucoseThresholds};
+use crate::utils::nightscout::{
+    Entry, Nightscout, Profile, StatusThresholds, Treatment, get_urgent_low_mg,
+};
+use anyhow::{Result, anyhow};
+use chrono::{DurationRound, Timelike};
+use chrono_tz::Tz;
+use image::Rgba;
+use std::fmt::Write;
+
+const FONT_FAMILY: &str = "Arial, Helvetica, sans-serif";
+
+/// `#rrggbb` for a theme colour; alpha is written separately as an opacity attribute
+fn hex(color: Rgba<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// Opacity of a theme colour, for `fill-opacity` and `stroke-opacity`
+fn opacity(color: Rgba<u8>) -> String {
+    format!("{:.2}", color[3] as f32 / 255.0)
+}
+
+/// Escape the characters that would otherwise end a `<text>` element or an attribute early
+fn escape_xml(content: &str) -> String {
+    let mut escaped = String::with_capacity(content.len());
+    for c in content.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A `<text>` element whose top-left corner is at (`x`, `y`), matching how the PNG renderer
+/// places text
+fn text(out: &mut String, x: f32, y: f32, size: f32, color: Rgba<u8>, content: &str) {
+    let _ = writeln!(
+        out,
+        r#"<text x="{:.1}" y="{:.1}" font-size="{}" fill="{}" dominant-baseline="hanging">{}</text>"#,
+        x,
+        y,
+        size,
+        hex(color),
+        escape_xml(content)
+    );
+}
+
+/// Same as [`text`], but centred horizontally on `x`
+fn centered_text(out: &mut String, x: f32, y: f32, size: f32, color: Rgba<u8>, content: &str) {
+    let _ = writeln!(
+        out,
+        r#"<text x="{:.1}" y="{:.1}" font-size="{}" fill="{}" dominant-baseline="hanging" text-anchor="middle">{}</text>"#,
+        x,
+        y,
+        size,
+        hex(color),
+        escape_xml(content)
+    );
+}
+
+fn line(out: &mut String, from: (f32, f32), to: (f32, f32), color: Rgba<u8>, dash: Option<&str>) {
+    let dash = dash
+        .map(|pattern| format!(r#" stroke-dasharray="{}""#, pattern))
+        .unwrap_or_default();
+    let _ = writeln!(
+        out,
+        r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-opacity="{}"{}/>"#,
+        from.0,
+        from.1,
+        to.0,
+        to.1,
+        hex(color),
+        opacity(color),
+        dash
+    );
+}
+
+/// Draw the same glucose graph as [`super::draw_graph`] as an SVG document, with shapes and
+/// `<text>` instead of pixels so it stays sharp when zoomed or printed. This is a simplified
+/// copy: stickers, the SD band, the smoothed line, reading gaps, the stale-data banner and
+/// the bars treatment layout are left off.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_graph_svg(
+    entries: &[Entry],
+    treatments: &[Treatment],
+    profile: &Profile,
+    user_settings: &NightscoutInfo,
+    nightscout_client: &Nightscout,
+    window: GraphWindow,
+    status_thresholds: Option<&StatusThresholds>,
+    hidden: HiddenOverlays,
+) -> Result<String> {
+    let profile_store = profile.default_store().ok_or_else(|| {
+        tracing::error!("[GRAPH] Profile has no stores");
+        anyhow!("Nightscout profile has no stores")
+    })?;
+    let user_timezone = user_settings.effective_timezone(Some(profile_store));
+    let user_tz: Tz = user_timezone.parse().unwrap_or(chrono_tz::UTC);
+
+    let target_low_mg =
+        profile_store.get_target_low_mg(user_settings.target_range_mg, status_thresholds);
+    let target_high_mg =
+        profile_store.get_target_high_mg(user_settings.target_range_mg, status_thresholds);
+    let urgent_low_mg = get_urgent_low_mg(user_settings.urgent_low_mg, status_thresholds);
+
+    let entries = match nightscout_client.filter_and_clean_entries_between(
+        entries,
+        window.start,
+        window.end,
+        user_timezone,
+    ) {
+        Ok(filtered) => filtered,
+        Err(e) => {
+            // Like the PNG, an empty window gets the axes and a message instead of an error
+            tracing::warn!("[GRAPH] No entries to plot in SVG: {}", e);
+            Vec::new()
+        }
+    };
+    // With no readings there is nothing to anchor treatments to, matching the empty PNG
+    let treatments: &[Treatment] = if entries.is_empty() { &[] } else { treatments };
+
+    let pref = if profile_store.uses_mmol() {
+        PrefUnit::Mmol
+    } else {
+        PrefUnit::MgDl
+    };
+
+    let layout = GraphLayout::new(user_settings.right_axis);
+    let (width, height) = (layout.width, layout.height);
+    let num_y_labels = 8;
+
+    let theme = GraphTheme::from(user_settings.graph_theme)
+        .with_tint(user_settings.graph_tint)
+        .with_palette(user_settings.graph_palette);
+
+    let plot_left = layout.plot_left;
+    let plot_top = layout.plot_top;
+    let plot_right = layout.plot_right;
+    let plot_bottom = layout.plot_bottom;
+    let inner_plot_left = layout.inner_left;
+    let inner_plot_right = layout.inner_right;
+    let inner_plot_top = layout.inner_top;
+    let inner_plot_bottom = layout.inner_bottom;
+
+    let max_sgv = entries.iter().map(|e| e.sgv).fold(0.0_f32, f32::max);
+    let (y_min, y_max) = y_axis_range(max_sgv, user_settings.fixed_scale_mg, pref);
+    let projection = layout.projection(
+        y_min,
+        y_max,
+        pref,
+        window.start.timestamp(),
+        window.end.timestamp(),
+    );
+    let in_plot_x = |x: f32| (inner_plot_left..=inner_plot_right).contains(&x);
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="{FONT_FAMILY}">"#,
+        w = width,
+        h = height,
+    );
+    let _ = writeln!(
+        out,
+        r#"<rect width="100%" height="100%" fill="{}"/>"#,
+        hex(theme.bg)
+    );
+
+    line(
+        &mut out,
+        (plot_left, plot_top),
+        (plot_left, plot_bottom),
+        theme.axis,
+        None,
+    );
+    line(
+        &mut out,
+        (plot_left, plot_bottom),
+        (plot_right, plot_bottom),
+        theme.axis,
+        None,
+    );
+
+    let y_values = y_axis_ticks(y_min, y_max, pref, num_y_labels);
+    for (y_px, is_minor) in y_gridlines(&projection, &y_values, user_settings.minor_gridlines) {
+        let color = if is_minor {
+            theme.faint_grid
+        } else {
+            theme.grid
+        };
+        line(
+            &mut out,
+            (inner_plot_left, y_px),
+            (inner_plot_right, y_px),
+            color,
+            None,
+        );
+    }
+
+    for y_val in y_values {
+        let y_px = projection.y_display(y_val);
+        let (primary, secondary) = match pref {
+            PrefUnit::MgDl => (format!("{}", y_val as i32), format!("{:.1}", y_val / 18.0)),
+            PrefUnit::Mmol => (
+                format!("{:.1}", y_val),
+                format!("{}", (y_val * 18.0) as i32),
+            ),
+        };
+        let label_x = plot_left - 136.0;
+        text(&mut out, label_x, y_px - 16.0, 40.0, theme.bright, &primary);
+        text(&mut out, label_x, y_px + 12.0, 36.0, theme.dim, &secondary);
+        if user_settings.right_axis {
+            text(
+                &mut out,
+                plot_right + 16.0,
+                y_px - 18.0,
+                36.0,
+                theme.dim,
+                &secondary,
+            );
+        }
+    }
+
+    for (target, color) in [
+        (target_high_mg, theme.target_high_line),
+        (target_low_mg, theme.target_low_line),
+    ] {
+        let y = projection.y(target);
+        if y >= inner_plot_top && y <= inner_plot_bottom {
+            line(
+                &mut out,
+                (inner_plot_left, y),
+                (inner_plot_right, y),
+                color,
+                Some("10 5"),
+            );
+        }
+    }
+
+    if entries.is_empty() {
+        centered_text(
+            &mut out,
+            (inner_plot_left + inner_plot_right) / 2.0,
+            (inner_plot_top + inner_plot_bottom - EMPTY_MESSAGE_FONT_SIZE) / 2.0,
+            EMPTY_MESSAGE_FONT_SIZE,
+            theme.dim,
+            &empty_message(window, user_tz),
+        );
+    }
+
+    // Labels fall on round times of day, so they line up across exports
+    let interval_minutes = label_interval_minutes(window.hours());
+    let start_local = window.start.with_timezone(&user_tz);
+    let mut label_time = start_local
+        .duration_trunc(chrono::Duration::hours(1))
+        .unwrap_or(start_local);
+    while label_time <= window.end {
+        let minute_of_day = label_time.hour() * 60 + label_time.minute();
+        if label_time >= window.start && minute_of_day.is_multiple_of(interval_minutes) {
+            let x = projection.x(label_time.timestamp());
+            centered_text(
+                &mut out,
+                x,
+                plot_bottom + 16.0,
+                40.0,
+                theme.bright,
+                &label_time.format("%H:%M").to_string(),
+            );
+        }
+        label_time += chrono::Duration::minutes(30);
+    }
+
+    if window.live {
+        let now_x = projection.x(window.end.timestamp());
+        line(
+            &mut out,
+            (now_x, inner_plot_top),
+            (now_x, inner_plot_bottom),
+            theme.dim,
+            Some("4 6"),
+        );
+        text(
+            &mut out,
+            now_x - 42.0,
+            plot_top - 30.0,
+            28.0,
+            theme.dim,
+            "now",
+        );
+    } else {
+        let day_text = start_local.format("%a %-d %b %Y").to_string();
+        let day_text_width = day_text.chars().count() as f32 * 14.0;
+        text(
+            &mut out,
+            inner_plot_right - day_text_width,
+            plot_top - 30.0,
+            28.0,
+            theme.dim,
+            &day_text,
+        );
+    }
+
+    let points_px: Vec<(f32, f32)> = entries
+        .iter()
+        .map(|entry| {
+            (
+                projection.x(entry.millis_to_user_timezone(user_timezone).timestamp()),
+                projection.y(projection.clamp_mg(entry.sgv)),
+            )
+        })
+        .collect();
+
+    let bolus_amounts: Vec<f32> = treatments
+        .iter()
+        .filter(|t| t.is_insulin() && t.type_.as_deref() != Some("SMB"))
+        .filter_map(Treatment::insulin_amount)
+        .filter(|amount| *amount > user_settings.microbolus_threshold)
+        .collect();
+    let typical_max_bolus = typical_max_bolus(&bolus_amounts).unwrap_or(10.0);
+
+    for treatment in treatments {
+        let Some(treatment_ms) = treatment.timestamp_millis() else {
+            continue;
+        };
+        let x = projection.x(treatment_ms as i64 / 1000);
+        if !in_plot_x(x) {
+            continue;
+        }
+        // Treatments sit above or below the nearest reading, like on the PNG graph
+        let closest_y = Entry::nearest_to(&entries, treatment_ms as i64)
+            .and_then(|nearest| entries.iter().position(|e| std::ptr::eq(e, nearest)))
+            .map(|i| points_px[i].1)
+            .unwrap_or((inner_plot_top + inner_plot_bottom) / 2.0);
+
+        if treatment.is_insulin() && !hidden.insulin {
+            let amount = treatment.insulin_amount().unwrap_or(0.0);
+            let is_microbolus = treatment.is_microbolus(user_settings.microbolus_threshold);
+            if !is_microbolus || user_settings.display_microbolus {
+                let size = if is_microbolus {
+                    MICROBOLUS_TRIANGLE_SIZE
+                } else {
+                    insulin_triangle_size(
+                        amount,
+                        user_settings.microbolus_threshold,
+                        typical_max_bolus,
+                    )
+                };
+                let label_height = if is_microbolus { 0.0 } else { 52.0 };
+                let y = place_marker(
+                    closest_y,
+                    TREATMENT_MARKER_OFFSET,
+                    size,
+                    size + label_height,
+                    inner_plot_top,
+                    inner_plot_bottom,
+                );
+                let _ = writeln!(
+                    out,
+                    r#"<polygon points="{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}" fill="{}"/>"#,
+                    x - size,
+                    y - size,
+                    x + size,
+                    y - size,
+                    x,
+                    y + size,
+                    hex(theme.insulin)
+                );
+                if !is_microbolus {
+                    centered_text(
+                        &mut out,
+                        x,
+                        y + size + 16.0,
+                        36.0,
+                        theme.bright,
+                        &format!("{:.1}u", amount),
+                    );
+                }
+            }
+        }
+
+        if treatment.is_carbs() && !hidden.carbs {
+            let amount = treatment.carbs.unwrap_or(0.0);
+            let radius = if amount < 0.5 {
+                8
+            } else if amount <= 2.0 {
+                14
+            } else {
+                24
+            };
+            let y = place_marker(
+                closest_y,
+                -TREATMENT_MARKER_OFFSET,
+                radius as f32 + 50.0,
+                radius as f32,
+                inner_plot_top,
+                inner_plot_bottom,
+            );
+            let _ = writeln!(
+                out,
+                r#"<circle cx="{:.1}" cy="{:.1}" r="{}" fill="{}"/>"#,
+                x,
+                y,
+                radius,
+                hex(carbs_color(amount, &theme))
+            );
+            let label = match treatment.fat_protein_units() {
+                Some(fpu) => format!("{}g +{:.1} FPU", amount as i32, fpu),
+                None => format!("{}g", amount as i32),
+            };
+            centered_text(
+                &mut out,
+                x,
+                y - radius as f32 - 50.0,
+                36.0,
+                theme.carbs,
+                &label,
+            );
+        }
+
+        if treatment.is_bg_check()
+            && !hidden.finger_sticks
+            && let Some(glucose_value) = treatment.glucose_value()
+        {
+            finger_stick(
+                &mut out,
+                x,
+                projection.y(glucose_value),
+                glucose_value,
+                pref,
+                &theme,
+            );
+        }
+    }
+
+    let thresholds =
+        GlucoseThresholds::new(target_low_mg, target_high_mg).with_urgent_low(urgent_low_mg);
+    let radius = point_radius(entries.len());
+    for (entry, &(x, y)) in entries.iter().zip(&points_px) {
+        if !entry.has_sgv() {
+            continue;
+        }
+        let status = thresholds.classify(entry.sgv);
+        let (color, r) = match status {
+            GlucoseStatus::High => (theme.high, radius),
+            GlucoseStatus::UrgentLow => (theme.urgent_low, radius + URGENT_LOW_RADIUS_BOOST),
+            GlucoseStatus::Low => (theme.low, radius),
+            GlucoseStatus::InRange => (theme.in_range, radius),
+        };
+        // Noisy readings are outlined rather than filled, as on the PNG graph
+        let paint = if entry.is_noisy() {
+            format!(r#"fill="none" stroke="{}" stroke-width="2""#, hex(color))
+        } else {
+            format!(r#"fill="{}""#, hex(color))
+        };
+
+        if theme.square_highs && status == GlucoseStatus::High {
+            let _ = writeln!(
+                out,
+                r#"<rect x="{:.1}" y="{:.1}" width="{}" height="{}" {}/>"#,
+                x - r as f32,
+                y - r as f32,
+                2 * r,
+                2 * r,
+                paint
+            );
+        } else {
+            let _ = writeln!(
+                out,
+                r#"<circle cx="{:.1}" cy="{:.1}" r="{}" {}/>"#,
+                x, y, r, paint
+            );
+        }
+    }
+
+    if !hidden.finger_sticks {
+        for (entry, &(x, _)) in entries.iter().zip(&points_px) {
+            if let Some(mbg) = entry.mbg.filter(|_| entry.has_mbg()) {
+                finger_stick(&mut out, x, projection.y(mbg), mbg, pref, &theme);
+            }
+        }
+    }
+
+    let (primary_unit, secondary_unit) = match pref {
+        PrefUnit::MgDl => ("mg/dL", "mmol/L"),
+        PrefUnit::Mmol => ("mmol/L", "mg/dL"),
+    };
+    let header_x = plot_left - 144.0;
+    let header_y = plot_bottom + 60.0;
+    text(
+        &mut out,
+        header_x,
+        header_y,
+        40.0,
+        theme.bright,
+        primary_unit,
+    );
+    text(
+        &mut out,
+        header_x,
+        header_y + 36.0,
+        36.0,
+        theme.dim,
+        secondary_unit,
+    );
+
+    if user_settings.show_watermark && !user_settings.anonymous_mode {
+        text(&mut out, 20.0, 10.0, 36.0, theme.dim, "Beetroot");
+    }
+
+    out.push_str("</svg>\n");
+    tracing::info!("[GRAPH] Generated SVG graph ({} bytes)", out.len());
+    Ok(out)
+}
+
+/// A finger stick: a filled dot with an outline ring and its value above it
+fn finger_stick(
+    out: &mut String,
+    x: f32,
+    y: f32,
+    value_mg: f32,
+    pref: PrefUnit,
+    theme: &GraphTheme,
+) {
+    let _ = writeln!(
+        out,
+        r#"<circle cx="{:.1}" cy="{:.1}" r="10" fill="{}" stroke="{}" stroke-width="4"/>"#,
+        x,
+        y,
+        hex(theme.bg_check_fill),
+        hex(theme.bg_check_outline)
+    );
+    let label = match pref {
+        PrefUnit::MgDl => format!("{:.0}", value_mg),
+        PrefUnit::Mmol => format!("{:.1}", value_mg / 18.0),
+    };
+    centered_text(out, x, y - 52.0, 32.0, theme.bright, &label);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_and_opacity() {
+        assert_eq!(hex(Rgba([255, 159, 10, 80])), "#ff9f0a");
+        assert_eq!(opacity(Rgba([255, 159, 10, 255])), "1.00");
+        assert_eq!(opacity(Rgba([0, 0, 0, 0])), "0.00");
+    }
+
+    #[test]
+    fn test_text_is_escaped() {
+        let mut out = String::new();
+        text(
+            &mut out,
+            0.0,
+            0.0,
+            12.0,
+            Rgba([0, 0, 0, 255]),
+            r#"<b> & "x""#,
+        );
+        centered_text(&mut out, 0.0, 0.0, 12.0, Rgba([0, 0, 0, 255]), "a<b");
+        assert!(out.contains(">&lt;b&gt; &amp; &quot;x&quot;</text>"));
+        assert!(out.contains(">a&lt;b</text>"));
+    }
+
+    #[test]
+    fn test_empty_window_still_draws_svg() {
+        let profile = Profile {
+            default_profile: "Default".to_string(),
+            store: [(
+                "Default".to_string(),
+                crate::utils::nightscout::ProfileStore {
+                    timezone: "UTC".to_string(),
+                    units: None,
+                    target_low: None,
+                    target_high: None,
+                    basal: None,
+                },
+            )]
+            .into_iter()
+            .collect(),
+        };
+
+        let svg = draw_graph_svg(
+            &[],
+            &[],
+            &profile,
+            &NightscoutInfo::default(),
+            &Nightscout::new(),
+            GraphWindow::last_hours(6),
+            None,
+            HiddenOverlays::default(),
+        )
+        .unwrap();
+        assert!(svg.contains("No data in the last 6 hours"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
@@ -0,0 +1,180 @@
+use ab_glyph::{Font, FontArc, GlyphId, PxScale, ScaleFont, point};
+use image::{Rgba, RgbaImage};
+use imageproc::pixelops::weighted_sum;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::bot::Handler;
+
+/// Horizontal sub-pixel positions each glyph is rasterized at, so cached glyphs keep
+/// roughly the spacing of per-call rasterization
+const SUBPIXEL_STEPS: f32 = 4.0;
+
+/// A rasterized glyph: its coverage bitmap and offset from the pen position
+struct CachedGlyph {
+    min_x: i32,
+    min_y: i32,
+    width: u32,
+    coverage: Vec<f32>,
+}
+
+/// Character, scale (as bits, since `f32` isn't hashable) and sub-pixel step
+type GlyphKey = (char, u32, u32, u8);
+
+/// Coverage bitmaps of glyphs already drawn, shared by every graph render. Glyphs without
+/// an outline (like spaces) are cached as `None`.
+#[derive(Default)]
+pub struct GlyphCache {
+    glyphs: RwLock<HashMap<GlyphKey, Option<Arc<CachedGlyph>>>>,
+}
+
+impl GlyphCache {
+    fn glyph(
+        &self,
+        font: &FontArc,
+        c: char,
+        id: GlyphId,
+        scale: PxScale,
+        subpixel: u8,
+    ) -> Option<Arc<CachedGlyph>> {
+        let key = (c, scale.x.to_bits(), scale.y.to_bits(), subpixel);
+        if let Some(cached) = self
+            .glyphs
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&key)
+        {
+            return cached.clone();
+        }
+
+        let ascent = font.as_scaled(scale).ascent();
+        let offset = subpixel as f32 / SUBPIXEL_STEPS;
+        let rasterized = font
+            .outline_glyph(id.with_scale_and_position(scale, point(offset, ascent)))
+            .map(|outlined| {
+                let bounds = outlined.px_bounds();
+                let width = bounds.width() as u32;
+                let mut coverage = vec![0.0; (width * bounds.height() as u32) as usize];
+                outlined.draw(|gx, gy, value| {
+                    coverage[(gy * width + gx) as usize] = value.clamp(0.0, 1.0);
+                });
+                Arc::new(CachedGlyph {
+                    min_x: bounds.min.x.round() as i32,
+                    min_y: bounds.min.y.round() as i32,
+                    width,
+                    coverage,
+                })
+            });
+
+        self.glyphs
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(key, rasterized.clone());
+        rasterized
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.glyphs.read().unwrap().len()
+    }
+}
+
+/// Drop-in replacement for `imageproc::drawing::draw_text_mut` that reuses rasterized glyphs
+/// from the handler's [`GlyphCache`]. Layout (advances and kerning) matches imageproc.
+pub fn draw_text(
+    img: &mut RgbaImage,
+    color: Rgba<u8>,
+    x: i32,
+    y: i32,
+    scale: PxScale,
+    handler: &Handler,
+    text: &str,
+) {
+    draw_cached_text(
+        img,
+        color,
+        x,
+        y,
+        scale,
+        &handler.font,
+        &handler.glyph_cache,
+        text,
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_cached_text(
+    img: &mut RgbaImage,
+    color: Rgba<u8>,
+    x: i32,
+    y: i32,
+    scale: PxScale,
+    font: &FontArc,
+    cache: &GlyphCache,
+    text: &str,
+) {
+    let scaled = font.as_scaled(scale);
+    let (image_width, image_height) = (img.width() as i32, img.height() as i32);
+    let mut pen_x = 0.0_f32;
+    let mut last: Option<GlyphId> = None;
+
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        let glyph_x = pen_x;
+        pen_x += scaled.h_advance(id);
+
+        let subpixel = ((glyph_x.fract() * SUBPIXEL_STEPS) as u8).min(SUBPIXEL_STEPS as u8 - 1);
+        let Some(glyph) = cache.glyph(font, c, id, scale, subpixel) else {
+            continue;
+        };
+        if let Some(last) = last {
+            pen_x += scaled.kern(id, last);
+        }
+        last = Some(id);
+
+        let origin_x = x + glyph_x.floor() as i32 + glyph.min_x;
+        let origin_y = y + glyph.min_y;
+        for (i, &value) in glyph.coverage.iter().enumerate() {
+            if value <= 0.0 {
+                continue;
+            }
+            let image_x = origin_x + (i as u32 % glyph.width) as i32;
+            let image_y = origin_y + (i as u32 / glyph.width) as i32;
+            if (0..image_width).contains(&image_x) && (0..image_height).contains(&image_y) {
+                let pixel = img.get_pixel_mut(image_x as u32, image_y as u32);
+                *pixel = weighted_sum(*pixel, color, 1.0 - value, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use imageproc::drawing::draw_text_mut;
+
+    fn font() -> FontArc {
+        FontArc::try_from_vec(std::fs::read("assets/fonts/GeistMono-Regular.ttf").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_cached_text_matches_imageproc_and_reuses_glyphs() {
+        let font = font();
+        let cache = GlyphCache::default();
+        let scale = PxScale::from(40.0);
+        let background = RgbaImage::from_pixel(200, 80, Rgba([17, 24, 28, 255]));
+        let color = Rgba([248, 250, 252, 255]);
+
+        // A single glyph starts on a whole pixel, so it must match exactly
+        let mut expected = background.clone();
+        draw_text_mut(&mut expected, color, 10, 10, scale, &font, "8");
+        let mut actual = background.clone();
+        draw_cached_text(&mut actual, color, 10, 10, scale, &font, &cache, "8");
+        assert_eq!(actual, expected);
+
+        let mut text = background.clone();
+        draw_cached_text(&mut text, color, 10, 10, scale, &font, &cache, "88 8");
+        assert!(cache.len() <= 1 + SUBPIXEL_STEPS as usize * 2);
+        assert_ne!(text, background);
+    }
+}
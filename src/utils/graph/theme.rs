@@ -0,0 +1,180 @@
+use image::Rgba;
+
+use crate::utils::database::{GraphPalette, GraphThemeKind, GraphTint};
+
+/// How much of the tint colour is mixed into the background, kept low so the
+/// readings and target lines stay legible
+const TINT_STRENGTH: f32 = 0.08;
+
+/// Colour palette used when rendering a graph
+#[derive(Clone, Copy, Debug)]
+pub struct GraphTheme {
+    pub bg: Rgba<u8>,
+    pub grid: Rgba<u8>,
+    pub faint_grid: Rgba<u8>,
+    pub axis: Rgba<u8>,
+    pub bright: Rgba<u8>,
+    pub dim: Rgba<u8>,
+    pub darker_dim: Rgba<u8>,
+    pub high: Rgba<u8>,
+    pub low: Rgba<u8>,
+    pub urgent_low: Rgba<u8>,
+    pub in_range: Rgba<u8>,
+    pub target_high_line: Rgba<u8>,
+    pub target_low_line: Rgba<u8>,
+    pub insulin: Rgba<u8>,
+    pub carbs: Rgba<u8>,
+    pub sensor_change: Rgba<u8>,
+    pub site_change: Rgba<u8>,
+    pub bg_check_outline: Rgba<u8>,
+    pub bg_check_fill: Rgba<u8>,
+    pub exercise: Rgba<u8>,
+    pub note: Rgba<u8>,
+    /// Draw high readings as squares, so highs and lows differ by shape as well as colour
+    pub square_highs: bool,
+}
+
+impl GraphTheme {
+    pub fn dark() -> Self {
+        Self {
+            bg: Rgba([17, 24, 28, 255]),
+            grid: Rgba([30, 41, 47, 255]),
+            faint_grid: Rgba([25, 35, 41, 255]),
+            axis: Rgba([148, 163, 184, 255]),
+            bright: Rgba([248, 250, 252, 255]),
+            dim: Rgba([148, 163, 184, 255]),
+            darker_dim: Rgba([98, 113, 134, 255]),
+            high: Rgba([255, 159, 10, 255]),
+            low: Rgba([255, 69, 58, 255]),
+            urgent_low: Rgba([255, 0, 102, 255]),
+            in_range: Rgba([148, 163, 184, 255]),
+            target_high_line: Rgba([255, 159, 10, 80]),
+            target_low_line: Rgba([255, 69, 58, 80]),
+            insulin: Rgba([96, 165, 250, 255]),
+            carbs: Rgba([251, 191, 36, 255]),
+            sensor_change: Rgba([167, 139, 250, 255]),
+            site_change: Rgba([45, 212, 191, 255]),
+            bg_check_outline: Rgba([128, 128, 128, 255]),
+            bg_check_fill: Rgba([220, 38, 27, 255]),
+            exercise: Rgba([74, 222, 128, 255]),
+            note: Rgba([244, 114, 182, 255]),
+            square_highs: false,
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            bg: Rgba([255, 255, 255, 255]),
+            grid: Rgba([226, 232, 240, 255]),
+            faint_grid: Rgba([241, 245, 249, 255]),
+            axis: Rgba([71, 85, 105, 255]),
+            bright: Rgba([15, 23, 42, 255]),
+            dim: Rgba([100, 116, 139, 255]),
+            darker_dim: Rgba([148, 163, 184, 255]),
+            high: Rgba([217, 119, 6, 255]),
+            low: Rgba([220, 38, 38, 255]),
+            urgent_low: Rgba([190, 18, 60, 255]),
+            in_range: Rgba([71, 85, 105, 255]),
+            target_high_line: Rgba([217, 119, 6, 110]),
+            target_low_line: Rgba([220, 38, 38, 110]),
+            insulin: Rgba([37, 99, 235, 255]),
+            carbs: Rgba([202, 138, 4, 255]),
+            sensor_change: Rgba([124, 58, 237, 255]),
+            site_change: Rgba([13, 148, 136, 255]),
+            bg_check_outline: Rgba([100, 116, 139, 255]),
+            bg_check_fill: Rgba([220, 38, 27, 255]),
+            exercise: Rgba([22, 163, 74, 255]),
+            note: Rgba([219, 39, 119, 255]),
+            square_highs: false,
+        }
+    }
+
+    /// Blend the chosen tint into the background, leaving every other colour untouched
+    pub fn with_tint(mut self, tint: GraphTint) -> Self {
+        let tint_rgb = match tint {
+            GraphTint::None => return self,
+            GraphTint::Blue => [59, 130, 246],
+            GraphTint::Green => [34, 197, 94],
+            GraphTint::Purple => [139, 92, 246],
+            GraphTint::Rose => [244, 63, 94],
+        };
+
+        for (channel, tint_channel) in self.bg.0.iter_mut().zip(tint_rgb) {
+            *channel = (*channel as f32 * (1.0 - TINT_STRENGTH)
+                + tint_channel as f32 * TINT_STRENGTH)
+                .round() as u8;
+        }
+        self
+    }
+}
+
+impl GraphTheme {
+    /// Swap in colour-blind friendly high and low colours, with a stronger axis and square
+    /// highs for contrast that doesn't rely on hue. Lighter shades are used on dark themes.
+    pub fn with_palette(mut self, palette: GraphPalette) -> Self {
+        let dark_bg = self.bg.0[..3].iter().map(|c| *c as u32).sum::<u32>() < 3 * 128;
+        let (high, low, urgent_low) = match (palette, dark_bg) {
+            (GraphPalette::Standard, _) => return self,
+            (GraphPalette::BlueOrange, true) => ([230, 159, 0], [86, 180, 233], [204, 121, 167]),
+            (GraphPalette::BlueOrange, false) => ([213, 120, 0], [0, 114, 178], [160, 60, 130]),
+            (GraphPalette::RedCyan, true) => ([0, 200, 220], [255, 85, 85], [220, 40, 120]),
+            (GraphPalette::RedCyan, false) => ([0, 139, 160], [200, 30, 30], [150, 0, 80]),
+        };
+
+        let opaque = |[r, g, b]: [u8; 3]| Rgba([r, g, b, 255]);
+        self.high = opaque(high);
+        self.low = opaque(low);
+        self.urgent_low = opaque(urgent_low);
+        self.target_high_line = Rgba([high[0], high[1], high[2], self.target_high_line.0[3]]);
+        self.target_low_line = Rgba([low[0], low[1], low[2], self.target_low_line.0[3]]);
+        self.axis = self.bright;
+        self.square_highs = true;
+        self
+    }
+}
+
+impl From<GraphThemeKind> for GraphTheme {
+    fn from(kind: GraphThemeKind) -> Self {
+        match kind {
+            GraphThemeKind::Dark => Self::dark(),
+            GraphThemeKind::Light => Self::light(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tint_only_changes_background() {
+        let dark = GraphTheme::dark();
+        assert_eq!(dark.with_tint(GraphTint::None).bg, dark.bg);
+
+        let tinted = dark.with_tint(GraphTint::Blue);
+        assert_ne!(tinted.bg, dark.bg);
+        assert!(tinted.bg.0[2] > dark.bg.0[2]);
+        assert_eq!(tinted.bg.0[3], 255);
+        assert_eq!(tinted.grid, dark.grid);
+        assert_eq!(tinted.bright, dark.bright);
+    }
+
+    #[test]
+    fn test_palette_replaces_status_colours() {
+        let dark = GraphTheme::dark();
+        let standard = dark.with_palette(GraphPalette::Standard);
+        assert_eq!(standard.high, dark.high);
+        assert!(!standard.square_highs);
+
+        for palette in [GraphPalette::BlueOrange, GraphPalette::RedCyan] {
+            for theme in [GraphTheme::dark(), GraphTheme::light()] {
+                let swapped = theme.with_palette(palette);
+                assert_ne!(swapped.high, swapped.low);
+                assert_ne!(swapped.low, theme.low);
+                assert_eq!(swapped.target_low_line.0[3], theme.target_low_line.0[3]);
+                assert_eq!(swapped.axis, theme.bright);
+                assert!(swapped.square_highs);
+            }
+        }
+    }
+}
@@ -1,3 +1,6 @@
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+
 /// Preference unit for glucose display
 #[derive(Clone, Copy, Debug)]
 #[allow(dead_code)]
@@ -6,31 +9,140 @@ pub enum PrefUnit {
     Mmol,
 }
 
-/// Glucose status ranges for contextual sticker placement
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub enum GlucoseStatus {
-    Low,
-    InRange,
-    High,
+/// Maps times and glucose values onto the inner plot area, shared by the PNG and SVG renderers
+#[derive(Clone, Copy, Debug)]
+pub struct PlotProjection {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+    /// Axis range in the display unit
+    pub y_min: f32,
+    pub y_max: f32,
+    pub pref: PrefUnit,
+    /// Unix seconds at the left and right edges
+    pub start_secs: i64,
+    pub end_secs: i64,
 }
 
-impl GlucoseStatus {
-    pub fn from_sgv(sgv: f32, target_low: f32, target_high: f32) -> Self {
-        if sgv < target_low {
-            Self::Low
-        } else if sgv > target_high {
-            Self::High
-        } else {
-            Self::InRange
+impl PlotProjection {
+    /// Horizontal position of a Unix timestamp in seconds
+    pub fn x(&self, timestamp_secs: i64) -> f32 {
+        let span = (self.end_secs - self.start_secs).max(1) as f32;
+        self.left + (timestamp_secs - self.start_secs) as f32 / span * (self.right - self.left)
+    }
+
+    /// Vertical position of a value already in the display unit, such as an axis tick
+    pub fn y_display(&self, value: f32) -> f32 {
+        self.bottom - (value - self.y_min) / (self.y_max - self.y_min) * (self.bottom - self.top)
+    }
+
+    /// Vertical position of a glucose value in mg/dL
+    pub fn y(&self, value_mg: f32) -> f32 {
+        match self.pref {
+            PrefUnit::MgDl => self.y_display(value_mg),
+            PrefUnit::Mmol => self.y_display(value_mg / 18.0),
+        }
+    }
+
+    /// A mg/dL value pulled inside the axis range, so off-scale readings sit on the edge
+    pub fn clamp_mg(&self, value_mg: f32) -> f32 {
+        match self.pref {
+            PrefUnit::MgDl => value_mg.clamp(self.y_min, self.y_max),
+            PrefUnit::Mmol => value_mg.clamp(self.y_min * 18.0, self.y_max * 18.0),
         }
     }
+}
+
+/// Overlays left off a single `/graph`, for a clean glucose trace
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HiddenOverlays {
+    pub carbs: bool,
+    /// Hides every bolus; `display_microbolus` only hides the small ones
+    pub insulin: bool,
+    pub finger_sticks: bool,
+    pub stickers: bool,
+}
+
+/// The stretch of time a graph covers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GraphWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Whether the window runs up to the present, so "now" and relative times make sense
+    pub live: bool,
+}
 
-    pub fn to_sticker_category(self) -> crate::utils::database::StickerCategory {
-        use crate::utils::database::StickerCategory;
-        match self {
-            Self::Low => StickerCategory::Low,
-            Self::InRange => StickerCategory::InRange,
-            Self::High => StickerCategory::High,
+impl GraphWindow {
+    /// The last `hours` hours, ending now
+    pub fn last_hours(hours: u16) -> Self {
+        let end = Utc::now();
+        Self {
+            start: end - Duration::hours(hours as i64),
+            end,
+            live: true,
         }
     }
+
+    /// A fixed calendar day, midnight to midnight in `tz`
+    pub fn day(date: NaiveDate, tz: Tz) -> Option<Self> {
+        let midnight = |date: NaiveDate| {
+            tz.from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+                .earliest()
+                .map(|time| time.with_timezone(&Utc))
+        };
+        Some(Self {
+            start: midnight(date)?,
+            end: midnight(date.succ_opt()?)?,
+            live: false,
+        })
+    }
+
+    /// Length of the window in whole hours, rounded up
+    pub fn hours(&self) -> u16 {
+        let minutes = (self.end - self.start).num_minutes().max(0);
+        ((minutes + 59) / 60).min(u16::MAX as i64) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plot_projection_maps_edges() {
+        let projection = PlotProjection {
+            left: 100.0,
+            right: 300.0,
+            top: 50.0,
+            bottom: 250.0,
+            y_min: 2.0,
+            y_max: 12.0,
+            pref: PrefUnit::Mmol,
+            start_secs: 1_000,
+            end_secs: 1_200,
+        };
+
+        assert_eq!(projection.x(1_000), 100.0);
+        assert_eq!(projection.x(1_100), 200.0);
+        assert_eq!(projection.y(36.0), 250.0);
+        assert_eq!(projection.y(216.0), 50.0);
+        assert_eq!(projection.y_display(7.0), 150.0);
+        assert_eq!(projection.clamp_mg(400.0), 216.0);
+    }
+
+    #[test]
+    fn test_day_window_follows_user_timezone() {
+        let date = NaiveDate::from_ymd_opt(2025, 9, 20).unwrap();
+        let window = GraphWindow::day(date, chrono_tz::Europe::Paris).unwrap();
+
+        assert_eq!(window.start.to_rfc3339(), "2025-09-19T22:00:00+00:00");
+        assert_eq!(window.hours(), 24);
+        assert!(!window.live);
+
+        // The day clocks go back is an hour longer
+        let fall_back = NaiveDate::from_ymd_opt(2025, 10, 26).unwrap();
+        let window = GraphWindow::day(fall_back, chrono_tz::Europe::Paris).unwrap();
+        assert_eq!(window.hours(), 25);
+    }
 }
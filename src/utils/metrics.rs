@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Counters shared by the whole bot and served on `/metrics` by the health server
+pub static METRICS: Metrics = Metrics::new();
+
+/// Process-wide counters for self-hosters to monitor the bot with
+pub struct Metrics {
+    /// Commands handled so far, by command name
+    commands: Mutex<BTreeMap<String, u64>>,
+    command_errors: AtomicU64,
+    nightscout_requests: AtomicU64,
+    nightscout_errors: AtomicU64,
+    nightscout_latency_micros: AtomicU64,
+    discord_connected: AtomicBool,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            commands: Mutex::new(BTreeMap::new()),
+            command_errors: AtomicU64::new(0),
+            nightscout_requests: AtomicU64::new(0),
+            nightscout_errors: AtomicU64::new(0),
+            nightscout_latency_micros: AtomicU64::new(0),
+            discord_connected: AtomicBool::new(false),
+        }
+    }
+
+    /// Count a handled command, and whether it failed
+    pub fn record_command(&self, name: &str, ok: bool) {
+        *self
+            .commands
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(name.to_string())
+            .or_default() += 1;
+        if !ok {
+            self.command_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Count one HTTP request to a Nightscout site. Anything but a 2xx response is an error.
+    pub fn record_nightscout_request(&self, latency: Duration, ok: bool) {
+        self.nightscout_requests.fetch_add(1, Ordering::Relaxed);
+        self.nightscout_latency_micros
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+        if !ok {
+            self.nightscout_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn set_discord_connected(&self, connected: bool) {
+        self.discord_connected.store(connected, Ordering::Relaxed);
+    }
+
+    pub fn discord_connected(&self) -> bool {
+        self.discord_connected.load(Ordering::Relaxed)
+    }
+
+    /// The counters in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP beetroot_commands_total Commands handled, by command name.\n");
+        out.push_str("# TYPE beetroot_commands_total counter\n");
+        for (name, count) in self
+            .commands
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+        {
+            let _ = writeln!(
+                out,
+                "beetroot_commands_total{{command=\"{}\"}} {}",
+                name.replace('\\', "\\\\").replace('"', "\\\""),
+                count
+            );
+        }
+
+        let counter = |out: &mut String, name: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        };
+        counter(
+            &mut out,
+            "beetroot_command_errors_total",
+            "Commands that failed with an unexpected error.",
+            self.command_errors.load(Ordering::Relaxed),
+        );
+        counter(
+            &mut out,
+            "beetroot_nightscout_request_errors_total",
+            "Nightscout requests that failed or didn't return a 2xx status.",
+            self.nightscout_errors.load(Ordering::Relaxed),
+        );
+
+        let requests = self.nightscout_requests.load(Ordering::Relaxed);
+        let latency = self.nightscout_latency_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        out.push_str(
+            "# HELP beetroot_nightscout_request_duration_seconds Time spent on Nightscout requests.\n",
+        );
+        out.push_str("# TYPE beetroot_nightscout_request_duration_seconds summary\n");
+        let _ = writeln!(
+            out,
+            "beetroot_nightscout_request_duration_seconds_sum {}",
+            latency
+        );
+        let _ = writeln!(
+            out,
+            "beetroot_nightscout_request_duration_seconds_count {}",
+            requests
+        );
+
+        out.push_str("# HELP beetroot_discord_connected Whether the gateway connection is up.\n");
+        out.push_str("# TYPE beetroot_discord_connected gauge\n");
+        let _ = writeln!(
+            out,
+            "beetroot_discord_connected {}",
+            self.discord_connected() as u8
+        );
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_prometheus_text() {
+        let metrics = Metrics::new();
+        metrics.record_command("bg", true);
+        metrics.record_command("bg", false);
+        metrics.record_command("graph", true);
+        metrics.record_nightscout_request(Duration::from_millis(250), true);
+        metrics.record_nightscout_request(Duration::from_millis(750), false);
+
+        let text = metrics.render();
+        assert!(text.contains("beetroot_commands_total{command=\"bg\"} 2\n"));
+        assert!(text.contains("beetroot_commands_total{command=\"graph\"} 1\n"));
+        assert!(text.contains("beetroot_command_errors_total 1\n"));
+        assert!(text.contains("beetroot_nightscout_request_errors_total 1\n"));
+        assert!(text.contains("beetroot_nightscout_request_duration_seconds_sum 1\n"));
+        assert!(text.contains("beetroot_nightscout_request_duration_seconds_count 2\n"));
+        assert!(text.contains("beetroot_discord_connected 0\n"));
+    }
+}
@@ -9,6 +9,39 @@ impl Migration {
         Self { pool }
     }
 
+    /// Add a column to a table unless it already exists.
+    ///
+    /// Returns `true` if the column was created by this call.
+    async fn add_column_if_missing(
+        &self,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let exists =
+            sqlx::query("SELECT COUNT(*) as count FROM pragma_table_info(?) WHERE name = ?")
+                .bind(table)
+                .bind(column)
+                .fetch_one(&self.pool)
+                .await?
+                .get::<i32, _>("count")
+                > 0;
+
+        if exists {
+            return Ok(false);
+        }
+
+        sqlx::query(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, definition
+        ))
+        .execute(&self.pool)
+        .await?;
+        tracing::info!("[MIGRATION] Added {} column to {}", column, table);
+
+        Ok(true)
+    }
+
     pub async fn add_microbolus_fields(&self) -> Result<(), sqlx::Error> {
         tracing::info!("[MIGRATION] Adding microbolus fields to users table");
 
@@ -211,4 +244,376 @@ impl Migration {
         tracing::info!("[MIGRATION] Sticker category field migration completed");
         Ok(())
     }
+
+    pub async fn add_device_change_display_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding display_device_changes field to users table");
+
+        self.add_column_if_missing("users", "display_device_changes", "INTEGER DEFAULT 1")
+            .await?;
+
+        tracing::info!("[MIGRATION] Device change display field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_graph_theme_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding graph_theme field to users table");
+
+        self.add_column_if_missing("users", "graph_theme", "TEXT DEFAULT 'dark'")
+            .await?;
+
+        tracing::info!("[MIGRATION] Graph theme field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_smooth_line_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding smooth_line field to users table");
+
+        self.add_column_if_missing("users", "smooth_line", "INTEGER DEFAULT 0")
+            .await?;
+
+        tracing::info!("[MIGRATION] Smooth line field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_right_axis_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding right_axis field to users table");
+
+        self.add_column_if_missing("users", "right_axis", "INTEGER DEFAULT 0")
+            .await?;
+
+        tracing::info!("[MIGRATION] Right axis field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_treatment_legend_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding treatment_legend field to users table");
+
+        self.add_column_if_missing("users", "treatment_legend", "INTEGER DEFAULT 0")
+            .await?;
+
+        tracing::info!("[MIGRATION] Treatment legend field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_target_range_fields(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding custom target range fields to users table");
+
+        self.add_column_if_missing("users", "target_low", "REAL DEFAULT NULL")
+            .await?;
+        self.add_column_if_missing("users", "target_high", "REAL DEFAULT NULL")
+            .await?;
+
+        tracing::info!("[MIGRATION] Target range fields migration completed");
+        Ok(())
+    }
+
+    pub async fn add_timezone_override_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding timezone_override field to users table");
+
+        self.add_column_if_missing("users", "timezone_override", "TEXT DEFAULT NULL")
+            .await?;
+
+        tracing::info!("[MIGRATION] Timezone override field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_weekly_digest_fields(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding weekly digest fields to users table");
+
+        self.add_column_if_missing("users", "weekly_digest", "INTEGER DEFAULT 0")
+            .await?;
+        self.add_column_if_missing("users", "digest_sent_week", "TEXT DEFAULT NULL")
+            .await?;
+
+        tracing::info!("[MIGRATION] Weekly digest fields migration completed");
+        Ok(())
+    }
+
+    pub async fn add_urgent_low_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding urgent_low field to users table");
+
+        self.add_column_if_missing("users", "urgent_low", "REAL DEFAULT NULL")
+            .await?;
+
+        tracing::info!("[MIGRATION] Urgent low field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_graph_background_fields(&self) -> Result<(), sqlx::Error> {
+        tracing::info!(
+            "[MIGRATION] Adding graph watermark and background tint fields to users table"
+        );
+
+        self.add_column_if_missing("users", "show_watermark", "INTEGER DEFAULT 1")
+            .await?;
+        self.add_column_if_missing("users", "graph_tint", "TEXT DEFAULT NULL")
+            .await?;
+
+        tracing::info!("[MIGRATION] Graph background fields migration completed");
+        Ok(())
+    }
+
+    pub async fn add_leaderboard_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding leaderboard_opt_in field to users table");
+
+        self.add_column_if_missing("users", "leaderboard_opt_in", "INTEGER DEFAULT 0")
+            .await?;
+
+        tracing::info!("[MIGRATION] Leaderboard field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_minor_gridlines_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding minor_gridlines field to users table");
+
+        self.add_column_if_missing("users", "minor_gridlines", "INTEGER DEFAULT 0")
+            .await?;
+
+        tracing::info!("[MIGRATION] Minor gridlines field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_default_graph_hours_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding default_graph_hours field to users table");
+
+        self.add_column_if_missing("users", "default_graph_hours", "INTEGER DEFAULT NULL")
+            .await?;
+
+        tracing::info!("[MIGRATION] Default graph hours field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_stale_minutes_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding stale_minutes field to users table");
+
+        self.add_column_if_missing("users", "stale_minutes", "INTEGER DEFAULT NULL")
+            .await?;
+
+        tracing::info!("[MIGRATION] Stale minutes field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_treatment_layout_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding treatment_layout field to users table");
+
+        self.add_column_if_missing("users", "treatment_layout", "TEXT DEFAULT NULL")
+            .await?;
+
+        tracing::info!("[MIGRATION] Treatment layout field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_graph_palette_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding graph_palette field to users table");
+
+        self.add_column_if_missing("users", "graph_palette", "TEXT DEFAULT NULL")
+            .await?;
+
+        tracing::info!("[MIGRATION] Graph palette field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_marker_legend_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding marker_legend field to users table");
+
+        self.add_column_if_missing("users", "marker_legend", "INTEGER DEFAULT 1")
+            .await?;
+
+        tracing::info!("[MIGRATION] Marker legend field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_sd_band_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding sd_band field to users table");
+
+        self.add_column_if_missing("users", "sd_band", "INTEGER DEFAULT 0")
+            .await?;
+
+        tracing::info!("[MIGRATION] SD band field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_anonymous_mode_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding anonymous_mode field to users table");
+
+        self.add_column_if_missing("users", "anonymous_mode", "INTEGER DEFAULT 0")
+            .await?;
+
+        tracing::info!("[MIGRATION] Anonymous mode field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_sticker_scale_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding scale field to stickers table");
+
+        self.add_column_if_missing("stickers", "scale", "REAL DEFAULT 1.0")
+            .await?;
+
+        tracing::info!("[MIGRATION] Sticker scale field migration completed");
+        Ok(())
+    }
+
+    pub async fn add_fixed_scale_fields(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding fixed y-axis scale fields to users table");
+
+        self.add_column_if_missing("users", "fixed_scale_min", "REAL")
+            .await?;
+        self.add_column_if_missing("users", "fixed_scale_max", "REAL")
+            .await?;
+
+        tracing::info!("[MIGRATION] Fixed scale fields migration completed");
+        Ok(())
+    }
+
+    pub async fn add_snooze_until_field(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Adding snooze_until field to users table");
+
+        self.add_column_if_missing("users", "snooze_until", "INTEGER")
+            .await?;
+
+        tracing::info!("[MIGRATION] Snooze field migration completed");
+        Ok(())
+    }
+
+    pub async fn create_reminders_table(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Ensuring reminders table exists");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS reminders (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                discord_id INTEGER NOT NULL,
+                message TEXT NOT NULL,
+                next_fire INTEGER NOT NULL,
+                repeat TEXT NOT NULL DEFAULT 'once',
+                timezone TEXT NOT NULL DEFAULT 'UTC',
+                FOREIGN KEY (discord_id) REFERENCES users(discord_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_reminders_next_fire ON reminders (next_fire)")
+            .execute(&self.pool)
+            .await?;
+
+        tracing::info!("[MIGRATION] Reminders table migration completed");
+        Ok(())
+    }
+
+    /// Create the saved Nightscout sites table and copy every existing user's single site
+    /// into it as their active "default" site
+    pub async fn create_nightscout_sites_table(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Ensuring nightscout_sites table exists");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS nightscout_sites (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                discord_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                nightscout_url TEXT,
+                nightscout_token TEXT,
+                is_private INTEGER NOT NULL DEFAULT 1,
+                allowed_people TEXT NOT NULL DEFAULT '[]',
+                is_active INTEGER NOT NULL DEFAULT 0,
+                UNIQUE (discord_id, name),
+                FOREIGN KEY (discord_id) REFERENCES users(discord_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO nightscout_sites (discord_id, name, nightscout_url, nightscout_token, is_private, allowed_people, is_active)
+            SELECT discord_id, 'default', nightscout_url, nightscout_token, is_private, COALESCE(allowed_people, '[]'), 1
+            FROM users
+            WHERE nightscout_url IS NOT NULL
+              AND discord_id NOT IN (SELECT discord_id FROM nightscout_sites)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        tracing::info!(
+            "[MIGRATION] Nightscout sites migration completed ({} users migrated)",
+            result.rows_affected()
+        );
+        Ok(())
+    }
+
+    /// Make `nightscout_sites` the only place a user's Nightscout connection is stored. Every
+    /// user gets an active site, the active site picks up whatever is still on the users row,
+    /// and the users copy is cleared so this is a no-op on every later startup.
+    pub async fn move_connection_to_sites(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Moving Nightscout connections onto nightscout_sites");
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO nightscout_sites (discord_id, name, nightscout_url, nightscout_token, is_private, allowed_people, is_active)
+            SELECT discord_id, 'default', nightscout_url, nightscout_token, is_private, COALESCE(allowed_people, '[]'), 1
+            FROM users
+            WHERE discord_id NOT IN (SELECT discord_id FROM nightscout_sites)
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE nightscout_sites SET
+                nightscout_url = u.nightscout_url,
+                nightscout_token = u.nightscout_token,
+                is_private = u.is_private,
+                allowed_people = COALESCE(u.allowed_people, '[]')
+            FROM users AS u
+            WHERE nightscout_sites.discord_id = u.discord_id
+              AND nightscout_sites.is_active = 1
+              AND (u.nightscout_url IS NOT NULL OR u.nightscout_token IS NOT NULL)
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE users SET nightscout_url = NULL, nightscout_token = NULL, allowed_people = '[]'
+            WHERE nightscout_url IS NOT NULL OR nightscout_token IS NOT NULL
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        tracing::info!(
+            "[MIGRATION] Nightscout connection move completed ({} users moved)",
+            result.rows_affected()
+        );
+        Ok(())
+    }
+
+    pub async fn create_guild_settings_table(&self) -> Result<(), sqlx::Error> {
+        tracing::info!("[MIGRATION] Ensuring guild_settings table exists");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS guild_settings (
+                guild_id INTEGER PRIMARY KEY,
+                allowed_channels TEXT NOT NULL DEFAULT '[]',
+                force_private INTEGER NOT NULL DEFAULT 0
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        tracing::info!("[MIGRATION] Guild settings table migration completed");
+        Ok(())
+    }
 }
@@ -1,4 +1,8 @@
 pub mod database;
+pub mod glucose;
 pub mod graph;
+pub mod metrics;
 pub mod migration;
 pub mod nightscout;
+pub mod reminders;
+pub mod stats;
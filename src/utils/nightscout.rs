@@ -1,24 +1,94 @@
-use chrono::{Duration, Local, TimeZone, Utc};
+use crate::utils::metrics::METRICS;
+use chrono::{DateTime, Duration, Local, TimeZone, Utc};
 use chrono_tz::Tz;
 use reqwest::{Client, Url};
 use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::Instant;
 use thiserror::Error;
+use tokio::sync::RwLock;
+
+/// How long a fetched profile is reused before it is requested again.
+const PROFILE_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// How many times a GET is retried after a connect error, timeout or 5xx response.
+const MAX_REQUEST_RETRIES: u32 = 3;
+/// Delay before the first retry; doubled on every following attempt.
+const RETRY_BASE_DELAY_MS: u64 = 500;
+/// Upper bound of the random jitter added to each retry delay.
+const RETRY_JITTER_MS: u64 = 250;
+/// No retry starts later than this after the first attempt, so a deferred command still
+/// answers in reasonable time when a site is down.
+const RETRY_BUDGET: std::time::Duration = std::time::Duration::from_secs(10);
+/// Sensor readings at or below this (mg/dL) are uploader error codes rather than glucose, such
+/// as Dexcom's 39 for "LOW" and the small codes behind "???", or a plain 0.
+pub const SENSOR_ERROR_SGV_CEILING: f32 = 39.0;
+/// A median `sgv` below this on a mmol/L profile means the site stores readings in mmol/L.
+const MMOL_SGV_MEDIAN_CEILING: f32 = 30.0;
+/// Readings older than this many minutes are flagged as stale by `/bg` and on graphs, unless
+/// the user picked their own threshold with `/set-stale-time`.
+pub const STALE_DATA_MINUTES: i64 = 15;
+/// Rates of change (mg/dL per 5 minutes) above which a derived trend gets each arrow.
+const FORTY_FIVE_ARROW_RATE: f32 = 3.0;
+const SINGLE_ARROW_RATE: f32 = 5.0;
+const DOUBLE_ARROW_RATE: f32 = 10.0;
+/// Readings further apart than this are too far apart to derive a trend from.
+const MAX_TREND_GAP_MINUTES: f32 = 15.0;
+/// How far a reading may be from the requested time for `Entry::delta_over` and
+/// `Nightscout::get_entry_near` to use it.
+const DELTA_MATCH_TOLERANCE_MS: i64 = 5 * 60_000;
+/// Clinical level 2 hypoglycemia, used when neither the user nor Nightscout sets an urgent low.
+const DEFAULT_URGENT_LOW_MG: f32 = 54.0;
+/// Careportal and loop event types that record an insulin bolus.
+pub const BOLUS_EVENT_TYPES: &[&str] = &[
+    "Bolus",
+    "Correction Bolus",
+    "Meal Bolus",
+    "Snack Bolus",
+    "Combo Bolus",
+    "SMB",
+];
+/// Careportal event types that record carbs.
+pub const CARB_EVENT_TYPES: &[&str] = &["Carb Correction", "Meal Bolus", "Snack Bolus"];
+
+/// Careportal event types that set a temporary basal rate.
+pub const TEMP_BASAL_EVENT_TYPES: &[&str] = &["Temp Basal"];
+
+/// Treatment event types that mark a new CGM sensor
+pub const SENSOR_CHANGE_EVENT_TYPES: &[&str] = &["Sensor Change", "Sensor Start"];
 
 #[derive(Debug, Clone)]
 pub enum AuthMethod {
     ApiSecret(String),
-    Bearer(String),
+    /// A JWT, along with the access token it was issued for when known so it can be refreshed.
+    Bearer {
+        jwt: String,
+        access_token: Option<String>,
+    },
 }
 
 impl AuthMethod {
     pub fn from_token(token: &str) -> Self {
         if token.starts_with("eyJ") {
-            Self::Bearer(token.to_string())
+            Self::Bearer {
+                jwt: token.to_string(),
+                access_token: None,
+            }
         } else {
             Self::ApiSecret(token.to_string())
         }
     }
 
+    /// The access token a fresh JWT can be requested with, if any.
+    /// Tokens that were given to us as a JWT directly cannot be refreshed.
+    pub fn access_token(&self) -> Option<&str> {
+        match self {
+            Self::ApiSecret(token) => Some(token),
+            Self::Bearer { access_token, .. } => access_token.as_deref(),
+        }
+    }
+
     /// Convert an access token to JWT using the Nightscout API
     #[allow(dead_code)]
     pub async fn to_jwt(
@@ -27,7 +97,10 @@ impl AuthMethod {
         access_token: &str,
     ) -> Result<Self, NightscoutError> {
         let jwt_response = nightscout.request_jwt_token(base_url, access_token).await?;
-        Ok(Self::Bearer(jwt_response.token))
+        Ok(Self::Bearer {
+            jwt: jwt_response.token,
+            access_token: Some(access_token.to_string()),
+        })
     }
 
     /// Apply the authentication method to an HTTP request
@@ -37,9 +110,9 @@ impl AuthMethod {
                 tracing::debug!("[AUTH] Using API-SECRET header authentication");
                 req.header("API-SECRET", secret)
             }
-            Self::Bearer(token) => {
+            Self::Bearer { jwt, .. } => {
                 tracing::debug!("[AUTH] Using Bearer token authentication");
-                req.header("Authorization", format!("Bearer {}", token))
+                req.header("Authorization", format!("Bearer {}", jwt))
             }
         }
     }
@@ -47,23 +120,80 @@ impl AuthMethod {
     pub fn description(&self) -> &'static str {
         match self {
             Self::ApiSecret(_) => "API-SECRET header",
-            Self::Bearer(_) => "Bearer token",
+            Self::Bearer { .. } => "Bearer token",
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct JwtResponse {
     pub token: String,
     pub exp: i64,
 }
 
+/// When two readings count as the same one uploaded twice
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DedupConfig {
+    /// Readings further apart than this are never merged
+    pub window_ms: i64,
+    /// Largest difference between two values still treated as the same reading, in the
+    /// site's own units (mmol/L sites are deduplicated before conversion)
+    pub value_tolerance: f32,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            window_ms: 30_000,
+            value_tolerance: 0.05,
+        }
+    }
+}
+
+impl DedupConfig {
+    /// Whether `entry` is a re-upload of `existing`. Meter readings are only compared with
+    /// other meter readings, and entries without a usable time are never merged.
+    pub fn is_duplicate(&self, entry: &Entry, existing: &Entry) -> bool {
+        let (Some(entry_timestamp), Some(existing_timestamp)) =
+            (entry.timestamp_millis(), existing.timestamp_millis())
+        else {
+            return false;
+        };
+        if (entry_timestamp as i64 - existing_timestamp as i64).abs() > self.window_ms {
+            return false;
+        }
+
+        let close = |a: f32, b: f32| (a - b).abs() <= self.value_tolerance;
+        match (entry.mbg, existing.mbg) {
+            (Some(entry_mbg), Some(existing_mbg)) => close(entry_mbg, existing_mbg),
+            (None, None) => close(entry.sgv, existing.sgv),
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug)]
 /// Represents a Nightscout client for interacting with the Nightscout API.
 ///
-/// This struct holds an HTTP client used to send requests to the Nightscout service.
+/// This struct holds an HTTP client used to send requests to the Nightscout service,
+/// along with a short-lived cache of profiles keyed by base URL and token.
 pub struct Nightscout {
     http_client: Client,
+    profile_cache: RwLock<HashMap<(String, u64), CachedProfile>>,
+    jwt_cache: RwLock<HashMap<(String, String), JwtResponse>>,
+    /// Sites whose v1 entries endpoint returned 404, so entries are read from v3 instead.
+    v3_sites: RwLock<HashSet<String>>,
+    /// How re-uploaded readings are detected when cleaning entries
+    dedup: DedupConfig,
+    /// Sensor readings at or below this are dropped as error codes when cleaning entries
+    sensor_error_ceiling: f32,
+}
+
+/// A profile (timezone, units and targets) along with the moment it was fetched.
+#[derive(Debug, Clone)]
+struct CachedProfile {
+    profile: Profile,
+    fetched_at: Instant,
 }
 
 #[derive(Debug, Error)]
@@ -85,13 +215,17 @@ pub enum NightscoutError {
     Url(#[from] url::ParseError),
     #[error("JSON parsing error: {0}")]
     Json(#[from] serde_json::Error),
+    /// The site rejected the access token (401/403) when exchanging it for a JWT.
+    #[error("The access token was rejected")]
+    InvalidToken,
 }
 
 #[allow(dead_code)]
-#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Entry {
-    #[serde(rename = "_id", default)]
+    // The v3 API calls the document id `identifier`
+    #[serde(rename = "_id", default, alias = "identifier")]
     pub id: Option<String>,
     #[serde(default)]
     pub sgv: f32,
@@ -109,6 +243,40 @@ pub struct Entry {
     // Meter blood glucose (finger stick reading)
     #[serde(default, deserialize_with = "deserialize_mbg", alias = "MBG")]
     pub mbg: Option<f32>,
+    // CGM signal noise level reported by the uploader (0-4, higher is noisier)
+    #[serde(default, deserialize_with = "deserialize_numeric_field")]
+    pub noise: Option<f32>,
+}
+
+// xDrip stores `insulinInjections` as a JSON-encoded string, other uploaders as an array.
+// Anything unreadable is treated as no injections so the rest of the treatment still parses.
+fn deserialize_insulin_injections<'de, D>(
+    deserializer: D,
+) -> Result<Vec<InsulinInjection>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(match value {
+        Some(serde_json::Value::String(text)) => serde_json::from_str(&text).unwrap_or_default(),
+        Some(value @ serde_json::Value::Array(_)) => {
+            serde_json::from_value(value).unwrap_or_default()
+        }
+        _ => Vec::new(),
+    })
+}
+
+// Nightscout clients store a treatment's `duration` (minutes) as a number or a string
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Option<f32>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(match value {
+        Some(serde_json::Value::Number(number)) => number.as_f64().map(|v| v as f32),
+        Some(serde_json::Value::String(text)) => text.trim().parse().ok(),
+        _ => None,
+    })
 }
 
 // Custom deserializer for glucose field that can handle both numbers and strings
@@ -260,6 +428,11 @@ pub struct Treatment {
     pub glucose_type: Option<String>,
     #[serde(default)]
     pub carbs: Option<f32>,
+    /// Grams of fat and protein, logged by AndroidAPS and the careportal for extended carbs
+    #[serde(default, deserialize_with = "deserialize_numeric_field")]
+    pub fat: Option<f32>,
+    #[serde(default, deserialize_with = "deserialize_numeric_field")]
+    pub protein: Option<f32>,
     #[serde(default)]
     pub insulin: Option<f32>,
     #[serde(default)]
@@ -270,6 +443,39 @@ pub struct Treatment {
     pub mills: Option<u64>,
     #[serde(rename = "type", default)]
     pub type_: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub entered_by: Option<String>,
+    /// Length of the event in minutes, e.g. for exercise
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub duration: Option<f32>,
+    /// Doses of several insulins logged as one treatment, used when `insulin` is missing
+    #[serde(
+        rename = "insulinInjections",
+        default,
+        deserialize_with = "deserialize_insulin_injections"
+    )]
+    pub insulin_injections: Vec<InsulinInjection>,
+    /// Temp basal rate in U/hr, as sent by most loops
+    #[serde(default, deserialize_with = "deserialize_numeric_field")]
+    pub absolute: Option<f32>,
+    /// Temp basal rate in U/hr, as sent by OpenAPS when `absolute` is missing
+    #[serde(default, deserialize_with = "deserialize_numeric_field")]
+    pub rate: Option<f32>,
+    /// Temp basal change relative to the scheduled rate, e.g. `50` for 150%
+    #[serde(default, deserialize_with = "deserialize_numeric_field")]
+    pub percent: Option<f32>,
+}
+
+/// One insulin's dose within a multi-insulin treatment
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct InsulinInjection {
+    /// The insulin's name, such as "Novorapid"
+    #[serde(default, alias = "name")]
+    pub insulin: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_numeric_field")]
+    pub units: Option<f32>,
 }
 
 #[allow(dead_code)]
@@ -299,6 +505,59 @@ impl Trend {
             Self::Else => "↮",
         }
     }
+
+    /// Derives a trend from a rate of change in mg/dL per 5 minutes, for uploaders that
+    /// don't send a `direction`
+    pub fn from_rate(mg_dl_per_5_min: f32) -> Self {
+        if !mg_dl_per_5_min.is_finite() {
+            return Self::Else;
+        }
+
+        let magnitude = mg_dl_per_5_min.abs();
+        let rising = mg_dl_per_5_min > 0.0;
+        match magnitude {
+            m if m > DOUBLE_ARROW_RATE => {
+                if rising {
+                    Self::DoubleUp
+                } else {
+                    Self::DoubleDown
+                }
+            }
+            m if m > SINGLE_ARROW_RATE => {
+                if rising {
+                    Self::SingleUp
+                } else {
+                    Self::SingleDown
+                }
+            }
+            m if m > FORTY_FIVE_ARROW_RATE => {
+                if rising {
+                    Self::FortyFiveUp
+                } else {
+                    Self::FortyFiveDown
+                }
+            }
+            _ => Self::Flat,
+        }
+    }
+
+    /// Angle of the trend arrow in degrees, counter-clockwise from pointing right (so `Flat`
+    /// is 0 and `SingleUp` is 90), or `None` when there's no trend to draw
+    pub fn as_degrees(&self) -> Option<f32> {
+        match self {
+            Self::DoubleUp | Self::SingleUp => Some(90.0),
+            Self::FortyFiveUp => Some(45.0),
+            Self::Flat => Some(0.0),
+            Self::FortyFiveDown => Some(-45.0),
+            Self::SingleDown | Self::DoubleDown => Some(-90.0),
+            Self::Else => None,
+        }
+    }
+
+    /// Whether the trend is drawn with a doubled arrowhead
+    pub fn is_double(&self) -> bool {
+        matches!(self, Self::DoubleUp | Self::DoubleDown)
+    }
 }
 
 //? Tried implementing Into For &str, but apparently rust's std automatically implements `Into` when creating
@@ -331,61 +590,78 @@ pub struct Delta {
 
 #[allow(dead_code)]
 impl Delta {
+    /// The change with an explicit sign, to one decimal place. Anything that rounds to zero
+    /// shows as `+0` rather than `-0`.
     pub fn as_signed_str(&self) -> String {
-        let sign = if self.value >= 0.0 { "+" } else { "" };
+        let value = round_delta(self.value);
+        let sign = if value >= 0.0 { "+" } else { "" };
 
-        format!("{}{}", &sign, &self.value)
+        format!("{}{}", sign, value)
     }
 
     pub fn as_mmol(&self) -> Self {
         Delta {
-            value: ((self.value / 18.) * 10.0).round() / 10.0,
+            value: round_delta(self.value / 18.),
         }
     }
 }
 
+/// Round a delta to one decimal place, turning negative zero into zero
+fn round_delta(value: f32) -> f32 {
+    let rounded = (value * 10.0).round() / 10.0;
+    if rounded == 0.0 { 0.0 } else { rounded }
+}
+
+/// Shared timestamp lookup for entries and treatments. A zero or negative time is treated as
+/// missing rather than as the Unix epoch.
+fn timestamp_millis_from(date: Option<u64>, mills: Option<u64>, text: Option<&str>) -> Option<u64> {
+    date.filter(|ms| *ms > 0)
+        .or(mills.filter(|ms| *ms > 0))
+        .or_else(|| {
+            let parsed = chrono::DateTime::parse_from_rfc3339(text?.trim()).ok()?;
+            u64::try_from(parsed.timestamp_millis())
+                .ok()
+                .filter(|ms| *ms > 0)
+        })
+}
+
+/// A timestamp in the user's timezone, falling back to now when it's missing
+fn millis_in_timezone(millis: Option<u64>, user_timezone: &str) -> chrono::DateTime<Tz> {
+    let tz: Tz = user_timezone.parse().unwrap_or(chrono_tz::UTC);
+    millis
+        .and_then(|ms| chrono::DateTime::from_timestamp_millis(ms as i64))
+        .unwrap_or_else(chrono::Utc::now)
+        .with_timezone(&tz)
+}
+
 #[allow(dead_code)]
 impl Entry {
     pub fn svg_as_mmol(&self) -> f32 {
         ((self.sgv / 18.) * 10.0).round() / 10.0
     }
 
+    /// Whether the entry carries a sensor reading at all, rather than a missing, zero or
+    /// negative `sgv` as on meter-only rows. Uploader error codes are dropped when entries are
+    /// cleaned (see [`Nightscout::is_valid_sgv`]), so this is all code working on cleaned
+    /// entries needs to check.
+    pub fn has_sgv(&self) -> bool {
+        self.sgv.is_finite() && self.sgv > 0.0
+    }
+
+    /// When the reading was taken, in Unix milliseconds. Tries `date`, then `mills`, then
+    /// parses `dateString`, and gives `None` when none of them hold a usable time.
+    pub fn timestamp_millis(&self) -> Option<u64> {
+        timestamp_millis_from(self.date, self.mills, self.date_string.as_deref())
+    }
+
     pub fn millis_to_timestamp(&self) -> chrono::DateTime<Local> {
-        let timestamp = self.date.or(self.mills);
-
-        if let Some(ms) = timestamp {
-            Local
-                .timestamp_millis_opt(ms as i64)
-                .single()
-                .unwrap_or_else(Local::now)
-        } else if let Some(date_str) = &self.date_string {
-            match chrono::DateTime::parse_from_rfc3339(date_str) {
-                Ok(parsed) => parsed.with_timezone(&Local),
-                Err(_) => Local::now(),
-            }
-        } else {
-            Local::now()
-        }
+        self.timestamp_millis()
+            .and_then(|ms| Local.timestamp_millis_opt(ms as i64).single())
+            .unwrap_or_else(Local::now)
     }
 
     pub fn millis_to_user_timezone(&self, user_timezone: &str) -> chrono::DateTime<chrono_tz::Tz> {
-        let tz: Tz = user_timezone.parse().unwrap_or(chrono_tz::UTC);
-        let timestamp = self.date.or(self.mills);
-
-        if let Some(ms) = timestamp {
-            if let Some(utc_dt) = chrono::DateTime::from_timestamp_millis(ms as i64) {
-                utc_dt.with_timezone(&tz)
-            } else {
-                chrono::Utc::now().with_timezone(&tz)
-            }
-        } else if let Some(date_str) = &self.date_string {
-            match chrono::DateTime::parse_from_rfc3339(date_str) {
-                Ok(parsed) => parsed.with_timezone(&tz),
-                Err(_) => chrono::Utc::now().with_timezone(&tz),
-            }
-        } else {
-            chrono::Utc::now().with_timezone(&tz)
-        }
+        millis_in_timezone(self.timestamp_millis(), user_timezone)
     }
 
     /// Converts the Nightscout trend text into a Trend enum.
@@ -409,6 +685,49 @@ impl Entry {
         Trend::Else
     }
 
+    /// Change per minute between this reading and an older one, or `None` when either time is
+    /// missing or the readings are more than 15 minutes apart
+    pub fn slope_per_minute(&self, older: &Entry) -> Option<f32> {
+        let minutes =
+            (self.timestamp_millis()? as f32 - older.timestamp_millis()? as f32) / 60_000.0;
+        if minutes <= 0.0 || minutes > MAX_TREND_GAP_MINUTES {
+            return None;
+        }
+
+        Some((self.sgv - older.sgv) / minutes)
+    }
+
+    /// Derives a trend from the slope between this reading and an older one, normalised to
+    /// 5 minutes. Gives `Trend::Else` when either time is missing or the readings are more
+    /// than 15 minutes apart.
+    pub fn trend_from_slope(&self, older: &Entry) -> Trend {
+        match self.slope_per_minute(older) {
+            Some(slope) => Trend::from_rate(slope * 5.0),
+            None => Trend::Else,
+        }
+    }
+
+    /// Delta against the reading closest to `minutes` before this one, or `None` when no
+    /// reading is within 5 minutes of that time
+    pub fn delta_over(&self, entries: &[Entry], minutes: i64) -> Option<Delta> {
+        let newest_ms = self.timestamp_millis()? as i64;
+        Self::nearest_to(entries, newest_ms - minutes * 60_000).map(|older| self.get_delta(older))
+    }
+
+    /// The sensor reading closest to `target_ms`, if one is within 5 minutes of it
+    pub fn nearest_to(entries: &[Entry], target_ms: i64) -> Option<&Entry> {
+        entries
+            .iter()
+            .filter(|entry| entry.has_sgv())
+            .filter_map(|entry| {
+                let ms = entry.timestamp_millis()? as i64;
+                let distance = (ms - target_ms).abs();
+                (distance <= DELTA_MATCH_TOLERANCE_MS).then_some((distance, entry))
+            })
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, entry)| entry)
+    }
+
     /// Calculates a delta using two different readings.
     pub fn get_delta(&self, old_entry: &Entry) -> Delta {
         let delta_value = self.sgv - old_entry.sgv;
@@ -424,59 +743,155 @@ impl Entry {
         }
         self.mbg.is_some() && self.mbg.unwrap_or(0.0) > 0.0
     }
+
+    /// Check if the uploader flagged this reading as noisy (noise level 2 or above)
+    pub fn is_noisy(&self) -> bool {
+        self.noise.is_some_and(|noise| noise >= 2.0)
+    }
 }
 
 #[allow(dead_code)]
 impl Treatment {
+    /// When the treatment happened, in Unix milliseconds. Tries `date`, then `mills`, then
+    /// parses `created_at`, and gives `None` when none of them hold a usable time.
+    pub fn timestamp_millis(&self) -> Option<u64> {
+        timestamp_millis_from(self.date, self.mills, self.created_at.as_deref())
+    }
+
     /// Get timestamp as local DateTime
     pub fn millis_to_timestamp(&self) -> chrono::DateTime<Local> {
-        let timestamp = self.date.or(self.mills);
-
-        if let Some(ms) = timestamp {
-            Local
-                .timestamp_millis_opt(ms as i64)
-                .single()
-                .unwrap_or_else(Local::now)
-        } else if let Some(date_str) = &self.created_at {
-            match chrono::DateTime::parse_from_rfc3339(date_str) {
-                Ok(parsed) => parsed.with_timezone(&Local),
-                Err(_) => Local::now(),
-            }
-        } else {
-            Local::now()
-        }
+        self.timestamp_millis()
+            .and_then(|ms| Local.timestamp_millis_opt(ms as i64).single())
+            .unwrap_or_else(Local::now)
     }
 
     pub fn millis_to_user_timezone(&self, user_timezone: &str) -> chrono::DateTime<chrono_tz::Tz> {
-        let tz: Tz = user_timezone.parse().unwrap_or(chrono_tz::UTC);
-        let timestamp = self.date.or(self.mills);
-
-        if let Some(ms) = timestamp {
-            if let Some(utc_dt) = chrono::DateTime::from_timestamp_millis(ms as i64) {
-                utc_dt.with_timezone(&tz)
-            } else {
-                chrono::Utc::now().with_timezone(&tz)
-            }
-        } else if let Some(date_str) = &self.created_at {
-            match chrono::DateTime::parse_from_rfc3339(date_str) {
-                Ok(parsed) => parsed.with_timezone(&tz),
-                Err(_) => chrono::Utc::now().with_timezone(&tz),
-            }
-        } else {
-            chrono::Utc::now().with_timezone(&tz)
-        }
+        millis_in_timezone(self.timestamp_millis(), user_timezone)
+    }
+
+    /// Units of insulin given: the `insulin` field, or the sum of `insulinInjections` when
+    /// only those were logged
+    pub fn insulin_amount(&self) -> Option<f32> {
+        self.insulin.filter(|units| *units > 0.0).or_else(|| {
+            let total: f32 = self
+                .insulin_injections
+                .iter()
+                .filter_map(|injection| injection.units)
+                .filter(|units| *units > 0.0)
+                .sum();
+            (total > 0.0).then_some(total)
+        })
+    }
+
+    /// Names of the insulins in a multi-insulin treatment, such as "Novorapid + Tresiba"
+    pub fn insulin_names(&self) -> Option<String> {
+        let names: Vec<&str> = self
+            .insulin_injections
+            .iter()
+            .filter(|injection| injection.units.is_some_and(|units| units > 0.0))
+            .filter_map(|injection| injection.insulin.as_deref().map(str::trim))
+            .filter(|name| !name.is_empty())
+            .collect();
+        (!names.is_empty()).then(|| names.join(" + "))
     }
 
     pub fn is_insulin(&self) -> bool {
-        self.insulin.is_some() && self.insulin.unwrap_or(0.0) > 0.0
+        self.insulin_amount().is_some()
     }
 
     pub fn is_carbs(&self) -> bool {
         self.carbs.is_some() && self.carbs.unwrap_or(0.0) > 0.0
     }
 
-    pub fn is_glucose_reading(&self) -> bool {
-        self.glucose.is_some() && self.glucose_type.as_deref() == Some("Finger")
+    /// Fat-protein units (100 kcal from fat and protein each), or `None` when neither was logged
+    pub fn fat_protein_units(&self) -> Option<f32> {
+        let fat = self.fat.unwrap_or(0.0).max(0.0);
+        let protein = self.protein.unwrap_or(0.0).max(0.0);
+        let kcal = fat * 9.0 + protein * 4.0;
+        (kcal > 0.0).then_some(kcal / 100.0)
+    }
+
+    /// Check if this is an automatic (SMB) dose or a bolus at or below the user's
+    /// microbolus threshold
+    pub fn is_microbolus(&self, threshold: f32) -> bool {
+        self.is_insulin()
+            && (self.type_.as_deref() == Some("SMB")
+                || self.insulin_amount().unwrap_or(0.0) <= threshold)
+    }
+
+    /// Check if this treatment is a finger-stick reading, logged either as a "BG Check"
+    /// event or with a "Finger" glucose type
+    pub fn is_bg_check(&self) -> bool {
+        (self.event_type.as_deref() == Some("BG Check")
+            || self.glucose_type.as_deref() == Some("Finger"))
+            && self.glucose_value().is_some()
+    }
+
+    /// The logged glucose value, if present and positive
+    pub fn glucose_value(&self) -> Option<f32> {
+        self.glucose
+            .as_deref()?
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|value| *value > 0.0)
+    }
+
+    /// Check if this treatment marks a new CGM sensor being inserted or started
+    pub fn is_sensor_change(&self) -> bool {
+        self.event_type
+            .as_deref()
+            .is_some_and(|event_type| SENSOR_CHANGE_EVENT_TYPES.contains(&event_type))
+    }
+
+    /// Check if this treatment marks a pump site (cannula) change
+    pub fn is_site_change(&self) -> bool {
+        self.event_type.as_deref() == Some("Site Change")
+    }
+
+    /// Check if this treatment logs exercise or other activity
+    pub fn is_exercise(&self) -> bool {
+        self.event_type.as_deref() == Some("Exercise")
+    }
+
+    /// The trimmed text of a "Note" treatment, or `None` for other events and empty notes
+    pub fn note_text(&self) -> Option<&str> {
+        if self.event_type.as_deref() != Some("Note") {
+            return None;
+        }
+        self.notes
+            .as_deref()
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+    }
+
+    /// Check if this treatment is an announcement left on the site
+    pub fn is_announcement(&self) -> bool {
+        self.event_type.as_deref() == Some("Announcement")
+    }
+
+    /// Check if this treatment sets a temporary basal rate
+    pub fn is_temp_basal(&self) -> bool {
+        self.event_type
+            .as_deref()
+            .is_some_and(|event_type| TEMP_BASAL_EVENT_TYPES.contains(&event_type))
+    }
+
+    /// Whole minutes left on a temp basal at `now`, or `None` once it has run out. A zero
+    /// duration temp basal cancels the previous one, so it is never active.
+    pub fn temp_basal_minutes_left(&self, now: DateTime<Utc>) -> Option<i64> {
+        let duration = self.duration.filter(|minutes| *minutes > 0.0)?;
+        let start = DateTime::from_timestamp_millis(self.timestamp_millis()? as i64)?;
+        let end = start + Duration::seconds((duration * 60.0) as i64);
+        let seconds_left = (end - now).num_seconds();
+        (self.is_temp_basal() && seconds_left > 0).then(|| (seconds_left + 59) / 60)
+    }
+
+    /// The rate a temp basal runs at in U/hr, given the scheduled rate it replaces
+    pub fn temp_basal_rate(&self, scheduled: Option<f32>) -> Option<f32> {
+        self.absolute
+            .or(self.rate)
+            .or_else(|| Some(scheduled? * (100.0 + self.percent?) / 100.0))
     }
 }
 
@@ -490,6 +905,27 @@ pub struct TargetRange {
     pub time_as_seconds: u32,
 }
 
+/// One step of a profile's basal schedule, starting at `time` and running until the next
+#[derive(Deserialize, Debug, Clone)]
+pub struct BasalRate {
+    pub time: String,
+    #[serde(default, deserialize_with = "deserialize_numeric_field")]
+    pub value: Option<f32>,
+    #[serde(rename = "timeAsSeconds", default)]
+    pub time_as_seconds: Option<u32>,
+}
+
+impl BasalRate {
+    /// Seconds after midnight this rate starts, read from `time` ("HH:MM") when the uploader
+    /// left out `timeAsSeconds`
+    fn start_seconds(&self) -> Option<u32> {
+        self.time_as_seconds.or_else(|| {
+            let (hours, minutes) = self.time.trim().split_once(':')?;
+            Some(hours.parse::<u32>().ok()? * 3600 + minutes.parse::<u32>().ok()? * 60)
+        })
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct ProfileStore {
     pub timezone: String,
@@ -499,6 +935,8 @@ pub struct ProfileStore {
     pub target_low: Option<Vec<TargetRange>>,
     #[serde(default)]
     pub target_high: Option<Vec<TargetRange>>,
+    #[serde(default)]
+    pub basal: Option<Vec<BasalRate>>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -508,67 +946,79 @@ pub struct Profile {
     pub store: std::collections::HashMap<String, ProfileStore>,
 }
 
+impl Profile {
+    /// The store named by `defaultProfile`. Some sites name a store that doesn't exist (a
+    /// case mismatch or a renamed profile), so fall back to a case-insensitive match and
+    /// then to the first store by name. `None` only when the store is empty.
+    pub fn default_store(&self) -> Option<&ProfileStore> {
+        if let Some(store) = self.store.get(&self.default_profile) {
+            return Some(store);
+        }
+
+        if let Some((name, store)) = self
+            .store
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&self.default_profile))
+        {
+            tracing::warn!(
+                "[PROFILE] Default profile '{}' not found, using '{}' (case-insensitive match)",
+                self.default_profile,
+                name
+            );
+            return Some(store);
+        }
+
+        let (name, store) = self.store.iter().min_by_key(|(name, _)| name.as_str())?;
+        tracing::warn!(
+            "[PROFILE] Default profile '{}' not found, falling back to '{}'",
+            self.default_profile,
+            name
+        );
+        Some(store)
+    }
+}
+
+/// Whether a profile `units` string means mmol/L. Sites report it inconsistently
+/// ("mmol/L ", "MMOL", "mmoll"), so compare a trimmed, lowercased form.
+pub fn is_mmol_units(units: &str) -> bool {
+    let normalized: String = units
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    matches!(normalized.as_str(), "mmol" | "mmol/l" | "mmoll")
+}
+
 impl ProfileStore {
-    /// Get the low target threshold in the profile's units (mg/dL or mmol/L)
-    /// Priority: status.json bgTargetBottom -> profile target_low -> default 70
-    pub fn get_target_low(&self, status_thresholds: Option<&StatusThresholds>) -> f32 {
-        status_thresholds
-            .map(|thresholds| {
-                tracing::debug!(
-                    "[THRESHOLDS] Using bgTargetBottom from status.json: {}",
-                    thresholds.bg_target_bottom
-                );
-                thresholds.bg_target_bottom as f32
-            })
-            .or_else(|| {
-                self.target_low
-                    .as_ref()
-                    .and_then(|ranges| ranges.first())
-                    .map(|range| {
-                        tracing::debug!(
-                            "[THRESHOLDS] Using target_low from profile: {}",
-                            range.value
-                        );
-                        range.value
-                    })
-            })
-            .unwrap_or_else(|| {
-                tracing::debug!("[THRESHOLDS] Using default target_low: 70");
-                70.0
-            })
+    /// Whether the profile's targets are in mmol/L
+    pub fn uses_mmol(&self) -> bool {
+        self.units.as_deref().is_some_and(is_mmol_units)
     }
 
-    /// Get the high target threshold in the profile's units (mg/dL or mmol/L)
-    /// Priority: status.json bgTargetTop -> profile target_high -> default 180
-    pub fn get_target_high(&self, status_thresholds: Option<&StatusThresholds>) -> f32 {
-        status_thresholds
-            .map(|thresholds| {
-                tracing::debug!(
-                    "[THRESHOLDS] Using bgTargetTop from status.json: {}",
-                    thresholds.bg_target_top
-                );
-                thresholds.bg_target_top as f32
-            })
-            .or_else(|| {
-                self.target_high
-                    .as_ref()
-                    .and_then(|ranges| ranges.first())
-                    .map(|range| {
-                        tracing::debug!(
-                            "[THRESHOLDS] Using target_high from profile: {}",
-                            range.value
-                        );
-                        range.value
-                    })
-            })
-            .unwrap_or_else(|| {
-                tracing::debug!("[THRESHOLDS] Using default target_high: 180");
-                180.0
-            })
+    /// The scheduled basal rate in U/hr at `seconds` after midnight
+    pub fn basal_at(&self, seconds: u32) -> Option<f32> {
+        self.basal
+            .as_ref()?
+            .iter()
+            .filter_map(|rate| Some((rate.start_seconds()?, rate.value?)))
+            .filter(|(start, _)| *start <= seconds)
+            .max_by_key(|(start, _)| *start)
+            .map(|(_, value)| value)
     }
 
-    /// Get the low target threshold always in mg/dL
-    pub fn get_target_low_mg(&self, status_thresholds: Option<&StatusThresholds>) -> f32 {
+    /// Get the low target threshold always in mg/dL. A range the user set with `/set-range`
+    /// wins over Nightscout's own thresholds.
+    pub fn get_target_low_mg(
+        &self,
+        user_range_mg: Option<(f32, f32)>,
+        status_thresholds: Option<&StatusThresholds>,
+    ) -> f32 {
+        if let Some((low, _)) = user_range_mg {
+            tracing::debug!("[THRESHOLDS] Using custom target_low: {} mg/dL", low);
+            return low;
+        }
+
         // If we have status thresholds and they're valid (non-zero), use them directly
         // Status.json thresholds are ALWAYS in mg/dL, never convert them
         if let Some(thresholds) = status_thresholds {
@@ -604,7 +1054,7 @@ impl ProfileStore {
             });
 
         // If profile units are mmol, convert to mg/dL
-        if self.units.as_deref() == Some("mmol") || self.units.as_deref() == Some("mmol/l") {
+        if self.uses_mmol() {
             tracing::debug!("[THRESHOLDS] Converting {} mmol/L to mg/dL", low);
             low * 18.0
         } else {
@@ -612,8 +1062,18 @@ impl ProfileStore {
         }
     }
 
-    /// Get the high target threshold always in mg/dL
-    pub fn get_target_high_mg(&self, status_thresholds: Option<&StatusThresholds>) -> f32 {
+    /// Get the high target threshold always in mg/dL. A range the user set with `/set-range`
+    /// wins over Nightscout's own thresholds.
+    pub fn get_target_high_mg(
+        &self,
+        user_range_mg: Option<(f32, f32)>,
+        status_thresholds: Option<&StatusThresholds>,
+    ) -> f32 {
+        if let Some((_, high)) = user_range_mg {
+            tracing::debug!("[THRESHOLDS] Using custom target_high: {} mg/dL", high);
+            return high;
+        }
+
         // If we have status thresholds and they're valid (non-zero), use them directly
         // Status.json thresholds are ALWAYS in mg/dL, never convert them
         if let Some(thresholds) = status_thresholds {
@@ -649,7 +1109,7 @@ impl ProfileStore {
             });
 
         // If profile units are mmol, convert to mg/dL
-        if self.units.as_deref() == Some("mmol") || self.units.as_deref() == Some("mmol/l") {
+        if self.uses_mmol() {
             tracing::debug!("[THRESHOLDS] Converting {} mmol/L to mg/dL", high);
             high * 18.0
         } else {
@@ -668,11 +1128,25 @@ pub struct StatusThresholds {
     pub bg_target_top: u16,
     #[serde(rename = "bgTargetBottom")]
     pub bg_target_bottom: u16,
-    #[allow(dead_code)]
     #[serde(rename = "bgLow")]
     pub bg_low: u16,
 }
 
+/// Get the urgent low threshold in mg/dL. A value the user set with `/set-range` wins over
+/// Nightscout's `bgLow`, which falls back to 54 mg/dL when unset.
+pub fn get_urgent_low_mg(
+    user_urgent_low_mg: Option<f32>,
+    status_thresholds: Option<&StatusThresholds>,
+) -> f32 {
+    user_urgent_low_mg
+        .or_else(|| {
+            status_thresholds
+                .map(|thresholds| thresholds.bg_low as f32)
+                .filter(|value| *value > 0.0)
+        })
+        .unwrap_or(DEFAULT_URGENT_LOW_MG)
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct StatusSettings {
@@ -691,17 +1165,80 @@ pub struct Status {
 }
 
 #[derive(Deserialize, Debug, Clone)]
-#[allow(dead_code)]
 pub struct DeviceStatus {
     #[serde(default)]
     pub openaps: Option<OpenApsStatus>,
     #[serde(default)]
-    #[allow(dead_code)]
     pub pump: Option<PumpStatus>,
+    #[serde(default)]
+    pub uploader: Option<UploaderStatus>,
+    /// Phone battery as sent by xDrip+ and AndroidAPS, instead of an `uploader` object
+    #[serde(
+        default,
+        rename = "uploaderBattery",
+        deserialize_with = "deserialize_numeric_field"
+    )]
+    pub uploader_battery: Option<f32>,
+    /// The uploading app and device, e.g. `loop://iPhone`
+    #[serde(default)]
+    pub device: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub mills: Option<u64>,
+}
+
+impl DeviceStatus {
+    /// When the loop uploaded this status
+    pub fn timestamp(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.created_at
+            .as_deref()
+            .and_then(|created_at| chrono::DateTime::parse_from_rfc3339(created_at).ok())
+            .map(|parsed| parsed.to_utc())
+            .or_else(|| chrono::DateTime::from_timestamp_millis(self.mills? as i64))
+    }
+
+    /// Battery of the phone or device uploading to Nightscout, in percent
+    pub fn uploader_battery(&self) -> Option<f32> {
+        self.uploader
+            .as_ref()
+            .and_then(|uploader| uploader.battery)
+            .or(self.uploader_battery)
+    }
+
+    /// Insulin on board reported by the loop, falling back to the pump's bolus IOB
+    pub fn iob(&self) -> Option<f32> {
+        self.openaps
+            .as_ref()
+            .and_then(|openaps| openaps.iob.as_ref())
+            .and_then(|iob| iob.iob)
+            .or_else(|| {
+                self.pump
+                    .as_ref()
+                    .and_then(|pump| pump.iob.as_ref())
+                    .and_then(|iob| iob.bolusiob)
+            })
+    }
+
+    /// Carbs on board from the loop's latest suggestion
+    pub fn cob(&self) -> Option<f32> {
+        self.openaps
+            .as_ref()
+            .and_then(|openaps| openaps.suggested.as_ref())
+            .and_then(|suggested| suggested.cob)
+    }
+
+    /// Predicted eventual glucose (mg/dL) from the loop's latest suggestion
+    pub fn eventual_bg(&self) -> Option<f32> {
+        self.openaps
+            .as_ref()
+            .and_then(|openaps| openaps.suggested.as_ref())
+            .and_then(|suggested| suggested.eventual_bg)
+            .filter(|bg| *bg > 0.0)
+    }
 }
 
 #[derive(Deserialize, Debug, Clone)]
-#[allow(dead_code)]
 pub struct OpenApsStatus {
     #[serde(default)]
     pub iob: Option<IobData>,
@@ -710,7 +1247,6 @@ pub struct OpenApsStatus {
 }
 
 #[derive(Deserialize, Debug, Clone)]
-#[allow(dead_code)]
 pub struct IobData {
     #[serde(default)]
     pub iob: Option<f32>,
@@ -723,23 +1259,58 @@ pub struct IobData {
 }
 
 #[derive(Deserialize, Debug, Clone)]
-#[allow(dead_code)]
 pub struct SuggestedData {
     #[serde(rename = "COB", default)]
     pub cob: Option<f32>,
+    /// Where the loop predicts glucose will settle, in mg/dL
+    #[serde(
+        rename = "eventualBG",
+        default,
+        deserialize_with = "deserialize_numeric_field"
+    )]
+    pub eventual_bg: Option<f32>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct PumpStatus {
     #[serde(default)]
-    #[allow(dead_code)]
     pub iob: Option<PumpIob>,
+    #[serde(default)]
+    pub battery: Option<PumpBattery>,
+    /// Insulin left in the reservoir, in units
+    #[serde(default, deserialize_with = "deserialize_numeric_field")]
+    pub reservoir: Option<f32>,
+    /// When the loop last heard from the pump
+    #[serde(default)]
+    pub clock: Option<String>,
+}
+
+impl PumpStatus {
+    /// When the loop last heard from the pump
+    pub fn clock_time(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::parse_from_rfc3339(self.clock.as_deref()?)
+            .ok()
+            .map(|parsed| parsed.to_utc())
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PumpBattery {
+    #[serde(default, deserialize_with = "deserialize_numeric_field")]
+    pub percent: Option<f32>,
+    #[serde(default, deserialize_with = "deserialize_numeric_field")]
+    pub voltage: Option<f32>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UploaderStatus {
+    #[serde(default, deserialize_with = "deserialize_numeric_field")]
+    pub battery: Option<f32>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct PumpIob {
     #[serde(default)]
-    #[allow(dead_code)]
     pub bolusiob: Option<f32>,
 }
 
@@ -778,11 +1349,40 @@ pub struct PebbleData {
     pub cob: Option<f32>,
 }
 
+impl PebbleData {
+    /// Time of the snapshot, taken from its glucose reading
+    pub fn timestamp(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::from_timestamp_millis(self.datetime? as i64)
+    }
+
+    /// The site's own delta for the newest reading in mg/dL, when it sent one
+    pub fn delta(&self) -> Option<Delta> {
+        self.bgdelta.map(|value| Delta { value })
+    }
+}
+
+/// Which Nightscout API the entries are read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    /// `api/v1/entries.json`, which returns a plain array
+    V1,
+    /// `api/v3/entries`, which wraps the documents in a `{ "result": [...] }` envelope
+    V3,
+}
+
+/// The response envelope used by every v3 endpoint
+#[derive(Deserialize, Debug)]
+struct V3Response<T> {
+    result: T,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Default, Clone, Copy)]
 pub struct NightscoutRequestOptions {
     pub count: Option<u16>,
     pub hours_back: Option<u16>,
+    pub between: Option<(u64, u64)>,
+    pub api_version: Option<ApiVersion>,
 }
 
 #[allow(dead_code)]
@@ -809,17 +1409,51 @@ impl NightscoutRequestOptions {
         self.hours_back = Some(hours);
         self
     }
-}
 
-#[allow(dead_code)]
-impl Nightscout {
-    /// Creates a new instance of `Nightscout` with a robust HTTP client.
-    pub fn new() -> Self {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .connect_timeout(std::time::Duration::from_secs(10))
-            .danger_accept_invalid_certs(false) // Keep SSL verification enabled
-            .build()
+    /// Fetches entries between two absolute timestamps (Unix millis, inclusive) instead of
+    /// a window ending now. Takes precedence over `hours_back`.
+    ///
+    /// ```
+    /// let options = NightscoutRequestOptions::default()
+    /// .between(1758326400000, 1758412800000); // 2025-09-20 UTC
+    /// ```
+    pub fn between(mut self, start_millis: u64, end_millis: u64) -> Self {
+        self.between = Some((start_millis, end_millis));
+        self
+    }
+
+    /// Forces the API the entries are read from. By default v1 is used, falling back to v3
+    /// for sites where v1 is disabled.
+    ///
+    /// ```
+    /// let options = NightscoutRequestOptions::default()
+    /// .api_version(ApiVersion::V3);
+    /// ```
+    pub fn api_version(mut self, version: ApiVersion) -> Self {
+        self.api_version = Some(version);
+        self
+    }
+}
+
+/// Whether another attempt is allowed after `attempt` retries, `elapsed` since the first
+/// request and a backoff of `delay`
+fn retry_fits_budget(
+    attempt: u32,
+    elapsed: std::time::Duration,
+    delay: std::time::Duration,
+) -> bool {
+    attempt < MAX_REQUEST_RETRIES && elapsed + delay <= RETRY_BUDGET
+}
+
+#[allow(dead_code)]
+impl Nightscout {
+    /// Creates a new instance of `Nightscout` with a robust HTTP client.
+    pub fn new() -> Self {
+        let client = Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .connect_timeout(std::time::Duration::from_secs(10))
+            .danger_accept_invalid_certs(false) // Keep SSL verification enabled
+            .build()
             .unwrap_or_else(|e| {
                 tracing::warn!("[HTTP] Failed to build custom client, using default: {}", e);
                 Client::new()
@@ -827,6 +1461,55 @@ impl Nightscout {
 
         Nightscout {
             http_client: client,
+            profile_cache: RwLock::new(HashMap::new()),
+            jwt_cache: RwLock::new(HashMap::new()),
+            v3_sites: RwLock::new(HashSet::new()),
+            dedup: DedupConfig::default(),
+            sensor_error_ceiling: SENSOR_ERROR_SGV_CEILING,
+        }
+    }
+
+    /// Use different thresholds for merging duplicate readings
+    pub fn with_dedup_config(mut self, dedup: DedupConfig) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Whether `entry` holds a real sensor reading in mg/dL: not missing, zero, negative or one
+    /// of the uploader error codes at or below this client's sensor error ceiling. This is
+    /// the only place the ceiling is applied.
+    pub fn is_valid_sgv(&self, entry: &Entry) -> bool {
+        entry.has_sgv() && entry.sgv > self.sensor_error_ceiling
+    }
+
+    /// Use a different cutoff (mg/dL) for dropping sensor error codes. Readings of 0 or below
+    /// are always dropped.
+    pub fn with_sensor_error_ceiling(mut self, ceiling: f32) -> Self {
+        self.sensor_error_ceiling = ceiling.max(0.0);
+        self
+    }
+
+    /// Identifies a site regardless of how its base URL was written
+    fn site_key(base_url: &str) -> String {
+        base_url.trim().trim_end_matches('/').to_string()
+    }
+
+    /// A profile is only reused for the same token, so a missing or wrong token is never
+    /// served a profile fetched with a valid one
+    fn profile_cache_key(base_url: &str, token: Option<&str>) -> (String, u64) {
+        let mut hasher = DefaultHasher::new();
+        token.hash(&mut hasher);
+        (Self::site_key(base_url), hasher.finish())
+    }
+
+    /// Drops every cached profile for the given base URL so the next call refetches it.
+    pub async fn invalidate_profile(&self, base_url: &str) {
+        let site = Self::site_key(base_url);
+        let mut cache = self.profile_cache.write().await;
+        let before = cache.len();
+        cache.retain(|(cached_site, _), _| *cached_site != site);
+        if cache.len() < before {
+            tracing::debug!("[CACHE] Invalidated cached profile for {}", site);
         }
     }
 
@@ -836,13 +1519,20 @@ impl Nightscout {
             return Err(NightscoutError::Url(url::ParseError::EmptyHost));
         }
 
-        let url = Url::parse(base_url.trim())?;
+        let mut url = Url::parse(base_url.trim())?;
         if url.host().is_none() {
             return Err(NightscoutError::Url(url::ParseError::EmptyHost));
         }
         if !matches!(url.scheme(), "http" | "https") {
             return Err(NightscoutError::Url(url::ParseError::InvalidIpv4Address));
         }
+
+        // `Url::join` replaces the last path segment unless the path ends in a slash, which
+        // would drop the subpath of sites served under e.g. `https://host/nightscout`
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
         Ok(url)
     }
 
@@ -879,6 +1569,118 @@ impl Nightscout {
         NightscoutError::Network(e)
     }
 
+    /// Sends a GET request, retrying connect errors, timeouts and 5xx responses with
+    /// exponential backoff and jitter. The last response or error is returned as-is so
+    /// callers keep their own status and error handling.
+    async fn get_with_retry(
+        &self,
+        url: &Url,
+        auth: Option<&AuthMethod>,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let first_started = Instant::now();
+        let mut attempt = 0;
+        loop {
+            let mut req = self.http_client.get(url.clone());
+            if let Some(auth) = auth {
+                req = auth.apply_to_request(req);
+            }
+
+            let started = Instant::now();
+            let result = req.send().await;
+            METRICS.record_nightscout_request(
+                started.elapsed(),
+                result.as_ref().is_ok_and(|r| r.status().is_success()),
+            );
+            let retryable = match &result {
+                Ok(response) => response.status().is_server_error(),
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+
+            let delay =
+                RETRY_BASE_DELAY_MS * 2u64.pow(attempt) + rand::random_range(0..=RETRY_JITTER_MS);
+            if !retryable
+                || !retry_fits_budget(
+                    attempt,
+                    first_started.elapsed(),
+                    std::time::Duration::from_millis(delay),
+                )
+            {
+                return result;
+            }
+            match &result {
+                Ok(response) => tracing::warn!(
+                    "[RETRY] {} returned {}, retrying in {}ms ({}/{})",
+                    url,
+                    response.status(),
+                    delay,
+                    attempt + 1,
+                    MAX_REQUEST_RETRIES
+                ),
+                Err(e) => tracing::warn!(
+                    "[RETRY] Request to {} failed: {}, retrying in {}ms ({}/{})",
+                    url,
+                    e,
+                    delay,
+                    attempt + 1,
+                    MAX_REQUEST_RETRIES
+                ),
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+            attempt += 1;
+        }
+    }
+
+    /// Sends an authenticated GET through [`Self::get_with_retry`]. Access tokens are swapped
+    /// for a cached JWT when one is available, and a 401 triggers a single JWT refresh and retry.
+    async fn get_authenticated(
+        &self,
+        base: &Url,
+        url: &Url,
+        auth: Option<&AuthMethod>,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let Some(access_token) = auth.and_then(AuthMethod::access_token) else {
+            return self.get_with_retry(url, auth).await;
+        };
+
+        let cache_key = (base.to_string(), access_token.to_string());
+        let cached_jwt = self
+            .jwt_cache
+            .read()
+            .await
+            .get(&cache_key)
+            .filter(|jwt| jwt.exp > Utc::now().timestamp() + 60)
+            .map(|jwt| AuthMethod::Bearer {
+                jwt: jwt.token.clone(),
+                access_token: Some(access_token.to_string()),
+            });
+
+        let res = self
+            .get_with_retry(url, cached_jwt.as_ref().or(auth))
+            .await?;
+        if res.status() != reqwest::StatusCode::UNAUTHORIZED {
+            return Ok(res);
+        }
+
+        tracing::info!("[JWT] Got 401 from {}, requesting a fresh JWT", url);
+        let jwt_response = match self.request_jwt_token(base.as_str(), access_token).await {
+            Ok(jwt_response) => jwt_response,
+            Err(e) => {
+                tracing::warn!("[JWT] Could not refresh JWT: {}", e);
+                self.jwt_cache.write().await.remove(&cache_key);
+                return Ok(res);
+            }
+        };
+
+        let refreshed = AuthMethod::Bearer {
+            jwt: jwt_response.token.clone(),
+            access_token: Some(access_token.to_string()),
+        };
+        self.jwt_cache.write().await.insert(cache_key, jwt_response);
+
+        self.get_with_retry(url, Some(&refreshed)).await
+    }
+
     /// Request a JWT token from Nightscout using an access token
     pub async fn request_jwt_token(
         &self,
@@ -887,12 +1689,19 @@ impl Nightscout {
     ) -> Result<JwtResponse, NightscoutError> {
         tracing::debug!("[JWT] Requesting JWT token from Nightscout");
 
-        let base = Url::parse(base_url.trim())?;
+        let base = Self::parse_base_url(base_url)?;
         let url = base.join(&format!("api/v2/authorization/request/{}", access_token))?;
 
         tracing::debug!("[JWT] Request URL: {}", url);
 
-        let res = self.http_client.get(url.clone()).send().await?;
+        let res = self.get_with_retry(&url, None).await?;
+        if matches!(
+            res.status(),
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN
+        ) {
+            tracing::warn!("[JWT] Access token rejected with {}", res.status());
+            return Err(NightscoutError::InvalidToken);
+        }
 
         let res = match res.error_for_status() {
             Ok(response) => {
@@ -914,10 +1723,37 @@ impl Nightscout {
         Ok(jwt_response)
     }
 
+    /// Returns the site's profile, reusing a cached copy for up to [`PROFILE_CACHE_TTL`].
     pub async fn get_profile(
         &self,
         base_url: &str,
         token: Option<&str>,
+    ) -> Result<Profile, NightscoutError> {
+        let cache_key = Self::profile_cache_key(base_url, token);
+        if let Some(cached) = self.profile_cache.read().await.get(&cache_key)
+            && cached.fetched_at.elapsed() < PROFILE_CACHE_TTL
+        {
+            tracing::debug!("[CACHE] Using cached profile for {}", cache_key.0);
+            return Ok(cached.profile.clone());
+        }
+
+        let profile = self.fetch_profile(base_url, token).await?;
+
+        self.profile_cache.write().await.insert(
+            cache_key,
+            CachedProfile {
+                profile: profile.clone(),
+                fetched_at: Instant::now(),
+            },
+        );
+
+        Ok(profile)
+    }
+
+    async fn fetch_profile(
+        &self,
+        base_url: &str,
+        token: Option<&str>,
     ) -> Result<Profile, NightscoutError> {
         tracing::debug!("[API] Fetching profile from URL: '{}'", base_url);
         let auth_method = token.map(AuthMethod::from_token);
@@ -929,7 +1765,7 @@ impl Nightscout {
                         &secret[..secret.len().min(8)]
                     );
                 }
-                AuthMethod::Bearer(jwt) => {
+                AuthMethod::Bearer { jwt, .. } => {
                     tracing::debug!(
                         "[AUTH] Using Bearer JWT authentication: {}***",
                         &jwt[..jwt.len().min(8)]
@@ -946,15 +1782,15 @@ impl Nightscout {
         let url = base.join("api/v1/profile.json")?;
         tracing::debug!("[API] Profile API URL: {}", url);
 
-        let mut req = self.http_client.get(url.clone());
-
-        if let Some(auth) = auth_method {
-            req = auth.apply_to_request(req);
+        if let Some(ref auth) = auth_method {
             tracing::debug!("[OK] Applied {} authentication", auth.description());
         }
 
         tracing::debug!("[HTTP] Sending HTTP request to Nightscout API...");
-        let res = match req.send().await {
+        let res = match self
+            .get_authenticated(&base, &url, auth_method.as_ref())
+            .await
+        {
             Ok(response) => {
                 tracing::debug!("[HTTP] Received HTTP response from Nightscout");
                 response
@@ -989,7 +1825,37 @@ impl Nightscout {
         Ok(profile)
     }
 
-    /// Returns an `Entry` if available, or a `NightscoutError::NoEntries` if no entries are found.
+    /// Returns the reading closest to `time`, or `None` when the site has nothing within
+    /// 5 minutes of it.
+    pub async fn get_entry_near(
+        &self,
+        base_url: &str,
+        time: DateTime<Utc>,
+        token: Option<&str>,
+    ) -> Result<Option<Entry>, NightscoutError> {
+        let target_ms = time.timestamp_millis();
+        let options = NightscoutRequestOptions::default()
+            .between(
+                (target_ms - DELTA_MATCH_TOLERANCE_MS) as u64,
+                (target_ms + DELTA_MATCH_TOLERANCE_MS) as u64,
+            )
+            .count(10);
+
+        match self.get_entries(base_url, options, token).await {
+            Ok(entries) => {
+                let readings: Vec<Entry> = entries
+                    .into_iter()
+                    .filter(|entry| self.is_valid_sgv(entry))
+                    .collect();
+                Ok(Entry::nearest_to(&readings, target_ms).cloned())
+            }
+            Err(NightscoutError::NoEntries) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the newest valid sensor reading, skipping finger sticks and uploader error codes,
+    /// or a `NightscoutError::NoEntries` if there is none.
     pub async fn get_entry(
         &self,
         base_url: &str,
@@ -998,12 +1864,16 @@ impl Nightscout {
         let entries = self
             .get_entries(base_url, NightscoutRequestOptions::default(), token)
             .await?;
-        entries.first().cloned().ok_or(NightscoutError::NoEntries)
+        entries
+            .into_iter()
+            .find(|entry| self.is_valid_sgv(entry))
+            .ok_or(NightscoutError::NoEntries)
     }
 
     /// Fetches entries from Nightscout based on the provided options.
     ///
     /// If `options.hours_back` is set, it will fetch all entries from the past X hours.
+    /// If `options.between` is set, it will fetch all entries in that absolute range instead.
     /// If `options.count` is set, it will limit the number of entries returned.
     /// If both are set, `hours_back` takes precedence for the query, but `count` can still limit results.
     ///
@@ -1017,58 +1887,60 @@ impl Nightscout {
         token: Option<&str>,
     ) -> Result<Vec<Entry>, NightscoutError> {
         let base = Self::parse_base_url(base_url)?;
-
-        let url = if let Some(hours) = options.hours_back {
-            let count = options.count.unwrap_or(2000); // Fetch up to 2000 entries for large time ranges
-            let now = Utc::now();
-            let hours_ago = now - Duration::hours(hours as i64);
-            let start_timestamp = hours_ago.timestamp_millis() as u64;
-            let end_timestamp = now.timestamp_millis() as u64;
-
-            let mut query_params = format!(
-                "api/v1/entries.json?find[date][$gte]={}&find[date][$lte]={}",
-                start_timestamp, end_timestamp
-            );
-
-            query_params.push_str(&format!("&count={}", count));
-
-            base.join(&query_params)?
-        } else {
-            let count = options.count.unwrap_or(2000); // Increase default count from u8::MAX (255) to 2000
-            base.join(&format!("api/v1/entries.json?count={count}"))?
-        };
-        tracing::debug!("[API] Entries API URL: {}", url);
-        let mut req = self.http_client.get(url.clone());
-
         let auth_method = token.map(AuthMethod::from_token);
-        if let Some(auth) = auth_method {
-            req = auth.apply_to_request(req);
+        if let Some(ref auth) = auth_method {
             tracing::debug!(
                 "[OK] Applied {} authentication for entries request",
                 auth.description()
             );
         }
 
-        tracing::debug!("[HTTP] Sending entries request to Nightscout...");
-        let res = match req.send().await {
-            Ok(response) => {
-                tracing::debug!("[HTTP] Received entries response from Nightscout");
-                response
-            }
-            Err(e) => return Err(Self::handle_connection_error(e, &url)),
+        let site_key = Self::site_key(base_url);
+        let version = match options.api_version {
+            Some(version) => version,
+            None if self.v3_sites.read().await.contains(&site_key) => ApiVersion::V3,
+            None => ApiVersion::V1,
         };
 
-        let res = match res.error_for_status() {
-            Ok(response) => {
-                tracing::info!("[HTTP] Entries response status: {}", response.status());
-                response
-            }
-            Err(e) => {
-                tracing::error!("[ERROR] Entries request returned error status: {}", e);
-                return Err(NightscoutError::Network(e));
+        let mut entries = match self
+            .fetch_entries(&base, options, version, auth_method.as_ref())
+            .await
+        {
+            Err(NightscoutError::Network(e))
+                if options.api_version.is_none()
+                    && version == ApiVersion::V1
+                    && e.status() == Some(reqwest::StatusCode::NOT_FOUND) =>
+            {
+                tracing::info!(
+                    "[API] v1 entries endpoint not found on {}, trying v3",
+                    site_key
+                );
+                let entries = self
+                    .fetch_entries(&base, options, ApiVersion::V3, auth_method.as_ref())
+                    .await?;
+                self.v3_sites.write().await.insert(site_key);
+                entries
             }
+            result => result?,
         };
-        let entries: Vec<Entry> = res.json().await?;
+
+        // Callers treat `entries[0]` as the newest reading, which not every endpoint guarantees
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.timestamp_millis()));
+
+        // Everything downstream compares against mg/dL, so sites that store mmol/L are
+        // converted here. The profile is only looked up when the readings look like mmol/L.
+        if median_sgv(&entries).is_some_and(|median| median < MMOL_SGV_MEDIAN_CEILING) {
+            let units = self
+                .get_profile(base_url, token)
+                .await
+                .ok()
+                .and_then(|profile| {
+                    profile
+                        .default_store()
+                        .and_then(|store| store.units.clone())
+                });
+            normalize_mmol_sgv(&mut entries, units.as_deref());
+        }
 
         tracing::debug!(
             "[ENTRIES] Retrieved {} entries (cleaning disabled)",
@@ -1094,6 +1966,77 @@ impl Nightscout {
         }
     }
 
+    /// Builds the entries URL for the given API version
+    fn entries_url(
+        base: &Url,
+        options: NightscoutRequestOptions,
+        version: ApiVersion,
+    ) -> Result<Url, NightscoutError> {
+        let count = options.count.unwrap_or(2000); // Fetch up to 2000 entries for large time ranges
+        let range = options.between.or_else(|| {
+            options.hours_back.map(|hours| {
+                let now = Utc::now();
+                let hours_ago = now - Duration::hours(hours as i64);
+                (
+                    hours_ago.timestamp_millis() as u64,
+                    now.timestamp_millis() as u64,
+                )
+            })
+        });
+
+        let query = match (version, range) {
+            (ApiVersion::V1, Some((start, end))) => format!(
+                "api/v1/entries.json?find[date][$gte]={}&find[date][$lte]={}&count={}",
+                start, end, count
+            ),
+            (ApiVersion::V1, None) => format!("api/v1/entries.json?count={count}"),
+            (ApiVersion::V3, Some((start, end))) => format!(
+                "api/v3/entries?date$gte={}&date$lte={}&limit={}&sort$desc=date",
+                start, end, count
+            ),
+            (ApiVersion::V3, None) => format!("api/v3/entries?limit={count}&sort$desc=date"),
+        };
+
+        Ok(base.join(&query)?)
+    }
+
+    /// Sends one entries request and parses either response shape into `Entry` values
+    async fn fetch_entries(
+        &self,
+        base: &Url,
+        options: NightscoutRequestOptions,
+        version: ApiVersion,
+        auth: Option<&AuthMethod>,
+    ) -> Result<Vec<Entry>, NightscoutError> {
+        let url = Self::entries_url(base, options, version)?;
+        tracing::debug!("[API] Entries API URL: {}", url);
+
+        tracing::debug!("[HTTP] Sending entries request to Nightscout...");
+        let res = match self.get_authenticated(base, &url, auth).await {
+            Ok(response) => {
+                tracing::debug!("[HTTP] Received entries response from Nightscout");
+                response
+            }
+            Err(e) => return Err(Self::handle_connection_error(e, &url)),
+        };
+
+        let res = match res.error_for_status() {
+            Ok(response) => {
+                tracing::info!("[HTTP] Entries response status: {}", response.status());
+                response
+            }
+            Err(e) => {
+                tracing::error!("[ERROR] Entries request returned error status: {}", e);
+                return Err(NightscoutError::Network(e));
+            }
+        };
+
+        Ok(match version {
+            ApiVersion::V1 => res.json::<Vec<Entry>>().await?,
+            ApiVersion::V3 => res.json::<V3Response<Vec<Entry>>>().await?.result,
+        })
+    }
+
     /// Convenience method to fetch entries from the past X hours.
     /// This is equivalent to using `get_entries` with `NightscoutRequestOptions::default().hours_back(hours)`.
     ///
@@ -1140,7 +2083,9 @@ impl Nightscout {
     ///
     /// This method combines time filtering and deduplication logic previously scattered across the codebase.
     /// It filters entries to only include those within the specified hours back from the current time
-    /// in the user's timezone, and removes duplicate entries based on timestamp and SGV values.
+    /// in the user's timezone, and removes duplicate entries based on their exact `dateString`, then
+    /// on timestamp and SGV values. Sensor error codes (see [`SENSOR_ERROR_SGV_CEILING`]) are
+    /// dropped, but finger sticks are kept.
     ///
     /// # Arguments
     /// * `entries` - A slice of Entry objects to filter
@@ -1155,21 +2100,37 @@ impl Nightscout {
         entries: &[Entry],
         hours: u16,
         user_timezone: &str,
+    ) -> Result<Vec<Entry>, NightscoutError> {
+        let cutoff_time = Utc::now() - Duration::hours(hours as i64);
+        self.filter_and_clean_entries_between(
+            entries,
+            cutoff_time,
+            DateTime::<Utc>::MAX_UTC,
+            user_timezone,
+        )
+    }
+
+    /// Same as [`Self::filter_and_clean_entries`], but keeps the entries between `start` and
+    /// `end` (inclusive) rather than those from the last few hours
+    pub fn filter_and_clean_entries_between(
+        &self,
+        entries: &[Entry],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        user_timezone: &str,
     ) -> Result<Vec<Entry>, NightscoutError> {
         if entries.is_empty() {
             return Err(NightscoutError::NoEntries);
         }
 
-        let user_tz: chrono_tz::Tz = user_timezone.parse().unwrap_or(chrono_tz::UTC);
-        let now = chrono::Utc::now().with_timezone(&user_tz);
-        let cutoff_time = now - chrono::Duration::hours(hours as i64);
-
         // First filter by time range
         let time_filtered: Vec<&Entry> = entries
             .iter()
             .filter(|entry| {
-                let entry_time = entry.millis_to_user_timezone(user_timezone);
-                entry_time >= cutoff_time
+                let entry_time = entry
+                    .millis_to_user_timezone(user_timezone)
+                    .with_timezone(&Utc);
+                entry_time >= start && entry_time <= end
             })
             .collect();
 
@@ -1179,9 +2140,19 @@ impl Nightscout {
 
         // Then remove duplicates
         let mut seen_ids = std::collections::HashSet::new();
+        let mut seen_date_strings = std::collections::HashSet::new();
         let mut processed_entries = Vec::new();
 
         for entry in time_filtered.into_iter().cloned() {
+            // Re-uploads of the same reading share the exact timestamp even when the id or
+            // value differ slightly, so collapse them before the heuristics below
+            if let Some(date_string) = &entry.date_string
+                && !seen_date_strings.insert((date_string.clone(), entry.entry_type.clone()))
+            {
+                tracing::trace!("[DEDUP] Skipping re-upload with dateString {}", date_string);
+                continue;
+            }
+
             // Skip entries with duplicate IDs
             if let Some(id) = &entry.id {
                 if seen_ids.contains(id) {
@@ -1190,33 +2161,25 @@ impl Nightscout {
                 seen_ids.insert(id.clone());
             }
 
-            let entry_timestamp = entry.date.or(entry.mills).unwrap_or(0);
-            let entry_sgv = (entry.sgv * 100.0) as i32;
-            let entry_mbg = entry.mbg.map(|v| (v * 100.0) as i32);
-
-            let is_duplicate = processed_entries.iter().any(|existing: &Entry| {
-                let existing_timestamp = existing.date.or(existing.mills).unwrap_or(0);
-                let existing_sgv = (existing.sgv * 100.0) as i32;
-                let existing_mbg = existing.mbg.map(|v| (v * 100.0) as i32);
-
-                let time_diff = (entry_timestamp as i64 - existing_timestamp as i64).abs();
-
-                let same_value = if entry_mbg.is_some() && existing_mbg.is_some() {
-                    entry_mbg == existing_mbg
-                } else if entry_mbg.is_none() && existing_mbg.is_none() {
-                    entry_sgv == existing_sgv
-                } else {
-                    false
-                };
-
-                time_diff <= 30000 && same_value
-            });
+            let is_duplicate = processed_entries
+                .iter()
+                .any(|existing| self.dedup.is_duplicate(&entry, existing));
 
             if !is_duplicate {
                 processed_entries.push(entry);
             }
         }
 
+        let before = processed_entries.len();
+        processed_entries.retain(|entry| self.is_valid_sgv(entry) || entry.has_mbg());
+        if processed_entries.len() < before {
+            tracing::debug!(
+                "[ENTRIES] Dropped {} sensor error readings at or below {} mg/dL",
+                before - processed_entries.len(),
+                self.sensor_error_ceiling
+            );
+        }
+
         if processed_entries.is_empty() {
             Err(NightscoutError::NoEntries)
         } else {
@@ -1253,19 +2216,9 @@ impl Nightscout {
         let mut cleaned: Vec<Entry> = Vec::new();
 
         for entry in entries {
-            let is_duplicate = cleaned.iter().any(|existing| {
-                let entry_timestamp = entry.date.or(entry.mills).unwrap_or(0);
-                let existing_timestamp = existing.date.or(existing.mills).unwrap_or(0);
-
-                // We consider the entries are duplicate only if:
-                // 1) Same SGV value
-                // 2) Timestamps within 5 seconds of each other
-                let timestamp_diff = (entry_timestamp as i64 - existing_timestamp as i64).abs();
-                let same_sgv = (entry.sgv - existing.sgv).abs() < 0.1;
-                let close_timestamps = timestamp_diff <= 5000;
-
-                same_sgv && close_timestamps
-            });
+            let is_duplicate = cleaned
+                .iter()
+                .any(|existing| self.dedup.is_duplicate(entry, existing));
 
             if !is_duplicate {
                 cleaned.push(entry.clone());
@@ -1288,9 +2241,14 @@ impl Nightscout {
         //? safer to pull more than two. A check to verify that enough entries are available
         //? is also mandatory to avoid stupid errors.
         let options = NightscoutRequestOptions::default().count(10);
-        let raw_entries = self.get_entries(base_url, options, token).await?;
+        let raw_entries: Vec<Entry> = self
+            .get_entries(base_url, options, token)
+            .await?
+            .into_iter()
+            .filter(|entry| self.is_valid_sgv(entry))
+            .collect();
         tracing::debug!(
-            "[DATA] Retrieved {} raw entries for delta calculation",
+            "[DATA] Retrieved {} valid entries for delta calculation",
             raw_entries.len()
         );
 
@@ -1310,6 +2268,74 @@ impl Nightscout {
         Ok(newer.get_delta(older))
     }
 
+    /// Fetch announcements posted between specific timestamps, newest first
+    pub async fn get_announcements(
+        &self,
+        base_url: &str,
+        start_time: &str,
+        end_time: &str,
+        token: Option<&str>,
+    ) -> Result<Vec<Treatment>, NightscoutError> {
+        let mut announcements: Vec<Treatment> = self
+            .fetch_treatments_between(base_url, start_time, end_time, token)
+            .await?
+            .into_iter()
+            .filter(Treatment::is_announcement)
+            .collect();
+
+        announcements.sort_by_key(|treatment| {
+            std::cmp::Reverse(treatment.millis_to_user_timezone("UTC").timestamp_millis())
+        });
+
+        tracing::info!("[TREATMENTS] Found {} announcements", announcements.len());
+        Ok(announcements)
+    }
+
+    /// Fetch the most recent treatment whose `eventType` is one of `event_types`, or `None`
+    /// when the site has none. The v1 API returns treatments newest first by `created_at`.
+    pub async fn get_latest_treatment(
+        &self,
+        base_url: &str,
+        token: Option<&str>,
+        event_types: &[&str],
+    ) -> Result<Option<Treatment>, NightscoutError> {
+        tracing::debug!(
+            "[TREATMENTS] Fetching latest treatment of types {:?}",
+            event_types
+        );
+
+        let base = Self::parse_base_url(base_url)?;
+        let mut url = base.join("api/v1/treatments.json")?;
+        {
+            let mut query = url.query_pairs_mut();
+            for event_type in event_types {
+                query.append_pair("find[eventType][$in][]", event_type);
+            }
+            query.append_pair("count", "1");
+        }
+        tracing::debug!("[TREATMENTS] Request URL: {}", url);
+
+        let auth_method = token.map(AuthMethod::from_token);
+        let res = match self
+            .get_authenticated(&base, &url, auth_method.as_ref())
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => return Err(Self::handle_connection_error(e, &url)),
+        };
+
+        let res = match res.error_for_status() {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::error!("[TREATMENTS] Request returned error status: {}", e);
+                return Err(NightscoutError::Network(e));
+            }
+        };
+
+        let treatments: Vec<Treatment> = res.json().await?;
+        Ok(treatments.into_iter().next())
+    }
+
     /// Fetch treatments between specific timestamps
     pub async fn fetch_treatments_between(
         &self,
@@ -1334,24 +2360,21 @@ impl Nightscout {
         let url = base.join(&query_params)?;
         tracing::debug!("[TREATMENTS] Request URL: {}", url);
 
-        let mut req = self.http_client.get(url.clone());
-
         let auth_method = token.map(AuthMethod::from_token);
-        if let Some(auth) = auth_method {
-            req = auth.apply_to_request(req);
+        if let Some(ref auth) = auth_method {
             tracing::debug!("[TREATMENTS] Applied {} authentication", auth.description());
         }
 
         tracing::debug!("[TREATMENTS] Sending HTTP request...");
-        let res = match req.send().await {
+        let res = match self
+            .get_authenticated(&base, &url, auth_method.as_ref())
+            .await
+        {
             Ok(response) => {
                 tracing::debug!("[TREATMENTS] Received response from Nightscout");
                 response
             }
-            Err(e) => {
-                tracing::error!("[TREATMENTS] HTTP request failed: {}", e);
-                return Err(NightscoutError::Network(e));
-            }
+            Err(e) => return Err(Self::handle_connection_error(e, &url)),
         };
 
         let res = match res.error_for_status() {
@@ -1380,19 +2403,21 @@ impl Nightscout {
 
         let base = Self::parse_base_url(base_url)?;
 
-        let url = base.join("pebble")?;
+        // Ask for mg/dL so `sgv` and `bgdelta` don't follow the site's display units
+        let mut url = base.join("pebble")?;
+        url.query_pairs_mut().append_pair("units", "mg");
         tracing::debug!("[API] Pebble API URL: {}", url);
 
-        let mut req = self.http_client.get(url.clone());
-
         let auth_method = token.map(AuthMethod::from_token);
-        if let Some(auth) = auth_method {
-            req = auth.apply_to_request(req);
+        if let Some(ref auth) = auth_method {
             tracing::debug!("[OK] Applied {} authentication", auth.description());
         }
 
         tracing::debug!("[HTTP] Sending pebble request...");
-        let res = match req.send().await {
+        let res = match self
+            .get_authenticated(&base, &url, auth_method.as_ref())
+            .await
+        {
             Ok(response) => {
                 tracing::debug!("[HTTP] Received pebble response");
                 response
@@ -1442,6 +2467,46 @@ impl Nightscout {
         }
     }
 
+    /// Fetch the most recent device status uploaded by a loop or pump. Like the pebble
+    /// endpoint this is best-effort: failures are logged and reported as `None`.
+    pub async fn get_latest_device_status(
+        &self,
+        base_url: &str,
+        token: Option<&str>,
+    ) -> Result<Option<DeviceStatus>, NightscoutError> {
+        tracing::debug!("[API] Fetching device status from URL: '{}'", base_url);
+
+        let base = Self::parse_base_url(base_url)?;
+        let mut url = base.join("api/v1/devicestatus.json")?;
+        url.query_pairs_mut().append_pair("count", "1");
+        tracing::debug!("[API] Device status API URL: {}", url);
+
+        let auth_method = token.map(AuthMethod::from_token);
+        if let Some(ref auth) = auth_method {
+            tracing::debug!("[OK] Applied {} authentication", auth.description());
+        }
+
+        let res = match self
+            .get_authenticated(&base, &url, auth_method.as_ref())
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::warn!("[WARN] Device status request failed: {}", e);
+                return Ok(None);
+            }
+        };
+
+        match res.json::<Vec<DeviceStatus>>().await {
+            Ok(statuses) => Ok(statuses.into_iter().next()),
+            Err(e) => {
+                tracing::warn!("[WARN] Failed to parse device status JSON: {}", e);
+                Ok(None)
+            }
+        }
+    }
+
     pub async fn get_status(
         &self,
         base_url: &str,
@@ -1453,16 +2518,16 @@ impl Nightscout {
         let url = base.join("api/v1/status.json")?;
         tracing::debug!("[API] Status API URL: {}", url);
 
-        let mut req = self.http_client.get(url.clone());
-
         let auth_method = token.map(AuthMethod::from_token);
-        if let Some(auth) = auth_method {
-            req = auth.apply_to_request(req);
+        if let Some(ref auth) = auth_method {
             tracing::debug!("[OK] Applied {} authentication", auth.description());
         }
 
         tracing::debug!("[HTTP] Sending status request...");
-        let res = match req.send().await {
+        let res = match self
+            .get_authenticated(&base, &url, auth_method.as_ref())
+            .await
+        {
             Ok(response) => {
                 tracing::debug!("[HTTP] Received status response");
                 response
@@ -1487,3 +2552,1011 @@ impl Nightscout {
         Ok(status)
     }
 }
+
+/// Convert `sgv` values to mg/dL for sites that store them in mmol/L. Detection is heuristic:
+/// the profile must use mmol/L and the median reading must be implausibly low for mg/dL.
+fn normalize_mmol_sgv(entries: &mut [Entry], profile_units: Option<&str>) {
+    let is_mmol_profile = profile_units.is_some_and(is_mmol_units);
+    if !is_mmol_profile {
+        return;
+    }
+
+    let Some(median) = median_sgv(entries).filter(|median| *median < MMOL_SGV_MEDIAN_CEILING)
+    else {
+        return;
+    };
+
+    tracing::info!(
+        "[ENTRIES] Median sgv {:.1} on a mmol/L profile, treating sgv values as mmol/L",
+        median
+    );
+
+    for entry in entries.iter_mut().filter(|entry| entry.has_sgv()) {
+        entry.sgv *= 18.0;
+    }
+}
+
+/// The median `sgv` of the entries that have one
+fn median_sgv(entries: &[Entry]) -> Option<f32> {
+    let mut values: Vec<f32> = entries
+        .iter()
+        .filter(|entry| entry.has_sgv())
+        .map(|entry| entry.sgv)
+        .collect();
+    values.sort_by(|a, b| a.total_cmp(b));
+    values.get(values.len() / 2).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, sgv: f32, date_string: &str, date: u64) -> Entry {
+        Entry {
+            id: Some(id.to_string()),
+            sgv,
+            direction: Some("Flat".to_string()),
+            entry_type: Some("sgv".to_string()),
+            date_string: Some(date_string.to_string()),
+            date: Some(date),
+            ..Default::default()
+        }
+    }
+
+    fn recent(minutes_ago: i64) -> (String, u64) {
+        let time = Utc::now() - Duration::minutes(minutes_ago);
+        (
+            time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            time.timestamp_millis() as u64,
+        )
+    }
+
+    /// A reading `minutes` after a fixed instant
+    fn at(sgv: f32, minutes: u64) -> Entry {
+        Entry {
+            sgv,
+            date: Some(1_700_000_000_000 + minutes * 60_000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_delta_over_uses_closest_reading_to_interval() {
+        let entries = vec![
+            at(150.0, 30),
+            at(145.0, 25),
+            at(138.0, 20),
+            at(132.0, 16),
+            at(120.0, 10),
+            at(100.0, 0),
+        ];
+        let newest = &entries[0];
+
+        assert_eq!(newest.delta_over(&entries, 15).map(|d| d.value), Some(18.0));
+        assert_eq!(newest.delta_over(&entries, 30).map(|d| d.value), Some(50.0));
+        assert!(newest.delta_over(&entries, 60).is_none());
+    }
+
+    #[test]
+    fn test_is_valid_sgv_boundaries() {
+        let client = Nightscout::new();
+        assert!(!client.is_valid_sgv(&at(0.0, 0)));
+        assert!(!client.is_valid_sgv(&at(-5.0, 0)));
+        assert!(!client.is_valid_sgv(&at(SENSOR_ERROR_SGV_CEILING, 0)));
+        assert!(!client.is_valid_sgv(&at(f32::NAN, 0)));
+        assert!(client.is_valid_sgv(&at(40.0, 0)));
+        assert!(client.is_valid_sgv(&at(400.0, 0)));
+
+        // The ceiling comes from the client, so a lenient one keeps low codes
+        let lenient = Nightscout::new().with_sensor_error_ceiling(20.0);
+        assert!(lenient.is_valid_sgv(&at(25.0, 0)));
+        assert!(!lenient.is_valid_sgv(&at(0.0, 0)));
+
+        assert!(at(25.0, 0).has_sgv());
+        assert!(!at(0.0, 0).has_sgv());
+        assert!(!at(f32::NAN, 0).has_sgv());
+
+        // A meter-only row sits exactly 15 minutes back, so the delta skips to the next reading
+        let entries = vec![at(120.0, 15), at(110.0, 4), at(0.0, 0)];
+        assert_eq!(
+            entries[0].delta_over(&entries, 15).map(|d| d.value),
+            Some(10.0)
+        );
+    }
+
+    #[test]
+    fn test_retries_stay_within_budget() {
+        use std::time::Duration;
+
+        assert!(retry_fits_budget(
+            0,
+            Duration::ZERO,
+            Duration::from_millis(500)
+        ));
+        assert!(!retry_fits_budget(
+            MAX_REQUEST_RETRIES,
+            Duration::ZERO,
+            Duration::from_millis(500)
+        ));
+        // A request that already timed out isn't tried again
+        assert!(!retry_fits_budget(
+            0,
+            Duration::from_secs(30),
+            Duration::ZERO
+        ));
+        assert!(!retry_fits_budget(
+            1,
+            RETRY_BUDGET - Duration::from_millis(500),
+            Duration::from_secs(1)
+        ));
+    }
+
+    #[test]
+    fn test_trend_from_rate_thresholds() {
+        assert_eq!(Trend::from_rate(0.0), Trend::Flat);
+        assert_eq!(Trend::from_rate(3.0), Trend::Flat);
+        assert_eq!(Trend::from_rate(-2.5), Trend::Flat);
+        assert_eq!(Trend::from_rate(4.0), Trend::FortyFiveUp);
+        assert_eq!(Trend::from_rate(-4.0), Trend::FortyFiveDown);
+        assert_eq!(Trend::from_rate(7.0), Trend::SingleUp);
+        assert_eq!(Trend::from_rate(-7.0), Trend::SingleDown);
+        assert_eq!(Trend::from_rate(12.0), Trend::DoubleUp);
+        assert_eq!(Trend::from_rate(-12.0), Trend::DoubleDown);
+        assert_eq!(Trend::from_rate(f32::NAN), Trend::Else);
+    }
+
+    #[test]
+    fn test_trend_from_slope_normalises_to_five_minutes() {
+        // +8 over 10 minutes is +4 per 5 minutes
+        assert_eq!(
+            at(128.0, 10).trend_from_slope(&at(120.0, 0)),
+            Trend::FortyFiveUp
+        );
+        assert_eq!(
+            at(108.0, 5).trend_from_slope(&at(120.0, 0)),
+            Trend::DoubleDown
+        );
+        assert_eq!(at(121.0, 5).trend_from_slope(&at(120.0, 0)), Trend::Flat);
+        // Too far apart, out of order, or undated
+        assert_eq!(at(160.0, 30).trend_from_slope(&at(120.0, 0)), Trend::Else);
+        assert_eq!(at(120.0, 0).trend_from_slope(&at(128.0, 10)), Trend::Else);
+        assert_eq!(
+            Entry::default().trend_from_slope(&at(120.0, 0)),
+            Trend::Else
+        );
+    }
+
+    #[test]
+    fn test_urgent_low_prefers_user_then_nightscout() {
+        let thresholds = StatusThresholds {
+            bg_high: 260,
+            bg_target_top: 180,
+            bg_target_bottom: 80,
+            bg_low: 60,
+        };
+
+        assert_eq!(get_urgent_low_mg(Some(50.0), Some(&thresholds)), 50.0);
+        assert_eq!(get_urgent_low_mg(None, Some(&thresholds)), 60.0);
+        assert_eq!(get_urgent_low_mg(None, None), DEFAULT_URGENT_LOW_MG);
+    }
+
+    #[test]
+    fn test_treatment_bg_check_shapes() {
+        let treatments: Vec<Treatment> = serde_json::from_str(
+            r#"[
+                {"eventType": "BG Check", "glucose": 120},
+                {"eventType": "BG Check", "glucose": "6.5", "glucoseType": "Finger"},
+                {"eventType": "Meal Bolus", "glucose": "140", "glucoseType": "Finger"},
+                {"eventType": "BG Check"},
+                {"eventType": "BG Check", "glucose": "abc"},
+                {"eventType": "Correction Bolus", "glucose": 180, "glucoseType": "Sensor"}
+            ]"#,
+        )
+        .unwrap();
+
+        assert!(treatments[0].is_bg_check());
+        assert_eq!(treatments[0].glucose_value(), Some(120.0));
+        assert!(treatments[1].is_bg_check());
+        assert_eq!(treatments[1].glucose_value(), Some(6.5));
+        assert!(treatments[2].is_bg_check());
+        assert!(!treatments[3].is_bg_check());
+        assert!(!treatments[4].is_bg_check());
+        assert!(!treatments[5].is_bg_check());
+    }
+
+    #[test]
+    fn test_treatment_duration_and_note_parsing() {
+        let treatments: Vec<Treatment> = serde_json::from_str(
+            r#"[
+                {"eventType": "Exercise", "duration": "45"},
+                {"eventType": "Exercise", "duration": 30.5},
+                {"eventType": "Note", "notes": "  pizza  "},
+                {"eventType": "Note", "notes": "   "}
+            ]"#,
+        )
+        .unwrap();
+
+        assert!(treatments[0].is_exercise());
+        assert_eq!(treatments[0].duration, Some(45.0));
+        assert_eq!(treatments[1].duration, Some(30.5));
+        assert_eq!(treatments[2].note_text(), Some("pizza"));
+        assert_eq!(treatments[3].note_text(), None);
+        assert_eq!(treatments[0].note_text(), None);
+    }
+
+    #[test]
+    fn test_dedup_identical_date_string_different_ids() {
+        let (date_string, date) = recent(5);
+        let entries = vec![
+            entry("a1", 120.0, &date_string, date),
+            entry("b2", 122.0, &date_string, date),
+        ];
+
+        let cleaned = Nightscout::new()
+            .filter_and_clean_entries(&entries, 3, "UTC")
+            .unwrap();
+
+        assert_eq!(cleaned.len(), 1);
+        assert_eq!(cleaned[0].id.as_deref(), Some("a1"));
+    }
+
+    #[test]
+    fn test_dedup_keeps_distinct_date_strings() {
+        let (first_string, first_date) = recent(10);
+        let (second_string, second_date) = recent(5);
+        let entries = vec![
+            entry("a1", 120.0, &second_string, second_date),
+            entry("b2", 120.0, &first_string, first_date),
+        ];
+
+        let cleaned = Nightscout::new()
+            .filter_and_clean_entries(&entries, 3, "UTC")
+            .unwrap();
+
+        assert_eq!(cleaned.len(), 2);
+    }
+
+    #[test]
+    fn test_dedup_config_window_and_tolerance() {
+        let (first_string, first_date) = recent(10);
+        let (second_string, _) = recent(9);
+        let entries = vec![
+            entry("a1", 120.0, &first_string, first_date),
+            entry("b2", 121.0, &second_string, first_date + 20_000),
+        ];
+
+        let strict = Nightscout::new();
+        assert_eq!(strict.clean_entries(&entries).unwrap().len(), 2);
+        assert_eq!(
+            strict
+                .filter_and_clean_entries(&entries, 3, "UTC")
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let loose = Nightscout::new().with_dedup_config(DedupConfig {
+            window_ms: 60_000,
+            value_tolerance: 2.0,
+        });
+        assert_eq!(loose.clean_entries(&entries).unwrap().len(), 1);
+        assert_eq!(
+            loose
+                .filter_and_clean_entries(&entries, 3, "UTC")
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let narrow = DedupConfig {
+            window_ms: 10_000,
+            value_tolerance: 2.0,
+        };
+        assert!(!narrow.is_duplicate(&entries[1], &entries[0]));
+    }
+
+    #[test]
+    fn test_delta_signs_around_zero() {
+        let signed = |value: f32| Delta { value }.as_signed_str();
+        let signed_mmol = |value: f32| Delta { value }.as_mmol().as_signed_str();
+
+        assert_eq!(signed(0.0), "+0");
+        assert_eq!(signed(-0.0), "+0");
+        assert_eq!(signed(-0.04), "+0");
+        assert_eq!(signed(0.04), "+0");
+        assert_eq!(signed(-3.0), "-3");
+        assert_eq!(signed(5.0), "+5");
+        assert_eq!(signed(1.8), "+1.8");
+
+        assert_eq!(signed_mmol(-0.04), "+0");
+        assert_eq!(signed_mmol(-0.8), "+0");
+        assert_eq!(signed_mmol(0.8), "+0");
+        assert_eq!(signed_mmol(-1.2), "-0.1");
+        assert_eq!(signed_mmol(18.0), "+1");
+        assert_eq!(signed_mmol(-27.0), "-1.5");
+    }
+
+    #[test]
+    fn test_timestamp_millis_fallbacks() {
+        let with = |date: Option<u64>, mills: Option<u64>, date_string: Option<&str>| Entry {
+            date,
+            mills,
+            date_string: date_string.map(str::to_string),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            with(Some(2_000), Some(1_000), None).timestamp_millis(),
+            Some(2_000)
+        );
+        assert_eq!(
+            with(Some(0), Some(1_000), None).timestamp_millis(),
+            Some(1_000)
+        );
+        assert_eq!(
+            with(None, None, Some("2025-09-23T08:38:01.546Z")).timestamp_millis(),
+            Some(1_758_616_681_546)
+        );
+        assert_eq!(
+            with(Some(0), None, Some("not a date")).timestamp_millis(),
+            None
+        );
+
+        let treatment: Treatment = serde_json::from_value(serde_json::json!({
+            "created_at": "2025-09-23T08:38:01.546Z"
+        }))
+        .unwrap();
+        assert_eq!(treatment.timestamp_millis(), Some(1_758_616_681_546));
+        let treatment: Treatment = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert_eq!(treatment.timestamp_millis(), None);
+    }
+
+    #[test]
+    fn test_insulin_injections_are_summed() {
+        let encoded: Treatment = serde_json::from_value(serde_json::json!({
+            "eventType": "Bolus",
+            "insulinInjections": "[{\"insulin\":\"Novorapid\",\"units\":4.0},{\"insulin\":\"Tresiba\",\"units\":\"12\"}]"
+        }))
+        .unwrap();
+        assert_eq!(encoded.insulin_amount(), Some(16.0));
+        assert!(encoded.is_insulin());
+        assert_eq!(
+            encoded.insulin_names().as_deref(),
+            Some("Novorapid + Tresiba")
+        );
+
+        let array: Treatment = serde_json::from_value(serde_json::json!({
+            "insulinInjections": [{ "name": "Fiasp", "units": 2.5 }]
+        }))
+        .unwrap();
+        assert_eq!(array.insulin_amount(), Some(2.5));
+
+        let scalar: Treatment = serde_json::from_value(serde_json::json!({
+            "insulin": 3.0,
+            "insulinInjections": "not json"
+        }))
+        .unwrap();
+        assert_eq!(scalar.insulin_amount(), Some(3.0));
+        assert_eq!(scalar.insulin_names(), None);
+    }
+
+    #[test]
+    fn test_fat_protein_units() {
+        let treatment: Treatment = serde_json::from_value(serde_json::json!({
+            "carbs": 40,
+            "fat": "20",
+            "protein": 25
+        }))
+        .unwrap();
+        assert_eq!(treatment.fat_protein_units(), Some(2.8));
+
+        let treatment: Treatment =
+            serde_json::from_value(serde_json::json!({ "carbs": 40 })).unwrap();
+        assert_eq!(treatment.fat_protein_units(), None);
+    }
+
+    #[test]
+    fn test_date_string_only_entries_filter_and_dedupe() {
+        let date_string_only = |id: &str, minutes_ago: i64| Entry {
+            date: None,
+            ..entry(id, 120.0, &recent(minutes_ago).0, 0)
+        };
+        let entries = vec![
+            date_string_only("a1", 5),
+            date_string_only("b2", 10),
+            date_string_only("c3", 300),
+        ];
+
+        let cleaned = Nightscout::new()
+            .filter_and_clean_entries(&entries, 3, "UTC")
+            .unwrap();
+
+        let ids: Vec<_> = cleaned.iter().filter_map(|e| e.id.as_deref()).collect();
+        assert_eq!(ids, vec!["a1", "b2"]);
+    }
+
+    #[test]
+    fn test_sensor_error_readings_are_dropped() {
+        let (first_string, first_date) = recent(15);
+        let (second_string, second_date) = recent(10);
+        let (third_string, third_date) = recent(5);
+        let (stick_string, stick_date) = recent(7);
+        let finger_stick = Entry {
+            entry_type: Some("mbg".to_string()),
+            mbg: Some(104.0),
+            ..entry("d4", 0.0, &stick_string, stick_date)
+        };
+        let entries = vec![
+            entry("a1", 0.0, &third_string, third_date),
+            entry("b2", 39.0, &second_string, second_date),
+            entry("c3", 110.0, &first_string, first_date),
+            finger_stick,
+        ];
+
+        let cleaned = Nightscout::new()
+            .filter_and_clean_entries(&entries, 3, "UTC")
+            .unwrap();
+        let ids: Vec<_> = cleaned.iter().filter_map(|e| e.id.as_deref()).collect();
+        assert_eq!(ids, vec!["c3", "d4"]);
+
+        let lenient = Nightscout::new()
+            .with_sensor_error_ceiling(20.0)
+            .filter_and_clean_entries(&entries, 3, "UTC")
+            .unwrap();
+        assert_eq!(lenient.len(), 3);
+    }
+
+    #[test]
+    fn test_mmol_native_sgv_is_converted_to_mg_dl() {
+        let (first_string, first_date) = recent(10);
+        let (second_string, second_date) = recent(5);
+        let entries = vec![
+            entry("a1", 5.5, &second_string, second_date),
+            entry("b2", 7.0, &first_string, first_date),
+        ];
+
+        let mut converted = entries.clone();
+        normalize_mmol_sgv(&mut converted, Some("mmol"));
+        assert_eq!(converted[0].sgv, 99.0);
+        assert_eq!(converted[1].sgv, 126.0);
+
+        // mg/dL readings on a mmol/L profile are left alone
+        let mut mg_entries = vec![entry("c3", 120.0, &second_string, second_date)];
+        normalize_mmol_sgv(&mut mg_entries, Some("mmol"));
+        assert_eq!(mg_entries[0].sgv, 120.0);
+
+        // Low values on a mg/dL profile are never rescaled
+        let mut unconverted = entries.clone();
+        normalize_mmol_sgv(&mut unconverted, Some("mg/dl"));
+        assert_eq!(unconverted[0].sgv, 5.5);
+    }
+
+    #[tokio::test]
+    async fn test_cached_profile_is_not_shared_across_tokens() {
+        use httpmock::prelude::*;
+
+        let server = MockServer::start_async().await;
+        let profile = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/profile.json");
+                then.status(200).json_body(serde_json::json!([{
+                    "defaultProfile": "Default",
+                    "store": { "Default": { "timezone": "UTC" } }
+                }]));
+            })
+            .await;
+
+        let client = Nightscout::new();
+        let base_url = server.base_url();
+        client.get_profile(&base_url, None).await.unwrap();
+        client.get_profile(&base_url, None).await.unwrap();
+        assert_eq!(profile.calls_async().await, 1);
+
+        client
+            .get_profile(&base_url, Some("other-token"))
+            .await
+            .unwrap();
+        assert_eq!(profile.calls_async().await, 2);
+
+        client.invalidate_profile(&format!("{}/", base_url)).await;
+        assert!(client.profile_cache.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_entry_converts_mmol_native_sites() {
+        use httpmock::prelude::*;
+
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/entries.json");
+                then.status(200).json_body(serde_json::json!([
+                    { "_id": "a1", "sgv": 6.2, "date": 1700000300000u64, "type": "sgv" }
+                ]));
+            })
+            .await;
+        let profile = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/profile.json");
+                then.status(200).json_body(serde_json::json!([{
+                    "defaultProfile": "Default",
+                    "store": { "Default": { "timezone": "UTC", "units": "mmol" } }
+                }]));
+            })
+            .await;
+
+        let entry = Nightscout::new()
+            .get_entry(&server.base_url(), None)
+            .await
+            .unwrap();
+
+        profile.assert_async().await;
+        assert!((entry.sgv - 111.6).abs() < 0.01);
+    }
+
+    #[tokio::test]
+    async fn test_get_entry_skips_error_codes_and_near_reads_mmol_sites() {
+        use httpmock::prelude::*;
+
+        let now = Utc::now().timestamp_millis() as u64;
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/entries.json");
+                then.status(200).json_body(serde_json::json!([
+                    { "_id": "e0", "sgv": 2.1, "date": now, "type": "sgv" },
+                    { "_id": "a1", "sgv": 6.2, "date": now - 300_000, "type": "sgv" },
+                    { "_id": "a0", "sgv": 6.0, "date": now - 600_000, "type": "sgv" }
+                ]));
+            })
+            .await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/profile.json");
+                then.status(200).json_body(serde_json::json!([{
+                    "defaultProfile": "Default",
+                    "store": { "Default": { "timezone": "UTC", "units": "mmol" } }
+                }]));
+            })
+            .await;
+
+        let client = Nightscout::new();
+        // 2.1 mmol/L is 37.8 mg/dL once converted, an error code rather than a reading
+        let entry = client.get_entry(&server.base_url(), None).await.unwrap();
+        assert_eq!(entry.id.as_deref(), Some("a1"));
+
+        let near = client
+            .get_entry_near(&server.base_url(), Utc::now() - Duration::minutes(5), None)
+            .await
+            .unwrap();
+        assert_eq!(near.and_then(|entry| entry.id).as_deref(), Some("a1"));
+    }
+
+    #[test]
+    fn test_entries_url_with_absolute_range() {
+        let base = Nightscout::parse_base_url("https://ns.example.com").unwrap();
+        let options = NightscoutRequestOptions::default()
+            .hours_back(3)
+            .between(1000, 2000);
+
+        let v1 = Nightscout::entries_url(&base, options, ApiVersion::V1).unwrap();
+        assert!(
+            v1.as_str()
+                .contains("find[date][$gte]=1000&find[date][$lte]=2000")
+        );
+        let v3 = Nightscout::entries_url(&base, options, ApiVersion::V3).unwrap();
+        assert!(v3.as_str().contains("date$gte=1000&date$lte=2000"));
+    }
+
+    fn profile_with_stores(default: &str, names: &[&str]) -> Profile {
+        let store = names
+            .iter()
+            .map(|name| {
+                (
+                    name.to_string(),
+                    ProfileStore {
+                        timezone: format!("tz-{}", name),
+                        units: None,
+                        target_low: None,
+                        target_high: None,
+                        basal: None,
+                    },
+                )
+            })
+            .collect();
+        Profile {
+            default_profile: default.to_string(),
+            store,
+        }
+    }
+
+    #[test]
+    fn test_default_store_fallbacks() {
+        let timezone = |profile: &Profile| profile.default_store().map(|s| s.timezone.clone());
+
+        let exact = profile_with_stores("Default", &["Default", "default", "Sick"]);
+        assert_eq!(timezone(&exact).as_deref(), Some("tz-Default"));
+
+        let case_mismatch = profile_with_stores("Default", &["Sick", "default"]);
+        assert_eq!(timezone(&case_mismatch).as_deref(), Some("tz-default"));
+
+        let stale = profile_with_stores("Old", &["Weekend", "Everyday"]);
+        assert_eq!(timezone(&stale).as_deref(), Some("tz-Everyday"));
+
+        let empty = profile_with_stores("Default", &[]);
+        assert!(empty.default_store().is_none());
+    }
+
+    #[test]
+    fn test_is_mmol_units_variants() {
+        for units in ["mmol", "mmol/l", "mmol/L ", " MMOL/L", "mmoll", "mmol / L"] {
+            assert!(is_mmol_units(units), "{:?} should be mmol/L", units);
+        }
+        for units in ["mg/dl", "mg/dL", "", "mmol/mol"] {
+            assert!(!is_mmol_units(units), "{:?} shouldn't be mmol/L", units);
+        }
+    }
+
+    #[test]
+    fn test_trend_degrees() {
+        assert_eq!(Trend::Flat.as_degrees(), Some(0.0));
+        assert_eq!(Trend::FortyFiveUp.as_degrees(), Some(45.0));
+        assert_eq!(Trend::DoubleDown.as_degrees(), Some(-90.0));
+        assert_eq!(Trend::Else.as_degrees(), None);
+        assert!(Trend::DoubleUp.is_double() && !Trend::SingleUp.is_double());
+    }
+
+    #[test]
+    fn test_device_status_timestamp() {
+        let status: DeviceStatus = serde_json::from_value(serde_json::json!({
+            "created_at": "2025-09-23T08:38:01.546Z",
+            "openaps": { "iob": { "iob": 1.2 } }
+        }))
+        .unwrap();
+        assert_eq!(
+            status.timestamp().map(|time| time.timestamp_millis()),
+            Some(1_758_616_681_546)
+        );
+
+        let status: DeviceStatus =
+            serde_json::from_value(serde_json::json!({ "mills": 1_758_616_681_546u64 })).unwrap();
+        assert_eq!(
+            status.timestamp().map(|time| time.timestamp_millis()),
+            Some(1_758_616_681_546)
+        );
+    }
+
+    #[test]
+    fn test_device_status_hardware_fields() {
+        let loop_status: DeviceStatus = serde_json::from_value(serde_json::json!({
+            "device": "loop://iPhone",
+            "uploader": { "battery": 64 },
+            "pump": {
+                "battery": { "percent": 75 },
+                "reservoir": "123.5",
+                "clock": "2025-09-23T08:35:00Z"
+            }
+        }))
+        .unwrap();
+        let pump = loop_status.pump.as_ref().unwrap();
+        assert_eq!(pump.battery.as_ref().and_then(|b| b.percent), Some(75.0));
+        assert_eq!(pump.reservoir, Some(123.5));
+        assert!(pump.clock_time().is_some());
+        assert_eq!(loop_status.uploader_battery(), Some(64.0));
+
+        let aaps_status: DeviceStatus =
+            serde_json::from_value(serde_json::json!({ "uploaderBattery": 41 })).unwrap();
+        assert_eq!(aaps_status.uploader_battery(), Some(41.0));
+    }
+
+    #[test]
+    fn test_base_url_join_keeps_subpath() {
+        let join = |base: &str| {
+            Nightscout::parse_base_url(base)
+                .unwrap()
+                .join("api/v1/entries.json")
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(
+            join("https://ns.example.com"),
+            "https://ns.example.com/api/v1/entries.json"
+        );
+        assert_eq!(
+            join("https://ns.example.com/"),
+            "https://ns.example.com/api/v1/entries.json"
+        );
+        assert_eq!(
+            join("https://example.com/nightscout"),
+            "https://example.com/nightscout/api/v1/entries.json"
+        );
+        assert_eq!(
+            join("https://example.com/nightscout/"),
+            "https://example.com/nightscout/api/v1/entries.json"
+        );
+    }
+
+    #[test]
+    fn test_basal_schedule_and_temp_basal() {
+        let store: ProfileStore = serde_json::from_value(serde_json::json!({
+            "timezone": "UTC",
+            "basal": [
+                { "time": "00:00", "value": 0.6, "timeAsSeconds": 0 },
+                { "time": "06:30", "value": "0.9" },
+                { "time": "22:00", "value": 0.7, "timeAsSeconds": 79200 }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(store.basal_at(3600), Some(0.6));
+        assert_eq!(store.basal_at(6 * 3600 + 1800), Some(0.9));
+        assert_eq!(store.basal_at(23 * 3600), Some(0.7));
+
+        let now = Utc::now();
+        let temp = |json: serde_json::Value| -> Treatment {
+            let mut json = json;
+            json["eventType"] = "Temp Basal".into();
+            json["mills"] = (now - Duration::minutes(10)).timestamp_millis().into();
+            serde_json::from_value(json).unwrap()
+        };
+
+        let percent = temp(serde_json::json!({ "percent": 50, "duration": 30 }));
+        assert_eq!(percent.temp_basal_minutes_left(now), Some(20));
+        assert!((percent.temp_basal_rate(Some(0.6)).unwrap() - 0.9).abs() < 1e-4);
+        assert_eq!(percent.temp_basal_rate(None), None);
+
+        let absolute = temp(serde_json::json!({ "absolute": 1.2, "rate": 1.0, "duration": 5 }));
+        assert_eq!(absolute.temp_basal_minutes_left(now), None);
+        assert_eq!(absolute.temp_basal_rate(Some(0.6)), Some(1.2));
+
+        let cancel = temp(serde_json::json!({ "duration": 0 }));
+        assert_eq!(cancel.temp_basal_minutes_left(now), None);
+    }
+
+    #[test]
+    fn test_microbolus_by_threshold_or_smb_type() {
+        let bolus = |insulin: f32, type_: Option<&str>| -> Treatment {
+            serde_json::from_value(serde_json::json!({ "insulin": insulin, "type": type_ }))
+                .unwrap()
+        };
+
+        assert!(bolus(0.3, None).is_microbolus(0.5));
+        assert!(bolus(0.5, None).is_microbolus(0.5));
+        assert!(!bolus(2.0, None).is_microbolus(0.5));
+        assert!(bolus(2.0, Some("SMB")).is_microbolus(0.5));
+        assert!(!bolus(0.0, None).is_microbolus(0.5));
+    }
+
+    #[tokio::test]
+    async fn test_latest_treatment_filters_by_event_type() {
+        use httpmock::prelude::*;
+
+        let server = MockServer::start_async().await;
+        let treatments = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/v1/treatments.json")
+                    .query_param("find[eventType][$in][]", "Carb Correction")
+                    .query_param("count", "1");
+                then.status(200).json_body(serde_json::json!([{
+                    "_id": "t1",
+                    "eventType": "Carb Correction",
+                    "created_at": "2025-09-23T08:38:01.546Z",
+                    "carbs": 30
+                }]));
+            })
+            .await;
+
+        let latest = Nightscout::new()
+            .get_latest_treatment(&server.base_url(), None, CARB_EVENT_TYPES)
+            .await
+            .unwrap()
+            .unwrap();
+
+        treatments.assert_async().await;
+        assert_eq!(latest.carbs, Some(30.0));
+    }
+
+    #[tokio::test]
+    async fn test_v3_entries_envelope_is_parsed() {
+        use httpmock::prelude::*;
+
+        let server = MockServer::start_async().await;
+        let entries = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/v3/entries")
+                    .query_param("limit", "2")
+                    .query_param("sort$desc", "date");
+                then.status(200).json_body(serde_json::json!({
+                    "status": 200,
+                    "result": [
+                        { "identifier": "a1", "sgv": 130, "date": 1700000300000u64, "type": "sgv" },
+                        { "identifier": "a0", "sgv": 125, "date": 1700000000000u64, "type": "sgv" }
+                    ]
+                }));
+            })
+            .await;
+
+        let client = Nightscout::new();
+        let options = NightscoutRequestOptions::default()
+            .count(2)
+            .api_version(ApiVersion::V3);
+        let result = client
+            .get_entries(&server.base_url(), options, None)
+            .await
+            .unwrap();
+
+        entries.assert_async().await;
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].id.as_deref(), Some("a1"));
+        assert_eq!(result[0].sgv, 130.0);
+        assert_eq!(result[1].date, Some(1700000000000));
+    }
+
+    #[tokio::test]
+    async fn test_pebble_delta_is_read_in_mg_dl() {
+        use httpmock::prelude::*;
+
+        let server = MockServer::start_async().await;
+        let pebble = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/pebble").query_param("units", "mg");
+                then.status(200).json_body(serde_json::json!({
+                    "bgs": [{ "sgv": "120", "bgdelta": "-3", "datetime": 1700000000000u64 }]
+                }));
+            })
+            .await;
+
+        let data = Nightscout::new()
+            .get_pebble_data(&server.base_url(), None)
+            .await
+            .unwrap()
+            .unwrap();
+
+        pebble.assert_async().await;
+        assert_eq!(data.delta().map(|delta| delta.value), Some(-3.0));
+
+        let without: PebbleData =
+            serde_json::from_value(serde_json::json!({ "sgv": "120" })).unwrap();
+        assert!(without.delta().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_entries_fall_back_to_v3_when_v1_is_disabled() {
+        use httpmock::prelude::*;
+
+        let server = MockServer::start_async().await;
+        let v1 = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/entries.json");
+                then.status(404);
+            })
+            .await;
+        let v3 = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v3/entries");
+                then.status(200).json_body(serde_json::json!({
+                    "status": 200,
+                    "result": [{ "sgv": 110, "date": 1700000000000u64 }]
+                }));
+            })
+            .await;
+
+        let client = Nightscout::new();
+        let base_url = server.base_url();
+        let options = NightscoutRequestOptions::default().count(1);
+
+        let first = client.get_entries(&base_url, options, None).await.unwrap();
+        assert_eq!(first[0].sgv, 110.0);
+
+        // The site is remembered as v3-only, so v1 isn't tried again
+        client.get_entries(&base_url, options, None).await.unwrap();
+
+        v1.assert_calls_async(1).await;
+        v3.assert_calls_async(2).await;
+    }
+
+    #[tokio::test]
+    async fn test_access_token_refreshes_jwt_on_unauthorized() {
+        use httpmock::prelude::*;
+
+        let server = MockServer::start_async().await;
+        let unauthorized = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/v1/entries.json")
+                    .header_missing("Authorization");
+                then.status(401);
+            })
+            .await;
+        let jwt = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/v2/authorization/request/reader-0123456789");
+                then.status(200)
+                    .json_body(serde_json::json!({ "token": "eyJfresh", "exp": 4102444800i64 }));
+            })
+            .await;
+        let entries = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/v1/entries.json")
+                    .header("Authorization", "Bearer eyJfresh");
+                then.status(200)
+                    .json_body(serde_json::json!([{ "sgv": 120, "date": 1700000000000u64 }]));
+            })
+            .await;
+
+        let client = Nightscout::new();
+        let base_url = server.base_url();
+        let options = NightscoutRequestOptions::default().count(1);
+
+        let first = client
+            .get_entries(&base_url, options, Some("reader-0123456789"))
+            .await
+            .unwrap();
+        assert_eq!(first[0].sgv, 120.0);
+
+        // The refreshed JWT is cached, so a second call goes straight to Bearer auth
+        client
+            .get_entries(&base_url, options, Some("reader-0123456789"))
+            .await
+            .unwrap();
+
+        unauthorized.assert_calls_async(1).await;
+        jwt.assert_calls_async(1).await;
+        entries.assert_calls_async(2).await;
+    }
+
+    #[tokio::test]
+    async fn test_rejected_jwt_request_is_invalid_token() {
+        use httpmock::prelude::*;
+
+        let server = MockServer::start_async().await;
+        let jwt = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/v2/authorization/request/reader-0123456789abcdef");
+                then.status(401);
+            })
+            .await;
+
+        let result = Nightscout::new()
+            .request_jwt_token(&server.base_url(), "reader-0123456789abcdef")
+            .await;
+
+        jwt.assert_calls_async(1).await;
+        assert!(matches!(result, Err(NightscoutError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn test_entries_are_returned_newest_first() {
+        use httpmock::prelude::*;
+
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/entries.json");
+                then.status(200).json_body(serde_json::json!([
+                    { "sgv": 110, "date": 1_700_000_300_000u64 },
+                    { "sgv": 130, "date": 1_700_000_900_000u64 },
+                    { "sgv": 100, "date": 1_700_000_000_000u64 },
+                    { "sgv": 120, "date": 1_700_000_600_000u64 }
+                ]));
+            })
+            .await;
+
+        let entries = Nightscout::new()
+            .get_entries(
+                &server.base_url(),
+                NightscoutRequestOptions::default().count(4),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let sgvs: Vec<f32> = entries.iter().map(|entry| entry.sgv).collect();
+        assert_eq!(sgvs, vec![130.0, 120.0, 110.0, 100.0]);
+    }
+}
@@ -0,0 +1,132 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::utils::database::ReminderRepeat;
+
+/// Parse a time of day such as `9:00`, `21:30` or `0930`
+pub fn parse_time_of_day(input: &str) -> Option<NaiveTime> {
+    let input = input.trim();
+    NaiveTime::parse_from_str(input, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(input, "%H%M"))
+        .ok()
+}
+
+/// Parse a weekday name such as `monday` or `mon`
+pub fn parse_weekday(input: &str) -> Option<Weekday> {
+    input.trim().parse::<Weekday>().ok()
+}
+
+/// Convert a wall-clock time in `tz` to UTC. Times skipped by a DST change are moved
+/// forward an hour, and ambiguous times use the earlier instant.
+fn local_to_utc(tz: Tz, date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+    let naive = date.and_time(time);
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(naive + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// The first time strictly after `now` that falls on `time` (and `weekday`, if given)
+/// in the given timezone.
+pub fn first_occurrence(
+    now: DateTime<Utc>,
+    tz: Tz,
+    time: NaiveTime,
+    weekday: Option<Weekday>,
+) -> Option<DateTime<Utc>> {
+    let today = now.with_timezone(&tz).date_naive();
+
+    (0..=7)
+        .map(|offset| today + Duration::days(offset))
+        .filter(|date| weekday.is_none_or(|day| date.weekday() == day))
+        .filter_map(|date| local_to_utc(tz, date, time))
+        .find(|candidate| *candidate > now)
+}
+
+/// The next time a reminder should fire after `fired_at`, keeping the same wall-clock time
+/// in its timezone across DST changes. Occurrences that were missed while the bot was
+/// offline are skipped so a recurring reminder only fires once when it catches up.
+pub fn next_occurrence(
+    fired_at: DateTime<Utc>,
+    repeat: ReminderRepeat,
+    tz: Tz,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let step = match repeat {
+        ReminderRepeat::Once => return None,
+        ReminderRepeat::Daily => Duration::days(1),
+        ReminderRepeat::Weekly => Duration::weeks(1),
+    };
+
+    let local = fired_at.with_timezone(&tz);
+    let time = local.time();
+    let mut date = local.date_naive();
+
+    loop {
+        date += step;
+        let candidate = local_to_utc(tz, date, time)?;
+        if candidate > now {
+            return Some(candidate);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_time_of_day() {
+        assert_eq!(parse_time_of_day("9:05"), NaiveTime::from_hms_opt(9, 5, 0));
+        assert_eq!(
+            parse_time_of_day("2130"),
+            NaiveTime::from_hms_opt(21, 30, 0)
+        );
+        assert_eq!(parse_time_of_day("25:00"), None);
+    }
+
+    #[test]
+    fn test_first_occurrence_on_weekday() {
+        let tz: Tz = "Europe/Paris".parse().unwrap();
+        // Wednesday 2025-01-15 12:00 UTC
+        let now = Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap();
+        let time = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+
+        let next = first_occurrence(now, tz, time, Some(Weekday::Sun)).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 1, 19, 8, 0, 0).unwrap());
+
+        let tomorrow = first_occurrence(now, tz, time, None).unwrap();
+        assert_eq!(
+            tomorrow,
+            Utc.with_ymd_and_hms(2025, 1, 16, 8, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_next_occurrence_keeps_local_time_across_dst() {
+        let tz: Tz = "Europe/Paris".parse().unwrap();
+        // Saturday 2025-03-29 09:00 in Paris (CET, UTC+1)
+        let fired_at = Utc.with_ymd_and_hms(2025, 3, 29, 8, 0, 0).unwrap();
+
+        let next = next_occurrence(fired_at, ReminderRepeat::Daily, tz, fired_at).unwrap();
+        // Sunday is CEST (UTC+2), so 09:00 local is 07:00 UTC
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 3, 30, 7, 0, 0).unwrap());
+
+        assert_eq!(
+            next_occurrence(fired_at, ReminderRepeat::Once, tz, fired_at),
+            None
+        );
+    }
+
+    #[test]
+    fn test_next_occurrence_skips_missed_runs() {
+        let fired_at = Utc.with_ymd_and_hms(2025, 1, 1, 9, 0, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 1, 20, 10, 0, 0).unwrap();
+
+        let next = next_occurrence(fired_at, ReminderRepeat::Weekly, Tz::UTC, now).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 1, 22, 9, 0, 0).unwrap());
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::utils::glucose::{GlucoseStatus, GlucoseThresholds};
+use crate::utils::nightscout::Entry;
+
+const SPARKLINE_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Reading counts below, inside and above a target range
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TimeInRange {
+    pub low: usize,
+    pub in_range: usize,
+    pub high: usize,
+}
+
+impl TimeInRange {
+    /// Buckets every sensor reading against the given mg/dL boundaries (inclusive range).
+    /// Entries without an SGV value, such as meter-only readings, are ignored.
+    pub fn from_entries(entries: &[Entry], target_low_mg: f32, target_high_mg: f32) -> Self {
+        let thresholds = GlucoseThresholds::new(target_low_mg, target_high_mg);
+        entries
+            .iter()
+            .filter(|entry| entry.has_sgv())
+            .fold(Self::default(), |mut tir, entry| {
+                match thresholds.classify(entry.sgv) {
+                    GlucoseStatus::UrgentLow | GlucoseStatus::Low => tir.low += 1,
+                    GlucoseStatus::InRange => tir.in_range += 1,
+                    GlucoseStatus::High => tir.high += 1,
+                }
+                tir
+            })
+    }
+
+    pub fn total(&self) -> usize {
+        self.low + self.in_range + self.high
+    }
+
+    fn percent(&self, count: usize) -> f32 {
+        if self.total() == 0 {
+            0.0
+        } else {
+            count as f32 / self.total() as f32 * 100.0
+        }
+    }
+
+    pub fn low_percent(&self) -> f32 {
+        self.percent(self.low)
+    }
+
+    pub fn in_range_percent(&self) -> f32 {
+        self.percent(self.in_range)
+    }
+
+    pub fn high_percent(&self) -> f32 {
+        self.percent(self.high)
+    }
+}
+
+/// Summary statistics shared by `/stats` and the weekly digest
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlucoseSummary {
+    pub mean: f32,
+    pub sd: f32,
+    pub tir: TimeInRange,
+    pub low_episodes: usize,
+}
+
+impl GlucoseSummary {
+    /// Summarize the sensor readings against a target range, or `None` without readings
+    pub fn from_entries(
+        entries: &[Entry],
+        target_low_mg: f32,
+        target_high_mg: f32,
+    ) -> Option<Self> {
+        let (mean, sd) = mean_and_sd(entries)?;
+        Some(Self {
+            mean,
+            sd,
+            tir: TimeInRange::from_entries(entries, target_low_mg, target_high_mg),
+            low_episodes: count_low_episodes(entries, target_low_mg),
+        })
+    }
+
+    /// Coefficient of variation (SD as a percentage of the mean)
+    pub fn cv_percent(&self) -> f32 {
+        self.sd / self.mean * 100.0
+    }
+
+    pub fn gmi_percent(&self) -> f32 {
+        gmi_percent(self.mean)
+    }
+
+    /// One line like `68% in range, avg 142 (7.9), 3 lows`, giving the mean in both units
+    pub fn caption(&self) -> String {
+        let lows = match self.low_episodes {
+            0 => "no lows".to_string(),
+            1 => "1 low".to_string(),
+            count => format!("{} lows", count),
+        };
+        format!(
+            "{:.0}% in range, avg {:.0} ({:.1}), {}",
+            self.tir.in_range_percent(),
+            self.mean,
+            self.mean / 18.0,
+            lows
+        )
+    }
+}
+
+/// Number of separate lows: runs of consecutive sensor readings below `target_low_mg`
+pub fn count_low_episodes(entries: &[Entry], target_low_mg: f32) -> usize {
+    let thresholds = GlucoseThresholds::new(target_low_mg, f32::INFINITY);
+    let mut episodes = 0;
+    let mut in_low = false;
+    for entry in entries.iter().filter(|entry| entry.has_sgv()) {
+        let is_low = thresholds.classify(entry.sgv).is_low();
+        if is_low && !in_low {
+            episodes += 1;
+        }
+        in_low = is_low;
+    }
+    episodes
+}
+
+/// Midnight at the start of the Monday of `now`'s week, in its own timezone
+pub fn week_start(now: DateTime<Tz>) -> Option<DateTime<Utc>> {
+    let monday = now.date_naive() - Duration::days(now.weekday().num_days_from_monday() as i64);
+    now.timezone()
+        .from_local_datetime(&monday.and_hms_opt(0, 0, 0)?)
+        .earliest()
+        .map(|start| start.with_timezone(&Utc))
+}
+
+/// Mean and standard deviation of the sensor readings in mg/dL, or `None` without readings
+pub fn mean_and_sd(entries: &[Entry]) -> Option<(f32, f32)> {
+    let values: Vec<f32> = entries
+        .iter()
+        .filter(|entry| entry.has_sgv())
+        .map(|entry| entry.sgv)
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32;
+    Some((mean, variance.sqrt()))
+}
+
+/// Glucose Management Indicator (estimated HbA1c, %) from a mean glucose in mg/dL
+pub fn gmi_percent(mean_mg: f32) -> f32 {
+    3.31 + 0.02392 * mean_mg
+}
+
+/// Estimated HbA1c (NGSP %) from an average glucose in mg/dL, using the ADAG relation
+/// eAG = 28.7 × A1c − 46.7
+pub fn estimated_a1c_percent(mean_mg: f32) -> f32 {
+    (mean_mg + 46.7) / 28.7
+}
+
+/// An HbA1c in NGSP % expressed in IFCC mmol/mol
+pub fn a1c_ifcc_mmol_mol(ngsp_percent: f32) -> f32 {
+    (ngsp_percent - 2.15) * 10.929
+}
+
+/// Average sensor glucose (mg/dL) for each calendar day in the user's timezone, oldest first
+pub fn daily_means(entries: &[Entry], user_timezone: &str) -> Vec<(NaiveDate, f32)> {
+    let mut days: BTreeMap<NaiveDate, (f32, usize)> = BTreeMap::new();
+    for entry in entries.iter().filter(|entry| entry.has_sgv()) {
+        let day = entry.millis_to_user_timezone(user_timezone).date_naive();
+        let (sum, count) = days.entry(day).or_default();
+        *sum += entry.sgv;
+        *count += 1;
+    }
+
+    days.into_iter()
+        .map(|(day, (sum, count))| (day, sum / count as f32))
+        .collect()
+}
+
+/// Render values as a row of unicode block characters scaled between their min and max
+pub fn sparkline(values: &[f32]) -> String {
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let top = SPARKLINE_BLOCKS.len() - 1;
+
+    values
+        .iter()
+        .map(|value| {
+            let level = if max <= min {
+                top / 2
+            } else {
+                ((value - min) / (max - min) * top as f32).round() as usize
+            };
+            SPARKLINE_BLOCKS[level.min(top)]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(sgv: f32) -> Entry {
+        Entry {
+            id: None,
+            sgv,
+            direction: None,
+            entry_type: Some("sgv".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_time_in_range_buckets_and_percentages() {
+        let entries: Vec<Entry> = [55.0, 70.0, 120.0, 180.0, 181.0, 0.0]
+            .into_iter()
+            .map(entry)
+            .collect();
+
+        let tir = TimeInRange::from_entries(&entries, 70.0, 180.0);
+
+        assert_eq!(
+            tir,
+            TimeInRange {
+                low: 1,
+                in_range: 3,
+                high: 1
+            }
+        );
+        assert!((tir.in_range_percent() - 60.0).abs() < 0.01);
+        assert!((tir.low_percent() - 20.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_time_in_range_empty() {
+        let tir = TimeInRange::from_entries(&[], 70.0, 180.0);
+        assert_eq!(tir.total(), 0);
+        assert_eq!(tir.in_range_percent(), 0.0);
+    }
+
+    #[test]
+    fn test_daily_means_bucket_by_local_day() {
+        let at = |sgv: f32, millis: u64| Entry {
+            date: Some(millis),
+            ..entry(sgv)
+        };
+        // 2023-11-14 22:13 UTC is already the 15th in Tokyo
+        let entries = vec![
+            at(100.0, 1_700_000_000_000),
+            at(200.0, 1_700_000_000_000 - 12 * 3_600_000),
+            at(120.0, 1_700_000_000_000 + 3_600_000),
+        ];
+
+        let utc = daily_means(&entries, "UTC");
+        assert_eq!(utc.len(), 1);
+        assert!((utc[0].1 - 140.0).abs() < 0.01);
+
+        let tokyo = daily_means(&entries, "Asia/Tokyo");
+        assert_eq!(tokyo.len(), 2);
+        assert!((tokyo[0].1 - 200.0).abs() < 0.01);
+        assert!((tokyo[1].1 - 110.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_glucose_summary_counts_low_episodes() {
+        let entries: Vec<Entry> = [120.0, 65.0, 60.0, 90.0, 0.0, 55.0, 150.0]
+            .into_iter()
+            .map(entry)
+            .collect();
+
+        let summary = GlucoseSummary::from_entries(&entries, 70.0, 180.0).unwrap();
+        assert_eq!(summary.low_episodes, 2);
+        assert_eq!(summary.tir.low, 3);
+        assert!((summary.mean - 90.0).abs() < 0.01);
+        assert!((summary.cv_percent() - summary.sd / 90.0 * 100.0).abs() < 0.01);
+
+        assert_eq!(GlucoseSummary::from_entries(&[], 70.0, 180.0), None);
+    }
+
+    #[test]
+    fn test_glucose_summary_caption() {
+        let entries: Vec<Entry> = [120.0, 65.0, 60.0, 90.0, 55.0, 150.0, 200.0, 130.0]
+            .into_iter()
+            .map(entry)
+            .collect();
+        let summary = GlucoseSummary::from_entries(&entries, 70.0, 180.0).unwrap();
+        assert_eq!(summary.caption(), "50% in range, avg 109 (6.0), 2 lows");
+
+        let summary = GlucoseSummary::from_entries(&[entry(126.0)], 70.0, 180.0).unwrap();
+        assert_eq!(summary.caption(), "100% in range, avg 126 (7.0), no lows");
+    }
+
+    #[test]
+    fn test_week_start_in_local_timezone() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+        // Sunday 2025-01-19 20:00 in New York (EST, UTC-5)
+        let now = Utc
+            .with_ymd_and_hms(2025, 1, 20, 1, 0, 0)
+            .unwrap()
+            .with_timezone(&tz);
+
+        assert_eq!(
+            week_start(now),
+            Some(Utc.with_ymd_and_hms(2025, 1, 13, 5, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_sparkline_scales_between_min_and_max() {
+        assert_eq!(sparkline(&[100.0, 150.0, 200.0]), "▁▅█");
+        assert_eq!(sparkline(&[120.0, 120.0]), "▄▄");
+        assert_eq!(sparkline(&[]), "");
+    }
+}